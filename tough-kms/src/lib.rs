@@ -29,24 +29,25 @@ use snafu::{ensure, OptionExt, ResultExt};
 use std::collections::HashMap;
 use std::fmt;
 use tough::key_source::KeySource;
-use tough::schema::decoded::{Decoded, RsaPem};
-use tough::schema::key::{Key, RsaKey, RsaScheme};
+use tough::schema::key::{EcdsaKey, EcdsaScheme, Key, RsaKey, RsaScheme};
 use tough::sign::Sign;
 
 /// Represents a Signing Algorithms for AWS KMS.
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum KmsSigningAlgorithm {
-    /// Signing Algorithm `RSASSA_PSS_SHA_256`
+    /// Signing Algorithm `RSASSA_PSS_SHA_256`, for the TUF `rsassa-pss-sha256` scheme
     RsassaPssSha256,
+    /// Signing Algorithm `ECDSA_SHA_256` with an `ECC_NIST_P256` key, for the TUF
+    /// `ecdsa-sha2-nistp256` scheme
+    EcdsaSha256,
 }
 
 impl KmsSigningAlgorithm {
     fn value(self) -> String {
-        // Currently we are supporting only single algorithm, but code stub is added to support
-        // multiple algorithms in future.
         String::from(match self {
             KmsSigningAlgorithm::RsassaPssSha256 => "RSASSA_PSS_SHA_256",
+            KmsSigningAlgorithm::EcdsaSha256 => "ECDSA_SHA_256",
         })
     }
 }
@@ -59,7 +60,7 @@ pub struct KmsKeySource {
     pub key_id: String,
     /// KmsClient Object to query AWS KMS
     pub client: Option<KmsClient>,
-    /// Signing Algorithm to be used for the message digest, only `KmsSigningAlgorithm::RsassaPssSha256` is supported at present.
+    /// Signing Algorithm to be used for the message digest; it determines the TUF key type and scheme.
     pub signing_algorithm: KmsSigningAlgorithm,
 }
 
@@ -110,18 +111,48 @@ impl KeySource for KmsKeySource {
                 .contains(&self.signing_algorithm.value()),
             error::ValidSignAlgorithm
         );
-        Ok(Box::new(KmsRsaKey {
+        let spec = response
+            .customer_master_key_spec
+            .as_ref()
+            .context(error::MissingCustomerMasterKeySpec)?;
+        let (public_key, modulus_size_bytes) = match self.signing_algorithm {
+            KmsSigningAlgorithm::RsassaPssSha256 => (
+                Key::Rsa {
+                    keyval: RsaKey {
+                        public: key.parse().context(error::PublicKeyParse)?,
+                        _extra: HashMap::new(),
+                    },
+                    scheme: RsaScheme::RsassaPssSha256,
+                    _extra: HashMap::new(),
+                },
+                Some(parse_modulus_length_bytes(spec)?),
+            ),
+            KmsSigningAlgorithm::EcdsaSha256 => {
+                // TUF's ECDSA scheme only uses the NIST P-256 curve
+                ensure!(
+                    spec == "ECC_NIST_P256",
+                    error::BadCustomerMasterKeySpec { spec }
+                );
+                (
+                    Key::Ecdsa {
+                        keyval: EcdsaKey {
+                            public: key.parse().context(error::PublicKeyParse)?,
+                            _extra: HashMap::new(),
+                        },
+                        scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                        _extra: HashMap::new(),
+                    },
+                    None,
+                )
+            }
+        };
+        Ok(Box::new(KmsKey {
             profile: self.profile.clone(),
             client: Some(kms_client.clone()),
             key_id: self.key_id.clone(),
-            public_key: key.parse().context(error::PublicKeyParse)?,
+            public_key,
             signing_algorithm: self.signing_algorithm,
-            modulus_size_bytes: parse_modulus_length_bytes(
-                response
-                    .customer_master_key_spec
-                    .as_ref()
-                    .context(error::MissingCustomerMasterKeySpec)?,
-            )?,
+            modulus_size_bytes,
         }))
     }
 
@@ -134,8 +165,8 @@ impl KeySource for KmsKeySource {
     }
 }
 
-/// Implements the Sign trait for KMS rsa Key
-pub struct KmsRsaKey {
+/// Implements the Sign trait for KMS RSA and ECDSA keys
+pub struct KmsKey {
     /// Key Id of Customer Managed Key in KMS used to sign the message
     key_id: String,
     /// Aws account profile
@@ -143,16 +174,19 @@ pub struct KmsRsaKey {
     /// KmsClient Object to query AWS KMS
     client: Option<KmsClient>,
     /// Public Key corresponding to Customer Managed Key
-    public_key: Decoded<RsaPem>,
+    public_key: Key,
     /// Signing Algorithm to be used for the Customer Managed Key
     signing_algorithm: KmsSigningAlgorithm,
-    /// The size of the RSA key modulus in bytes.
-    modulus_size_bytes: usize,
+    /// The size of the RSA key modulus in bytes, if this is an RSA key.
+    modulus_size_bytes: Option<usize>,
 }
 
-impl fmt::Debug for KmsRsaKey {
+/// The previous name of `KmsKey`, from when only RSA keys were supported.
+pub type KmsRsaKey = KmsKey;
+
+impl fmt::Debug for KmsKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("KmsRsaKey")
+        f.debug_struct("KmsKey")
             .field("key_id", &self.key_id)
             .field("signing_algorithm", &self.signing_algorithm)
             .field("public_key", &self.public_key)
@@ -160,17 +194,9 @@ impl fmt::Debug for KmsRsaKey {
     }
 }
 
-impl Sign for KmsRsaKey {
+impl Sign for KmsKey {
     fn tuf_key(&self) -> Key {
-        // Create a Key struct for the public key
-        Key::Rsa {
-            keyval: RsaKey {
-                public: self.public_key.to_owned(),
-                _extra: HashMap::new(),
-            },
-            scheme: RsaScheme::RsassaPssSha256,
-            _extra: HashMap::new(),
-        }
+        self.public_key.clone()
     }
 
     fn sign(
@@ -205,10 +231,12 @@ impl Sign for KmsRsaKey {
         // we have observed that openssl and KMS will both validate the signature, but ring will
         // not. if we pad the beginning of the signature with zeros to make the signature exactly
         // the same length as the modulus, then ring will verify the signature.
-        let signature = match &self.signing_algorithm {
-            KmsSigningAlgorithm::RsassaPssSha256 => {
-                pad_signature(signature, self.modulus_size_bytes)?
+        // ECDSA signatures are DER-encoded, as TUF expects, so they are used as they are.
+        let signature = match (&self.signing_algorithm, self.modulus_size_bytes) {
+            (KmsSigningAlgorithm::RsassaPssSha256, Some(modulus_size_bytes)) => {
+                pad_signature(signature, modulus_size_bytes)?
             }
+            _ => signature,
         };
         Ok(signature)
     }
@@ -14,7 +14,7 @@ use tough::key_source::KeySource;
 use tough::schema::decoded::{Decoded, RsaPem};
 use tough::schema::key::Key;
 use tough_kms::KmsKeySource;
-use tough_kms::KmsSigningAlgorithm::RsassaPssSha256;
+use tough_kms::KmsSigningAlgorithm::{EcdsaSha256, RsassaPssSha256};
 
 /// Deserialize base64 to `bytes::Bytes`
 fn de_bytes<'de, D>(deserializer: D) -> Result<bytes::Bytes, D::Error>
@@ -366,3 +366,92 @@ fn check_write_ok() {
     };
     assert_eq!((), kms_key.write("", "").unwrap())
 }
+
+#[test]
+// Ensure an ECDSA public key is returned on calling tuf_key
+fn check_tuf_key_ecdsa_success() {
+    let file = File::open(test_utils::test_data().join("expected_public_key_ecdsa.json")).unwrap();
+    let expected_key: Key = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let mock = MockRequestDispatcher::default().with_body(
+        MockResponseReader::read_response(
+            test_utils::test_data().to_str().unwrap(),
+            "response_public_key_ecdsa.json",
+        )
+        .as_ref(),
+    );
+    let mock_client = KmsClient::new_with(mock, MockCredentialsProvider, Region::UsEast1);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(mock_client),
+        signing_algorithm: EcdsaSha256,
+    };
+    let key = kms_key.as_sign().unwrap().tuf_key();
+    assert!(matches!(key, Key::Ecdsa { .. }));
+    assert_eq!(key, expected_key);
+}
+
+#[test]
+// Ensure an ECDSA signature is returned as it is, without RSA padding
+fn check_sign_ecdsa_success() {
+    let resp_signature = "response_signature_ecdsa.json";
+    let file = File::open(test_utils::test_data().join(resp_signature)).unwrap();
+    let expected_json: SignResp = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let mock = MultipleMockRequestDispatcher::new(vec![
+        MockRequestDispatcher::with_status(200).with_body(
+            MockResponseReader::read_response(
+                test_utils::test_data().to_str().unwrap(),
+                "response_public_key_ecdsa.json",
+            )
+            .as_ref(),
+        ),
+        MockRequestDispatcher::with_status(200)
+            .with_request_checker(|request: &SignedRequest| {
+                assert!(request
+                    .headers
+                    .get("x-amz-target")
+                    .unwrap()
+                    .contains(&Vec::from("TrentService.Sign")));
+            })
+            .with_body(
+                MockResponseReader::read_response(
+                    test_utils::test_data().to_str().unwrap(),
+                    resp_signature,
+                )
+                .as_ref(),
+            ),
+    ]);
+    let mock_client = KmsClient::new_with(mock, MockCredentialsProvider, Region::UsEast1);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(mock_client),
+        signing_algorithm: EcdsaSha256,
+    };
+    let signature = kms_key
+        .as_sign()
+        .unwrap()
+        .sign("Some message to sign".as_bytes(), &SystemRandom::new())
+        .unwrap();
+    assert_eq!(signature, expected_json.signature.to_vec());
+}
+
+#[test]
+// Ensure an RSA key can't be used with the ECDSA signing algorithm
+fn check_public_key_ecdsa_wrong_spec() {
+    let mock = MockRequestDispatcher::default().with_body(
+        MockResponseReader::read_response(
+            test_utils::test_data().to_str().unwrap(),
+            "response_public_key.json",
+        )
+        .as_ref(),
+    );
+    let mock_client = KmsClient::new_with(mock, MockCredentialsProvider, Region::UsEast1);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(mock_client),
+        signing_algorithm: EcdsaSha256,
+    };
+    assert!(kms_key.as_sign().is_err());
+}
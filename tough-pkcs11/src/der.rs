@@ -0,0 +1,139 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The small amount of DER encoding needed to convert between PKCS#11 and TUF key and signature
+//! formats.
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// The DER encoding of the NIST P-256 curve OID (1.2.840.10045.3.1.7), which is what `CKA_EC_PARAMS`
+/// holds for P-256 keys.
+pub(crate) const EC_PARAMS_P256: &[u8] =
+    &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+/// Encodes a tag-length-value.
+// The casts are of values that were checked to fit in a byte.
+#[allow(clippy::cast_possible_truncation)]
+fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .iter()
+            .copied()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | len_bytes.len() as u8);
+        out.extend(len_bytes);
+    }
+    out.extend(value);
+    out
+}
+
+/// Encodes a big-endian unsigned integer, such as an RSA modulus or an ECDSA signature component.
+fn integer(bytes: &[u8]) -> Vec<u8> {
+    let trimmed: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    let mut value = Vec::with_capacity(trimmed.len() + 1);
+    // A leading zero keeps the integer positive if its high bit is set.
+    if trimmed.first().map_or(true, |b| b & 0x80 != 0) {
+        value.push(0);
+    }
+    value.extend(trimmed);
+    tlv(TAG_INTEGER, &value)
+}
+
+/// Encodes a PKCS#1 `RSAPublicKey` from the `CKA_MODULUS` and `CKA_PUBLIC_EXPONENT` of an RSA key.
+pub(crate) fn rsa_public_key(modulus: &[u8], exponent: &[u8]) -> Vec<u8> {
+    let mut value = integer(modulus);
+    value.extend(integer(exponent));
+    tlv(TAG_SEQUENCE, &value)
+}
+
+/// Converts a PKCS#11 ECDSA signature, which is `r` and `s` concatenated, to the DER-encoded
+/// `ECDSA-Sig-Value` that TUF uses. Returns `None` if the signature isn't two equal halves.
+pub(crate) fn ecdsa_signature(raw: &[u8]) -> Option<Vec<u8>> {
+    if raw.is_empty() || raw.len() % 2 != 0 {
+        return None;
+    }
+    let (r, s) = raw.split_at(raw.len() / 2);
+    let mut value = integer(r);
+    value.extend(integer(s));
+    Some(tlv(TAG_SEQUENCE, &value))
+}
+
+/// Extracts the EC point from `CKA_EC_POINT`, which holds it as a DER-encoded octet string.
+pub(crate) fn ec_point(der: &[u8]) -> Option<&[u8]> {
+    let (&tag, rest) = der.split_first()?;
+    if tag != TAG_OCTET_STRING {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (usize::from(first), rest)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+            return None;
+        }
+        let (len_bytes, rest) = rest.split_at(count);
+        let len = len_bytes
+            .iter()
+            .fold(0_usize, |len, b| (len << 8) | usize::from(*b));
+        (len, rest)
+    };
+    if rest.len() == len {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+#[test]
+fn integer_high_bit() {
+    assert_eq!(integer(&[0x7f]), vec![0x02, 0x01, 0x7f]);
+    assert_eq!(integer(&[0x80]), vec![0x02, 0x02, 0x00, 0x80]);
+    assert_eq!(integer(&[0x00, 0x00, 0x01]), vec![0x02, 0x01, 0x01]);
+    assert_eq!(integer(&[0x00]), vec![0x02, 0x01, 0x00]);
+}
+
+#[test]
+fn long_form_length() {
+    let encoded = tlv(TAG_OCTET_STRING, &[0xaa; 300]);
+    assert_eq!(&encoded[..4], &[0x04, 0x82, 0x01, 0x2c]);
+    assert_eq!(ec_point(&encoded), Some(&[0xaa; 300][..]));
+}
+
+#[test]
+fn rsa_public_key_encoding() {
+    // A toy modulus with its high bit set, and the usual exponent 65537
+    let encoded = rsa_public_key(&[0xc0, 0x01], &[0x01, 0x00, 0x01]);
+    assert_eq!(
+        encoded,
+        vec![0x30, 0x0a, 0x02, 0x03, 0x00, 0xc0, 0x01, 0x02, 0x03, 0x01, 0x00, 0x01]
+    );
+}
+
+#[test]
+fn ecdsa_signature_encoding() {
+    let mut raw = vec![0x00; 32];
+    raw[31] = 0x01;
+    raw.extend(vec![0xff; 32]);
+    let mut expected = vec![0x30, 0x26, 0x02, 0x01, 0x01, 0x02, 0x21, 0x00];
+    expected.extend(vec![0xff; 32]);
+    assert_eq!(ecdsa_signature(&raw), Some(expected));
+    assert_eq!(ecdsa_signature(&[0x01, 0x02, 0x03]), None);
+}
+
+#[test]
+fn ec_point_rejects_bad_input() {
+    assert_eq!(ec_point(&[0x04, 0x02, 0x01]), None);
+    assert_eq!(ec_point(&[0x03, 0x01, 0x01]), None);
+    assert_eq!(ec_point(&[]), None);
+}
@@ -0,0 +1,148 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Contains the error type for this library.
+
+#![allow(clippy::default_trait_access)]
+
+use snafu::{Backtrace, Snafu};
+use std::path::PathBuf;
+
+/// Alias for `Result<T, Error>`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error type for this library.
+#[derive(Debug, Snafu)]
+#[snafu(visibility = "pub(crate)")]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum Error {
+    /// The PKCS#11 module could not be loaded.
+    #[snafu(display("Failed to load PKCS#11 module '{}': {}", path.display(), source))]
+    ModuleLoad {
+        path: PathBuf,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The PKCS#11 module could not be initialized.
+    #[snafu(display("Failed to initialize PKCS#11 module '{}': {}", path.display(), source))]
+    ModuleInitialize {
+        path: PathBuf,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The slots with tokens could not be listed.
+    #[snafu(display("Failed to list PKCS#11 slots: {}", source))]
+    ListSlots {
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// Information about a token could not be read.
+    #[snafu(display("Failed to get PKCS#11 token info: {}", source))]
+    TokenInfo {
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// No token has the requested label.
+    #[snafu(display("No PKCS#11 token with label '{}' was found", token))]
+    TokenNotFound { token: String, backtrace: Backtrace },
+
+    /// A session could not be opened with the token.
+    #[snafu(display("Failed to open session with PKCS#11 token '{}': {}", token, source))]
+    OpenSession {
+        token: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// Logging in to the token failed, e.g. because the PIN is wrong.
+    #[snafu(display("Failed to log in to PKCS#11 token '{}': {}", token, source))]
+    Login {
+        token: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// Neither a label nor an ID was given to identify the key.
+    #[snafu(display("A key label or key ID is required to find a key on PKCS#11 token"))]
+    KeySelectorMissing { backtrace: Backtrace },
+
+    /// Searching the token for the key failed.
+    #[snafu(display("Failed to search PKCS#11 token '{}' for {}: {}", token, key, source))]
+    FindObjects {
+        token: String,
+        key: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The key was not found on the token.
+    #[snafu(display("No {} for {} was found on PKCS#11 token '{}'", class, key, token))]
+    KeyNotFound {
+        token: String,
+        key: String,
+        class: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// More than one key on the token matches the label and ID.
+    #[snafu(display(
+        "More than one {} for {} was found on PKCS#11 token '{}'",
+        class,
+        key,
+        token
+    ))]
+    KeyAmbiguous {
+        token: String,
+        key: String,
+        class: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// The attributes of the public key could not be read.
+    #[snafu(display("Failed to read attributes of {}: {}", key, source))]
+    GetAttributes {
+        key: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The public key is missing an attribute needed to build the TUF key.
+    #[snafu(display("Public key for {} is missing attribute {}", key, attribute))]
+    MissingAttribute {
+        key: String,
+        attribute: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// The key is not a type that TUF supports.
+    #[snafu(display(
+        "Key type of {} is not supported; RSA and NIST P-256 ECDSA keys are supported",
+        key
+    ))]
+    UnsupportedKeyType { key: String, backtrace: Backtrace },
+
+    /// The EC point of the public key is not a DER-encoded octet string.
+    #[snafu(display("Failed to parse EC point of {}", key))]
+    EcPointParse { key: String, backtrace: Backtrace },
+
+    /// The token failed to sign the message.
+    #[snafu(display("Failed to sign message with {}: {}", key, source))]
+    SignMessage {
+        key: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The token returned an ECDSA signature that is not the expected length.
+    #[snafu(display("ECDSA signature from {} has unexpected length {}", key, length))]
+    EcdsaSignatureLength {
+        key: String,
+        length: usize,
+        backtrace: Backtrace,
+    },
+}
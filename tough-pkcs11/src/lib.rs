@@ -0,0 +1,429 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! tough-pkcs11 implements the `KeySource` trait found in [tough, a Rust TUF client](https://github.com/awslabs/tough).
+//!
+//! By implementing this trait, a key on a PKCS#11 token, such as a hardware security module, can
+//! be used to sign a [TUF repository](https://theupdateframework.github.io/). The private key
+//! never leaves the token; the public key is read from the token to build the TUF key.
+//!
+//! RSA keys are used with the `rsassa-pss-sha256` scheme and NIST P-256 EC keys with the
+//! `ecdsa-sha2-nistp256` scheme.
+//!
+//! A PKCS#11 module may only be initialized once in a process, so it is loaded and initialized
+//! the first time that a key source uses it, and is then shared by every key source that names
+//! the same module path until the process exits.
+//!
+//! # Testing
+//!
+//! Unit tests are run in the usual manner: `cargo test`. They don't require a token.
+//!
+//! The tests in `tests/softhsm.rs` sign with keys on a SoftHSM token, and are ignored unless
+//! requested with `cargo test -- --ignored`. See that file for how to set up the token.
+
+#![forbid(missing_debug_implementations, missing_copy_implementations)]
+#![deny(rust_2018_idioms)]
+// missing_docs is on its own line to make it easy to comment out when making changes.
+#![deny(missing_docs)]
+#![warn(clippy::pedantic)]
+#![allow(
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc
+)]
+
+mod der;
+pub mod error;
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+use cryptoki::mechanism::{Mechanism, MechanismType};
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, SessionFlags, UserType};
+use cryptoki::slot::Slot;
+use ring::digest::{digest, SHA256, SHA256_OUTPUT_LEN};
+use ring::rand::SecureRandom;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use tough::key_source::KeySource;
+use tough::schema::key::{EcdsaKey, EcdsaScheme, Key, RsaKey, RsaScheme};
+use tough::sign::Sign;
+
+/// Implements the `KeySource` trait for keys that live on a PKCS#11 token.
+///
+/// The key is found by its label (`CKA_LABEL`), its ID (`CKA_ID`), or both; the token must hold
+/// exactly one private key and one public key that match.
+pub struct Pkcs11KeySource {
+    /// Path to the PKCS#11 module for the token, e.g. `/usr/lib/softhsm/libsofthsm2.so`.
+    pub module_path: PathBuf,
+    /// Label of the token that holds the key.
+    pub token_label: String,
+    /// Label of the key.
+    pub key_label: Option<String>,
+    /// ID of the key.
+    pub key_id: Option<Vec<u8>>,
+    /// User PIN to log in to the token with. If not provided, the token is used without logging in.
+    pub pin: Option<String>,
+}
+
+impl fmt::Debug for Pkcs11KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11KeySource")
+            .field("module_path", &self.module_path)
+            .field("token_label", &self.token_label)
+            .field("key_label", &self.key_label)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+/// Implement the `KeySource` trait.
+impl KeySource for Pkcs11KeySource {
+    fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        ensure!(
+            self.key_label.is_some() || self.key_id.is_some(),
+            error::KeySelectorMissing
+        );
+        let context = module(&self.module_path)?;
+        let slot = find_slot(&context, &self.token_label)?;
+        let token = Token {
+            context,
+            slot,
+            token_label: self.token_label.clone(),
+            key_label: self.key_label.clone(),
+            key_id: self.key_id.clone(),
+            pin: self.pin.clone(),
+        };
+
+        // Read the public key, and make sure the private key is there too, so that errors such
+        // as a wrong PIN or a missing key surface before anything is signed.
+        let session = token.open_session()?;
+        let public_key = token.find_key(&session, ObjectClass::PUBLIC_KEY)?;
+        let public_key = token.read_public_key(&session, public_key)?;
+        token.find_key(&session, ObjectClass::PRIVATE_KEY)?;
+        Ok(Box::new(Pkcs11Key { token, public_key }))
+    }
+
+    fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(())
+    }
+}
+
+/// The PKCS#11 modules that have been loaded and initialized, by path.
+static MODULES: OnceLock<Mutex<HashMap<PathBuf, Arc<Pkcs11>>>> = OnceLock::new();
+
+/// Returns the PKCS#11 module at `path`, loading and initializing it if this is its first use.
+/// `C_Initialize` fails if it is called again for a module, so the module is kept for the rest of
+/// the process and shared by every key source that uses it.
+fn module(path: &Path) -> error::Result<Arc<Pkcs11>> {
+    // a poisoned lock only means that another thread panicked while loading a module.
+    let mut modules = MODULES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(context) = modules.get(path) {
+        return Ok(Arc::clone(context));
+    }
+    let context = Pkcs11::new(path).context(error::ModuleLoad { path })?;
+    context
+        .initialize(CInitializeArgs::OsThreads)
+        .context(error::ModuleInitialize { path })?;
+    let context = Arc::new(context);
+    modules.insert(path.to_owned(), Arc::clone(&context));
+    Ok(context)
+}
+
+/// Finds the slot that holds the token labeled `token_label`
+fn find_slot(context: &Pkcs11, token_label: &str) -> error::Result<Slot> {
+    for slot in context.get_slots_with_token().context(error::ListSlots)? {
+        let info = context.get_token_info(slot).context(error::TokenInfo)?;
+        // Token labels are padded with spaces to 32 bytes
+        if info.label().trim_end() == token_label {
+            return Ok(slot);
+        }
+    }
+    error::TokenNotFound { token: token_label }.fail()
+}
+
+/// Implements the Sign trait for a key on a PKCS#11 token
+pub struct Pkcs11Key {
+    /// The token that holds the private key
+    token: Token,
+    /// Public key corresponding to the private key on the token
+    public_key: Key,
+}
+
+impl fmt::Debug for Pkcs11Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Key")
+            .field("token_label", &self.token.token_label)
+            .field("key_label", &self.token.key_label)
+            .field("key_id", &self.token.key_id)
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+/// A key on a PKCS#11 token, and what's needed to open sessions to use it
+struct Token {
+    /// The loaded and initialized PKCS#11 module, which is shared with other key sources
+    context: Arc<Pkcs11>,
+    /// The slot that holds the token
+    slot: Slot,
+    /// Label of the token that holds the key
+    token_label: String,
+    /// Label of the key
+    key_label: Option<String>,
+    /// ID of the key
+    key_id: Option<Vec<u8>>,
+    /// User PIN for the token
+    pin: Option<String>,
+}
+
+impl Token {
+    /// Describes the key for error messages
+    fn describe(&self) -> String {
+        match (&self.key_label, &self.key_id) {
+            (Some(label), Some(id)) => format!("key '{}' (ID {})", label, hex(id)),
+            (Some(label), None) => format!("key '{}'", label),
+            (None, Some(id)) => format!("key ID {}", hex(id)),
+            (None, None) => String::from("key"),
+        }
+    }
+
+    /// Opens a session with the token, logging in if we have a PIN
+    fn open_session(&self) -> error::Result<Session> {
+        let mut flags = SessionFlags::new();
+        flags.set_serial_session(true);
+        let session = self
+            .context
+            .open_session_no_callback(self.slot, flags)
+            .context(error::OpenSession {
+                token: &self.token_label,
+            })?;
+        if let Some(pin) = &self.pin {
+            session
+                .login(UserType::User, Some(pin.as_str()))
+                .context(error::Login {
+                    token: &self.token_label,
+                })?;
+        }
+        Ok(session)
+    }
+
+    /// Finds the one object of `class` that matches our key label and ID
+    fn find_key(&self, session: &Session, class: ObjectClass) -> error::Result<ObjectHandle> {
+        let class_name = if class == ObjectClass::PRIVATE_KEY {
+            "private key"
+        } else {
+            "public key"
+        };
+        let mut template = vec![Attribute::Class(class)];
+        if let Some(label) = &self.key_label {
+            template.push(Attribute::Label(label.as_bytes().to_vec()));
+        }
+        if let Some(id) = &self.key_id {
+            template.push(Attribute::Id(id.clone()));
+        }
+        let objects = session
+            .find_objects(&template)
+            .context(error::FindObjects {
+                token: &self.token_label,
+                key: self.describe(),
+            })?;
+        ensure!(
+            objects.len() <= 1,
+            error::KeyAmbiguous {
+                token: &self.token_label,
+                key: self.describe(),
+                class: class_name,
+            }
+        );
+        objects.into_iter().next().context(error::KeyNotFound {
+            token: &self.token_label,
+            key: self.describe(),
+            class: class_name,
+        })
+    }
+
+    /// Builds the TUF key from the public key on the token
+    fn read_public_key(&self, session: &Session, object: ObjectHandle) -> error::Result<Key> {
+        let attributes = session
+            .get_attributes(
+                object,
+                &[
+                    AttributeType::KeyType,
+                    AttributeType::Modulus,
+                    AttributeType::PublicExponent,
+                    AttributeType::EcParams,
+                    AttributeType::EcPoint,
+                ],
+            )
+            .context(error::GetAttributes {
+                key: self.describe(),
+            })?;
+        let mut key_type = None;
+        let mut modulus = None;
+        let mut exponent = None;
+        let mut ec_params = None;
+        let mut ec_point = None;
+        for attribute in attributes {
+            match attribute {
+                Attribute::KeyType(value) => key_type = Some(value),
+                Attribute::Modulus(value) => modulus = Some(value),
+                Attribute::PublicExponent(value) => exponent = Some(value),
+                Attribute::EcParams(value) => ec_params = Some(value),
+                Attribute::EcPoint(value) => ec_point = Some(value),
+                _ => {}
+            }
+        }
+        let key_type = key_type.context(error::MissingAttribute {
+            key: self.describe(),
+            attribute: "CKA_KEY_TYPE",
+        })?;
+
+        if key_type == KeyType::RSA {
+            let modulus = modulus.context(error::MissingAttribute {
+                key: self.describe(),
+                attribute: "CKA_MODULUS",
+            })?;
+            let exponent = exponent.context(error::MissingAttribute {
+                key: self.describe(),
+                attribute: "CKA_PUBLIC_EXPONENT",
+            })?;
+            Ok(Key::Rsa {
+                keyval: RsaKey {
+                    public: der::rsa_public_key(&modulus, &exponent).into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: RsaScheme::RsassaPssSha256,
+                _extra: HashMap::new(),
+            })
+        } else if key_type == KeyType::EC {
+            // TUF's ECDSA scheme only uses the NIST P-256 curve
+            ensure!(
+                ec_params.as_deref() == Some(der::EC_PARAMS_P256),
+                error::UnsupportedKeyType {
+                    key: self.describe()
+                }
+            );
+            let ec_point = ec_point.context(error::MissingAttribute {
+                key: self.describe(),
+                attribute: "CKA_EC_POINT",
+            })?;
+            let point = der::ec_point(&ec_point).context(error::EcPointParse {
+                key: self.describe(),
+            })?;
+            Ok(Key::Ecdsa {
+                keyval: EcdsaKey {
+                    public: point.to_vec().into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                _extra: HashMap::new(),
+            })
+        } else {
+            error::UnsupportedKeyType {
+                key: self.describe(),
+            }
+            .fail()
+        }
+    }
+
+    /// Signs `msg` on the token with the mechanism for the scheme of `public_key`
+    fn sign(&self, public_key: &Key, msg: &[u8]) -> error::Result<Vec<u8>> {
+        let session = self.open_session()?;
+        let private_key = self.find_key(&session, ObjectClass::PRIVATE_KEY)?;
+        match public_key {
+            Key::Ecdsa { .. } => {
+                // CKM_ECDSA signs a digest that we provide, and returns `r` and `s` concatenated
+                let digest = digest(&SHA256, msg);
+                let raw = session
+                    .sign(&Mechanism::Ecdsa, private_key, digest.as_ref())
+                    .context(error::SignMessage {
+                        key: self.describe(),
+                    })?;
+                der::ecdsa_signature(&raw).context(error::EcdsaSignatureLength {
+                    key: self.describe(),
+                    length: raw.len(),
+                })
+            }
+            _ => {
+                let params = PkcsPssParams {
+                    hash_alg: MechanismType::SHA256,
+                    mgf: PkcsMgfType::MGF1_SHA256,
+                    s_len: (SHA256_OUTPUT_LEN as u64).into(),
+                };
+                session
+                    .sign(&Mechanism::Sha256RsaPkcsPss(params), private_key, msg)
+                    .context(error::SignMessage {
+                        key: self.describe(),
+                    })
+            }
+        }
+    }
+}
+
+impl Sign for Pkcs11Key {
+    fn tuf_key(&self) -> Key {
+        self.public_key.clone()
+    }
+
+    fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.token.sign(&self.public_key, msg)?)
+    }
+}
+
+/// Formats a key ID for error messages
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+#[test]
+fn key_selector_required() {
+    // The module isn't loaded when no key is selected, so this doesn't need a token.
+    let key_source = Pkcs11KeySource {
+        module_path: PathBuf::from("/nonexistent/libpkcs11.so"),
+        token_label: String::from("tuf"),
+        key_label: None,
+        key_id: None,
+        pin: None,
+    };
+    let err = key_source.as_sign().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::KeySelectorMissing { .. })
+    ));
+}
+
+#[test]
+fn missing_module() {
+    let key_source = Pkcs11KeySource {
+        module_path: PathBuf::from("/nonexistent/libpkcs11.so"),
+        token_label: String::from("tuf"),
+        key_label: Some(String::from("root")),
+        key_id: None,
+        pin: None,
+    };
+    let err = key_source.as_sign().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::ModuleLoad { .. })
+    ));
+}
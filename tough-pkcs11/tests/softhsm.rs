@@ -0,0 +1,74 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// These tests sign with keys on a SoftHSM token, so they are ignored by default. To run them,
+// create the token and keys, then run `cargo test -- --ignored`:
+//
+//   softhsm2-util --init-token --free --label tough-test --so-pin 5678 --pin 1234
+//   pkcs11-tool --module "$MODULE" --token-label tough-test --login --pin 1234 \
+//       --keypairgen --key-type rsa:2048 --label rsa --id 01
+//   pkcs11-tool --module "$MODULE" --token-label tough-test --login --pin 1234 \
+//       --keypairgen --key-type EC:prime256v1 --label ecdsa --id 02
+//
+// The module is found at `$PKCS11_SOFTHSM2_MODULE`, or `/usr/lib/softhsm/libsofthsm2.so`.
+
+use ring::rand::SystemRandom;
+use ring::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, RSA_PSS_2048_8192_SHA256,
+};
+use std::path::PathBuf;
+use tough::key_source::KeySource;
+use tough::schema::key::Key;
+use tough_pkcs11::Pkcs11KeySource;
+
+fn module_path() -> PathBuf {
+    std::env::var_os("PKCS11_SOFTHSM2_MODULE")
+        .map_or_else(|| "/usr/lib/softhsm/libsofthsm2.so".into(), PathBuf::from)
+}
+
+fn key_source(key_label: Option<&str>, key_id: Option<&[u8]>) -> Pkcs11KeySource {
+    Pkcs11KeySource {
+        module_path: module_path(),
+        token_label: String::from("tough-test"),
+        key_label: key_label.map(str::to_owned),
+        key_id: key_id.map(<[u8]>::to_vec),
+        pin: Some(String::from("1234")),
+    }
+}
+
+/// Signs a message with the key on the token, and checks the signature with its public key.
+fn assert_sign_verify(key_source: &Pkcs11KeySource) {
+    let msg = b"a message for the token to sign";
+    let sign = key_source.as_sign().unwrap();
+    let signature = sign.sign(msg, &SystemRandom::new()).unwrap();
+    let (algorithm, public): (&'static dyn VerificationAlgorithm, Vec<u8>) = match sign.tuf_key() {
+        Key::Rsa { keyval, .. } => (&RSA_PSS_2048_8192_SHA256, keyval.public.to_vec()),
+        Key::Ecdsa { keyval, .. } => (&ECDSA_P256_SHA256_ASN1, keyval.public.to_vec()),
+        key => panic!("unexpected key type: {:?}", key),
+    };
+    UnparsedPublicKey::new(algorithm, public)
+        .verify(msg, &signature)
+        .unwrap();
+}
+
+#[test]
+#[ignore]
+fn sign_verify_rsa() {
+    assert_sign_verify(&key_source(Some("rsa"), None));
+}
+
+#[test]
+#[ignore]
+fn sign_verify_ecdsa() {
+    assert_sign_verify(&key_source(Some("ecdsa"), None));
+}
+
+/// Key sources for the same module share it, so it is only initialized once.
+#[test]
+#[ignore]
+fn shared_module() {
+    for _ in 0..2 {
+        assert_sign_verify(&key_source(None, Some(&[0x01])));
+        assert_sign_verify(&key_source(Some("ecdsa"), Some(&[0x02])));
+    }
+}
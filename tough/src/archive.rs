@@ -0,0 +1,310 @@
+//! The `archive` module provides `ArchiveTransport`, which enables `Repository` objects to be
+//! loaded from a tar or zip archive without unpacking it.
+use crate::{Transport, TransportError, TransportErrorKind};
+use flate2::read::{DeflateDecoder, GzDecoder};
+use percent_encoding::percent_decode_str;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use url::Url;
+use zip::{CompressionMethod, ZipArchive};
+
+/// A [`Transport`] for files in a tar archive, a gzip-compressed tar archive, or a zip archive.
+///
+/// The transport handles `file` URLs, whose paths are taken to be relative to the root of the
+/// archive. For an archive that contains `metadata/` and `targets/` directories, the repository
+/// would be loaded with a metadata base URL of `file:///metadata/` and a targets base URL of
+/// `file:///targets/`. A URL for which there is no regular file in the archive results in
+/// [`TransportErrorKind::FileNotFound`].
+///
+/// The archive is read once when the transport is created, to find where each file is; files are
+/// then read from the archive as they are fetched, so nothing is unpacked to disk or buffered in
+/// memory. Files in a gzip-compressed tar archive can't be found by seeking, so each fetch
+/// decompresses the archive from the start up to the file.
+///
+/// Requires the `archive` feature.
+#[derive(Clone)]
+pub struct ArchiveTransport {
+    archive: Arc<Mutex<dyn ReadSeek + Send>>,
+    format: Format,
+    entries: Arc<HashMap<String, Entry>>,
+}
+
+impl Debug for ArchiveTransport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveTransport")
+            .field("format", &self.format)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+/// A blanket trait for the archive reader, since trait objects can only have one non-auto trait.
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+/// Where a file is in the archive.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// The offset of the file's data, in the decompressed stream for a gzip-compressed tar archive.
+    offset: u64,
+    /// The size of the file's data in the archive, which is compressed if `deflated` is true.
+    size: u64,
+    /// Whether the data is compressed with DEFLATE, for files in a zip archive.
+    deflated: bool,
+}
+
+impl ArchiveTransport {
+    /// Opens the archive at `path`. The format is chosen by the file extension: `.tar`, `.tar.gz`
+    /// or `.tgz`, or `.zip`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let file = File::open(path).context(Open { path })?;
+        if name.ends_with(".tar") {
+            Self::from_tar(file)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Self::from_tar_gz(file)
+        } else if name.ends_with(".zip") {
+            Self::from_zip(file)
+        } else {
+            UnknownFormat { path }.fail()
+        }
+    }
+
+    /// Creates an `ArchiveTransport` for the tar archive read from `reader`.
+    pub fn from_tar<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self, ArchiveError> {
+        Self::tar(reader, Format::Tar)
+    }
+
+    /// Creates an `ArchiveTransport` for the gzip-compressed tar archive read from `reader`.
+    pub fn from_tar_gz<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self, ArchiveError> {
+        Self::tar(reader, Format::TarGz)
+    }
+
+    /// Creates an `ArchiveTransport` for the zip archive read from `reader`.
+    pub fn from_zip<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self, ArchiveError> {
+        let mut reader = BufReader::new(reader);
+        let mut entries = HashMap::new();
+        {
+            let mut zip = ZipArchive::new(&mut reader).context(ReadZip)?;
+            for i in 0..zip.len() {
+                // fails for encrypted files.
+                let file = zip.by_index(i).context(ReadZip)?;
+                if file.is_dir() {
+                    continue;
+                }
+                let deflated = match file.compression() {
+                    CompressionMethod::Stored => false,
+                    CompressionMethod::Deflated => true,
+                    method => {
+                        return UnsupportedCompression {
+                            name: file.name(),
+                            method: format!("{:?}", method),
+                        }
+                        .fail()
+                    }
+                };
+                entries.insert(
+                    normalize(file.name()),
+                    Entry {
+                        offset: file.data_start(),
+                        size: file.compressed_size(),
+                        deflated,
+                    },
+                );
+            }
+        }
+        Ok(Self::new(reader.into_inner(), Format::Zip, entries))
+    }
+
+    fn tar<R: Read + Seek + Send + 'static>(
+        mut reader: R,
+        format: Format,
+    ) -> Result<Self, ArchiveError> {
+        reader.seek(SeekFrom::Start(0)).context(ReadTar)?;
+        let mut entries = HashMap::new();
+        {
+            let stream: Box<dyn Read + '_> = match format {
+                Format::TarGz => Box::new(GzDecoder::new(BufReader::new(&mut reader))),
+                _ => Box::new(BufReader::new(&mut reader)),
+            };
+            let mut tar = tar::Archive::new(stream);
+            for entry in tar.entries().context(ReadTar)? {
+                let entry = entry.context(ReadTar)?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let path = entry.path().context(ReadTar)?;
+                let name = path.to_str().context(InvalidName {
+                    name: path.to_string_lossy(),
+                })?;
+                entries.insert(
+                    normalize(name),
+                    Entry {
+                        offset: entry.raw_file_position(),
+                        size: entry.size(),
+                        deflated: false,
+                    },
+                );
+            }
+        }
+        Ok(Self::new(reader, format, entries))
+    }
+
+    fn new<R: Read + Seek + Send + 'static>(
+        reader: R,
+        format: Format,
+        entries: HashMap<String, Entry>,
+    ) -> Self {
+        Self {
+            archive: Arc::new(Mutex::new(reader)),
+            format,
+            entries: Arc::new(entries),
+        }
+    }
+
+    /// Returns a reader of the archive's data, as a stream that starts at `offset`.
+    fn read_from(&self, offset: u64) -> SharedRead {
+        SharedRead {
+            archive: Arc::clone(&self.archive),
+            position: offset,
+        }
+    }
+
+    fn open_entry(&self, entry: Entry) -> io::Result<Box<dyn Read + Send>> {
+        Ok(match self.format {
+            Format::Tar => Box::new(self.read_from(entry.offset).take(entry.size)),
+            Format::TarGz => {
+                let mut stream = GzDecoder::new(BufReader::new(self.read_from(0)));
+                // skip to the file in the decompressed stream.
+                let skipped = io::copy(&mut (&mut stream).take(entry.offset), &mut io::sink())?;
+                if skipped < entry.offset {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "archive ended before file",
+                    ));
+                }
+                Box::new(stream.take(entry.size))
+            }
+            Format::Zip => {
+                let data = self.read_from(entry.offset).take(entry.size);
+                if entry.deflated {
+                    Box::new(DeflateDecoder::new(data))
+                } else {
+                    Box::new(data)
+                }
+            }
+        })
+    }
+}
+
+impl Transport for ArchiveTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let entry = match self.entries.get(&entry_name(&url)?) {
+            Some(entry) => *entry,
+            None => return Err(TransportError::new(TransportErrorKind::FileNotFound, url)),
+        };
+        self.open_entry(entry)
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        Ok(self.entries.contains_key(&entry_name(&url)?))
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the name of the file in the archive that `url` refers to.
+fn entry_name(url: &Url) -> Result<String, TransportError> {
+    if url.scheme() != "file" {
+        return Err(TransportError::new(
+            TransportErrorKind::UnsupportedUrlScheme,
+            url,
+        ));
+    }
+    let name = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .map(|segment| percent_decode_str(segment).decode_utf8_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default();
+    Ok(normalize(&name))
+}
+
+/// Normalizes the name of a file in an archive, e.g. `./metadata//root.json` to
+/// `metadata/root.json`.
+fn normalize(name: &str) -> String {
+    name.split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A reader of the shared archive that keeps its own position, so that files can be read from the
+/// archive at the same time, e.g. by different threads.
+struct SharedRead {
+    archive: Arc<Mutex<dyn ReadSeek + Send>>,
+    position: u64,
+}
+
+impl Read for SharedRead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut archive = self
+            .archive
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "archive lock is poisoned"))?;
+        archive.seek(SeekFrom::Start(self.position))?;
+        let size = archive.read(buf)?;
+        self.position += size as u64;
+        Ok(size)
+    }
+}
+
+/// The error type for the archive transport module.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum ArchiveError {
+    #[snafu(display("File name '{}' in archive is not valid UTF-8", name))]
+    InvalidName { name: String },
+
+    #[snafu(display("Unable to open archive '{}': {}", path.display(), source))]
+    Open { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Unable to read tar archive: {}", source))]
+    ReadTar { source: io::Error },
+
+    #[snafu(display("Unable to read zip archive: {}", source))]
+    ReadZip { source: zip::result::ZipError },
+
+    #[snafu(display(
+        "Unable to tell the format of archive '{}', expected .tar, .tar.gz, .tgz or .zip",
+        path.display()
+    ))]
+    UnknownFormat { path: PathBuf },
+
+    #[snafu(display("File '{}' in archive uses unsupported compression {}", name, method))]
+    UnsupportedCompression { name: String, method: String },
+}
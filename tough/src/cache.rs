@@ -1,11 +1,15 @@
 use crate::error::{self, Result};
-use crate::fetch::{fetch_max_size, fetch_sha256};
+use crate::fetch::{fetch_exact, fetch_max_size};
+use crate::io::set_default_permissions;
 use crate::schema::{RoleType, Target};
-use crate::Repository;
+use crate::{Repository, Transport};
 use snafu::{OptionExt, ResultExt};
-use std::fs::OpenOptions;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{ErrorKind, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, PoisonError};
+use tempfile::NamedTempFile;
 
 impl Repository {
     /// Cache an entire or partial repository to disk, including all required metadata.
@@ -28,26 +32,160 @@ impl Repository {
         P2: AsRef<Path>,
         S: AsRef<str>,
     {
-        // Create the output directories if the do not exist.
-        std::fs::create_dir_all(metadata_outdir.as_ref()).context(error::CacheDirectoryCreate {
-            path: metadata_outdir.as_ref(),
-        })?;
-        std::fs::create_dir_all(targets_outdir.as_ref()).context(error::CacheDirectoryCreate {
-            path: targets_outdir.as_ref(),
-        })?;
+        self.cache_with_progress(
+            metadata_outdir,
+            targets_outdir,
+            targets_subset,
+            cache_root_chain,
+            |_, _, _| {},
+        )
+    }
+
+    /// Cache an entire or partial repository to disk, like [`Repository::cache`], calling
+    /// `progress` as each target is saved.
+    ///
+    /// `progress` is called with the name of the target, the number of bytes of it that have been
+    /// saved so far, and its length from the targets metadata. It is called once with zero bytes
+    /// when each target is started, and then after every chunk that is saved, so it can be used to
+    /// update a progress bar.
+    pub fn cache_with_progress<P1, P2, S, F>(
+        &self,
+        metadata_outdir: P1,
+        targets_outdir: P2,
+        targets_subset: Option<&[S]>,
+        cache_root_chain: bool,
+        mut progress: F,
+    ) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+        S: AsRef<str>,
+        F: FnMut(&str, u64, u64),
+    {
+        create_cache_dirs(metadata_outdir.as_ref(), targets_outdir.as_ref())?;
 
         // Fetch targets and save them to the outdir
         if let Some(target_list) = targets_subset {
             for target_name in target_list.iter() {
-                self.cache_target(&targets_outdir, target_name.as_ref())?;
+                self.cache_target(&targets_outdir, target_name.as_ref(), &mut progress)?;
             }
         } else {
-            let targets = &self.targets.signed.targets_map();
-            for target_name in targets.keys() {
-                self.cache_target(&targets_outdir, target_name)?;
+            let targets = self.loaded_targets()?;
+            for target_name in targets.signed.targets_map().keys() {
+                self.cache_target(&targets_outdir, target_name, &mut progress)?;
             }
         }
 
+        self.cache_metadata(metadata_outdir, cache_root_chain)
+    }
+
+    /// Cache an entire or partial repository to disk, like [`Repository::cache`], downloading up to
+    /// `concurrency` targets at a time.
+    ///
+    /// The targets are requested in order, and each is downloaded on one of `concurrency` threads,
+    /// where its length and hash are verified before it is moved into `targets_outdir`, just as
+    /// with [`Repository::cache`]. The metadata is
+    /// saved once all of the targets have been saved.
+    ///
+    /// If any target fails, no more targets are started, the targets that are in progress are
+    /// finished, and the first error is returned. Targets that were saved before the error are left
+    /// in `targets_outdir`, but no metadata is saved, so the output is not a loadable repository.
+    pub fn cache_parallel<P1, P2, S>(
+        &self,
+        metadata_outdir: P1,
+        targets_outdir: P2,
+        targets_subset: Option<&[S]>,
+        cache_root_chain: bool,
+        concurrency: NonZeroUsize,
+    ) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+        S: AsRef<str>,
+    {
+        create_cache_dirs(metadata_outdir.as_ref(), targets_outdir.as_ref())?;
+
+        let targets;
+        let targets_map;
+        let names: Vec<&str> = if let Some(target_list) = targets_subset {
+            target_list.iter().map(AsRef::as_ref).collect()
+        } else {
+            targets = self.loaded_targets()?;
+            targets_map = targets.signed.targets_map();
+            targets_map.keys().map(String::as_str).collect()
+        };
+
+        // The targets are requested in order on this thread, since the transport may not be shared
+        // between threads, and each worker takes the next download and saves it, until there are
+        // none left or until any target has failed.
+        let (sender, receiver) = mpsc::sync_channel::<(usize, &str, TargetDownload)>(0);
+        let (receiver, failed) = (&Mutex::new(receiver), &AtomicBool::new(false));
+        let targets_outdir = targets_outdir.as_ref();
+        let errors: Vec<(usize, error::Error)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..concurrency.get().min(names.len()))
+                .map(|_| {
+                    scope.spawn(move || loop {
+                        let next = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let (index, name, download) = match next {
+                            Ok(next) => next,
+                            Err(_) => return None,
+                        };
+                        if let Err(e) =
+                            save_target(targets_outdir, name, download, &mut |_, _, _| {})
+                        {
+                            failed.store(true, Ordering::SeqCst);
+                            return Some((index, e));
+                        }
+                    })
+                })
+                .collect();
+            let mut errors = Vec::new();
+            for (index, name) in names.iter().enumerate() {
+                if failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                match self.download_target(targets_outdir, name) {
+                    Ok(download) => {
+                        if sender.send((index, name, download)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        errors.push((index, e));
+                        break;
+                    }
+                }
+            }
+            drop(sender);
+            errors.extend(
+                workers
+                    .into_iter()
+                    .filter_map(|worker| match worker.join() {
+                        Ok(result) => result,
+                        Err(panic) => std::panic::resume_unwind(panic),
+                    }),
+            );
+            errors
+        });
+        // Report the error for the earliest target in the list, so that the error does not depend
+        // on the order in which the workers happened to fail.
+        if let Some((_, e)) = errors.into_iter().min_by_key(|(index, _)| *index) {
+            return Err(e);
+        }
+
+        self.cache_metadata(metadata_outdir, cache_root_chain)
+    }
+
+    /// Saves the snapshot, targets and timestamp metadata files, the metadata files of delegated
+    /// roles, and (optionally) the root files to `metadata_outdir`.
+    fn cache_metadata<P: AsRef<Path>>(
+        &self,
+        metadata_outdir: P,
+        cache_root_chain: bool,
+    ) -> Result<()> {
         // Save the snapshot, targets and timestamp metadata files, and (optionally) the root files.
         self.cache_file_from_transport(
             self.snapshot_filename().as_str(),
@@ -68,7 +206,7 @@ impl Repository {
             &metadata_outdir,
         )?;
 
-        for name in self.targets.signed.role_names() {
+        for name in self.loaded_targets()?.signed.role_names() {
             if let Some(filename) = self.delegated_filename(name) {
                 self.cache_file_from_transport(
                     filename.as_str(),
@@ -139,12 +277,7 @@ impl Repository {
     ) -> Result<()> {
         let mut read = fetch_max_size(
             self.transport.as_ref(),
-            self.metadata_base_url
-                .join(filename)
-                .context(error::JoinUrl {
-                    path: filename,
-                    url: self.metadata_base_url.to_owned(),
-                })?,
+            crate::join_url(&self.metadata_base_url, filename)?,
             max_size,
             max_size_specifier,
         )?;
@@ -163,27 +296,37 @@ impl Repository {
 
     /// Saves a signed target to the specified `outdir`. Retains the digest-prepended filename if
     /// consistent snapshots are used.
-    fn cache_target<P: AsRef<Path>>(&self, outdir: P, name: &str) -> Result<()> {
-        let t = self
-            .targets
-            .signed
-            .find_target(name)
+    fn cache_target<P: AsRef<Path>>(
+        &self,
+        outdir: P,
+        name: &str,
+        progress: &mut dyn FnMut(&str, u64, u64),
+    ) -> Result<()> {
+        let download = self.download_target(outdir.as_ref(), name)?;
+        save_target(outdir.as_ref(), name, download, progress)
+    }
+
+    /// Starts fetching the signed target `name`, to be saved to `outdir` with [`save_target`].
+    fn download_target(&self, outdir: &Path, name: &str) -> Result<TargetDownload> {
+        crate::check_target_name(name)?;
+        let (_, t) = self
+            .resolve_target(name)?
+            .ok_or_else(|| crate::schema::Error::TargetNotFound {
+                target_file: name.to_owned(),
+            })
             .context(error::CacheTargetMissing {
                 target_name: name.to_owned(),
             })?;
-        let (sha, filename) = self.target_digest_and_filename(&t, name);
-        let mut reader = self.fetch_target(t, &sha, filename.as_str())?;
-        let path = outdir.as_ref().join(filename);
-        let mut f = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&path)
-            .context(error::CacheTargetWrite { path: path.clone() })?;
-        let _ = std::io::copy(&mut reader, &mut f).context(error::CacheTargetWrite { path })?;
-        Ok(())
+        let filename = self.target_filename(&t, name);
+        Ok(TargetDownload {
+            reader: Box::new(self.fetch_target(t, filename.as_str())?),
+            path: outdir.join(filename),
+            length: t.length,
+        })
     }
 
-    /// Gets the max size of the snapshot.json file as specified by the timestamp file.
+    /// Gets the max size of the snapshot.json file as specified by the timestamp file, capped by
+    /// the `max_snapshot_size` limit.
     fn max_snapshot_size(&self) -> Result<u64> {
         let snapshot_meta =
             self.timestamp()
@@ -194,43 +337,102 @@ impl Repository {
                     file: "snapshot.json",
                     role: RoleType::Timestamp,
                 })?;
-        Ok(snapshot_meta.length)
+        Ok(snapshot_meta.length.min(self.limits.max_snapshot_size))
     }
 
-    /// Prepends the target digest to the name if using consistent snapshots. Returns both the
-    /// digest and the filename.
-    pub(crate) fn target_digest_and_filename(
-        &self,
-        target: &Target,
-        name: &str,
-    ) -> (Vec<u8>, String) {
-        let sha256 = &target.hashes.sha256.clone().into_vec();
-        if self.consistent_snapshot {
-            (sha256.clone(), format!("{}.{}", hex::encode(sha256), name))
-        } else {
-            (sha256.clone(), name.to_owned())
+    /// Returns the transport used to fetch targets: the targets transport if one was set, otherwise
+    /// the metadata transport.
+    fn targets_transport(&self) -> &dyn Transport {
+        self.targets_transport
+            .as_deref()
+            .unwrap_or(self.transport.as_ref())
+    }
+
+    /// Prepends the target digest to the name if using consistent snapshots. The SHA-256 digest is
+    /// used if the target has one, otherwise the SHA-512 digest.
+    pub(crate) fn target_filename(&self, target: &Target, name: &str) -> String {
+        match target.hashes.consistent_snapshot_digest() {
+            Some(digest) if self.consistent_snapshot => format!("{}.{}", hex::encode(digest), name),
+            _ => name.to_owned(),
         }
     }
 
-    /// Fetches the signed target using `Transport`. Aborts with error if the fetched target is
-    /// larger than its signed size.
-    pub(crate) fn fetch_target(
-        &self,
-        target: &Target,
-        digest: &[u8],
-        filename: &str,
-    ) -> Result<impl Read> {
-        fetch_sha256(
-            self.transport.as_ref(),
-            self.targets_base_url
-                .join(&filename)
-                .context(error::JoinUrl {
-                    path: filename,
-                    url: self.targets_base_url.to_owned(),
-                })?,
+    /// Fetches the signed target using the targets `Transport`. The reader returns an error as soon
+    /// as the fetched target is larger than its signed length, or at the end of the target if it is
+    /// shorter than its signed length or its hash does not match.
+    pub(crate) fn fetch_target(&self, target: &Target, filename: &str) -> Result<impl Read + Send> {
+        fetch_exact(
+            self.targets_transport(),
+            crate::join_url(&self.targets_base_url, filename)?,
             target.length,
             "targets.json",
-            digest,
+            &target.hashes,
         )
     }
 }
+
+/// A target that is being fetched to be cached.
+struct TargetDownload {
+    /// Checks the length and hash of the target as it is read.
+    reader: Box<dyn Read + Send>,
+    /// The path that the target is saved to.
+    path: PathBuf,
+    /// The length of the target in the targets metadata.
+    length: u64,
+}
+
+/// Saves the target `name` that is being fetched with `download`.
+///
+/// The target is downloaded to a staging file in `outdir` and is only renamed to its final path
+/// once its length and hash have been verified, so a partial or unverified target is never
+/// observed at the final path.
+fn save_target(
+    outdir: &Path,
+    name: &str,
+    download: TargetDownload,
+    progress: &mut dyn FnMut(&str, u64, u64),
+) -> Result<()> {
+    let TargetDownload {
+        mut reader,
+        path,
+        length,
+    } = download;
+    // The staging file is in the same directory as the final path so that the rename is atomic.
+    // It is deleted when dropped if we return early.
+    let mut staging =
+        NamedTempFile::new_in(outdir).context(error::CacheTargetWrite { path: path.clone() })?;
+    // The reader checks the length and hash as it goes, and returns an error at the end of the
+    // stream if the hash does not match.
+    let mut buf = [0; 8 * 1024];
+    let mut done = 0;
+    progress(name, done, length);
+    loop {
+        let size = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context(error::CacheTargetWrite { path }),
+        };
+        staging
+            .write_all(&buf[..size])
+            .context(error::CacheTargetWrite { path: path.clone() })?;
+        done += size as u64;
+        progress(name, done, length);
+    }
+    set_default_permissions(&staging).context(error::CacheTargetWrite { path: path.clone() })?;
+    staging
+        .persist(&path)
+        .map_err(|e| e.error)
+        .context(error::CacheTargetWrite { path })?;
+    Ok(())
+}
+
+/// Creates the output directories for a cached repository if they do not exist.
+fn create_cache_dirs(metadata_outdir: &Path, targets_outdir: &Path) -> Result<()> {
+    std::fs::create_dir_all(metadata_outdir).context(error::CacheDirectoryCreate {
+        path: metadata_outdir,
+    })?;
+    std::fs::create_dir_all(targets_outdir).context(error::CacheDirectoryCreate {
+        path: targets_outdir,
+    })
+}
@@ -0,0 +1,46 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides the [`Clock`] trait, which a [`Repository`](crate::Repository) uses to get the current
+//! time when checking whether metadata has expired.
+
+use chrono::{DateTime, Utc};
+use dyn_clone::DynClone;
+use std::fmt::Debug;
+
+/// A source of the current time, which is compared with the expiration times of metadata.
+///
+/// [`SystemClock`] is used unless another is set with
+/// [`RepositoryLoader::clock`](crate::RepositoryLoader::clock). Setting a [`FixedClock`] allows
+/// expiration to be tested deterministically.
+///
+/// Inclusion of the `DynClone` trait means that you will need to implement `Clone` when
+/// implementing a `Clock`.
+pub trait Clock: Debug + DynClone + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// Implements `Clone` for `Clock` trait objects (i.e. on `Box::<dyn Clone>`). To facilitate this,
+// `Clone` needs to be implemented for any `Clock`s. The compiler will enforce this.
+dyn_clone::clone_trait_object!(Clock);
+
+/// A [`Clock`] that returns the system time, with `Utc::now()`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A [`Clock`] that always returns the same time.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
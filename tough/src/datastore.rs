@@ -1,75 +1,170 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+//! Provides the [`Datastore`] trait, which a [`Repository`](crate::Repository) uses to keep the
+//! metadata it trusts between loads, and [`FilesystemDatastore`], which keeps it in a directory.
+
 use crate::error::{self, Result};
+use dyn_clone::DynClone;
 use log::debug;
 use serde::Serialize;
 use snafu::ResultExt;
-use std::fs::{self, File};
-use std::io::{ErrorKind, Read};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, Cursor, ErrorKind, Read};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tempfile::TempDir;
 
+/// Storage for the most recently trusted timestamp, snapshot, and targets metadata (and the latest
+/// known system time), which a [`Repository`](crate::Repository) uses to detect rollback attacks.
+///
+/// Values are stored by key, such as `timestamp.json`. Implementations can keep them anywhere, such
+/// as in a directory ([`FilesystemDatastore`]), in memory ([`MemoryDatastore`]), or in a key-value
+/// store.
+///
+/// Inclusion of the `DynClone` trait means that you will need to implement `Clone` when
+/// implementing a `Datastore`.
+pub trait Datastore: Debug + DynClone + Send + Sync {
+    /// Returns the value stored at `key`, or `None` if there is no value.
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores `value` at `key`, replacing any value that is already there.
+    fn write(&self, key: &str, value: &[u8]) -> io::Result<()>;
+
+    /// Removes the value stored at `key`. Removing a key that has no value is not an error.
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+// Implements `Clone` for `Datastore` trait objects (i.e. on `Box::<dyn Clone>`). To facilitate
+// this, `Clone` needs to be implemented for any `Datastore`s. The compiler will enforce this.
+dyn_clone::clone_trait_object!(Datastore);
+
+impl dyn Datastore + '_ {
+    pub(crate) fn reader(&self, key: &str) -> Result<Option<impl Read>> {
+        Ok(self
+            .read(key)
+            .context(error::DatastoreOpen { key })?
+            .map(Cursor::new))
+    }
+
+    pub(crate) fn create<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let value = serde_json::to_vec_pretty(value).context(error::DatastoreSerialize {
+            what: format!("{} in datastore", key),
+            key,
+        })?;
+        self.write(key, &value)
+            .context(error::DatastoreCreate { key })
+    }
+
+    pub(crate) fn remove(&self, key: &str) -> Result<()> {
+        debug!("removing '{}' from datastore", key);
+        self.delete(key).context(error::DatastoreRemove { key })
+    }
+}
+
+/// A [`Datastore`] that stores each value as a file in a directory, named by its key.
+///
+/// This is the default datastore. If no directory is given, a temporary directory is created, and
+/// is removed when the last clone of the `FilesystemDatastore` is dropped.
 #[derive(Debug, Clone)]
-pub(crate) struct Datastore(Arc<RwLock<DatastorePath>>);
-
-impl Datastore {
-    pub(crate) fn new(path: Option<PathBuf>) -> Result<Self> {
-        // using pattern matching instead of mapping because TempDir::new() can error
-        Ok(Self(Arc::new(RwLock::new(match path {
-            None => DatastorePath::TempDir(TempDir::new().context(error::DatastoreInit)?),
-            Some(p) => DatastorePath::Path(p),
-        }))))
+pub struct FilesystemDatastore(Arc<RwLock<DatastorePath>>);
+
+impl FilesystemDatastore {
+    /// Creates a `FilesystemDatastore` that stores files in `path`, which must be a directory on a
+    /// persistent filesystem that exists prior to calling [`RepositoryLoader::load`].
+    ///
+    /// [`RepositoryLoader::load`]: crate::RepositoryLoader::load
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self(Arc::new(RwLock::new(DatastorePath::Path(path.into()))))
+    }
+
+    /// Creates a `FilesystemDatastore` that stores files in a new temporary directory.
+    pub fn temporary() -> Result<Self> {
+        Ok(Self(Arc::new(RwLock::new(DatastorePath::TempDir(
+            TempDir::new().context(error::DatastoreInit)?,
+        )))))
     }
 
     // Because we are not actually changing the underlying data in the lock, we can ignore when a
     // lock is poisoned.
 
-    fn read(&self) -> RwLockReadGuard<'_, DatastorePath> {
+    fn read_lock(&self) -> RwLockReadGuard<'_, DatastorePath> {
         self.0.read().unwrap_or_else(PoisonError::into_inner)
     }
 
-    fn write(&self) -> RwLockWriteGuard<'_, DatastorePath> {
+    fn write_lock(&self) -> RwLockWriteGuard<'_, DatastorePath> {
         self.0.write().unwrap_or_else(PoisonError::into_inner)
     }
+}
 
-    pub(crate) fn reader(&self, file: &str) -> Result<Option<impl Read>> {
-        let path = self.read().path().join(file);
-        match File::open(&path) {
-            Ok(file) => Ok(Some(file)),
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => Ok(None),
-                _ => Err(err).context(error::DatastoreOpen { path: &path }),
-            },
+impl Datastore for FilesystemDatastore {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = self.read_lock().path().join(key);
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(with_path(err, &path)),
         }
     }
 
-    pub(crate) fn create<T: Serialize>(&self, file: &str, value: &T) -> Result<()> {
-        let path = self.write().path().join(file);
-        serde_json::to_writer_pretty(
-            File::create(&path).context(error::DatastoreCreate { path: &path })?,
-            value,
-        )
-        .context(error::DatastoreSerialize {
-            what: format!("{} in datastore", file),
-            path,
-        })
+    fn write(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        let path = self.write_lock().path().join(key);
+        fs::write(&path, value).map_err(|err| with_path(err, &path))
     }
 
-    pub(crate) fn remove(&self, file: &str) -> Result<()> {
-        let path = self.write().path().join(file);
-        debug!("removing '{}'", path.display());
+    fn delete(&self, key: &str) -> io::Result<()> {
+        let path = self.write_lock().path().join(key);
         match fs::remove_file(&path) {
             Ok(()) => Ok(()),
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => Ok(()),
-                _ => Err(err).context(error::DatastoreRemove { path: &path }),
-            },
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(with_path(err, &path)),
         }
     }
 }
 
+/// A [`Datastore`] that keeps values in memory, for tests and short-lived clients that don't need
+/// to keep trusted metadata between runs.
+///
+/// Clones of a `MemoryDatastore` share the same values, so a clone can be passed to
+/// [`RepositoryLoader::datastore_backend`] and the values can still be read from the original.
+///
+/// [`RepositoryLoader::datastore_backend`]: crate::RepositoryLoader::datastore_backend
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDatastore(Arc<RwLock<HashMap<String, Vec<u8>>>>);
+
+impl MemoryDatastore {
+    /// Creates an empty `MemoryDatastore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Datastore for MemoryDatastore {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let values = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(values.get(key).cloned())
+    }
+
+    fn write(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        let mut values = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        values.insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        let mut values = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        values.remove(key);
+        Ok(())
+    }
+}
+
+/// Adds the path of the file to an error, since `std::fs` errors don't include it.
+fn with_path(err: io::Error, path: &Path) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
+
 /// Because `TempDir` is an RAII object, we need to hold on to it. This private enum allows us to
 /// hold either a `TempDir` or a `PathBuf` depending on whether or not the user wants to manage the
 /// directory.
@@ -0,0 +1,355 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides loading of the metadata of delegated targets roles.
+//!
+//! By default, `Repository::load` loads the whole delegation tree. If lazy loading is enabled with
+//! `RepositoryLoader::lazy_delegated_roles`, it only loads the top-level roles, and the metadata of
+//! a delegated role is fetched and verified the first time that the search for a target reaches
+//! it, and is kept for later searches, so a repository with many delegated roles (such as hashed
+//! bins) does not fetch them all up front.
+
+use crate::error::{self, Result};
+#[cfg(feature = "async")]
+use crate::fetch::fetch_metadata_async;
+use crate::schema::{DelegatedRole, Delegations, RoleSearch, RoleType, Signed, Snapshot, Target};
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+#[cfg(feature = "async")]
+use crate::{delegated_role_fetch, verify_delegated_role};
+use crate::{load_delegated_role, verify_delegated, Repository};
+use once_cell::sync::OnceCell;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+
+/// The metadata of the delegated roles of a repository, keyed by role name. There is a cell for
+/// each role that is listed in the snapshot, which is filled when the role is first loaded.
+pub(crate) type DelegatedMetadata = HashMap<String, OnceCell<LoadedRole>>;
+
+/// The verified metadata of a delegated role.
+#[derive(Debug, Clone)]
+pub(crate) struct LoadedRole {
+    /// The name of the role whose delegation the metadata was verified against.
+    parent: String,
+    targets: Signed<crate::schema::Targets>,
+}
+
+/// Returns an empty cell for each delegated role that `snapshot` lists.
+pub(crate) fn delegated_metadata(snapshot: &Signed<Snapshot>) -> DelegatedMetadata {
+    snapshot
+        .signed
+        .meta
+        .keys()
+        .filter_map(|file| file.strip_suffix(".json"))
+        .filter(|name| *name != "targets")
+        .map(|name| (name.to_owned(), OnceCell::new()))
+        .collect()
+}
+
+impl Repository {
+    /// Finds the target `name`, loading the metadata of the delegated roles that the search
+    /// reaches. Returns the chain of delegated roles that ends with the role that lists the
+    /// target, and the target, or `None` if no role signs for it.
+    pub(crate) fn resolve_target(
+        &self,
+        name: &str,
+    ) -> Result<Option<(Vec<&DelegatedRole>, &Target)>> {
+        let search = self.targets.signed.search_signing_role_with(
+            name,
+            &mut Vec::new(),
+            &mut |delegations, role, ancestors| {
+                self.delegated_targets(delegations, role, ancestors)
+                    .map(Some)
+            },
+        )?;
+        Ok(match search {
+            RoleSearch::Found(chain, target) => Some((chain, target)),
+            RoleSearch::NotFound | RoleSearch::Terminated => None,
+        })
+    }
+
+    /// Returns the metadata of `role`, which is listed in `delegations`. `ancestors` holds the names
+    /// of the roles between the top-level targets role and `role`.
+    ///
+    /// If the metadata has not been loaded yet, it is fetched and verified now, and kept for later
+    /// calls.
+    fn delegated_targets<'a>(
+        &'a self,
+        delegations: &'a Delegations,
+        role: &'a DelegatedRole,
+        ancestors: &[&str],
+    ) -> Result<&'a Signed<crate::schema::Targets>> {
+        if let Some(targets) = &role.targets {
+            return Ok(targets);
+        }
+
+        let parent = ancestors.last().copied().unwrap_or("targets");
+        ensure!(
+            role.name != "targets" && !ancestors.contains(&role.name.as_str()),
+            error::DelegationCycle {
+                parent,
+                name: role.name.clone(),
+            }
+        );
+        ensure!(
+            ancestors.len() < self.limits.max_delegation_depth,
+            error::MaxDelegationDepthExceeded {
+                name: role.name.clone(),
+                max_delegation_depth: self.limits.max_delegation_depth,
+            }
+        );
+
+        let cell = self
+            .delegated
+            .get(&role.name)
+            .context(error::RoleNotInMeta {
+                name: role.name.clone(),
+            })?;
+        let loaded = cell.get_or_try_init(|| -> Result<LoadedRole> {
+            Ok(LoadedRole {
+                parent: parent.to_owned(),
+                targets: load_delegated_role(
+                    self.transport.as_ref(),
+                    &self.snapshot,
+                    self.consistent_snapshot,
+                    &self.metadata_base_url,
+                    self.limits.max_targets_size,
+                    delegations,
+                    role,
+                    self.datastore.as_ref(),
+                    self.on_metadata_fetched.as_ref(),
+                    self.signature_verifier.as_ref(),
+                )?,
+            })
+        })?;
+        // The metadata was verified against the keys of the role that delegated to it when it was
+        // loaded. If another role also delegates to it, check it against that role's keys too.
+        if loaded.parent != parent {
+            verify_delegated(
+                delegations,
+                &loaded.targets,
+                &role.name,
+                self.signature_verifier.as_ref(),
+            )
+            .context(error::VerifyMetadata {
+                role: RoleType::Targets,
+            })?;
+        }
+        Ok(&loaded.targets)
+    }
+
+    /// Finds the delegated role `name` in the delegation tree, and returns it with its metadata, or
+    /// `None` if no role delegates to it. The roles that each level of the tree delegates to are
+    /// checked before any of them is loaded, and only the roles that must be searched to reach
+    /// `name` are loaded.
+    pub(crate) fn find_delegated_role(
+        &self,
+        name: &str,
+    ) -> Result<Option<(&DelegatedRole, &Signed<crate::schema::Targets>)>> {
+        match &self.targets.signed.delegations {
+            Some(delegations) => self.find_delegated_role_in(delegations, name, &mut Vec::new()),
+            None => Ok(None),
+        }
+    }
+
+    fn find_delegated_role_in<'a>(
+        &'a self,
+        delegations: &'a Delegations,
+        name: &str,
+        ancestors: &mut Vec<&'a str>,
+    ) -> Result<Option<(&'a DelegatedRole, &'a Signed<crate::schema::Targets>)>> {
+        if let Some(role) = delegations.roles_iter().find(|role| role.name == name) {
+            let targets = self.delegated_targets(delegations, role, ancestors)?;
+            return Ok(Some((role, targets)));
+        }
+        for role in delegations.roles_iter() {
+            let targets = self.delegated_targets(delegations, role, ancestors)?;
+            if let Some(child_delegations) = &targets.signed.delegations {
+                ancestors.push(&role.name);
+                let found = self.find_delegated_role_in(child_delegations, name, ancestors)?;
+                ancestors.pop();
+                if found.is_some() {
+                    return Ok(found);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Adds the names of the targets that start with `prefix` to `names`, from `targets` and from
+    /// the roles it delegates to whose paths could match the prefix, loading their metadata if it
+    /// has not been loaded yet. `ancestors` holds the names of the roles between the top-level
+    /// targets role and `targets`.
+    pub(crate) fn collect_prefixed_names<'a>(
+        &'a self,
+        targets: &'a crate::schema::Targets,
+        prefix: &str,
+        ancestors: &mut Vec<&'a str>,
+        names: &mut BTreeSet<&'a str>,
+    ) -> Result<()> {
+        names.extend(
+            targets
+                .targets
+                .keys()
+                .map(String::as_str)
+                .filter(|name| name.starts_with(prefix)),
+        );
+        if let Some(delegations) = &targets.delegations {
+            for role in delegations
+                .roles_iter()
+                .filter(|role| role.paths.could_match_prefix(prefix))
+            {
+                let role_targets = self.delegated_targets(delegations, role, ancestors)?;
+                ancestors.push(&role.name);
+                self.collect_prefixed_names(&role_targets.signed, prefix, ancestors, names)?;
+                ancestors.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the metadata of every delegated role that has not been loaded yet, so that the whole
+    /// delegation tree is available from [`Repository::targets`], [`Repository::delegated_roles`],
+    /// [`Repository::target_names`] and the other methods that list every role or target.
+    ///
+    /// This is only needed if [`RepositoryLoader::lazy_delegated_roles`] was enabled; otherwise
+    /// the whole tree is loaded along with the repository. Searching for a single target, e.g. with
+    /// [`Repository::read_target`], only loads the roles that the search reaches, and does not need
+    /// this.
+    ///
+    /// [`RepositoryLoader::lazy_delegated_roles`]: crate::RepositoryLoader::lazy_delegated_roles
+    pub fn load_delegated_roles(&mut self) -> Result<()> {
+        self.targets = self.loaded_targets()?;
+        Ok(())
+    }
+
+    /// Returns a copy of the top-level targets metadata with the metadata of every delegated role
+    /// loaded into it.
+    pub(crate) fn loaded_targets(&self) -> Result<Signed<crate::schema::Targets>> {
+        let mut targets = self.targets.clone();
+        if let Some(delegations) = &mut targets.signed.delegations {
+            self.load_delegation_tree(delegations, &mut Vec::new())?;
+        }
+        Ok(targets)
+    }
+
+    /// Loads the metadata of each role in `delegations`, and of the roles that they delegate to,
+    /// into `delegations`.
+    fn load_delegation_tree(
+        &self,
+        delegations: &mut Delegations,
+        ancestors: &mut Vec<String>,
+    ) -> Result<()> {
+        let names: Vec<&str> = ancestors.iter().map(String::as_str).collect();
+        let mut loaded = Vec::new();
+        for role in delegations.roles_iter() {
+            loaded.push(match role.targets {
+                Some(_) => None,
+                None => Some(self.delegated_targets(delegations, role, &names)?.clone()),
+            });
+        }
+        for (role, targets) in delegations.roles_iter_mut().zip(loaded) {
+            if targets.is_some() {
+                role.targets = targets;
+            }
+            if let Some(child_delegations) = role
+                .targets
+                .as_mut()
+                .and_then(|targets| targets.signed.delegations.as_mut())
+            {
+                ancestors.push(role.name.clone());
+                self.load_delegation_tree(child_delegations, ancestors)?;
+                ancestors.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches the metadata of every delegated role that has not been loaded yet with `transport`,
+    /// in the same order as [`Repository::load_delegated_roles`], so that it then finds every role
+    /// loaded. Roles that would form a cycle or exceed the maximum delegation depth are skipped,
+    /// and are reported by `load_delegated_roles`.
+    #[cfg(feature = "async")]
+    pub(crate) async fn fetch_delegated_roles_async(
+        &self,
+        transport: &dyn AsyncTransport,
+    ) -> Result<()> {
+        match &self.targets.signed.delegations {
+            Some(delegations) => {
+                self.fetch_delegation_tree_async(transport, delegations, Vec::new())
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Fetches the metadata of each role in `delegations`, and of the roles that they delegate to.
+    /// `ancestors` holds the names of the roles between the top-level targets role and
+    /// `delegations`.
+    #[cfg(feature = "async")]
+    fn fetch_delegation_tree_async<'a>(
+        &'a self,
+        transport: &'a dyn AsyncTransport,
+        delegations: &'a Delegations,
+        ancestors: Vec<&'a str>,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>> {
+        Box::pin(async move {
+            let parent = ancestors.last().copied().unwrap_or("targets");
+            let reachable = |role: &DelegatedRole| {
+                role.name != "targets"
+                    && !ancestors.contains(&role.name.as_str())
+                    && ancestors.len() < self.limits.max_delegation_depth
+            };
+            for role in delegations.roles_iter() {
+                let cell = match self.delegated.get(&role.name) {
+                    Some(cell) if role.targets.is_none() && reachable(role) => cell,
+                    _ => continue,
+                };
+                if cell.get().is_some() {
+                    continue;
+                }
+                let fetch = delegated_role_fetch(
+                    &self.snapshot,
+                    self.consistent_snapshot,
+                    &self.metadata_base_url,
+                    self.limits.max_targets_size,
+                    role,
+                )?;
+                let targets = verify_delegated_role(
+                    fetch_metadata_async(transport, &fetch).await?,
+                    &self.snapshot,
+                    self.consistent_snapshot,
+                    delegations,
+                    role,
+                    self.datastore.as_ref(),
+                    self.on_metadata_fetched.as_ref(),
+                    self.signature_verifier.as_ref(),
+                )?;
+                // The cell was empty before the fetch, and nothing else fills it meanwhile.
+                let _ = cell.set(LoadedRole {
+                    parent: parent.to_owned(),
+                    targets,
+                });
+            }
+            for role in delegations.roles_iter().filter(|role| reachable(role)) {
+                let targets = match (&role.targets, self.delegated.get(&role.name)) {
+                    (Some(targets), _) => targets,
+                    (None, Some(cell)) => match cell.get() {
+                        Some(loaded) => &loaded.targets,
+                        None => continue,
+                    },
+                    (None, None) => continue,
+                };
+                if let Some(child_delegations) = &targets.signed.delegations {
+                    let mut ancestors = ancestors.clone();
+                    ancestors.push(&role.name);
+                    self.fetch_delegation_tree_async(transport, child_delegations, ancestors)
+                        .await?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::schema::{Target, Targets};
+use std::collections::HashMap;
+
+/// What [`Repository::refresh`](crate::Repository::refresh) found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshOutcome {
+    /// Whether new metadata was loaded, even if it lists the same targets.
+    pub updated: bool,
+    /// The targets that were added, removed or changed by the new metadata.
+    pub changes: TargetChanges,
+}
+
+/// The targets that differ between two states of a repository, as returned by
+/// [`Repository::refresh`](crate::Repository::refresh).
+///
+/// Each list of target names is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetChanges {
+    /// Targets that are listed now but were not listed before.
+    pub added: Vec<String>,
+    /// Targets that were listed before but are no longer listed.
+    pub removed: Vec<String>,
+    /// Targets that are listed in both, but whose length, hashes or custom metadata differ.
+    pub changed: Vec<String>,
+}
+
+impl TargetChanges {
+    /// Returns `true` if no targets were added, removed or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Compares the targets of `current` with the targets of `previous`. If `delegated` is `true`,
+    /// the delegated targets that are loaded into each are compared too; otherwise only the
+    /// targets that each lists itself are compared.
+    pub(crate) fn between(previous: &Targets, current: &Targets, delegated: bool) -> Self {
+        let current = compared_targets(current, delegated);
+        let previous = compared_targets(previous, delegated);
+        let mut changes = Self::default();
+        for (name, target) in &current {
+            match previous.get(name) {
+                None => changes.added.push(name.clone()),
+                Some(old) if old != target => changes.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        for name in previous.keys() {
+            if !current.contains_key(name) {
+                changes.removed.push(name.clone());
+            }
+        }
+        changes.added.sort();
+        changes.removed.sort();
+        changes.changed.sort();
+        changes
+    }
+}
+
+/// Returns the targets of `targets` by name, including the delegated targets that are loaded into
+/// it if `delegated` is `true`.
+fn compared_targets(targets: &Targets, delegated: bool) -> HashMap<String, &Target> {
+    if delegated {
+        targets.targets_map()
+    } else {
+        targets
+            .targets
+            .iter()
+            .map(|(name, target)| (name.clone(), target))
+            .collect()
+    }
+}
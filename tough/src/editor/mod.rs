@@ -5,6 +5,7 @@
 //! Provides a `RepositoryEditor` object for building and editing TUF repositories.
 
 mod keys;
+pub mod root;
 pub mod signed;
 pub mod targets;
 mod test;
@@ -17,13 +18,13 @@ use crate::key_source::KeySource;
 use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::key::Key;
 use crate::schema::{
-    Hashes, KeyHolder, PathSet, Role, RoleType, Root, Signed, Snapshot, SnapshotMeta, Target,
-    Targets, Timestamp, TimestampMeta,
+    Hashes, KeyHolder, PathSet, Role, RoleType, Root, Signature, Signed, Snapshot, SnapshotMeta,
+    Target, Targets, Timestamp, TimestampMeta,
 };
 use crate::transport::Transport;
 use crate::Limits;
-use crate::Repository;
-use chrono::{DateTime, Utc};
+use crate::{parse_metadata, Repository};
+use chrono::{DateTime, Duration, Utc};
 use ring::digest::{SHA256, SHA256_OUTPUT_LEN};
 use ring::rand::SystemRandom;
 use serde_json::Value;
@@ -62,6 +63,61 @@ const SPEC_VERSION: &str = "1.0.0";
 /// To update an existing targets from a metadata file use `update_delegated_targets()`.
 ///
 /// To add a new role from metadata to the `Targets` in `TargetsEditor` use `add_role()`.
+///
+/// # Example
+///
+/// Create a repository with one target, sign it, write it to disk, and load it:
+///
+/// ```rust
+/// # use chrono::{Duration, Utc};
+/// # use std::fs::File;
+/// # use std::num::NonZeroU64;
+/// # use std::path::PathBuf;
+/// # use tempfile::TempDir;
+/// # use tough::editor::signed::PathExists;
+/// # use tough::editor::RepositoryEditor;
+/// # use tough::key_source::{KeySource, LocalKeySource};
+/// # use tough::RepositoryLoader;
+/// # use url::Url;
+/// # let data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("data");
+/// # let root = data.join("simple-rsa").join("root.json");
+/// # let key_path = data.join("snakeoil.pem");
+/// # let targets_indir = data.join("tuf-reference-impl").join("targets");
+/// # let outdir = TempDir::new().unwrap();
+/// let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path })];
+/// let expires = Utc::now() + Duration::days(7);
+/// let version = NonZeroU64::new(1).unwrap();
+///
+/// let mut editor = RepositoryEditor::new(&root).unwrap();
+/// editor
+///     .targets_version(version)
+///     .unwrap()
+///     .targets_expires(expires)
+///     .unwrap()
+///     .snapshot_version(version)
+///     .snapshot_expires(expires)
+///     .timestamp_version(version)
+///     .timestamp_expires(expires)
+///     .add_target_path(targets_indir.join("file1.txt"))
+///     .unwrap();
+/// let signed_repo = editor.sign(&keys).unwrap();
+///
+/// let metadata_dir = outdir.path().join("metadata");
+/// let targets_dir = outdir.path().join("targets");
+/// signed_repo.write(&metadata_dir).unwrap();
+/// signed_repo
+///     .copy_targets(&targets_indir, &targets_dir, PathExists::Skip)
+///     .unwrap();
+///
+/// let repo = RepositoryLoader::new(
+///     File::open(&root).unwrap(),
+///     Url::from_directory_path(&metadata_dir).unwrap(),
+///     Url::from_directory_path(&targets_dir).unwrap(),
+/// )
+/// .load()
+/// .unwrap();
+/// assert!(repo.read_target("file1.txt").unwrap().is_some());
+/// ```
 #[derive(Debug)]
 pub struct RepositoryEditor {
     signed_root: SignedRole<Root>,
@@ -81,6 +137,33 @@ pub struct RepositoryEditor {
 
     transport: Option<Box<dyn Transport>>,
     limits: Option<Limits>,
+
+    /// Roles that are being signed out-of-process, see `signing_payload()`
+    detached: DetachedRoles,
+
+    /// Versions of the existing metadata, see `bump_version()`
+    existing_versions: ExistingVersions,
+}
+
+/// The roles of a `RepositoryEditor` that are being signed out-of-process. Each role is built only
+/// once, when its signing payload is first requested, so that the metadata that is assembled
+/// matches the bytes that were signed.
+#[derive(Debug, Default)]
+struct DetachedRoles {
+    root: Option<Signed<Root>>,
+    targets: Option<Signed<Targets>>,
+    snapshot: Option<Signed<Snapshot>>,
+    timestamp: Option<Signed<Timestamp>>,
+}
+
+/// The versions of the existing metadata that was added to a `RepositoryEditor`, e.g. by
+/// `from_repo()`. These are kept, though not used for the new metadata, so that `bump_version()`
+/// can increment them.
+#[derive(Debug, Default)]
+struct ExistingVersions {
+    targets: Option<NonZeroU64>,
+    snapshot: Option<NonZeroU64>,
+    timestamp: Option<NonZeroU64>,
 }
 
 impl RepositoryEditor {
@@ -133,6 +216,8 @@ impl RepositoryEditor {
             signed_targets: None,
             transport: None,
             limits: None,
+            detached: DetachedRoles::default(),
+            existing_versions: ExistingVersions::default(),
         })
     }
 
@@ -145,7 +230,7 @@ impl RepositoryEditor {
         P: AsRef<Path>,
     {
         let mut editor = RepositoryEditor::new(root_path)?;
-        editor.targets(repo.targets)?;
+        editor.targets(repo.loaded_targets()?)?;
         editor.snapshot(repo.snapshot.signed)?;
         editor.timestamp(repo.timestamp.signed)?;
         editor.transport = Some(repo.transport.clone());
@@ -165,27 +250,9 @@ impl RepositoryEditor {
         // Sign the targets editor if able to with the provided keys
         self.sign_targets_editor(keys)?;
         let targets = self.signed_targets.clone().context(error::NoTargets)?;
-        let delegated_targets = targets.signed.signed_delegated_targets();
+        let signed_delegated_targets = self.signed_delegated_targets(&targets.signed)?;
         let signed_targets = SignedRole::from_signed(targets)?;
 
-        let signed_delegated_targets = if delegated_targets.is_empty() {
-            // If we don't have any delegated targets, there is no reason to create
-            // a `SignedDelegatedTargets`
-            None
-        } else {
-            // If we have delegated targets
-            let mut roles = Vec::new();
-            for role in delegated_targets {
-                // Create a `SignedRole<DelegatedTargets>` for each delegated targets
-                roles.push(SignedRole::from_signed(role)?)
-            }
-            // SignedDelegatedTargets is a wrapper for a set of `SignedRole<DelegatedTargets>`
-            Some(SignedDelegatedTargets {
-                roles,
-                consistent_snapshot: self.signed_root.signed.signed.consistent_snapshot,
-            })
-        };
-
         let signed_snapshot = self
             .build_snapshot(&signed_targets, &signed_delegated_targets)
             .and_then(|snapshot| SignedRole::new(snapshot, &root, keys, &rng))?;
@@ -202,6 +269,97 @@ impl RepositoryEditor {
         })
     }
 
+    /// Returns the bytes that must be signed for `role` when signing out-of-process, for example
+    /// with keys that are kept on an air-gapped machine. This is the canonical JSON form of the
+    /// role's metadata.
+    ///
+    /// Signatures over these bytes are imported with `add_signature()`, and the signed repository
+    /// is built with `assemble()`. The metadata for a role is fixed the first time its payload is
+    /// requested, so make all other changes to the repository first. Snapshot metadata includes
+    /// the hash of the signed targets metadata, and timestamp metadata includes the hash of the
+    /// signed snapshot metadata, so the targets signatures must be added before requesting the
+    /// snapshot payload, and the snapshot signatures before requesting the timestamp payload.
+    pub fn signing_payload(&mut self, role: RoleType) -> Result<Vec<u8>> {
+        match role {
+            RoleType::Root => canonical_form(&self.detached_root().signed),
+            RoleType::Targets => canonical_form(&self.detached_targets()?.signed),
+            RoleType::Snapshot => canonical_form(&self.detached_snapshot()?.signed),
+            RoleType::Timestamp => canonical_form(&self.detached_timestamp()?.signed),
+            RoleType::DelegatedTargets | RoleType::Mirrors => error::DetachedRole { role }.fail(),
+        }
+    }
+
+    /// Adds a signature that was made out-of-process over the `signing_payload()` for `role`.
+    /// The signature is verified with the key `keyid` from root.json before it is added, and the
+    /// key must be one of the keys listed for `role`.
+    pub fn add_signature(
+        &mut self,
+        role: RoleType,
+        keyid: Decoded<Hex>,
+        sig: Vec<u8>,
+    ) -> Result<&mut Self> {
+        let payload = self.signing_payload(role)?;
+        let root = &self.signed_root.signed.signed;
+        let key = root
+            .roles
+            .get(&role)
+            .filter(|role_keys| role_keys.keyids.contains(&keyid))
+            .and_then(|_| root.keys.get(&keyid))
+            .context(error::SignatureKeyNotFound {
+                role,
+                keyid: hex::encode(&keyid),
+            })?;
+        ensure!(
+            key.verify(&payload, &sig),
+            error::InvalidSignature {
+                role,
+                keyid: hex::encode(&keyid),
+            }
+        );
+
+        let signature = Signature {
+            keyid,
+            sig: sig.into(),
+        };
+        match role {
+            RoleType::Root => add_detached_signature(&mut self.detached.root, signature),
+            RoleType::Targets => add_detached_signature(&mut self.detached.targets, signature),
+            RoleType::Snapshot => add_detached_signature(&mut self.detached.snapshot, signature),
+            RoleType::Timestamp => add_detached_signature(&mut self.detached.timestamp, signature),
+            RoleType::DelegatedTargets | RoleType::Mirrors => {}
+        }
+        Ok(self)
+    }
+
+    /// Builds a complete signed set of TUF repository metadata from the signatures that were added
+    /// with `add_signature()`, rather than by signing with keys. Each role must have enough
+    /// signatures to meet its threshold in root.json. Root is written as it was loaded unless
+    /// signatures were added to it.
+    pub fn assemble(mut self) -> Result<SignedRepository> {
+        let (signed_targets, signed_delegated_targets) = self.detached_signed_targets()?;
+        let snapshot = self.detached_snapshot()?;
+        let signed_snapshot = self.verified_signed_role(snapshot)?;
+        let timestamp = self.detached_timestamp()?;
+        let signed_timestamp = self.verified_signed_role(timestamp)?;
+
+        // Only rebuild root.json if signatures were added to it, so that the original file is
+        // preserved otherwise.
+        let root = self.detached_root();
+        let signed_root = if root.signatures == self.signed_root.signed.signatures {
+            self.signed_root
+        } else {
+            self.verified_signed_role(root)?
+        };
+
+        Ok(SignedRepository {
+            root: signed_root,
+            targets: signed_targets,
+            snapshot: signed_snapshot,
+            timestamp: signed_timestamp,
+            delegated_targets: signed_delegated_targets,
+        })
+    }
+
     /// Add an existing `Targets` struct to the repository.
     pub fn targets(&mut self, targets: Signed<Targets>) -> Result<&mut Self> {
         ensure!(
@@ -211,6 +369,7 @@ impl RepositoryEditor {
                 supported: SPEC_VERSION
             }
         );
+        self.existing_versions.targets = Some(targets.signed.version);
         // Save the existing targets
         self.signed_targets = Some(targets.clone());
         // Create a targets editor so that targets can be updated
@@ -223,7 +382,7 @@ impl RepositoryEditor {
     }
 
     /// Add an existing `Snapshot` to the repository. Only the `_extra` data
-    /// is preserved
+    /// is preserved, though the version is remembered for `bump_version()`
     pub fn snapshot(&mut self, snapshot: Snapshot) -> Result<&mut Self> {
         ensure!(
             snapshot.spec_version == SPEC_VERSION,
@@ -232,12 +391,13 @@ impl RepositoryEditor {
                 supported: SPEC_VERSION
             }
         );
+        self.existing_versions.snapshot = Some(snapshot.version);
         self.snapshot_extra = Some(snapshot._extra);
         Ok(self)
     }
 
     /// Add an existing `Timestamp` to the repository. Only the `_extra` data
-    /// is preserved
+    /// is preserved, though the version is remembered for `bump_version()`
     pub fn timestamp(&mut self, timestamp: Timestamp) -> Result<&mut Self> {
         ensure!(
             timestamp.spec_version == SPEC_VERSION,
@@ -246,6 +406,7 @@ impl RepositoryEditor {
                 supported: SPEC_VERSION
             }
         );
+        self.existing_versions.timestamp = Some(timestamp.version);
         self.timestamp_extra = Some(timestamp._extra);
         Ok(self)
     }
@@ -255,7 +416,9 @@ impl RepositoryEditor {
         self.targets_editor.as_mut().ok_or(error::Error::NoTargets)
     }
 
-    /// Add a `Target` to the repository
+    /// Add a `Target` to the repository, replacing any target with the same name. To set custom
+    /// metadata for a file, such as `file_permissions`, build its `Target` with `build_target()`
+    /// and add to its `custom` map before adding it here.
     pub fn add_target(&mut self, name: &str, target: Target) -> Result<&mut Self> {
         self.targets_editor_mut()?.add_target(&name, target);
         Ok(self)
@@ -407,6 +570,44 @@ impl RepositoryEditor {
         self
     }
 
+    /// Sets the version of the targets, snapshot, and timestamp roles to one greater than the
+    /// version of the existing metadata, e.g. the metadata of the repository given to
+    /// `from_repo()`. Roles without existing metadata are set to version 1.
+    ///
+    /// Snapshot and timestamp metadata are built when the repository is signed, so they always
+    /// list the new versions of the roles they describe.
+    pub fn bump_version(&mut self) -> Result<&mut Self> {
+        let targets_version = next_version(RoleType::Targets, self.existing_versions.targets)?;
+        let snapshot_version = next_version(RoleType::Snapshot, self.existing_versions.snapshot)?;
+        let timestamp_version =
+            next_version(RoleType::Timestamp, self.existing_versions.timestamp)?;
+        self.targets_version(targets_version)?;
+        self.snapshot_version(snapshot_version);
+        self.timestamp_version(timestamp_version);
+        Ok(self)
+    }
+
+    /// Sets the expiration of `role` to `duration` from now. Only the targets, snapshot, and
+    /// timestamp roles can be set this way.
+    pub fn set_expiration_from_now(
+        &mut self,
+        role: RoleType,
+        duration: Duration,
+    ) -> Result<&mut Self> {
+        let expires = Utc::now()
+            .checked_add_signed(duration)
+            .context(error::ExpirationOverflow { role })?;
+        match role {
+            RoleType::Targets => self.targets_expires(expires)?,
+            RoleType::Snapshot => self.snapshot_expires(expires),
+            RoleType::Timestamp => self.timestamp_expires(expires),
+            RoleType::Root | RoleType::DelegatedTargets | RoleType::Mirrors => {
+                return error::UneditableRole { role }.fail()
+            }
+        };
+        Ok(self)
+    }
+
     /// Takes the current Targets from `targets_editor` and inserts the role to its proper place in `signed_targets`
     /// Sets `targets_editor` to None
     /// Must be called before `change_delegated_targets()`
@@ -501,10 +702,7 @@ impl RepositoryEditor {
             "max targets limit",
         )?);
         // Load incoming role metadata as Signed<Targets>
-        let mut role: Signed<crate::schema::Targets> =
-            serde_json::from_reader(reader).context(error::ParseMetadata {
-                role: RoleType::Targets,
-            })?;
+        let mut role: Signed<crate::schema::Targets> = parse_metadata(reader, RoleType::Targets)?;
         //verify role with the parent delegation
         let (parent, current_targets) = if name == "targets" {
             (
@@ -562,10 +760,8 @@ impl RepositoryEditor {
                 "max targets limit",
             )?);
             // Load new role metadata as Signed<Targets>
-            let new_role: Signed<crate::schema::Targets> = serde_json::from_reader(reader)
-                .context(error::ParseMetadata {
-                    role: RoleType::Targets,
-                })?;
+            let new_role: Signed<crate::schema::Targets> =
+                parse_metadata(reader, RoleType::Targets)?;
             // verify the role
             key_holder.verify_role(&new_role, &name)?;
             // add the new role
@@ -621,6 +817,129 @@ impl RepositoryEditor {
 
     // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
+    /// Build a `SignedDelegatedTargets` for the roles delegated by `targets`, if there are any
+    fn signed_delegated_targets(
+        &self,
+        targets: &Targets,
+    ) -> Result<Option<SignedDelegatedTargets>> {
+        let delegated_targets = targets.signed_delegated_targets();
+        if delegated_targets.is_empty() {
+            // If we don't have any delegated targets, there is no reason to create
+            // a `SignedDelegatedTargets`
+            return Ok(None);
+        }
+        // If we have delegated targets
+        let mut roles = Vec::new();
+        for role in delegated_targets {
+            // Create a `SignedRole<DelegatedTargets>` for each delegated targets
+            roles.push(SignedRole::from_signed(role)?)
+        }
+        // SignedDelegatedTargets is a wrapper for a set of `SignedRole<DelegatedTargets>`
+        Ok(Some(SignedDelegatedTargets {
+            roles,
+            consistent_snapshot: self.signed_root.signed.signed.consistent_snapshot,
+        }))
+    }
+
+    /// Returns the root that is being signed out-of-process
+    fn detached_root(&mut self) -> Signed<Root> {
+        let signed_root = &self.signed_root;
+        self.detached
+            .root
+            .get_or_insert_with(|| signed_root.signed.clone())
+            .clone()
+    }
+
+    /// Returns the targets that are being signed out-of-process, building them from the targets
+    /// editor if this hasn't been done yet
+    fn detached_targets(&mut self) -> Result<Signed<Targets>> {
+        if let Some(targets) = &self.detached.targets {
+            return Ok(targets.clone());
+        }
+        let targets = match self.targets_editor.as_ref() {
+            Some(targets_editor) => {
+                let targets = targets_editor.build_targets()?;
+                // Only the top level targets can be signed out-of-process
+                ensure!(
+                    targets.name == "targets",
+                    error::DetachedDelegatedRole {
+                        name: targets.name.clone()
+                    }
+                );
+                targets.targets
+            }
+            None => self
+                .signed_targets
+                .as_ref()
+                .context(error::NoTargets)?
+                .signed
+                .clone(),
+        };
+        let targets = Signed {
+            signed: targets,
+            signatures: Vec::new(),
+        };
+        self.detached.targets = Some(targets.clone());
+        Ok(targets)
+    }
+
+    /// Returns the signed targets and delegated targets that are being signed out-of-process
+    fn detached_signed_targets(
+        &mut self,
+    ) -> Result<(SignedRole<Targets>, Option<SignedDelegatedTargets>)> {
+        let targets = self.detached_targets()?;
+        let signed_delegated_targets = self.signed_delegated_targets(&targets.signed)?;
+        Ok((
+            self.verified_signed_role(targets)?,
+            signed_delegated_targets,
+        ))
+    }
+
+    /// Returns the snapshot that is being signed out-of-process, building it if this hasn't been
+    /// done yet. The targets must already be signed.
+    fn detached_snapshot(&mut self) -> Result<Signed<Snapshot>> {
+        if let Some(snapshot) = &self.detached.snapshot {
+            return Ok(snapshot.clone());
+        }
+        let (signed_targets, signed_delegated_targets) = self.detached_signed_targets()?;
+        let snapshot = Signed {
+            signed: self.build_snapshot(&signed_targets, &signed_delegated_targets)?,
+            signatures: Vec::new(),
+        };
+        self.detached.snapshot = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Returns the timestamp that is being signed out-of-process, building it if this hasn't been
+    /// done yet. The snapshot must already be signed.
+    fn detached_timestamp(&mut self) -> Result<Signed<Timestamp>> {
+        if let Some(timestamp) = &self.detached.timestamp {
+            return Ok(timestamp.clone());
+        }
+        let snapshot = self.detached_snapshot()?;
+        let signed_snapshot = self.verified_signed_role(snapshot)?;
+        let timestamp = Signed {
+            signed: self.build_timestamp(&signed_snapshot)?,
+            signatures: Vec::new(),
+        };
+        self.detached.timestamp = Some(timestamp.clone());
+        Ok(timestamp)
+    }
+
+    /// Creates a `SignedRole` from a role that was signed out-of-process, ensuring that its
+    /// signatures meet the threshold in root.json
+    fn verified_signed_role<R>(&self, role: Signed<R>) -> Result<SignedRole<R>>
+    where
+        R: Role,
+    {
+        self.signed_root
+            .signed
+            .signed
+            .verify_role(&role)
+            .context(error::VerifyMetadata { role: R::TYPE })?;
+        SignedRole::from_signed(role)
+    }
+
     /// Build the `Snapshot` struct
     fn build_snapshot(
         &self,
@@ -664,7 +983,8 @@ impl RepositoryEditor {
     {
         SnapshotMeta {
             hashes: Some(Hashes {
-                sha256: role.sha256.to_vec().into(),
+                sha256: Some(role.sha256.to_vec().into()),
+                sha512: None,
                 _extra: HashMap::new(),
             }),
             length: Some(role.length),
@@ -702,7 +1022,8 @@ impl RepositoryEditor {
     {
         TimestampMeta {
             hashes: Hashes {
-                sha256: role.sha256.to_vec().into(),
+                sha256: Some(role.sha256.to_vec().into()),
+                sha512: None,
                 _extra: HashMap::new(),
             },
             length: role.length,
@@ -712,6 +1033,36 @@ impl RepositoryEditor {
     }
 }
 
+/// Returns the version that follows `version`, or version 1 if there is no existing version
+fn next_version(role: RoleType, version: Option<NonZeroU64>) -> Result<NonZeroU64> {
+    match version {
+        None => Ok(NonZeroU64::new(1).unwrap()),
+        Some(version) => version
+            .get()
+            .checked_add(1)
+            .and_then(NonZeroU64::new)
+            .context(error::VersionOverflow {
+                role,
+                version: version.get(),
+            }),
+    }
+}
+
+/// Returns the canonical JSON form of `role`, which is what gets signed
+fn canonical_form<R: Role>(role: &R) -> Result<Vec<u8>> {
+    role.canonical_form().context(error::JsonSerialization)
+}
+
+/// Adds `signature` to a role that is being signed out-of-process, replacing any signature that
+/// was previously added for the same key
+fn add_detached_signature<T>(role: &mut Option<Signed<T>>, signature: Signature) {
+    if let Some(role) = role {
+        role.signatures
+            .retain(|existing| existing.keyid != signature.keyid);
+        role.signatures.push(signature);
+    }
+}
+
 fn parse_url(url: &str) -> Result<Url> {
     let mut url = Cow::from(url);
     if !url.ends_with('/') {
@@ -0,0 +1,180 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides a `RootEditor` object for producing the next version of a repository's root role, e.g.
+//! to rotate root keys.
+
+use crate::editor::signed::SignedRole;
+use crate::error::{self, Result};
+use crate::key_source::KeySource;
+use crate::schema::decoded::{Decoded, Hex};
+use crate::schema::key::Key;
+use crate::schema::{KeyHolder, RoleKeys, RoleType, Root, Signed};
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use snafu::{OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::path::Path;
+
+/// `RootEditor` produces the root role that follows a given root role, with one greater version.
+///
+/// Keys may be added to and removed from roles, and role thresholds adjusted, via the provided
+/// "setter" methods. The `sign()` method then signs the new root with the given keys. Per TUF
+/// 5.3.4, a client only accepts the new root if it is signed by a threshold of the root keys of
+/// both the previous version and the new version, so `sign()` should be given the keys needed for
+/// both; it fails unless both thresholds are met.
+///
+/// The resulting `SignedRole<Root>` can be written next to the existing metadata (as
+/// `N.root.json`), and clients that trust the previous version will follow the rotation when they
+/// next load the repository.
+#[derive(Debug, Clone)]
+pub struct RootEditor {
+    /// The root role being rotated away from
+    previous: Signed<Root>,
+    /// The root role being built, with its version already incremented
+    root: Root,
+}
+
+impl RootEditor {
+    /// Creates a `RootEditor` for the root role that follows the root.json at `root_path`
+    pub fn new<P>(root_path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let root_path = root_path.as_ref();
+        let root_buf = std::fs::read(root_path).context(error::FileRead { path: root_path })?;
+        let previous = serde_json::from_slice::<Signed<Root>>(&root_buf)
+            .context(error::FileParseJson { path: root_path })?;
+        Self::from_root(previous)
+    }
+
+    /// Creates a `RootEditor` for the root role that follows `previous`, e.g. the root of a loaded
+    /// `Repository`
+    pub fn from_root(previous: Signed<Root>) -> Result<Self> {
+        let mut root = previous.signed.clone();
+        root.version = previous
+            .signed
+            .version
+            .get()
+            .checked_add(1)
+            .and_then(NonZeroU64::new)
+            .context(error::RootVersionOverflow {
+                version: previous.signed.version.get(),
+            })?;
+        Ok(RootEditor { previous, root })
+    }
+
+    /// Returns the root role as it has been edited so far
+    pub fn root(&self) -> &Root {
+        &self.root
+    }
+
+    /// Set the expiration
+    pub fn expires(&mut self, expires: DateTime<Utc>) -> &mut Self {
+        self.root.expires = expires;
+        self
+    }
+
+    /// Adds a key to root.json if not already present, and adds its key ID to each of `roles`
+    pub fn add_key(&mut self, key: Key, roles: &[RoleType]) -> Result<&mut Self> {
+        let keyid = if let Some((keyid, _)) = self
+            .root
+            .keys
+            .iter()
+            .find(|(_, candidate_key)| key.eq(candidate_key))
+        {
+            keyid.clone()
+        } else {
+            // Key isn't present yet, so we need to add it
+            let keyid = key.key_id().context(error::JsonSerialization {})?;
+            self.root.keys.insert(keyid.clone(), key);
+            keyid
+        };
+
+        for role in roles {
+            let role_keys = self.root.roles.entry(*role).or_insert_with(|| RoleKeys {
+                keyids: Vec::new(),
+                threshold: NonZeroU64::new(1).unwrap(),
+                _extra: HashMap::new(),
+            });
+            if !role_keys.keyids.contains(&keyid) {
+                role_keys.keyids.push(keyid.clone());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Removes a key ID from `role`, or if no role is specified, removes the key from root.json
+    /// along with its key ID from every role
+    pub fn remove_key(&mut self, keyid: &Decoded<Hex>, role: Option<RoleType>) -> &mut Self {
+        if let Some(role) = role {
+            if let Some(role_keys) = self.root.roles.get_mut(&role) {
+                role_keys.keyids.retain(|key| keyid != key);
+            }
+        } else {
+            for role_keys in self.root.roles.values_mut() {
+                role_keys.keyids.retain(|key| keyid != key);
+            }
+            self.root.keys.remove(keyid);
+        }
+        self
+    }
+
+    /// Set the threshold of signatures required for `role`
+    pub fn threshold(&mut self, role: RoleType, threshold: NonZeroU64) -> &mut Self {
+        self.root
+            .roles
+            .entry(role)
+            .and_modify(|role_keys| role_keys.threshold = threshold)
+            .or_insert_with(|| RoleKeys {
+                keyids: Vec::new(),
+                threshold,
+                _extra: HashMap::new(),
+            });
+        self
+    }
+
+    /// Signs the new root with each of `keys` that is listed for the root role in either the
+    /// previous or the new root, and checks that the result is signed by a threshold of both.
+    pub fn sign(&self, keys: &[Box<dyn KeySource>]) -> Result<SignedRole<Root>> {
+        // Quick check that every role can still meet its threshold
+        for (roletype, rolekeys) in &self.root.roles {
+            if rolekeys.threshold.get() > rolekeys.keyids.len() as u64 {
+                return Err(error::Error::UnstableRoot {
+                    role: *roletype,
+                    threshold: rolekeys.threshold.get(),
+                    actual: rolekeys.keyids.len(),
+                });
+            }
+        }
+
+        let rng = SystemRandom::new();
+        let cross_signed = SignedRole::new(
+            self.root.clone(),
+            &KeyHolder::Root(self.previous.signed.clone()),
+            keys,
+            &rng,
+        )?;
+        let signed_root = SignedRole::new(
+            self.root.clone(),
+            &KeyHolder::Root(self.root.clone()),
+            keys,
+            &rng,
+        )?
+        .add_old_signatures(cross_signed.signed.signatures)?;
+
+        self.root
+            .verify_role(&signed_root.signed)
+            .context(error::VerifyRoleMetadata {
+                role: RoleType::Root.to_string(),
+            })?;
+        self.previous
+            .signed
+            .verify_role(&signed_root.signed)
+            .context(error::VerifyRootRotation {
+                version: self.previous.signed.version.get(),
+            })?;
+        Ok(signed_root)
+    }
+}
@@ -10,7 +10,7 @@ use crate::error::{self, Result};
 use crate::io::DigestAdapter;
 use crate::key_source::KeySource;
 use crate::schema::{
-    DelegatedTargets, KeyHolder, Role, RoleType, Root, Signature, Signed, Snapshot, Target,
+    DelegatedTargets, Hashes, KeyHolder, Role, RoleType, Root, Signature, Signed, Snapshot, Target,
     Targets, Timestamp,
 };
 use olpc_cjson::CanonicalFormatter;
@@ -665,21 +665,29 @@ trait TargetsWalker {
         // compare the hashes of the target from the repo and the target we just created.  They
         // should match, or we alert the caller; if target replacement is intended, it should
         // happen earlier, in RepositoryEditor.
-        ensure!(
-            target_from_path.hashes.sha256 == repo_target.hashes.sha256,
-            error::HashMismatch {
-                context: "target",
-                calculated: hex::encode(target_from_path.hashes.sha256),
-                expected: hex::encode(&repo_target.hashes.sha256),
-            }
-        );
+        if let (Some(calculated), Some(expected)) =
+            (&target_from_path.hashes.sha256, &repo_target.hashes.sha256)
+        {
+            ensure!(
+                calculated == expected,
+                error::HashMismatch {
+                    context: "target",
+                    calculated: hex::encode(calculated),
+                    expected: hex::encode(expected),
+                }
+            );
+        } else {
+            // The repo doesn't list a SHA-256 hash for the target, so check the file against its
+            // other hashes.
+            verify_file(input, &repo_target.hashes)?;
+        }
 
         let dest = if self.consistent_snapshot() {
-            outdir.join(format!(
-                "{}.{}",
-                hex::encode(&target_from_path.hashes.sha256),
-                file_name
-            ))
+            let digest = repo_target
+                .hashes
+                .consistent_snapshot_digest()
+                .context(error::NoSupportedHash { context: file_name })?;
+            outdir.join(format!("{}.{}", hex::encode(digest), file_name))
         } else {
             outdir.join(&file_name)
         };
@@ -694,20 +702,7 @@ trait TargetsWalker {
         // unique; if we're not, then there could be a target from another repo with the same name
         // but different checksum.  We can't assume such conflicts are OK, so we fail.
         if !self.consistent_snapshot() {
-            // Use DigestAdapter to get a streaming checksum of the file without needing to hold
-            // its contents.
-            let f = fs::File::open(&dest).context(error::FileOpen { path: &dest })?;
-            let mut reader = DigestAdapter::sha256(
-                Box::new(f),
-                &repo_target.hashes.sha256,
-                Url::from_file_path(&dest)
-                    .ok() // dump unhelpful `()` error
-                    .context(error::FileUrl { path: &dest })?,
-            );
-            let mut dev_null = std::io::sink();
-            // The act of reading with the DigestAdapter verifies the checksum, assuming the read
-            // succeeds.
-            std::io::copy(&mut reader, &mut dev_null).context(error::FileRead { path: &dest })?;
+            verify_file(&dest, &repo_target.hashes)?;
         }
 
         let metadata = fs::symlink_metadata(&dest).context(error::FileMetadata { path: &dest })?;
@@ -720,3 +715,21 @@ trait TargetsWalker {
         }
     }
 }
+
+/// Checks the file at `path` against the strongest of `hashes` that is supported.
+fn verify_file(path: &Path, hashes: &Hashes) -> Result<()> {
+    // Use DigestAdapter to get a streaming checksum of the file without needing to hold its
+    // contents.
+    let f = fs::File::open(path).context(error::FileOpen { path })?;
+    let mut reader = DigestAdapter::new(
+        Box::new(f),
+        hashes,
+        Url::from_file_path(path)
+            .ok() // dump unhelpful `()` error
+            .context(error::FileUrl { path })?,
+    )?;
+    let mut dev_null = std::io::sink();
+    // The act of reading with the DigestAdapter verifies the checksum, assuming the read succeeds.
+    std::io::copy(&mut reader, &mut dev_null).context(error::FileRead { path })?;
+    Ok(())
+}
@@ -16,7 +16,7 @@ use crate::schema::{
 };
 use crate::transport::Transport;
 use crate::Limits;
-use crate::Repository;
+use crate::{parse_metadata, Repository};
 use chrono::{DateTime, Utc};
 use ring::rand::SystemRandom;
 use serde_json::Value;
@@ -121,7 +121,8 @@ impl TargetsEditor {
     /// Creates a `TargetsEditor` with the provided targets from an already loaded repo
     /// `version` and `expires` are thrown out to encourage updating the version and expiration
     /// If a `Repository` has been loaded, use `from_repo()` to preserve the `Transport` and `Limits`.
-    pub fn from_repo(repo: Repository, name: &str) -> Result<Self> {
+    pub fn from_repo(mut repo: Repository, name: &str) -> Result<Self> {
+        repo.load_delegated_roles()?;
         let (targets, key_holder) = if name == "targets" {
             (
                 repo.targets.signed.clone(),
@@ -392,10 +393,7 @@ impl TargetsEditor {
             "max targets limit",
         )?);
         // Load incoming role metadata as Signed<Targets>
-        let role: Signed<crate::schema::Targets> =
-            serde_json::from_reader(reader).context(error::ParseMetadata {
-                role: RoleType::Targets,
-            })?;
+        let role: Signed<crate::schema::Targets> = parse_metadata(reader, RoleType::Targets)?;
 
         // Create `Signed<DelegatedTargets>` for the role
         let delegated_targets = Signed {
@@ -38,39 +38,59 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
-    /// The library failed to create a file in the datastore.
-    #[snafu(display("Failed to create file at datastore path {}: {}", path.display(), source))]
+    /// The library failed to write a value to the datastore.
+    #[snafu(display("Failed to write '{}' to datastore: {}", key, source))]
     DatastoreCreate {
-        path: PathBuf,
+        key: String,
         source: std::io::Error,
         backtrace: Backtrace,
     },
 
-    /// The library failed to open a file in the datastore.
-    #[snafu(display("Failed to open file from datastore path {}: {}", path.display(), source))]
+    /// The library failed to read a value from the datastore.
+    #[snafu(display("Failed to read '{}' from datastore: {}", key, source))]
     DatastoreOpen {
-        path: PathBuf,
+        key: String,
         source: std::io::Error,
         backtrace: Backtrace,
     },
 
-    /// The library failed to remove a file in the datastore.
-    #[snafu(display("Failed to remove file at datastore path {}: {}", path.display(), source))]
+    /// The library failed to remove a value from the datastore.
+    #[snafu(display("Failed to remove '{}' from datastore: {}", key, source))]
     DatastoreRemove {
-        path: PathBuf,
+        key: String,
         source: std::io::Error,
         backtrace: Backtrace,
     },
 
-    /// The library failed to serialize an object to JSON to the datastore.
-    #[snafu(display("Failed to serialize {} to JSON at datastore path {}: {}", what, path.display(), source))]
+    /// The library failed to serialize an object to JSON for the datastore.
+    #[snafu(display(
+        "Failed to serialize {} to JSON for datastore key '{}': {}",
+        what,
+        key,
+        source
+    ))]
     DatastoreSerialize {
         what: String,
-        path: PathBuf,
+        key: String,
         source: serde_json::Error,
         backtrace: Backtrace,
     },
 
+    /// Out-of-process signing was requested for a role that doesn't support it.
+    #[snafu(display("Signing payloads are not supported for {} metadata", role))]
+    DetachedRole {
+        role: RoleType,
+        backtrace: Backtrace,
+    },
+
+    /// Out-of-process signing of the targets role was requested while the targets editor holds a
+    /// delegated role, which can't be signed out-of-process.
+    #[snafu(display(
+        "Signing payloads are only supported for the top-level targets role, but the targets editor holds '{}'",
+        name
+    ))]
+    DetachedDelegatedRole { name: String, backtrace: Backtrace },
+
     #[snafu(display("Failed to create directory '{}': {}", path.display(), source))]
     DirCreate {
         path: PathBuf,
@@ -78,6 +98,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// An expiration was requested that is too far in the future to represent.
+    #[snafu(display("Expiration for {} metadata is out of range", role))]
+    ExpirationOverflow {
+        role: RoleType,
+        backtrace: Backtrace,
+    },
+
     /// A metadata file has expired.
     #[snafu(display("{} metadata is expired", role))]
     ExpiredMetadata {
@@ -138,9 +165,27 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A file's hashes are all for algorithms that tough does not support.
+    #[snafu(display(
+        "Unable to verify '{}': no SHA-256 or SHA-512 hash is listed in metadata",
+        context
+    ))]
+    NoSupportedHash {
+        context: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Source path for target must be file or symlink - '{}'", path.display()))]
     InvalidFileType { path: PathBuf, backtrace: Backtrace },
 
+    /// A signature that was added to a role is not valid for the role's metadata.
+    #[snafu(display("Signature from key ID {} is not valid for {} metadata", keyid, role))]
+    InvalidSignature {
+        role: RoleType,
+        keyid: String,
+        backtrace: Backtrace,
+    },
+
     /// The library failed to create a URL from a base URL and a path.
     #[snafu(display("Failed to join \"{}\" to URL \"{}\": {}", path, url, source))]
     JoinUrl {
@@ -168,6 +213,15 @@ pub enum Error {
     #[snafu(display("Unrecognized private key format"))]
     KeyUnrecognized { backtrace: Backtrace },
 
+    #[snafu(display(
+        "SEC1 ('EC PRIVATE KEY') keys are not supported; convert the key to PKCS#8 with \
+         `openssl pkcs8 -topk8 -nocrypt`"
+    ))]
+    KeySec1Unsupported { backtrace: Backtrace },
+
+    #[snafu(display("Unsupported ECDSA curve; only NIST P-256 (secp256r1) keys are supported"))]
+    KeyUnsupportedCurve { backtrace: Backtrace },
+
     #[snafu(display("Failed to create symlink at '{}': {}", path.display(), source))]
     LinkCreate {
         path: PathBuf,
@@ -183,6 +237,35 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A role's metadata file exceeded a size limit set by the consumer of this library or the
+    /// metadata.
+    #[snafu(display(
+        "{} metadata exceeded maximum size {} (specified by {})",
+        role,
+        max_size,
+        specifier
+    ))]
+    MetadataSizeExceeded {
+        role: RoleType,
+        max_size: u64,
+        specifier: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// A file ended before the length given by the consumer of this library or the metadata.
+    #[snafu(display(
+        "File is shorter than its length {} (specified by {}): read {} bytes",
+        length,
+        specifier,
+        read
+    ))]
+    LengthMismatch {
+        length: u64,
+        specifier: &'static str,
+        read: u64,
+        backtrace: Backtrace,
+    },
+
     /// The maximum root updates setting was exceeded.
     #[snafu(display("Maximum root updates {} exceeded", max_root_updates))]
     MaxUpdatesExceeded {
@@ -190,6 +273,38 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// Delegated targets roles were nested more deeply than the maximum delegation depth setting.
+    #[snafu(display(
+        "Delegated role '{}' exceeds maximum delegation depth {}",
+        name,
+        max_delegation_depth
+    ))]
+    MaxDelegationDepthExceeded {
+        name: String,
+        max_delegation_depth: usize,
+        backtrace: Backtrace,
+    },
+
+    /// A delegated targets role delegates to one of its own ancestors.
+    #[snafu(display("Delegated role '{}' delegates to its ancestor '{}'", parent, name))]
+    DelegationCycle {
+        parent: String,
+        name: String,
+        backtrace: Backtrace,
+    },
+
+    /// A succinct hash delegation's `bit_length` is outside of the range 1 to 32.
+    #[snafu(display(
+        "Succinct delegation '{}' has bit_length {}, which must be from 1 to 32",
+        name_prefix,
+        bit_length
+    ))]
+    SuccinctBitLength {
+        name_prefix: String,
+        bit_length: u8,
+        backtrace: Backtrace,
+    },
+
     /// A required reference to a metadata file is missing from a metadata file.
     #[snafu(display("Meta for {:?} missing from {} metadata", file, role))]
     MetaMissing {
@@ -208,17 +323,18 @@ pub enum Error {
     #[snafu(display("Key for role '{}' doesn't exist in root.json", role))]
     NoRoleKeysinRoot { role: String },
 
-    /// A downloaded metadata file has an older version than a previously downloaded metadata file.
+    /// A downloaded metadata file has an older version than a previously downloaded (trusted)
+    /// metadata file, which may be a rollback attack.
     #[snafu(display(
-        "Found version {} of {} metadata when we had previously fetched version {}",
-        new_version,
+        "Rollback detected: found version {} of {} metadata when version {} was previously trusted",
+        offered_version,
         role,
-        current_version
+        trusted_version
     ))]
-    OlderMetadata {
+    RollbackDetected {
         role: RoleType,
-        current_version: u64,
-        new_version: u64,
+        trusted_version: u64,
+        offered_version: u64,
         backtrace: Backtrace,
     },
 
@@ -231,9 +347,14 @@ pub enum Error {
     /// Schema non-conformance errors read like:
     /// * invalid type: integer `2`, expected a string at line 1 column 11
     /// * missing field `sig` at line 1 column 16
+    ///
+    /// `line` and `column` are where in the file parsing failed, starting from 1. They are 0 if the
+    /// file could not be read, rather than parsed.
     #[snafu(display("Failed to parse {} metadata: {}", role, source))]
     ParseMetadata {
         role: RoleType,
+        line: usize,
+        column: usize,
         source: serde_json::Error,
         backtrace: Backtrace,
     },
@@ -302,6 +423,14 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A signature was added for a key that isn't authorized to sign the role.
+    #[snafu(display("Key ID {} is not listed for {} in root.json", keyid, role))]
+    SignatureKeyNotFound {
+        role: RoleType,
+        keyid: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unable to find signing keys for role '{}'", role))]
     SigningKeysNotFound { role: String },
 
@@ -316,6 +445,34 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A target name could escape the directory or URL that targets are saved to or fetched from.
+    #[snafu(display(
+        "Unsafe target name '{}': target names must be relative paths without '..' components",
+        name
+    ))]
+    UnsafeTargetName { name: String, backtrace: Backtrace },
+
+    /// A role was given to `RepositoryEditor` that it doesn't edit.
+    #[snafu(display("{} metadata can't be edited with RepositoryEditor", role))]
+    UneditableRole {
+        role: RoleType,
+        backtrace: Backtrace,
+    },
+
+    /// Metadata was written for a major version of the TUF specification that is not supported.
+    #[snafu(display(
+        "{} metadata has unsupported spec version '{}'; major version {} is supported",
+        role,
+        found,
+        supported_major
+    ))]
+    UnsupportedSpecVersion {
+        role: RoleType,
+        found: String,
+        supported_major: u64,
+        backtrace: Backtrace,
+    },
+
     /// System time is behaving irrationally, went back in time
     #[snafu(display(
         "System time stepped backward: system time '{}', last known time '{}'",
@@ -350,6 +507,14 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A role's version can't be incremented.
+    #[snafu(display("Version {} of {} metadata can't be incremented", version, role))]
+    VersionOverflow {
+        role: RoleType,
+        version: u64,
+        backtrace: Backtrace,
+    },
+
     /// A metadata file could not be verified.
     #[snafu(display("Failed to verify {} metadata: {}", role, source))]
     VerifyMetadata {
@@ -365,6 +530,19 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A new root role is not signed by a threshold of the previous root role's keys, so clients
+    /// that trust the previous root would not follow the rotation.
+    #[snafu(display(
+        "Failed to verify new root against root version {}: {}",
+        version,
+        source
+    ))]
+    VerifyRootRotation {
+        version: u64,
+        source: crate::schema::Error,
+        backtrace: Backtrace,
+    },
+
     /// The trusted root metadata file could not be verified.
     #[snafu(display("Failed to verify trusted root metadata: {}", source))]
     VerifyTrustedMetadata {
@@ -386,6 +564,36 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A fetched metadata file's version does not match the version that is listed for it: the
+    /// snapshot's version in `timestamp.json`, or a targets role's version in `snapshot.json`. This
+    /// usually means that the metadata comes from a different (e.g. stale) state of the repository.
+    #[snafu(display(
+        "{} metadata version {} does not match the listed version {}",
+        role,
+        actual,
+        expected
+    ))]
+    MetaVersionMismatch {
+        role: RoleType,
+        expected: u64,
+        actual: u64,
+        backtrace: Backtrace,
+    },
+
+    /// The fetched `snapshot.json` does not have the hash that is listed for it in
+    /// `timestamp.json`. Its contents differ from the snapshot that the timestamp was signed for,
+    /// e.g. because it was corrupted or tampered with.
+    #[snafu(display(
+        "snapshot.json hash mismatch: calculated {}, expected {}",
+        actual,
+        expected
+    ))]
+    SnapshotHashMismatch {
+        expected: String,
+        actual: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Error reading data from '{}': {}", url, source))]
     CacheFileRead {
         url: Url,
@@ -428,9 +636,53 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A target could not be read, e.g. because it is larger than its signed length or its hash
+    /// does not match.
+    #[snafu(display("Failed to read target '{}': {}", name, source))]
+    TargetRead {
+        name: String,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A custom metadata field of a target could not be deserialized as the requested type.
+    #[snafu(display(
+        "Failed to deserialize custom field '{}' of target '{}': {}",
+        key,
+        name,
+        source
+    ))]
+    TargetCustom {
+        name: String,
+        key: String,
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A target could not be saved, e.g. because it could not be read or written.
+    #[snafu(display("Failed to save target '{}' to '{}': {}", name, path.display(), source))]
+    SaveTarget {
+        name: String,
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A target that was to be saved is not listed in the repository metadata.
+    #[snafu(display("Unable to save target '{}': not found in repository metadata", name))]
+    SaveTargetNotFound { name: String, backtrace: Backtrace },
+
     #[snafu(display("Target file not delegated: {}", target_url))]
     TargetNotFound { target_url: String },
 
+    /// A target that was to be read from a specific role is not listed in that role's metadata.
+    #[snafu(display("Role '{}' does not list target '{}'", role, target))]
+    RoleTargetNotFound {
+        role: String,
+        target: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Delegated role not found: {}", name))]
     DelegateNotFound { name: String },
 
@@ -462,6 +714,37 @@ pub enum Error {
     #[snafu(display("Role missing from snapshot meta: {}", name))]
     RoleNotInMeta { name: String },
 
+    /// A delegated role lists a target outside of the paths that were delegated to it.
+    #[snafu(display(
+        "Delegated role '{}' lists target '{}', which was not delegated to it",
+        role,
+        target
+    ))]
+    UndelegatedTarget { role: String, target: String },
+
+    /// A delegated role's metadata is not signed by a threshold of the keys that were delegated
+    /// to it.
+    #[snafu(display("Failed to verify delegated role '{}': {}", name, source))]
+    VerifyDelegatedRole {
+        name: String,
+        source: crate::schema::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A delegated role's version does not match the version listed in the snapshot.
+    #[snafu(display(
+        "Delegated role '{}' version mismatch: fetched {}, expected {}",
+        name,
+        fetched,
+        expected
+    ))]
+    DelegatedVersionMismatch {
+        name: String,
+        fetched: u64,
+        expected: u64,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("The key for {} was not included", role))]
     KeyNotFound {
         role: String,
@@ -527,8 +810,25 @@ pub enum Error {
         threshold: u64,
     },
 
+    /// The root version can't be incremented.
+    #[snafu(display("Root version {} can't be incremented", version))]
+    RootVersionOverflow { version: u64, backtrace: Backtrace },
+
     #[snafu(display("The targets editor was not cleared"))]
     TargetsEditorSome,
+
+    /// A delegated role delegated paths that are broader than the paths delegated to it.
+    #[snafu(display(
+        "Role '{}' delegated paths to '{}' that it was not delegated: {}",
+        parent,
+        child,
+        source
+    ))]
+    UnauthorizedDelegation {
+        parent: String,
+        child: String,
+        source: schema::Error,
+    },
 }
 
 // used in `std::io::Read` implementations
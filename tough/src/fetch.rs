@@ -3,9 +3,14 @@
 
 use crate::error::{self, Result};
 use crate::io::{DigestAdapter, MaxSizeAdapter};
+use crate::schema::Hashes;
 use crate::transport::Transport;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncTransport, TransportError, TransportErrorKind};
 use snafu::ResultExt;
 use std::io::Read;
+#[cfg(feature = "async")]
+use tokio::io::AsyncReadExt;
 use url::Url;
 
 pub(crate) fn fetch_max_size(
@@ -23,22 +28,123 @@ pub(crate) fn fetch_max_size(
     ))
 }
 
-pub(crate) fn fetch_sha256(
+/// Fetches `url`, verifying its length against `size` and its contents against the strongest of
+/// `hashes` that is supported.
+pub(crate) fn fetch_hashed(
     transport: &dyn Transport,
     url: Url,
     size: u64,
     specifier: &'static str,
-    sha256: &[u8],
+    hashes: &Hashes,
 ) -> Result<impl Read + Send> {
-    Ok(DigestAdapter::sha256(
-        Box::new(MaxSizeAdapter::new(
-            transport
-                .fetch(url.clone())
-                .context(error::Transport { url: url.clone() })?,
+    let reader = MaxSizeAdapter::new(
+        transport
+            .fetch(url.clone())
+            .context(error::Transport { url: url.clone() })?,
+        specifier,
+        size,
+    );
+    DigestAdapter::new(Box::new(reader), hashes, url)
+}
+
+/// Fetches `url` like [`fetch_hashed`], but the file must be exactly `length` bytes long. A stream
+/// that is longer is rejected as soon as too many bytes are read, and one that is shorter is
+/// rejected at the end of the stream, before its hash is checked.
+pub(crate) fn fetch_exact(
+    transport: &dyn Transport,
+    url: Url,
+    length: u64,
+    specifier: &'static str,
+    hashes: &Hashes,
+) -> Result<impl Read + Send> {
+    let reader = MaxSizeAdapter::exact(
+        transport
+            .fetch(url.clone())
+            .context(error::Transport { url: url.clone() })?,
+        specifier,
+        length,
+    );
+    DigestAdapter::new(Box::new(reader), hashes, url)
+}
+
+/// Where to fetch a metadata file from, and how to check it as it is read: the most bytes that
+/// may be read, and the hashes that it must match, if the metadata that refers to it lists them.
+/// This is shared by the blocking and async load paths.
+#[derive(Debug)]
+pub(crate) struct MetadataFetch<'a> {
+    pub(crate) url: Url,
+    max_size: u64,
+    specifier: &'static str,
+    hashes: Option<&'a Hashes>,
+}
+
+impl<'a> MetadataFetch<'a> {
+    /// A file of unknown size that may be at most `max_size` bytes long.
+    pub(crate) fn max_size(url: Url, max_size: u64, specifier: &'static str) -> Self {
+        Self {
+            url,
+            max_size,
             specifier,
-            size,
-        )),
-        sha256,
-        url,
-    ))
+            hashes: None,
+        }
+    }
+
+    /// A file that may be at most `size` bytes long, whose contents must match the strongest of
+    /// `hashes` that is supported.
+    pub(crate) fn hashed(url: Url, size: u64, specifier: &'static str, hashes: &'a Hashes) -> Self {
+        Self {
+            hashes: Some(hashes),
+            ..Self::max_size(url, size, specifier)
+        }
+    }
+
+    /// Wraps `reader`, which reads the file, with the checks of its size and hashes.
+    fn check(&self, reader: Box<dyn Read + Send>) -> Result<Box<dyn Read + Send>> {
+        let reader = MaxSizeAdapter::new(reader, self.specifier, self.max_size);
+        Ok(match self.hashes {
+            Some(hashes) => Box::new(DigestAdapter::new(
+                Box::new(reader),
+                hashes,
+                self.url.clone(),
+            )?),
+            None => Box::new(reader),
+        })
+    }
+}
+
+/// Fetches the metadata file that `fetch` describes.
+pub(crate) fn fetch_metadata(
+    transport: &dyn Transport,
+    fetch: &MetadataFetch<'_>,
+) -> Result<Box<dyn Read + Send>> {
+    fetch.check(
+        transport
+            .fetch(fetch.url.clone())
+            .context(error::Transport {
+                url: fetch.url.clone(),
+            })?,
+    )
+}
+
+/// Fetches the metadata file that `fetch` describes with an [`AsyncTransport`]. The file is read
+/// into memory as it is fetched, up to one byte more than its limit, so that a file that is too
+/// large is still reported as such when it is parsed.
+#[cfg(feature = "async")]
+pub(crate) async fn fetch_metadata_async(
+    transport: &dyn AsyncTransport,
+    fetch: &MetadataFetch<'_>,
+) -> Result<Box<dyn Read + Send>> {
+    let url = fetch.url.clone();
+    let stream = transport
+        .fetch(url.clone())
+        .await
+        .context(error::Transport { url: url.clone() })?;
+    let mut bytes = Vec::new();
+    stream
+        .take(fetch.max_size.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, &url, e))
+        .context(error::Transport { url })?;
+    fetch.check(Box::new(std::io::Cursor::new(bytes)))
 }
@@ -0,0 +1,442 @@
+//! The `gcs` module provides `GcsTransport`, which enables `Repository` objects to be loaded from a
+//! Google Cloud Storage bucket.
+use crate::{HttpTransport, HttpTransportBuilder, Transport, TransportError, TransportErrorKind};
+use chrono::{DateTime, Duration, Utc};
+use log::debug;
+use reqwest::blocking::Client;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// The endpoint of the Cloud Storage XML API.
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com/";
+/// The scope requested for access tokens, which only allows reading objects.
+const READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+/// The endpoint that exchanges refresh tokens (and service account assertions) for access tokens.
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+/// The endpoint of the Compute Engine metadata server that provides access tokens.
+const METADATA_TOKEN_URI: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+/// Access tokens are refreshed when they are this close to expiring.
+const REFRESH_MARGIN_SECONDS: i64 = 60;
+
+/// A [`Transport`] for objects in a Google Cloud Storage bucket.
+///
+/// The transport handles URLs of the form `gs://<bucket>/<object>`, so a repository in the
+/// `my-repo` bucket under the `prod` prefix would be loaded with a metadata base URL of
+/// `gs://my-repo/prod/metadata`. Objects are fetched from the Cloud Storage XML API with an
+/// [`HttpTransport`], so they are retried per its settings, and a missing object results in
+/// [`TransportErrorKind::FileNotFound`].
+///
+/// [`GcsTransport::new`] authenticates with [application default credentials], which are found
+/// on the first fetch:
+/// 1. The credentials file named by the `GOOGLE_APPLICATION_CREDENTIALS` environment variable.
+/// 2. The credentials file written by `gcloud auth application-default login`.
+/// 3. The service account of the Compute Engine instance, from the metadata server.
+///
+/// Use [`GcsTransport::anonymous`] for public buckets.
+///
+/// Requires the `gcs` feature.
+///
+/// [application default credentials]: https://cloud.google.com/docs/authentication/production
+#[derive(Debug, Clone)]
+pub struct GcsTransport {
+    endpoint: Url,
+    settings: HttpTransportBuilder,
+    /// `None` for anonymous access.
+    auth: Option<Arc<Auth>>,
+    /// The transport for anonymous access.
+    anonymous: HttpTransport,
+}
+
+impl GcsTransport {
+    /// Create a new `GcsTransport` that authenticates with application default credentials.
+    pub fn new() -> Self {
+        Self::with_auth(Some(CredentialsSource::ApplicationDefault))
+    }
+
+    /// Create a new `GcsTransport` that authenticates with the service account or authorized user
+    /// credentials in the JSON file at `path`.
+    pub fn from_credentials_file<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_auth(Some(CredentialsSource::File(path.into())))
+    }
+
+    /// Create a new `GcsTransport` that does not authenticate, for public buckets.
+    pub fn anonymous() -> Self {
+        Self::with_auth(None)
+    }
+
+    fn with_auth(source: Option<CredentialsSource>) -> Self {
+        Self {
+            endpoint: Url::parse(DEFAULT_ENDPOINT).expect("default endpoint is a valid URL"),
+            settings: HttpTransportBuilder::default(),
+            auth: source.map(|source| {
+                Arc::new(Auth {
+                    source,
+                    cached: Mutex::default(),
+                })
+            }),
+            anonymous: HttpTransport::default(),
+        }
+    }
+
+    /// Set the HTTP settings used to fetch objects, e.g. the number of tries. Any bearer token in
+    /// the settings is replaced by the access token.
+    pub fn http_settings(mut self, settings: HttpTransportBuilder) -> Self {
+        self.anonymous = settings.clone().build();
+        self.settings = settings;
+        self
+    }
+
+    /// Set the endpoint of the Cloud Storage XML API, e.g. for an emulator. Defaults to
+    /// `https://storage.googleapis.com/`.
+    pub fn endpoint(mut self, endpoint: Url) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Returns the XML API URL of the object that `url` refers to.
+    fn object_url(&self, url: &Url) -> Result<Url, TransportError> {
+        if url.scheme() != "gs" {
+            return Err(TransportError::new(
+                TransportErrorKind::UnsupportedUrlScheme,
+                url,
+            ));
+        }
+        let bucket = url.host_str().ok_or_else(|| {
+            TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                "URL does not name a bucket",
+            )
+        })?;
+        // the path is already percent-encoded, so it can be used as is.
+        self.endpoint
+            .join(&format!("{}{}", bucket, url.path()))
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))
+    }
+}
+
+impl Default for GcsTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GcsTransport {
+    /// Returns the transport that sends authorized requests for objects, which requests an access
+    /// token if there is no valid one.
+    fn object_transport(&self, url: &Url) -> Result<HttpTransport, TransportError> {
+        match &self.auth {
+            None => Ok(self.anonymous.clone()),
+            Some(auth) => {
+                // access tokens are requested with the client built from the HTTP settings.
+                let client = self.anonymous.client().map_err(|e| {
+                    TransportError::new_with_cause(TransportErrorKind::Other, url, e)
+                })?;
+                auth.transport(&self.settings, &client)
+                    .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))
+            }
+        }
+    }
+}
+
+impl Transport for GcsTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let object_url = self.object_url(&url)?;
+        // report errors with the `gs` URL that was requested.
+        self.object_transport(&url)?
+            .fetch(object_url)
+            .map_err(|e| TransportError::new_with_cause(e.kind(), &url, e))
+    }
+
+    /// Sends a `HEAD` request for the object, so that it is not downloaded.
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        let object_url = self.object_url(&url)?;
+        self.object_transport(&url)?
+            .exists(object_url)
+            .map_err(|e| TransportError::new_with_cause(e.kind(), &url, e))
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// Where to find credentials.
+#[derive(Debug, Clone)]
+enum CredentialsSource {
+    ApplicationDefault,
+    File(PathBuf),
+}
+
+/// Credentials that can be exchanged for an access token.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Credentials {
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        #[serde(default = "default_token_uri")]
+        token_uri: String,
+    },
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+    /// Not read from a file; the credentials of the Compute Engine instance.
+    #[serde(skip)]
+    MetadataServer,
+}
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_owned()
+}
+
+/// The private key is left out of the `Debug` output so that it does not end up in logs.
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Credentials::ServiceAccount { client_email, .. } => f
+                .debug_struct("ServiceAccount")
+                .field("client_email", client_email)
+                .finish(),
+            Credentials::AuthorizedUser { client_id, .. } => f
+                .debug_struct("AuthorizedUser")
+                .field("client_id", client_id)
+                .finish(),
+            Credentials::MetadataServer => f.write_str("MetadataServer"),
+        }
+    }
+}
+
+/// The response from a token endpoint.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// Finds credentials, and caches an `HttpTransport` that sends the current access token.
+#[derive(Debug)]
+struct Auth {
+    source: CredentialsSource,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    transport: HttpTransport,
+    expires: DateTime<Utc>,
+}
+
+impl Auth {
+    /// Returns a transport that sends a valid access token, getting a new token if needed.
+    /// Tokens are requested with `client`.
+    fn transport(
+        &self,
+        settings: &HttpTransportBuilder,
+        client: &Client,
+    ) -> Result<HttpTransport, GcsError> {
+        // a poisoned lock only means that another thread panicked while getting a token.
+        let mut cached = self
+            .cached
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(token) = cached.as_ref() {
+            if Utc::now() + Duration::seconds(REFRESH_MARGIN_SECONDS) < token.expires {
+                return Ok(token.transport.clone());
+            }
+        }
+        let credentials = self.credentials()?;
+        debug!(
+            "getting a Cloud Storage access token with {:?}",
+            credentials
+        );
+        let token = fetch_token(client, &credentials)?;
+        let transport = settings.clone().bearer_token(token.access_token).build();
+        *cached = Some(CachedToken {
+            transport: transport.clone(),
+            expires: Utc::now() + Duration::seconds(token.expires_in),
+        });
+        Ok(transport)
+    }
+
+    fn credentials(&self) -> Result<Credentials, GcsError> {
+        match &self.source {
+            CredentialsSource::File(path) => read_credentials(path),
+            CredentialsSource::ApplicationDefault => {
+                if let Some(path) = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+                    return read_credentials(Path::new(&path));
+                }
+                if let Some(path) = well_known_credentials_file().filter(|path| path.is_file()) {
+                    return read_credentials(&path);
+                }
+                Ok(Credentials::MetadataServer)
+            }
+        }
+    }
+}
+
+/// Returns the path of the credentials file that `gcloud auth application-default login` writes.
+fn well_known_credentials_file() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else {
+        PathBuf::from(std::env::var_os("HOME")?).join(".config")
+    };
+    Some(
+        config_dir
+            .join("gcloud")
+            .join("application_default_credentials.json"),
+    )
+}
+
+fn read_credentials(path: &Path) -> Result<Credentials, GcsError> {
+    let file = std::fs::File::open(path).context(CredentialsRead { path })?;
+    serde_json::from_reader(file).context(CredentialsParse { path })
+}
+
+/// Exchanges the credentials for an access token, with `client`.
+fn fetch_token(client: &Client, credentials: &Credentials) -> Result<TokenResponse, GcsError> {
+    let request = match credentials {
+        Credentials::ServiceAccount {
+            client_email,
+            private_key,
+            token_uri,
+        } => {
+            let assertion = service_account_assertion(client_email, private_key, token_uri)?;
+            client.post(token_uri).form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+        }
+        Credentials::AuthorizedUser {
+            client_id,
+            client_secret,
+            refresh_token,
+        } => client.post(DEFAULT_TOKEN_URI).form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("refresh_token", refresh_token.as_str()),
+        ]),
+        Credentials::MetadataServer => client
+            .get(METADATA_TOKEN_URI)
+            .header("Metadata-Flavor", "Google"),
+    };
+    let response = request
+        .send()
+        .and_then(reqwest::blocking::Response::error_for_status)
+        .context(TokenRequest)?;
+    serde_json::from_reader(response).context(TokenParse)
+}
+
+/// Creates the signed JWT that a service account exchanges for an access token.
+fn service_account_assertion(
+    client_email: &str,
+    private_key: &str,
+    token_uri: &str,
+) -> Result<String, GcsError> {
+    let now = Utc::now().timestamp();
+    let header = base64_url(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = base64_url(
+        serde_json::json!({
+            "iss": client_email,
+            "scope": READ_ONLY_SCOPE,
+            "aud": token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        })
+        .to_string()
+        .as_bytes(),
+    );
+    let message = format!("{}.{}", header, claims);
+
+    let pem = pem::parse(private_key).context(PrivateKeyPem)?;
+    let key_pair = RsaKeyPair::from_pkcs8(&pem.contents).context(PrivateKey)?;
+    let mut signature = vec![0; key_pair.public_modulus_len()];
+    key_pair
+        .sign(
+            &RSA_PKCS1_SHA256,
+            &SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )
+        .ok()
+        .context(Sign)?;
+    Ok(format!("{}.{}", message, base64_url(&signature)))
+}
+
+/// Encodes `data` as unpadded base64url, as JWTs require.
+fn base64_url<T: AsRef<[u8]>>(data: T) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+/// The error type for the Cloud Storage transport module.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum GcsError {
+    #[snafu(display("Unable to read credentials file '{}': {}", path.display(), source))]
+    CredentialsRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to parse credentials file '{}': {}", path.display(), source))]
+    CredentialsParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    // The key is deliberately left out of the messages.
+    #[snafu(display("Service account private key is not valid PEM: {}", source))]
+    PrivateKeyPem { source: pem::PemError },
+
+    #[snafu(display("Service account private key was rejected: {}", source))]
+    PrivateKey { source: ring::error::KeyRejected },
+
+    #[snafu(display("Unable to sign the service account token request"))]
+    Sign,
+
+    #[snafu(display("Unable to get an access token: {}", source))]
+    TokenRequest { source: reqwest::Error },
+
+    #[snafu(display("Unable to parse the access token response: {}", source))]
+    TokenParse { source: serde_json::Error },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_url_encoding() {
+        assert_eq!(base64_url(b""), "");
+        assert_eq!(base64_url(b"f"), "Zg");
+        assert_eq!(base64_url(b"fo"), "Zm8");
+        assert_eq!(base64_url(b"foo"), "Zm9v");
+        assert_eq!(base64_url(b"foob"), "Zm9vYg");
+        assert_eq!(base64_url(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn object_url() {
+        let transport = GcsTransport::anonymous();
+        let url = Url::parse("gs://my-repo/prod/metadata/1.root.json").unwrap();
+        assert_eq!(
+            transport.object_url(&url).unwrap().as_str(),
+            "https://storage.googleapis.com/my-repo/prod/metadata/1.root.json"
+        );
+    }
+}
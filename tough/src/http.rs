@@ -1,17 +1,26 @@
 //! The `http` module provides `HttpTransport` which enables `Repository` objects to be
 //! loaded over HTTP
 use crate::{Transport, TransportError, TransportErrorKind};
+use chrono::{DateTime, Utc};
 use log::{debug, error, trace};
 use reqwest::blocking::{Client, ClientBuilder, Request, Response};
-use reqwest::header::{self, HeaderValue, ACCEPT_RANGES};
-use reqwest::{Error, Method};
-use snafu::ResultExt;
+use reqwest::header::{self, HeaderMap, HeaderValue, ACCEPT_RANGES};
+use reqwest::redirect::Policy;
+use reqwest::{Error, Method, StatusCode};
+use ring::rand::{SecureRandom, SystemRandom};
 use snafu::Snafu;
+use snafu::{ensure, ResultExt};
 use std::cmp::Ordering;
-use std::io::Read;
+use std::io::{BufReader, Read};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use url::Url;
 
+#[cfg(feature = "async")]
+mod nonblocking;
+#[cfg(feature = "async")]
+pub use nonblocking::AsyncHttpTransport;
+
 /// A builder for [`HttpTransport`] which allows settings customization.
 ///
 /// # Example
@@ -26,7 +35,7 @@ use url::Url;
 ///
 /// See [`HttpTransport`] for proxy support and other behavior details.
 ///
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone)]
 pub struct HttpTransportBuilder {
     timeout: Duration,
     connect_timeout: Duration,
@@ -34,6 +43,68 @@ pub struct HttpTransportBuilder {
     initial_backoff: Duration,
     max_backoff: Duration,
     backoff_factor: f32,
+    jitter_factor: f32,
+    jitter_source: Option<Arc<dyn Fn() -> f32 + Send + Sync>>,
+    headers: HeaderMap,
+    bearer_token: Option<String>,
+    user_agent: Option<String>,
+    redirect_policy: RedirectPolicy,
+    restrict_redirects_to_same_host: bool,
+    max_response_bytes: Option<u64>,
+    accept_compression: bool,
+    on_retry: Option<Arc<dyn Fn(&RetryEvent) + Send + Sync>>,
+    treat_403_as_not_found: bool,
+    read_buffer_size: usize,
+    max_connections: Option<usize>,
+    /// A PKCS#12 archive and its password.
+    #[cfg(feature = "native-tls")]
+    client_identity: Option<(Vec<u8>, String)>,
+    /// PEM or DER encoded certificates to trust in addition to the system's root certificates.
+    #[cfg(feature = "native-tls")]
+    root_certificates: Vec<Vec<u8>>,
+}
+
+/// Secrets (the bearer token and client identity) are left out of the `Debug` output so that they
+/// do not end up in logs.
+impl std::fmt::Debug for HttpTransportBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("HttpTransportBuilder");
+        s.field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("tries", &self.tries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("backoff_factor", &self.backoff_factor)
+            .field("jitter_factor", &self.jitter_factor)
+            .field(
+                "jitter_source",
+                &self.jitter_source.as_ref().map(|_| "<callback>"),
+            )
+            .field("headers", &self.headers)
+            .field(
+                "bearer_token",
+                &self.bearer_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("user_agent", &self.user_agent)
+            .field("redirect_policy", &self.redirect_policy)
+            .field(
+                "restrict_redirects_to_same_host",
+                &self.restrict_redirects_to_same_host,
+            )
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("accept_compression", &self.accept_compression)
+            .field("on_retry", &self.on_retry.as_ref().map(|_| "<callback>"))
+            .field("treat_403_as_not_found", &self.treat_403_as_not_found)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("max_connections", &self.max_connections);
+        #[cfg(feature = "native-tls")]
+        s.field(
+            "client_identity",
+            &self.client_identity.as_ref().map(|_| "<redacted>"),
+        )
+        .field("root_certificates", &self.root_certificates.len());
+        s.finish()
+    }
 }
 
 impl Default for HttpTransportBuilder {
@@ -46,6 +117,23 @@ impl Default for HttpTransportBuilder {
             initial_backoff: std::time::Duration::from_millis(100),
             max_backoff: std::time::Duration::from_secs(1),
             backoff_factor: 1.5,
+            jitter_factor: 0.0,
+            jitter_source: None,
+            headers: HeaderMap::new(),
+            bearer_token: None,
+            user_agent: None,
+            redirect_policy: RedirectPolicy::default(),
+            restrict_redirects_to_same_host: false,
+            max_response_bytes: None,
+            accept_compression: false,
+            on_retry: None,
+            treat_403_as_not_found: true,
+            read_buffer_size: 0,
+            max_connections: None,
+            #[cfg(feature = "native-tls")]
+            client_identity: None,
+            #[cfg(feature = "native-tls")]
+            root_certificates: Vec::new(),
         }
     }
 }
@@ -94,9 +182,221 @@ impl HttpTransportBuilder {
         self
     }
 
+    /// Set the jitter factor, the fraction by which each pause between tries is randomly lengthened
+    /// or shortened. For example, `0.25` results in pauses of 75% to 125% of the backoff, which
+    /// keeps many clients from retrying in lockstep. Pauses never exceed `max_backoff`. Must be
+    /// between `0.0` (the default, no jitter) and `1.0`; other values are clamped to that range.
+    pub fn jitter_factor(mut self, value: f32) -> Self {
+        self.jitter_factor = value;
+        self
+    }
+
+    /// Set the source of the random part of the jitter: a function that returns a number between
+    /// `-1.0` and `1.0` (other values are clamped to that range) each time a pause is jittered, where
+    /// `-1.0` shortens the pause the most and `1.0` lengthens it the most. Defaults to the system's
+    /// random number generator. Replacing it is mostly useful to make pauses predictable in tests.
+    pub fn jitter_source<F>(mut self, source: F) -> Self
+    where
+        F: Fn() -> f32 + Send + Sync + 'static,
+    {
+        self.jitter_source = Some(Arc::new(source));
+        self
+    }
+
+    /// Set headers that will be added to every request, including retries. For example, a header
+    /// that is required by an API gateway in front of the repository.
+    pub fn headers(mut self, value: HeaderMap) -> Self {
+        self.headers = value;
+        self
+    }
+
+    /// Adds a header to the headers that will be added to every request.
+    #[cfg(feature = "oci")]
+    pub(crate) fn header(mut self, name: header::HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set a token that will be sent as `Authorization: Bearer <token>` with every request,
+    /// including retries.
+    pub fn bearer_token<S: Into<String>>(mut self, value: S) -> Self {
+        self.bearer_token = Some(value.into());
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request. If this is not set, `reqwest`'s default
+    /// is used.
+    pub fn user_agent<S: Into<String>>(mut self, value: S) -> Self {
+        self.user_agent = Some(value.into());
+        self
+    }
+
+    /// Set a client certificate and private key to present for mutual TLS. `der` is a DER-encoded
+    /// PKCS#12 archive, and `password` is the password that protects it. An archive that cannot be
+    /// read results in an error from the first fetch.
+    ///
+    /// Requires the `native-tls` feature.
+    #[cfg(feature = "native-tls")]
+    pub fn client_identity_pkcs12<S: Into<String>>(mut self, der: Vec<u8>, password: S) -> Self {
+        self.client_identity = Some((der, password.into()));
+        self
+    }
+
+    /// Add a PEM or DER encoded certificate to trust as a root certificate, in addition to the
+    /// system's root certificates. For example, the certificate of a private CA that signs the
+    /// certificate of an internal mirror. This may be called more than once to add several
+    /// certificates. A certificate that cannot be read results in an error from the first fetch.
+    ///
+    /// Requires the `native-tls` feature.
+    #[cfg(feature = "native-tls")]
+    pub fn root_certificate(mut self, certificate: Vec<u8>) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Set how redirects are handled. Defaults to following up to 10 redirects.
+    pub fn redirect_policy(mut self, value: RedirectPolicy) -> Self {
+        self.redirect_policy = value;
+        self
+    }
+
+    /// Reject redirects that lead to a host other than the host of the original request. This
+    /// prevents a compromised mirror from sending fetches to an arbitrary host. Defaults to
+    /// `false`.
+    pub fn restrict_redirects_to_same_host(mut self, value: bool) -> Self {
+        self.restrict_redirects_to_same_host = value;
+        self
+    }
+
+    /// Set the maximum number of bytes that will be read from a single response body. A response
+    /// whose `Content-Length` is larger fails right away, and reading a response body fails once
+    /// more than this many bytes have been read. By default there is no limit.
+    ///
+    /// This protects against a server that sends an endless response. Note that [`Limits`] are
+    /// also enforced when loading a repository.
+    ///
+    /// [`Limits`]: crate::Limits
+    pub fn max_response_bytes(mut self, value: u64) -> Self {
+        self.max_response_bytes = Some(value);
+        self
+    }
+
+    /// Ask the server to compress responses with gzip (`Accept-Encoding: gzip`), and decompress
+    /// them as they are read. Defaults to `false`.
+    ///
+    /// Byte offsets in a compressed response do not match the decompressed bytes, so when this is
+    /// enabled a read that fails partway is retried by fetching the file again from the start,
+    /// and skipping the bytes that have already been read, rather than with a range request.
+    pub fn accept_compression(mut self, value: bool) -> Self {
+        self.accept_compression = value;
+        self
+    }
+
+    /// Set whether a `403 Forbidden` response is treated as a file that was not found, which is
+    /// what S3 returns for a missing file when the caller is not allowed to list the bucket.
+    /// Defaults to `true`. When `false`, a 403 is a non-retryable error, so that an
+    /// authorization failure is not mistaken for a missing file.
+    pub fn treat_403_as_not_found(mut self, value: bool) -> Self {
+        self.treat_403_as_not_found = value;
+        self
+    }
+
+    /// Set the size in bytes of the buffer that each response body is read through. A larger
+    /// buffer turns many small reads into fewer reads of the response. Defaults to `0`, which
+    /// reads the response directly without buffering.
+    pub fn read_buffer_size(mut self, value: usize) -> Self {
+        self.read_buffer_size = value;
+        self
+    }
+
+    /// Set the maximum number of idle connections to keep open to each host, for reuse by later
+    /// fetches. By default `reqwest`'s limit is used, which does not limit idle connections.
+    pub fn max_connections(mut self, value: usize) -> Self {
+        self.max_connections = Some(value);
+        self
+    }
+
+    /// Set a callback that is called each time a fetch or a read is about to be retried, for
+    /// example to count retries in metrics. The callback is called before the pause, so it
+    /// should return quickly.
+    pub fn on_retry<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&RetryEvent) + Send + Sync + 'static,
+    {
+        self.on_retry = Some(Arc::new(callback));
+        self
+    }
+
     /// Construct an [`HttpTransport`] transport from this builder's settings.
     pub fn build(self) -> HttpTransport {
-        HttpTransport { settings: self }
+        HttpTransport {
+            settings: self,
+            client: OnceLock::new(),
+        }
+    }
+
+    /// Construct an [`AsyncHttpTransport`] transport from this builder's settings.
+    ///
+    /// Requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> AsyncHttpTransport {
+        AsyncHttpTransport::new(self)
+    }
+}
+
+/// How an [`HttpTransport`] handles HTTP redirects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Do not follow redirects. A redirect response results in an error.
+    None,
+    /// Follow up to this many redirects. More redirects result in an error.
+    Limited(usize),
+}
+
+/// `RedirectPolicy` defaults to following up to 10 redirects, which matches `reqwest`.
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::Limited(10)
+    }
+}
+
+/// Describes a retry, passed to the callback set with [`HttpTransportBuilder::on_retry`].
+#[derive(Debug, Clone)]
+pub struct RetryEvent {
+    /// The URL that is being fetched.
+    pub url: Url,
+    /// The number of the try that is about to be made. The first try is `1`, so the first retry
+    /// is `2`.
+    pub attempt: u32,
+    /// How long the transport will pause before the retry.
+    pub wait: Duration,
+    /// Why the retry is needed.
+    pub reason: RetryReason,
+}
+
+/// Why an [`HttpTransport`] is retrying, see [`RetryEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryReason {
+    /// The server responded with a retryable HTTP status code, e.g. `503` or `429`.
+    Status(u16),
+    /// The request timed out.
+    Timeout,
+    /// The request could not be sent, e.g. because the connection failed.
+    Request,
+    /// Reading the response body failed after the response was received.
+    Read,
+}
+
+impl RetryReason {
+    /// Categorizes a retryable `reqwest` error.
+    fn from_error(err: &reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            RetryReason::Status(status.as_u16())
+        } else if err.is_timeout() {
+            RetryReason::Timeout
+        } else {
+            RetryReason::Request
+        }
     }
 }
 
@@ -104,18 +404,72 @@ impl HttpTransportBuilder {
 /// custom `HttpTransport`, or use `HttpTransport::default()`.
 ///
 /// This transport returns `FileNotFound` for the following HTTP response codes:
-/// - 403: Forbidden. (Some services return this code when a file does not exist.)
+/// - 403: Forbidden. (Some services return this code when a file does not exist.) This can be
+///   turned off with [`HttpTransportBuilder::treat_403_as_not_found`].
 /// - 404: Not Found.
 /// - 410: Gone.
 ///
+/// Server errors (5xx) and 429 (Too Many Requests) are retried. When such a response includes a
+/// `Retry-After` header, the transport waits for the requested duration (capped by `max_backoff`)
+/// instead of its own backoff.
+///
+/// Redirects are followed per the [`RedirectPolicy`]. If
+/// [`HttpTransportBuilder::restrict_redirects_to_same_host`] is set, a redirect to a different host
+/// results in a non-retryable error. A redirect response that is not followed is also an error.
+///
+/// # TLS
+///
+/// `tough` does not choose a TLS backend for `reqwest`, so to fetch over HTTPS either enable a
+/// `reqwest` TLS feature in your own crate or enable the `native-tls` feature of `tough`. The
+/// `native-tls` feature is also needed to present a client certificate for mutual TLS, or to trust
+/// a private CA with [`HttpTransportBuilder::root_certificate`].
+///
 /// # Proxy Support
 ///
 /// To use the `HttpTransport` with a proxy, specify the `HTTPS_PROXY` environment variable.
 /// The transport will also respect the `NO_PROXY` environment variable.
 ///
-#[derive(Clone, Copy, Debug, Default)]
+/// # Connection Reuse
+///
+/// The underlying HTTP client is built on the first fetch and reused by all later fetches, and by
+/// clones of the transport made after that, so that connections can be kept alive between them.
+///
+#[derive(Clone, Debug, Default)]
 pub struct HttpTransport {
     settings: HttpTransportBuilder,
+    client: OnceLock<Client>,
+}
+
+impl HttpTransport {
+    /// Creates an `HttpTransport` that sends requests per `settings` with `client`, which must
+    /// have been built from settings that only differ in their headers, e.g. to share the
+    /// connection pool of another `HttpTransport`.
+    pub(crate) fn with_client(settings: HttpTransportBuilder, client: Client) -> Self {
+        Self {
+            settings,
+            client: OnceLock::from(client),
+        }
+    }
+
+    /// Returns the HTTP client, building it if this is the first fetch.
+    pub(crate) fn client(&self) -> Result<Client, HttpError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        let client = build_client(&self.settings)?;
+        // if another thread built a client first, use that one so that there is only one pool.
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
+    /// Send a GET request to the URL, like [`Transport::fetch`], but return the concrete
+    /// [`RetryRead`] rather than a boxed `Read`, so that the caller can query its progress with
+    /// [`RetryRead::bytes_read`].
+    pub fn fetch_retry_read(&self, url: Url) -> Result<RetryRead, TransportError> {
+        let mut r = RetryState::new(self.settings.initial_backoff);
+        self.client()
+            .and_then(|client| fetch_with_retries(&mut r, &self.settings, &client, &url))
+            .map_err(|e| TransportError::from((url, e)))
+    }
 }
 
 /// Implement the `tough` `Transport` trait for `HttpRetryTransport`
@@ -123,11 +477,20 @@ impl Transport for HttpTransport {
     /// Send a GET request to the URL. Request will be retried per the `ClientSettings`. The
     /// returned `RetryRead` will also retry as necessary per the `ClientSettings`.
     fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        Ok(Box::new(self.fetch_retry_read(url)?))
+    }
+
+    /// Send a `HEAD` request to the URL, so that the file is not downloaded. Request will be
+    /// retried per the `ClientSettings`.
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
         let mut r = RetryState::new(self.settings.initial_backoff);
-        Ok(Box::new(
-            fetch_with_retries(&mut r, &self.settings, &url)
-                .map_err(|e| TransportError::from((url, e)))?,
-        ))
+        self.client()
+            .and_then(|client| exists_with_retries(&mut r, &self.settings, &client, &url))
+            .map_err(|e| TransportError::from((url, e)))
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
     }
 }
 
@@ -136,8 +499,12 @@ impl Transport for HttpTransport {
 pub struct RetryRead {
     retry_state: RetryState,
     settings: HttpTransportBuilder,
-    response: Response,
+    client: Client,
+    response: BufReader<Response>,
     url: Url,
+    /// The number of bytes to discard from the start of `response` because they were read before
+    /// a retry. Only used when compression is accepted.
+    skip: usize,
 }
 
 impl Read for RetryRead {
@@ -146,10 +513,21 @@ impl Read for RetryRead {
         // retry loop
         loop {
             let retry_err = match self.response.read(buf) {
-                Ok(sz) => {
-                    self.retry_state.next_byte += sz;
-                    return Ok(sz);
+                Ok(0) if self.skip > 0 => std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "response ended before reaching the bytes that were already read",
+                ),
+                Ok(sz) if self.skip > 0 => {
+                    // discard bytes that were already returned before the retry.
+                    let discard = sz.min(self.skip);
+                    self.skip -= discard;
+                    if discard < sz {
+                        buf.copy_within(discard..sz, 0);
+                        return self.advance(sz - discard);
+                    }
+                    continue;
                 }
+                Ok(sz) => return self.advance(sz),
                 // store the error in `retry_err` to return later if there are no more retries
                 Err(err) => err,
             };
@@ -161,29 +539,58 @@ impl Read for RetryRead {
                 return Err(retry_err);
             }
             self.retry_state.increment(&self.settings);
-            self.err_if_no_range_support(retry_err)?;
-            // wait, then retry the request (with a range header).
-            std::thread::sleep(self.retry_state.wait);
-            let new_retry_read =
-                fetch_with_retries(&mut self.retry_state, &self.settings, &self.url)
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            if !self.settings.accept_compression {
+                self.err_if_no_range_support(retry_err)?;
+            }
+            let wait = self.settings.jittered(self.retry_state.wait);
+            notify_retry(
+                &self.settings,
+                &self.url,
+                &self.retry_state,
+                wait,
+                RetryReason::Read,
+            );
+            // wait, then retry the request (with a range header, or from the start if compressed).
+            std::thread::sleep(wait);
+            let new_retry_read = fetch_with_retries(
+                &mut self.retry_state,
+                &self.settings,
+                &self.client,
+                &self.url,
+            )
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
             // the new fetch succeeded so we need to replace our read object with the new one.
             self.response = new_retry_read.response;
+            self.skip = new_retry_read.skip;
         }
     }
 }
 
 impl RetryRead {
-    /// Checks for the header `Accept-Ranges: bytes`
-    fn supports_range(&self) -> bool {
-        if let Some(ranges) = self.response.headers().get(ACCEPT_RANGES) {
-            if let Ok(val) = ranges.to_str() {
-                if val.contains("bytes") {
-                    return true;
-                }
+    /// Returns the number of bytes of the response that have been read so far. After a read is
+    /// retried, this is the position that the download resumed from.
+    pub fn bytes_read(&self) -> usize {
+        self.retry_state.next_byte
+    }
+
+    /// Records that `sz` bytes have been read, and returns them or an error if the response has
+    /// exceeded `max_response_bytes`.
+    fn advance(&mut self, sz: usize) -> std::io::Result<usize> {
+        self.retry_state.next_byte += sz;
+        if let Some(max_response_bytes) = self.settings.max_response_bytes {
+            if self.retry_state.next_byte as u64 > max_response_bytes {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    HttpError::ResponseTooLarge { max_response_bytes },
+                ));
             }
         }
-        false
+        Ok(sz)
+    }
+
+    /// Checks for the header `Accept-Ranges: bytes`
+    fn supports_range(&self) -> bool {
+        supports_range(self.response.get_ref().headers())
     }
 
     /// Returns an error when we have received an error during read, but our server does not support
@@ -204,6 +611,18 @@ impl RetryRead {
     }
 }
 
+/// Checks response headers for `Accept-Ranges: bytes`.
+fn supports_range(headers: &HeaderMap) -> bool {
+    if let Some(ranges) = headers.get(ACCEPT_RANGES) {
+        if let Ok(val) = ranges.to_str() {
+            if val.contains("bytes") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
 /// A private struct that serves as the retry counter.
 #[derive(Clone, Copy, Debug)]
 struct RetryState {
@@ -244,93 +663,321 @@ impl RetryState {
     }
 }
 
+/// Creates a reqwest client per the `ClientSettings`.
+fn build_client(cs: &HttpTransportBuilder) -> Result<Client, HttpError> {
+    let mut builder = ClientBuilder::new()
+        .timeout(cs.timeout)
+        .connect_timeout(cs.connect_timeout)
+        .redirect(redirect_policy(cs))
+        .gzip(cs.accept_compression);
+    if let Some(user_agent) = &cs.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+    if let Some(max_connections) = cs.max_connections {
+        builder = builder.pool_max_idle_per_host(max_connections);
+    }
+    #[cfg(feature = "native-tls")]
+    {
+        let (identity, certificates) = tls_settings(cs)?;
+        if let Some(identity) = identity {
+            builder = builder.identity(identity);
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build().context(HttpClient)
+}
+
+/// Reads the client identity and root certificates from the `ClientSettings`.
+#[cfg(feature = "native-tls")]
+fn tls_settings(
+    cs: &HttpTransportBuilder,
+) -> Result<(Option<reqwest::Identity>, Vec<reqwest::Certificate>), HttpError> {
+    let identity = match &cs.client_identity {
+        Some((der, password)) => {
+            Some(reqwest::Identity::from_pkcs12_der(der, password).context(ClientIdentity)?)
+        }
+        None => None,
+    };
+    let certificates = cs
+        .root_certificates
+        .iter()
+        .map(|certificate| {
+            // PEM certificates are text that begins with a header, anything else should be DER.
+            if certificate.starts_with(b"-----BEGIN") {
+                reqwest::Certificate::from_pem(certificate)
+            } else {
+                reqwest::Certificate::from_der(certificate)
+            }
+            .context(RootCertificate)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((identity, certificates))
+}
+
 /// Sends a `GET` request to the `url`. Retries the request as necessary per the `ClientSettings`.
 fn fetch_with_retries(
     r: &mut RetryState,
     cs: &HttpTransportBuilder,
+    client: &Client,
     url: &Url,
 ) -> Result<RetryRead, HttpError> {
     trace!("beginning fetch for '{}'", url);
-    // create a reqwest client
-    let client = ClientBuilder::new()
-        .timeout(cs.timeout)
-        .connect_timeout(cs.connect_timeout)
-        .build()
-        .context(HttpClient)?;
 
     // retry loop
     loop {
         // build the request
-        let request = build_request(&client, r.next_byte, &url)?;
+        // a compressed response can't be resumed with a range request, so start over instead.
+        let range_start = if cs.accept_compression {
+            0
+        } else {
+            r.next_byte
+        };
+        let request = build_request(client, range_start, url, cs)?;
 
         // send the GET request, then categories the outcome by converting to an HttpResult.
-        let http_result: HttpResult = client.execute(request).into();
+        let http_result = HttpResult::from_result(client.execute(request), cs);
 
-        match http_result {
+        let (retry_after, reason) = match http_result {
             HttpResult::Ok(response) => {
                 trace!("{:?} - returning from successful fetch", r);
+                // fail fast if the server says that it will send too much.
+                if let (Some(max_response_bytes), Some(content_length)) =
+                    (cs.max_response_bytes, response.content_length())
+                {
+                    ensure!(
+                        range_start as u64 + content_length <= max_response_bytes,
+                        ResponseTooLarge { max_response_bytes }
+                    );
+                }
                 return Ok(RetryRead {
                     retry_state: *r,
-                    settings: *cs,
-                    response,
+                    settings: cs.clone(),
+                    client: client.clone(),
+                    response: BufReader::with_capacity(cs.read_buffer_size, response),
                     url: url.clone(),
+                    skip: r.next_byte - range_start,
                 });
             }
             HttpResult::Fatal(err) => {
                 trace!("{:?} - returning fatal error from fetch: {}", r, err);
                 return Err(err).context(FetchFatal);
             }
+            HttpResult::Redirect(status) => {
+                trace!(
+                    "{:?} - returning redirect that was not followed: {}",
+                    r,
+                    status
+                );
+                return RedirectNotFollowed {
+                    status: status.as_u16(),
+                }
+                .fail();
+            }
             HttpResult::FileNotFound(err) => {
                 trace!("{:?} - returning file not found from fetch: {}", r, err);
                 return Err(err).context(FetchFileNotFound);
             }
-            HttpResult::Retryable(err) => {
+            HttpResult::Retryable(err, retry_after) => {
                 trace!("{:?} - retryable error: {}", r, err);
                 if r.current_try >= cs.tries - 1 {
                     debug!("{:?} - returning failure, no more retries: {}", r, err);
                     return Err(err).context(FetchNoMoreRetries { tries: cs.tries });
                 }
+                (retry_after, RetryReason::from_error(&err))
             }
+        };
+
+        backoff(r, cs, url, retry_after, reason);
+    }
+}
+
+/// Sends a `HEAD` request to the `url` to find out whether the file exists. Retries the request as
+/// necessary per the `ClientSettings`.
+fn exists_with_retries(
+    r: &mut RetryState,
+    cs: &HttpTransportBuilder,
+    client: &Client,
+    url: &Url,
+) -> Result<bool, HttpError> {
+    trace!("beginning exists check for '{}'", url);
+
+    // retry loop
+    loop {
+        let request = client
+            .request(Method::HEAD, url.as_str())
+            .headers(request_headers(0, cs)?)
+            .build()
+            .context(RequestBuild)?;
+        let (retry_after, reason) = match HttpResult::from_result(client.execute(request), cs) {
+            HttpResult::Ok(_) => return Ok(true),
+            HttpResult::FileNotFound(_) => return Ok(false),
+            HttpResult::Fatal(err) => return Err(err).context(FetchFatal),
+            HttpResult::Redirect(status) => {
+                return RedirectNotFollowed {
+                    status: status.as_u16(),
+                }
+                .fail()
+            }
+            HttpResult::Retryable(err, retry_after) => {
+                if r.current_try >= cs.tries - 1 {
+                    debug!("{:?} - returning failure, no more retries: {}", r, err);
+                    return Err(err).context(FetchNoMoreRetries { tries: cs.tries });
+                }
+                (retry_after, RetryReason::from_error(&err))
+            }
+        };
+        backoff(r, cs, url, retry_after, reason);
+    }
+}
+
+/// Waits before the next try of a request, per the `ClientSettings` or the server's requested
+/// delay.
+fn backoff(
+    r: &mut RetryState,
+    cs: &HttpTransportBuilder,
+    url: &Url,
+    retry_after: Option<Duration>,
+    reason: RetryReason,
+) {
+    r.increment(cs);
+    // the server's requested delay takes the place of our backoff, but is capped by it.
+    let wait = retry_after.map_or_else(|| cs.jittered(r.wait), |d| d.min(cs.max_backoff));
+    trace!("{:?} - waiting {:?} before retrying", r, wait);
+    notify_retry(cs, url, r, wait, reason);
+    std::thread::sleep(wait);
+}
+
+/// Calls the `on_retry` callback, if there is one, for the retry described by the `RetryState`.
+fn notify_retry(
+    cs: &HttpTransportBuilder,
+    url: &Url,
+    r: &RetryState,
+    wait: Duration,
+    reason: RetryReason,
+) {
+    if let Some(on_retry) = &cs.on_retry {
+        on_retry(&RetryEvent {
+            url: url.clone(),
+            // `current_try` has already been incremented and counts from zero.
+            attempt: r.current_try + 1,
+            wait,
+            reason,
+        });
+    }
+}
+
+/// Creates the redirect policy for the client per the `ClientSettings`.
+fn redirect_policy(cs: &HttpTransportBuilder) -> Policy {
+    let max_redirects = match cs.redirect_policy {
+        RedirectPolicy::None => return Policy::none(),
+        RedirectPolicy::Limited(max_redirects) => max_redirects,
+    };
+    let restrict_to_same_host = cs.restrict_redirects_to_same_host;
+    // `Policy::limited` follows one redirect fewer than its limit in some versions of reqwest, so
+    // the limit is checked here. `previous` holds the original URL and each redirect followed.
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            return attempt.error("too many redirects");
+        }
+        if !restrict_to_same_host {
+            return attempt.follow();
+        }
+        // `previous` always contains at least the URL of the original request.
+        let original_host = attempt
+            .previous()
+            .first()
+            .and_then(Url::host_str)
+            .map(str::to_owned);
+        let redirect_host = attempt.url().host_str().map(str::to_owned);
+        if original_host == redirect_host {
+            attempt.follow()
+        } else {
+            attempt.error(format!(
+                "redirect from host '{}' to host '{}' is not allowed",
+                original_host.unwrap_or_default(),
+                redirect_host.unwrap_or_default()
+            ))
         }
+    })
+}
+
+/// Randomly lengthens or shortens `wait` by up to `jitter_factor` of its duration, without exceeding
+/// `max`. `sample` is the random part, in the range `-1.0..=1.0`.
+fn apply_jitter(wait: Duration, jitter_factor: f32, max: Duration, sample: f32) -> Duration {
+    let jitter_factor = jitter_factor.max(0.0).min(1.0);
+    if jitter_factor <= 0.0 {
+        return wait;
+    }
+    let sample = sample.max(-1.0).min(1.0);
+    wait.mul_f64(f64::from(1.0 + jitter_factor * sample))
+        .min(max)
+}
+
+impl HttpTransportBuilder {
+    /// Randomly lengthens or shortens the pause `wait` before the next try, per the `jitter_factor`
+    /// and `jitter_source` settings.
+    fn jittered(&self, wait: Duration) -> Duration {
+        let sample = match &self.jitter_source {
+            Some(source) => source(),
+            None => random_sample(),
+        };
+        apply_jitter(wait, self.jitter_factor, self.max_backoff, sample)
+    }
+}
 
-        r.increment(&cs);
-        std::thread::sleep(r.wait);
+/// Returns a random number in the range `-1.0..=1.0`, or zero (no jitter) if the system's random
+/// number generator fails.
+fn random_sample() -> f32 {
+    let mut bytes = [0u8; 2];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 0.0;
     }
+    f32::from(u16::from_le_bytes(bytes)) / f32::from(u16::MAX) * 2.0 - 1.0
 }
 
 /// Much of the complexity in the `fetch_with_retries` function is in deciphering the `Result`
 /// we get from `reqwest::Client::execute`. Using this enum we categorize the states of the
 /// `Result` into the categories that we need to understand.
-enum HttpResult {
+enum HttpResult<R = Response> {
     /// We got a response with an HTTP code that indicates success.
-    Ok(reqwest::blocking::Response),
+    Ok(R),
     /// We got an `Error` (other than file-not-found) which we will not retry.
     Fatal(reqwest::Error),
+    /// We got a redirect response that was not followed, e.g. because redirects are disabled.
+    Redirect(StatusCode),
     /// The file could not be found (HTTP status 403 or 404).
     FileNotFound(reqwest::Error),
-    /// We received an `Error`, or we received an HTTP response code that we can retry.
-    Retryable(reqwest::Error),
+    /// We received an `Error`, or we received an HTTP response code that we can retry. Includes the
+    /// delay requested by a `Retry-After` header, if there was one.
+    Retryable(reqwest::Error, Option<Duration>),
 }
 
-/// Takes the `Result` type from `reqwest::Client::execute`, and categorizes it into an
-/// `HttpResult` variant.
-impl From<Result<reqwest::blocking::Response, reqwest::Error>> for HttpResult {
-    fn from(result: Result<Response, Error>) -> Self {
+impl<R: HttpResponse> HttpResult<R> {
+    /// Takes the `Result` type from `reqwest::Client::execute`, and categorizes it into an
+    /// `HttpResult` variant per the `ClientSettings`.
+    fn from_result(result: Result<R, Error>, cs: &HttpTransportBuilder) -> Self {
         match result {
             Ok(response) => {
                 trace!("response received");
                 // checks the status code of the response for errors
-                parse_response_code(response)
+                parse_response_code(response, cs)
             }
             Err(e) if e.is_timeout() => {
                 // a connection timeout occurred
                 trace!("timeout error during fetch: {}", e);
-                HttpResult::Retryable(e)
+                HttpResult::Retryable(e, None)
+            }
+            Err(e) if e.is_redirect() => {
+                // a redirect was rejected by the redirect policy, retries will not succeed.
+                trace!("redirect error during fetch: {}", e);
+                HttpResult::Fatal(e)
             }
             Err(e) if e.is_request() => {
                 // an error occurred while sending the request
                 trace!("error sending request during fetch: {}", e);
-                HttpResult::Retryable(e)
+                HttpResult::Retryable(e, None)
             }
             Err(e) => {
                 // the error is not from an HTTP status code or a timeout, retries will not succeed.
@@ -342,8 +989,36 @@ impl From<Result<reqwest::blocking::Response, reqwest::Error>> for HttpResult {
     }
 }
 
+/// The parts of a `reqwest` response that are needed to categorize it, so that blocking and async
+/// responses are categorized the same way.
+trait HttpResponse: Sized {
+    fn status(&self) -> StatusCode;
+    fn headers(&self) -> &HeaderMap;
+    fn error_for_status(self) -> Result<Self, Error>;
+}
+
+impl HttpResponse for Response {
+    fn status(&self) -> StatusCode {
+        Response::status(self)
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        Response::headers(self)
+    }
+
+    fn error_for_status(self) -> Result<Self, Error> {
+        Response::error_for_status(self)
+    }
+}
+
 /// Checks the HTTP response code and converts a non-successful response code to an error.
-fn parse_response_code(response: reqwest::blocking::Response) -> HttpResult {
+fn parse_response_code<R: HttpResponse>(response: R, cs: &HttpTransportBuilder) -> HttpResult<R> {
+    // `error_for_status` considers a redirect to be a success, but it is not the file we asked for.
+    if response.status().is_redirection() {
+        trace!("response is a redirect that was not followed");
+        return HttpResult::Redirect(response.status());
+    }
+    let retry_after = parse_retry_after(response.headers());
     match response.error_for_status() {
         Ok(ok) => {
             trace!("response is success");
@@ -358,9 +1033,13 @@ fn parse_response_code(response: reqwest::blocking::Response) -> HttpResult {
                 trace!("error is fatal (no status): {}", err);
                 HttpResult::Fatal(err)
             }
-            Some(status) if status.is_server_error() => {
+            Some(status) if status.is_server_error() || status.as_u16() == 429 => {
                 trace!("error is retryable: {}", err);
-                HttpResult::Retryable(err)
+                HttpResult::Retryable(err, retry_after)
+            }
+            Some(status) if status.as_u16() == 403 && !cs.treat_403_as_not_found => {
+                trace!("error is fatal (403 is not treated as not found): {}", err);
+                HttpResult::Fatal(err)
             }
             Some(status) if matches!(status.as_u16(), 403 | 404 | 410) => {
                 trace!("error is file not found: {}", err);
@@ -374,27 +1053,56 @@ fn parse_response_code(response: reqwest::blocking::Response) -> HttpResult {
     }
 }
 
-/// Builds a GET request. If `next_byte` is greater than zero, adds a byte range header to the request.
-fn build_request(client: &Client, next_byte: usize, url: &Url) -> Result<Request, HttpError> {
-    if next_byte == 0 {
-        let request = client
-            .request(Method::GET, url.as_str())
-            .build()
-            .context(RequestBuild)?;
-        Ok(request)
-    } else {
+/// Parses the `Retry-After` header, which is either a number of seconds or an HTTP-date. Returns
+/// `None` if the header is missing or cannot be parsed.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // a date in the past means that we can retry right away.
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Builds a GET request with the headers and bearer token from the `ClientSettings`. If
+/// `next_byte` is greater than zero, adds a byte range header to the request.
+fn build_request(
+    client: &Client,
+    next_byte: usize,
+    url: &Url,
+    cs: &HttpTransportBuilder,
+) -> Result<Request, HttpError> {
+    client
+        .request(Method::GET, url.as_str())
+        .headers(request_headers(next_byte, cs)?)
+        .build()
+        .context(RequestBuild)
+}
+
+/// Returns the headers to send with a request: the headers and bearer token from the
+/// `ClientSettings`, and a byte range header if `next_byte` is greater than zero.
+fn request_headers(next_byte: usize, cs: &HttpTransportBuilder) -> Result<HeaderMap, HttpError> {
+    let mut headers = cs.headers.clone();
+    if let Some(token) = &cs.bearer_token {
+        let mut header_value =
+            HeaderValue::from_str(&format!("Bearer {}", token)).context(InvalidBearerToken)?;
+        header_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, header_value);
+    }
+    if next_byte > 0 {
         let header_value_string = format!("bytes={}-", next_byte);
         let header_value =
             HeaderValue::from_str(header_value_string.as_str()).context(InvalidHeader {
                 header_value: &header_value_string,
             })?;
-        let request = client
-            .request(Method::GET, url.as_str())
-            .header(header::RANGE, header_value)
-            .build()
-            .context(RequestBuild)?;
-        Ok(request)
+        headers.insert(header::RANGE, header_value);
     }
+    Ok(headers)
 }
 
 /// The error type for the HTTP transport module.
@@ -411,15 +1119,33 @@ pub enum HttpError {
     #[snafu(display("Fetch failed after {} retries: {}", tries, source))]
     FetchNoMoreRetries { tries: u32, source: reqwest::Error },
 
+    #[snafu(display("The client TLS identity could not be loaded: {}", source))]
+    ClientIdentity { source: reqwest::Error },
+
     #[snafu(display("The HTTP client could not be built: {}", source))]
     HttpClient { source: reqwest::Error },
 
+    // The token is deliberately left out of the message.
+    #[snafu(display("Bearer token is not a valid header value: {}", source))]
+    InvalidBearerToken {
+        source: reqwest::header::InvalidHeaderValue,
+    },
+
     #[snafu(display("Invalid header value '{}': {}", header_value, source))]
     InvalidHeader {
         header_value: String,
         source: reqwest::header::InvalidHeaderValue,
     },
 
+    #[snafu(display("Response is larger than the limit of {} bytes", max_response_bytes))]
+    ResponseTooLarge { max_response_bytes: u64 },
+
+    #[snafu(display("Redirect response (HTTP status {}) was not followed", status))]
+    RedirectNotFollowed { status: u16 },
+
+    #[snafu(display("A root certificate could not be loaded: {}", source))]
+    RootCertificate { source: reqwest::Error },
+
     #[snafu(display("Unable to create HTTP request: {}", source))]
     RequestBuild { source: reqwest::Error },
 }
@@ -435,3 +1161,212 @@ impl From<(Url, HttpError)> for TransportError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Custom headers must be sent with the range request that is made when retrying a read.
+    #[test]
+    fn custom_headers_on_range_request() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Tenant-Id", HeaderValue::from_static("tenant"));
+        let settings = HttpTransportBuilder::new().headers(headers);
+        let client = Client::new();
+        let url = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+
+        let request = build_request(&client, 0, &url, &settings).unwrap();
+        assert_eq!(request.headers().get("X-Tenant-Id").unwrap(), "tenant");
+        assert!(request.headers().get(header::RANGE).is_none());
+
+        let request = build_request(&client, 42, &url, &settings).unwrap();
+        assert_eq!(request.headers().get("X-Tenant-Id").unwrap(), "tenant");
+        assert_eq!(request.headers().get(header::RANGE).unwrap(), "bytes=42-");
+    }
+
+    // The bearer token must be sent with the range request that is made when retrying a read.
+    #[test]
+    fn bearer_token_on_range_request() {
+        let settings = HttpTransportBuilder::new().bearer_token("abc123");
+        let client = Client::new();
+        let url = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+
+        for next_byte in &[0, 42] {
+            let request = build_request(&client, *next_byte, &url, &settings).unwrap();
+            let authorization = request.headers().get(header::AUTHORIZATION).unwrap();
+            assert_eq!(authorization, "Bearer abc123");
+            assert!(authorization.is_sensitive());
+        }
+    }
+
+    // A token that cannot be sent as a header is an error, and the token is not in the message.
+    #[test]
+    fn malformed_bearer_token() {
+        let settings = HttpTransportBuilder::new().bearer_token("abc\n123");
+        let client = Client::new();
+        let url = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+
+        let err = build_request(&client, 0, &url, &settings).unwrap_err();
+        assert!(matches!(err, HttpError::InvalidBearerToken { .. }));
+        assert!(!err.to_string().contains("abc"));
+        assert!(!format!("{:?}", settings).contains("abc"));
+    }
+
+    // Builds a response with the given status, as if it had been received from a server.
+    fn response(status: u16) -> reqwest::blocking::Response {
+        http::Response::builder()
+            .status(status)
+            .body("")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn response_code_classification() {
+        let cs = HttpTransportBuilder::new();
+        let parse_response_code = |response| super::parse_response_code(response, &cs);
+        assert!(matches!(
+            parse_response_code(response(200)),
+            HttpResult::Ok(_)
+        ));
+        assert!(matches!(
+            parse_response_code(response(429)),
+            HttpResult::Retryable(_, None)
+        ));
+        assert!(matches!(
+            parse_response_code(response(503)),
+            HttpResult::Retryable(_, None)
+        ));
+        assert!(matches!(
+            parse_response_code(response(404)),
+            HttpResult::FileNotFound(_)
+        ));
+        assert!(matches!(
+            parse_response_code(response(400)),
+            HttpResult::Fatal(_)
+        ));
+    }
+
+    #[test]
+    fn response_code_403() {
+        let cs = HttpTransportBuilder::new();
+        assert!(matches!(
+            parse_response_code(response(403), &cs),
+            HttpResult::FileNotFound(_)
+        ));
+        let cs = HttpTransportBuilder::new().treat_403_as_not_found(false);
+        assert!(matches!(
+            parse_response_code(response(403), &cs),
+            HttpResult::Fatal(_)
+        ));
+        assert!(matches!(
+            parse_response_code(response(404), &cs),
+            HttpResult::FileNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn retry_after_date() {
+        let mut headers = HeaderMap::new();
+        let date = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+        let wait = parse_retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+
+        // HTTP-dates in the past, and in the GMT form that servers send, are both accepted.
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn retry_after_invalid() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn jitter() {
+        // these values are exact in binary floating point.
+        let wait = Duration::from_millis(500);
+        let max = Duration::from_secs(1);
+        let millis = |d: Duration| d.as_millis();
+        assert_eq!(apply_jitter(wait, 0.0, max, 1.0), wait);
+        assert_eq!(millis(apply_jitter(wait, 0.5, max, -1.0)), 250);
+        assert_eq!(millis(apply_jitter(wait, 0.5, max, 1.0)), 750);
+        // the wait never goes below zero or above the max.
+        assert_eq!(millis(apply_jitter(wait, 5.0, max, -1.0)), 0);
+        assert_eq!(apply_jitter(max, 0.5, max, 1.0), max);
+    }
+
+    #[test]
+    fn jitter_source() {
+        let wait = Duration::from_millis(500);
+        let builder = HttpTransportBuilder::new().jitter_source(|| -1.0);
+        assert_eq!(builder.jittered(wait), wait);
+        let builder = builder.jitter_factor(0.5);
+        assert_eq!(builder.jittered(wait).as_millis(), 250);
+        let builder = builder.jitter_source(|| 7.0);
+        assert_eq!(builder.jittered(wait).as_millis(), 750);
+    }
+
+    #[test]
+    fn random_sample_range() {
+        for _ in 0..100 {
+            let sample = random_sample();
+            assert!((-1.0..=1.0).contains(&sample));
+        }
+    }
+
+    // A client identity that can't be parsed is an error when the client is built.
+    #[cfg(feature = "native-tls")]
+    #[test]
+    fn invalid_client_identity() {
+        let settings = HttpTransportBuilder::new().client_identity_pkcs12(vec![1, 2, 3], "secret");
+        let err = build_client(&settings).unwrap_err();
+        assert!(matches!(err, HttpError::ClientIdentity { .. }));
+        assert!(!format!("{:?}", settings).contains("secret"));
+
+        let url = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+        let err = settings.build().fetch(url).err().unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+    }
+
+    // The response body is read through a buffer of the configured size, and reads still return
+    // the whole body.
+    #[test]
+    fn read_buffer_size_applied() {
+        let body = vec![7; 20000];
+        let mock_file = mockito::mock("GET", "/targets/buffered.bin")
+            .with_status(200)
+            .with_body(&body)
+            .expect(2)
+            .create();
+        let url = Url::parse(&mockito::server_url())
+            .unwrap()
+            .join("targets/buffered.bin")
+            .unwrap();
+
+        let transport = HttpTransportBuilder::new().read_buffer_size(16384).build();
+        let mut read = transport.fetch_retry_read(url.clone()).unwrap();
+        assert_eq!(read.response.capacity(), 16384);
+        let mut buf = Vec::new();
+        read.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, body);
+
+        let read = HttpTransport::default().fetch_retry_read(url).unwrap();
+        assert_eq!(read.response.capacity(), 0);
+        mock_file.assert();
+    }
+}
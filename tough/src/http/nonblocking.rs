@@ -0,0 +1,387 @@
+//! The `nonblocking` module provides `AsyncHttpTransport`, the [`AsyncTransport`] counterpart of
+//! `HttpTransport`. It shares its settings and retry logic with `HttpTransport`, but sends requests
+//! with the async `reqwest` client and waits between tries with `tokio::time::sleep`.
+use super::{
+    notify_retry, redirect_policy, request_headers, supports_range, FetchFatal, FetchFileNotFound,
+    FetchNoMoreRetries, HttpClient, HttpError, HttpResponse, HttpResult, HttpTransportBuilder,
+    RedirectNotFollowed, RequestBuild, ResponseTooLarge, RetryReason, RetryState,
+};
+use crate::{AsyncFetch, AsyncTransport, TransportError};
+use futures_util::stream;
+use log::{debug, error, trace};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, ClientBuilder, Error, Method, Response, StatusCode};
+use snafu::{ensure, ResultExt};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::io::StreamReader;
+use url::Url;
+
+/// An [`AsyncTransport`] over HTTP with retry logic. Use [`HttpTransportBuilder::build_async`] to
+/// construct a custom `AsyncHttpTransport`, or use `AsyncHttpTransport::default()`.
+///
+/// This transport behaves like [`HttpTransport`], including retries of failed reads, and honors
+/// all of the same settings. It must be used from within a Tokio runtime.
+///
+/// [`HttpTransport`]: crate::HttpTransport
+#[derive(Clone, Debug, Default)]
+pub struct AsyncHttpTransport {
+    settings: HttpTransportBuilder,
+    client: OnceLock<Client>,
+}
+
+impl AsyncHttpTransport {
+    pub(super) fn new(settings: HttpTransportBuilder) -> Self {
+        Self {
+            settings,
+            client: OnceLock::new(),
+        }
+    }
+
+    /// Returns the HTTP client, building it if this is the first fetch.
+    fn client(&self) -> Result<Client, HttpError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        let client = build_client(&self.settings)?;
+        // if another task built a client first, use that one so that there is only one pool.
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
+    async fn fetch_reader(&self, url: &Url) -> Result<AsyncRetryRead, HttpError> {
+        let client = self.client()?;
+        let r = RetryState::new(self.settings.initial_backoff);
+        let fetched =
+            fetch_with_retries(r, self.settings.clone(), client.clone(), url.clone()).await?;
+        Ok(AsyncRetryRead::new(
+            fetched,
+            self.settings.clone(),
+            client,
+            url.clone(),
+        ))
+    }
+}
+
+impl AsyncTransport for AsyncHttpTransport {
+    /// Send a GET request to the URL. Request will be retried per the `ClientSettings`. The
+    /// returned reader will also retry as necessary per the `ClientSettings`.
+    fn fetch(&self, url: Url) -> AsyncFetch<'_> {
+        Box::pin(async move {
+            match self.fetch_reader(&url).await {
+                Ok(reader) => Ok(Box::pin(reader) as Pin<Box<dyn AsyncRead + Send>>),
+                Err(e) => Err(TransportError::from((url, e))),
+            }
+        })
+    }
+}
+
+/// The body of a response, or of a series of responses when a read is retried.
+type Body = Pin<Box<dyn AsyncRead + Send>>;
+
+/// A successful response, as returned by `fetch_with_retries`.
+struct Fetched {
+    body: Body,
+    supports_range: bool,
+    retry_state: RetryState,
+    /// The number of bytes to discard from the start of `body` because they were read before a
+    /// retry. Only used when compression is accepted.
+    skip: usize,
+}
+
+enum ReadState {
+    /// Reading the body of a response.
+    Reading(Body),
+    /// Waiting, then fetching the rest of the file after a read failed.
+    Refetching(Pin<Box<dyn Future<Output = Result<Fetched, HttpError>> + Send>>),
+}
+
+/// The async counterpart of `RetryRead`, which retries failed reads per the `ClientSettings`.
+struct AsyncRetryRead {
+    retry_state: RetryState,
+    settings: HttpTransportBuilder,
+    client: Client,
+    url: Url,
+    state: ReadState,
+    supports_range: bool,
+    skip: usize,
+}
+
+impl AsyncRetryRead {
+    fn new(fetched: Fetched, settings: HttpTransportBuilder, client: Client, url: Url) -> Self {
+        Self {
+            retry_state: fetched.retry_state,
+            settings,
+            client,
+            url,
+            state: ReadState::Reading(fetched.body),
+            supports_range: fetched.supports_range,
+            skip: fetched.skip,
+        }
+    }
+
+    /// Records that `sz` bytes have been read, and returns an error if the response has exceeded
+    /// `max_response_bytes`.
+    fn advance(&mut self, sz: usize) -> io::Result<()> {
+        self.retry_state.next_byte += sz;
+        if let Some(max_response_bytes) = self.settings.max_response_bytes {
+            if self.retry_state.next_byte as u64 > max_response_bytes {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    HttpError::ResponseTooLarge { max_response_bytes },
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads from the current response into `buf`, discarding bytes that were already read before
+    /// a retry. Returns `Ready(Ok(None))` if bytes were discarded and nothing is left to return.
+    fn poll_body(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<Option<usize>>> {
+        let body = match &mut self.state {
+            ReadState::Reading(body) => body,
+            ReadState::Refetching(_) => unreachable!("poll_body called while refetching"),
+        };
+        let before = buf.filled().len();
+        match body.as_mut().poll_read(cx, buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {
+                let sz = buf.filled().len() - before;
+                if self.skip == 0 {
+                    return Poll::Ready(Ok(Some(sz)));
+                }
+                if sz == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "response ended before reaching the bytes that were already read",
+                    )));
+                }
+                // discard bytes that were already returned before the retry.
+                let discard = sz.min(self.skip);
+                self.skip -= discard;
+                buf.filled_mut()[before..].copy_within(discard..sz, 0);
+                buf.set_filled(before + sz - discard);
+                Poll::Ready(Ok(if discard < sz {
+                    Some(sz - discard)
+                } else {
+                    None
+                }))
+            }
+        }
+    }
+}
+
+impl AsyncRead for AsyncRetryRead {
+    /// Read bytes into `buf`, retrying as necessary.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // retry loop
+        loop {
+            if let ReadState::Refetching(fetch) = &mut this.state {
+                match fetch.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, e)));
+                    }
+                    Poll::Ready(Ok(fetched)) => {
+                        // the new fetch succeeded so we need to replace our body with the new one.
+                        this.retry_state = fetched.retry_state;
+                        this.supports_range = fetched.supports_range;
+                        this.skip = fetched.skip;
+                        this.state = ReadState::Reading(fetched.body);
+                    }
+                }
+            }
+
+            // store the error in `retry_err` to return later if there are no more retries
+            let retry_err = match this.poll_body(cx, buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(Some(sz))) => return Poll::Ready(this.advance(sz)),
+                Poll::Ready(Ok(None)) => continue,
+                Poll::Ready(Err(err)) => err,
+            };
+            debug!("error during read of '{}': {:?}", this.url, retry_err);
+
+            // increment the `retry_state` and fetch a new body if retries are not exhausted
+            if this.retry_state.current_try >= this.settings.tries - 1 {
+                // we are out of retries, so return the last known error.
+                return Poll::Ready(Err(retry_err));
+            }
+            this.retry_state.increment(&this.settings);
+            if !this.settings.accept_compression && !this.supports_range {
+                // we cannot send a byte range request to this server, so return the error
+                error!(
+                    "an error occurred and we cannot retry because the server \
+                        does not support range requests '{}': {:?}",
+                    this.url, retry_err
+                );
+                return Poll::Ready(Err(retry_err));
+            }
+            let wait = this.settings.jittered(this.retry_state.wait);
+            notify_retry(
+                &this.settings,
+                &this.url,
+                &this.retry_state,
+                wait,
+                RetryReason::Read,
+            );
+            // wait, then retry the request (with a range header, or from the start if compressed).
+            let r = this.retry_state;
+            let settings = this.settings.clone();
+            let client = this.client.clone();
+            let url = this.url.clone();
+            this.state = ReadState::Refetching(Box::pin(async move {
+                tokio::time::sleep(wait).await;
+                fetch_with_retries(r, settings, client, url).await
+            }));
+        }
+    }
+}
+
+impl HttpResponse for Response {
+    fn status(&self) -> StatusCode {
+        Response::status(self)
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        Response::headers(self)
+    }
+
+    fn error_for_status(self) -> Result<Self, Error> {
+        Response::error_for_status(self)
+    }
+}
+
+/// Creates an async reqwest client per the `ClientSettings`.
+fn build_client(cs: &HttpTransportBuilder) -> Result<Client, HttpError> {
+    let mut builder = ClientBuilder::new()
+        .timeout(cs.timeout)
+        .connect_timeout(cs.connect_timeout)
+        .redirect(redirect_policy(cs))
+        .gzip(cs.accept_compression);
+    if let Some(user_agent) = &cs.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+    if let Some(max_connections) = cs.max_connections {
+        builder = builder.pool_max_idle_per_host(max_connections);
+    }
+    #[cfg(feature = "native-tls")]
+    {
+        let (identity, certificates) = super::tls_settings(cs)?;
+        if let Some(identity) = identity {
+            builder = builder.identity(identity);
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build().context(HttpClient)
+}
+
+/// Converts the body of a response into an `AsyncRead`.
+fn body_reader(response: Response) -> Body {
+    let chunks = stream::unfold(response, |mut response| async move {
+        match response.chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), response)),
+            Ok(None) => None,
+            Err(e) => Some((Err(io::Error::new(io::ErrorKind::Other, e)), response)),
+        }
+    });
+    Box::pin(StreamReader::new(Box::pin(chunks)))
+}
+
+/// Sends a `GET` request to the `url`. Retries the request as necessary per the `ClientSettings`.
+async fn fetch_with_retries(
+    mut r: RetryState,
+    cs: HttpTransportBuilder,
+    client: Client,
+    url: Url,
+) -> Result<Fetched, HttpError> {
+    trace!("beginning fetch for '{}'", url);
+
+    // retry loop
+    loop {
+        // build the request
+        // a compressed response can't be resumed with a range request, so start over instead.
+        let range_start = if cs.accept_compression {
+            0
+        } else {
+            r.next_byte
+        };
+        let request = client
+            .request(Method::GET, url.as_str())
+            .headers(request_headers(range_start, &cs)?)
+            .build()
+            .context(RequestBuild)?;
+
+        // send the GET request, then categories the outcome by converting to an HttpResult.
+        let http_result = HttpResult::from_result(client.execute(request).await, &cs);
+
+        let (retry_after, reason) = match http_result {
+            HttpResult::Ok(response) => {
+                trace!("{:?} - returning from successful fetch", r);
+                // fail fast if the server says that it will send too much.
+                if let (Some(max_response_bytes), Some(content_length)) =
+                    (cs.max_response_bytes, response.content_length())
+                {
+                    ensure!(
+                        range_start as u64 + content_length <= max_response_bytes,
+                        ResponseTooLarge { max_response_bytes }
+                    );
+                }
+                return Ok(Fetched {
+                    supports_range: supports_range(response.headers()),
+                    body: body_reader(response),
+                    retry_state: r,
+                    skip: r.next_byte - range_start,
+                });
+            }
+            HttpResult::Fatal(err) => {
+                trace!("{:?} - returning fatal error from fetch: {}", r, err);
+                return Err(err).context(FetchFatal);
+            }
+            HttpResult::Redirect(status) => {
+                trace!(
+                    "{:?} - returning redirect that was not followed: {}",
+                    r,
+                    status
+                );
+                return RedirectNotFollowed {
+                    status: status.as_u16(),
+                }
+                .fail();
+            }
+            HttpResult::FileNotFound(err) => {
+                trace!("{:?} - returning file not found from fetch: {}", r, err);
+                return Err(err).context(FetchFileNotFound);
+            }
+            HttpResult::Retryable(err, retry_after) => {
+                trace!("{:?} - retryable error: {}", r, err);
+                if r.current_try >= cs.tries - 1 {
+                    debug!("{:?} - returning failure, no more retries: {}", r, err);
+                    return Err(err).context(FetchNoMoreRetries { tries: cs.tries });
+                }
+                (retry_after, RetryReason::from_error(&err))
+            }
+        };
+
+        r.increment(&cs);
+        // the server's requested delay takes the place of our backoff, but is capped by it.
+        let wait = retry_after.map_or_else(|| cs.jittered(r.wait), |d| d.min(cs.max_backoff));
+        trace!("{:?} - waiting {:?} before retrying", r, wait);
+        notify_retry(&cs, &url, &r, wait, reason);
+        tokio::time::sleep(wait).await;
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::{self, Result};
+use crate::fetch::{fetch_hashed, fetch_max_size};
+use crate::schema::{Delegations, RoleType, Signed, Targets};
+use crate::{add_succinct_bins, parse_metadata, verify_delegated, verify_signed, Repository};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::io::Read;
+
+impl Repository {
+    /// Verifies all of the repository's metadata without fetching any targets.
+    ///
+    /// The signatures of the top-level roles are checked again, along with the versions that each
+    /// role records for the next: the snapshot version in `timestamp.json`, and the `targets.json`
+    /// version in `snapshot.json`. Then the metadata of every delegated role is fetched again and
+    /// checked against the delegating role's keys and the snapshot (its version, and its hashes and
+    /// length if the snapshot lists them), and every target that it lists is checked to be within
+    /// the paths that were delegated to it. The delegation tree is walked depth-first, in the order
+    /// that roles are listed.
+    ///
+    /// Returns the first inconsistency that is found as `Err`, or an error if the repository
+    /// metadata is expired (unless expiration enforcement is disabled).
+    pub fn verify_metadata(&self) -> Result<()> {
+        self.check_expiration()?;
+
+        let root = &self.root.signed;
+        let verifier = self.signature_verifier.as_ref();
+        verify_signed(root, &self.root, verifier).context(error::VerifyMetadata {
+            role: RoleType::Root,
+        })?;
+        verify_signed(root, &self.timestamp, verifier).context(error::VerifyMetadata {
+            role: RoleType::Timestamp,
+        })?;
+        verify_signed(root, &self.snapshot, verifier).context(error::VerifyMetadata {
+            role: RoleType::Snapshot,
+        })?;
+        verify_signed(root, &self.targets, verifier).context(error::VerifyMetadata {
+            role: RoleType::Targets,
+        })?;
+
+        let snapshot_meta =
+            self.timestamp
+                .signed
+                .meta
+                .get("snapshot.json")
+                .context(error::MetaMissing {
+                    file: "snapshot.json",
+                    role: RoleType::Timestamp,
+                })?;
+        ensure!(
+            snapshot_meta.version == self.snapshot.signed.version,
+            error::MetaVersionMismatch {
+                role: RoleType::Snapshot,
+                expected: snapshot_meta.version,
+                actual: self.snapshot.signed.version,
+            }
+        );
+        let targets_meta =
+            self.snapshot
+                .signed
+                .meta
+                .get("targets.json")
+                .context(error::MetaMissing {
+                    file: "targets.json",
+                    role: RoleType::Snapshot,
+                })?;
+        ensure!(
+            targets_meta.version == self.targets.signed.version,
+            error::MetaVersionMismatch {
+                role: RoleType::Targets,
+                expected: targets_meta.version,
+                actual: self.targets.signed.version,
+            }
+        );
+
+        match &self.targets.signed.delegations {
+            Some(delegations) => self.verify_delegations(delegations),
+            None => Ok(()),
+        }
+    }
+
+    /// Fetches and verifies the metadata of each role in `delegations`, and then of the roles that
+    /// they delegate to.
+    fn verify_delegations(&self, delegations: &Delegations) -> Result<()> {
+        delegations.verify_paths().context(error::InvalidPath {})?;
+        for delegated_role in delegations.roles_iter() {
+            let mut role = self.fetch_delegated_role(&delegated_role.name)?;
+            verify_delegated(
+                delegations,
+                &role,
+                &delegated_role.name,
+                self.signature_verifier.as_ref(),
+            )
+            .context(error::VerifyDelegatedRole {
+                name: &delegated_role.name,
+            })?;
+
+            for name in role.signed.targets.keys() {
+                ensure!(
+                    delegated_role.paths.matched_target(name),
+                    error::UndelegatedTarget {
+                        role: &delegated_role.name,
+                        target: name,
+                    }
+                );
+            }
+
+            if let Some(child_delegations) = &mut role.signed.delegations {
+                add_succinct_bins(child_delegations, &self.snapshot)?;
+                for child in &child_delegations.roles {
+                    delegated_role.verify_paths(&child.paths).context(
+                        error::UnauthorizedDelegation {
+                            parent: delegated_role.name.clone(),
+                            child: child.name.clone(),
+                        },
+                    )?;
+                }
+                self.verify_delegations(child_delegations)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches the metadata of the delegated role `name`, checking its version, and its length and
+    /// hash if they are listed, against the snapshot.
+    fn fetch_delegated_role(&self, name: &str) -> Result<Signed<Targets>> {
+        let role_meta = self
+            .snapshot
+            .signed
+            .meta
+            .get(&format!("{}.json", name))
+            .context(error::RoleNotInMeta { name })?;
+        let path = if self.consistent_snapshot {
+            format!("{}.{}.json", role_meta.version, name)
+        } else {
+            format!("{}.json", name)
+        };
+        let url = crate::join_url(&self.metadata_base_url, &path)?;
+        let (max_size, specifier) = match role_meta.length {
+            Some(length) => (length, "snapshot.json"),
+            None => (self.limits.max_targets_size, "max_targets_size parameter"),
+        };
+        let reader: Box<dyn Read + Send> = match &role_meta.hashes {
+            Some(hashes) => Box::new(fetch_hashed(
+                self.transport.as_ref(),
+                url,
+                max_size,
+                specifier,
+                hashes,
+            )?),
+            None => Box::new(fetch_max_size(
+                self.transport.as_ref(),
+                url,
+                max_size,
+                specifier,
+            )?),
+        };
+        let role: Signed<Targets> = parse_metadata(reader, RoleType::Targets)?;
+        ensure!(
+            role.signed.version == role_meta.version,
+            error::DelegatedVersionMismatch {
+                name,
+                fetched: role.signed.version,
+                expected: role_meta.version,
+            }
+        );
+        Ok(role)
+    }
+}
@@ -1,9 +1,16 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::error;
-use ring::digest::{Context, SHA256};
-use std::io::{self, Read};
+use crate::error::{self, Result};
+use crate::schema::Hashes;
+use ring::digest::{Algorithm, Context, SHA256, SHA512};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
 use url::Url;
 
 pub(crate) struct DigestAdapter {
@@ -14,12 +21,39 @@ pub(crate) struct DigestAdapter {
 }
 
 impl DigestAdapter {
+    /// Creates a `DigestAdapter` that verifies the strongest of `hashes` that is supported.
+    pub(crate) fn new(reader: Box<dyn Read + Send>, hashes: &Hashes, url: Url) -> Result<Self> {
+        if let Some(sha512) = &hashes.sha512 {
+            Ok(Self::sha512(reader, sha512, url))
+        } else if let Some(sha256) = &hashes.sha256 {
+            Ok(Self::sha256(reader, sha256, url))
+        } else {
+            error::NoSupportedHash {
+                context: url.to_string(),
+            }
+            .fail()
+        }
+    }
+
     pub(crate) fn sha256(reader: Box<dyn Read + Send>, hash: &[u8], url: Url) -> Self {
+        Self::with_algorithm(reader, &SHA256, hash, url)
+    }
+
+    pub(crate) fn sha512(reader: Box<dyn Read + Send>, hash: &[u8], url: Url) -> Self {
+        Self::with_algorithm(reader, &SHA512, hash, url)
+    }
+
+    fn with_algorithm(
+        reader: Box<dyn Read + Send>,
+        algorithm: &'static Algorithm,
+        hash: &[u8],
+        url: Url,
+    ) -> Self {
         Self {
             url,
             reader,
             hash: hash.to_owned(),
-            digest: Some(Context::new(&SHA256)),
+            digest: Some(Context::new(algorithm)),
         }
     }
 }
@@ -55,10 +89,13 @@ impl Read for DigestAdapter {
 pub(crate) struct MaxSizeAdapter {
     reader: Box<dyn Read + Send>,
     /// How the `max_size` was specified. For example the max size of `root.json` is specified by
-    /// the `max_root_size` argument in `Settings`. `specifier` is used to construct an error
-    /// message when the `MaxSizeAdapter` detects that too many bytes have been read.
+    /// the `max_root_size` field of the `Limits` set with `RepositoryLoader::limits`. `specifier` is
+    /// used to construct an error message when the `MaxSizeAdapter` detects that too many bytes
+    /// have been read.
     specifier: &'static str,
     max_size: u64,
+    /// Whether the stream must be exactly `max_size` bytes, rather than at most `max_size` bytes.
+    exact: bool,
     counter: u64,
 }
 
@@ -72,14 +109,35 @@ impl MaxSizeAdapter {
             reader,
             specifier,
             max_size,
+            exact: false,
             counter: 0,
         }
     }
+
+    /// Creates a `MaxSizeAdapter` that also returns an error at the end of the stream if fewer than
+    /// `size` bytes were read.
+    pub(crate) fn exact(reader: Box<dyn Read + Send>, specifier: &'static str, size: u64) -> Self {
+        Self {
+            exact: true,
+            ..Self::new(reader, specifier, size)
+        }
+    }
 }
 
 impl Read for MaxSizeAdapter {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let size = self.reader.read(buf)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Read no further than the maximum; once it has been reached, read a single byte, which is
+        // enough to tell that the stream is too long without reading any further.
+        let remaining = match self.max_size.saturating_sub(self.counter) {
+            0 => 1,
+            remaining => remaining,
+        };
+        let len =
+            usize::try_from(remaining).map_or(buf.len(), |remaining| remaining.min(buf.len()));
+        let size = self.reader.read(&mut buf[..len])?;
         self.counter += size as u64;
         if self.counter > self.max_size {
             error::MaxSizeExceeded {
@@ -88,17 +146,272 @@ impl Read for MaxSizeAdapter {
             }
             .fail()?;
         }
+        if size == 0 && self.exact && self.counter < self.max_size {
+            error::LengthMismatch {
+                length: self.max_size,
+                specifier: self.specifier,
+                read: self.counter,
+            }
+            .fail()?;
+        }
+        Ok(size)
+    }
+}
+
+/// Keeps a copy of the bytes read through it, if enabled, so that the exact bytes of a metadata
+/// file can be reported after it has been parsed and verified.
+pub(crate) struct CaptureAdapter<R> {
+    reader: R,
+    bytes: Option<Vec<u8>>,
+}
+
+impl<R: Read> CaptureAdapter<R> {
+    /// Creates a `CaptureAdapter` that keeps the bytes read from `reader` if `capture` is true, and
+    /// otherwise passes them through.
+    pub(crate) fn new(reader: R, capture: bool) -> Self {
+        Self {
+            reader,
+            bytes: if capture { Some(Vec::new()) } else { None },
+        }
+    }
+
+    /// Returns the bytes that have been read, or an empty slice if they were not kept.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        self.bytes.as_deref().unwrap_or_default()
+    }
+}
+
+impl<R: Read> Read for CaptureAdapter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.reader.read(buf)?;
+        if let Some(bytes) = &mut self.bytes {
+            bytes.extend_from_slice(&buf[..size]);
+        }
+        Ok(size)
+    }
+}
+
+/// The period over which a `ThrottleAdapter` limits its reads.
+const THROTTLE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Sleeps during reads as needed to stay under `bytes_per_second`. The limit applies to any window
+/// of a second, which slides along with the reads, so that short bursts are smoothed out rather
+/// than each read being delayed on its own, and a burst at the end of one second can't be followed
+/// by another at the start of the next.
+pub(crate) struct ThrottleAdapter {
+    reader: Box<dyn Read + Send>,
+    window: Duration,
+    /// The most bytes that may be read within any one `window`.
+    window_bytes: u64,
+    /// When each read within the last `window` finished, and how many bytes it read, oldest
+    /// first.
+    reads: VecDeque<(Instant, u64)>,
+}
+
+impl ThrottleAdapter {
+    pub(crate) fn new(reader: Box<dyn Read + Send>, bytes_per_second: NonZeroU64) -> Self {
+        Self::with_window(reader, bytes_per_second, THROTTLE_WINDOW)
+    }
+
+    fn with_window(
+        reader: Box<dyn Read + Send>,
+        bytes_per_second: NonZeroU64,
+        window: Duration,
+    ) -> Self {
+        let window_bytes = u128::from(bytes_per_second.get()) * window.as_nanos() / 1_000_000_000;
+        Self {
+            reader,
+            window,
+            window_bytes: u64::try_from(window_bytes).unwrap_or(u64::MAX).max(1),
+            reads: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of bytes read within the last `window`, forgetting older reads.
+    fn bytes_in_window(&mut self, now: Instant) -> u64 {
+        while let Some((at, _)) = self.reads.front() {
+            if now.saturating_duration_since(*at) < self.window {
+                break;
+            }
+            self.reads.pop_front();
+        }
+        self.reads.iter().map(|(_, size)| size).sum()
+    }
+}
+
+impl Read for ThrottleAdapter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // don't read more than one window's worth at a time, so that a large buffer doesn't
+        // result in one large burst followed by a long sleep.
+        let max = usize::try_from(self.window_bytes)
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+        let size = self.reader.read(&mut buf[..max])?;
+        if size == 0 {
+            return Ok(0);
+        }
+
+        // wait until enough of the earlier reads have left the window to make room for this one.
+        let size_u64 = size as u64;
+        loop {
+            let now = Instant::now();
+            let in_window = self.bytes_in_window(now);
+            match self.reads.front() {
+                Some((oldest, _)) if in_window + size_u64 > self.window_bytes => {
+                    std::thread::sleep((*oldest + self.window).saturating_duration_since(now));
+                }
+                _ => break,
+            }
+        }
+        self.reads.push_back((Instant::now(), size_u64));
+        Ok(size)
+    }
+}
+
+/// Gives a file staged with `NamedTempFile`, which only its owner can read, the permissions that
+/// `File::create` would have given it: read and write for everyone, less the process umask. The
+/// umask can't be read without changing it for the whole process, so it is found by creating an
+/// empty file next to the staged one.
+#[cfg(unix)]
+pub(crate) fn set_default_permissions(staged: &NamedTempFile) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut probe_path = staged.path().as_os_str().to_owned();
+    probe_path.push(".mode");
+    let probe = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o666)
+        .open(&probe_path)?;
+    let permissions = probe.metadata().map(|metadata| metadata.permissions());
+    drop(probe);
+    let removed = std::fs::remove_file(&probe_path);
+    staged.as_file().set_permissions(permissions?)?;
+    removed
+}
+
+/// Files staged with `NamedTempFile` already get the default permissions on other platforms.
+#[cfg(not(unix))]
+pub(crate) fn set_default_permissions(_staged: &NamedTempFile) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copies everything that is read to a temporary file, which is renamed to `path` once the end of
+/// the file is reached. If the reader is dropped before then, the temporary file is removed, so a
+/// partial read never leaves a partial file at `path`.
+pub(crate) struct CacheAdapter {
+    reader: Box<dyn Read + Send>,
+    file: Option<NamedTempFile>,
+    path: PathBuf,
+}
+
+impl CacheAdapter {
+    /// Creates the temporary file in the same directory as `path`, so that it can be renamed.
+    pub(crate) fn new(reader: Box<dyn Read + Send>, path: PathBuf) -> io::Result<Self> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        Ok(Self {
+            reader,
+            file: Some(NamedTempFile::new_in(dir)?),
+            path,
+        })
+    }
+}
+
+impl Read for CacheAdapter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.reader.read(buf)?;
+        if size == 0 {
+            if let Some(file) = self.file.take() {
+                set_default_permissions(&file)?;
+                // a concurrent fetch of the same file may have already put it in place, in which
+                // case renaming over it (or failing to, on Windows) is fine.
+                if let Err(e) = file.persist(&self.path) {
+                    if !self.path.is_file() {
+                        return Err(e.error);
+                    }
+                }
+            }
+        } else if let Some(file) = &mut self.file {
+            file.write_all(&buf[..size])?;
+        }
         Ok(size)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::io::{DigestAdapter, MaxSizeAdapter};
+    use crate::io::{CacheAdapter, DigestAdapter, MaxSizeAdapter, ThrottleAdapter};
+    use crate::schema::Hashes;
     use hex_literal::hex;
+    use std::collections::HashMap;
     use std::io::{Cursor, Read};
+    use std::num::NonZeroU64;
+    use std::time::{Duration, Instant};
     use url::Url;
 
+    #[test]
+    fn test_throttle_adapter() {
+        // 1000 bytes per second is 100 bytes per 100ms window, so after the first 100 bytes, each
+        // 100 bytes waits for the previous ones to leave the window.
+        let window = Duration::from_millis(100);
+        let mut reader = ThrottleAdapter::with_window(
+            Box::new(Cursor::new(vec![0; 300])),
+            NonZeroU64::new(1000).unwrap(),
+            window,
+        );
+        let start = Instant::now();
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_ok());
+        assert_eq!(buf.len(), 300);
+        assert!(start.elapsed() >= window * 2);
+
+        // the window slides: half a window after a full window's worth was read, the next read
+        // waits until the first one has left the window.
+        let mut reader = ThrottleAdapter::with_window(
+            Box::new(Cursor::new(vec![0; 200])),
+            NonZeroU64::new(1000).unwrap(),
+            window,
+        );
+        let mut buf = [0; 100];
+        reader.read_exact(&mut buf).unwrap();
+        std::thread::sleep(window / 2);
+        let start = Instant::now();
+        reader.read_exact(&mut buf).unwrap();
+        assert!(start.elapsed() >= window / 4);
+    }
+
+    #[test]
+    fn test_cache_adapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cached");
+
+        // a partial read leaves nothing behind.
+        let mut reader =
+            CacheAdapter::new(Box::new(Cursor::new(b"hello".to_vec())), path.clone()).unwrap();
+        let mut buf = [0; 2];
+        reader.read_exact(&mut buf).unwrap();
+        drop(reader);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let mut reader =
+            CacheAdapter::new(Box::new(Cursor::new(b"hello".to_vec())), path.clone()).unwrap();
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_ok());
+        assert_eq!(buf, b"hello");
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+        // the cached file gets the permissions of any other new file, not those of the temporary
+        // file it was staged in.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path| std::fs::metadata(path).unwrap().permissions().mode();
+            let created = dir.path().join("created");
+            std::fs::File::create(&created).unwrap();
+            assert_eq!(mode(&path), mode(&created));
+        }
+    }
+
     #[test]
     fn test_max_size_adapter() {
         let mut reader = MaxSizeAdapter::new(Box::new(Cursor::new(b"hello".to_vec())), "test", 5);
@@ -111,6 +424,25 @@ mod tests {
         assert!(reader.read_to_end(&mut buf).is_err());
     }
 
+    #[test]
+    fn test_max_size_adapter_exact() {
+        let mut reader = MaxSizeAdapter::exact(Box::new(Cursor::new(b"hello".to_vec())), "test", 5);
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_ok());
+        assert_eq!(buf, b"hello");
+
+        // truncated
+        let mut reader = MaxSizeAdapter::exact(Box::new(Cursor::new(b"hello".to_vec())), "test", 6);
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_err());
+
+        // padded; the error is returned as soon as the sixth byte is read.
+        let mut reader = MaxSizeAdapter::exact(Box::new(Cursor::new(vec![0; 100])), "test", 5);
+        let mut buf = [0; 50];
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert!(reader.read(&mut buf).is_err());
+    }
+
     #[test]
     fn test_digest_adapter() {
         let mut reader = DigestAdapter::sha256(
@@ -130,4 +462,44 @@ mod tests {
         let mut buf = Vec::new();
         assert!(reader.read_to_end(&mut buf).is_err());
     }
+
+    #[test]
+    fn test_digest_adapter_sha512() {
+        let sha512 = hex!("9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043");
+        let mut reader = DigestAdapter::sha512(
+            Box::new(Cursor::new(b"hello".to_vec())),
+            &sha512,
+            Url::parse("file:///").unwrap(),
+        );
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_ok());
+        assert_eq!(buf, b"hello");
+
+        // The SHA-512 hash is preferred, so the wrong SHA-256 hash is not checked.
+        let hashes = Hashes {
+            sha256: Some(vec![0; 32].into()),
+            sha512: Some(sha512.to_vec().into()),
+            _extra: HashMap::new(),
+        };
+        let mut reader = DigestAdapter::new(
+            Box::new(Cursor::new(b"hello".to_vec())),
+            &hashes,
+            Url::parse("file:///").unwrap(),
+        )
+        .unwrap();
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_ok());
+
+        let hashes = Hashes {
+            sha256: None,
+            sha512: None,
+            _extra: HashMap::new(),
+        };
+        assert!(DigestAdapter::new(
+            Box::new(Cursor::new(b"hello".to_vec())),
+            &hashes,
+            Url::parse("file:///").unwrap(),
+        )
+        .is_err());
+    }
 }
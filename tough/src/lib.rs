@@ -29,35 +29,90 @@
     clippy::missing_errors_doc
 )]
 
+#[cfg(feature = "archive")]
+pub mod archive;
 mod cache;
+mod clock;
 mod datastore;
+mod delegated;
+mod diff;
 pub mod editor;
 pub mod error;
 mod fetch;
+#[cfg(feature = "gcs")]
+pub mod gcs;
 #[cfg(feature = "http")]
 pub mod http;
+mod integrity;
 mod io;
 pub mod key_source;
+#[cfg(feature = "async")]
+mod load_async;
+mod mirrors;
+#[cfg(feature = "oci")]
+pub mod oci;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod sbom;
 pub mod schema;
 pub mod sign;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod transport;
 
-use crate::datastore::Datastore;
+/// A transport for files in a tar or zip archive.
+#[cfg(feature = "archive")]
+pub use crate::archive::ArchiveTransport;
+pub use crate::clock::{Clock, FixedClock, SystemClock};
+pub use crate::datastore::{Datastore, FilesystemDatastore, MemoryDatastore};
+use crate::delegated::{delegated_metadata, DelegatedMetadata};
+pub use crate::diff::{RefreshOutcome, TargetChanges};
 use crate::error::Result;
-use crate::fetch::{fetch_max_size, fetch_sha256};
+use crate::fetch::{fetch_metadata, MetadataFetch};
+/// A transport for objects in a Google Cloud Storage bucket.
+#[cfg(feature = "gcs")]
+pub use crate::gcs::GcsTransport;
+/// An async HTTP transport that includes retries.
+#[cfg(feature = "async")]
+pub use crate::http::AsyncHttpTransport;
 /// An HTTP transport that includes retries.
 #[cfg(feature = "http")]
-pub use crate::http::{HttpTransport, HttpTransportBuilder, RetryRead};
-use crate::schema::{DelegatedRole, Delegations};
-use crate::schema::{Role, RoleType, Root, Signed, Snapshot, Timestamp};
+pub use crate::http::{
+    HttpTransport, HttpTransportBuilder, RedirectPolicy, RetryEvent, RetryRead, RetryReason,
+};
+use crate::io::{set_default_permissions, CaptureAdapter};
+use crate::mirrors::{MirrorTransport, MirrorUrls};
+/// A transport for files that are stored as blobs in an OCI registry.
+#[cfg(feature = "oci")]
+pub use crate::oci::OciTransport;
+/// A transport for files in an Amazon S3 bucket.
+#[cfg(feature = "s3")]
+pub use crate::s3::S3Transport;
+use crate::schema::decoded::{Decoded, Hex};
+use crate::schema::key::Key;
+use crate::schema::{DelegatedRole, Delegations, Hashes, SnapshotMeta, Target, TimestampMeta};
+use crate::schema::{
+    Mirrors, Role, RoleType, Root, SignatureVerifier, Signed, Snapshot, Timestamp,
+};
+#[cfg(feature = "async")]
+pub use crate::transport::{AsyncFetch, AsyncTransport};
 pub use crate::transport::{
-    DefaultTransport, FilesystemTransport, Transport, TransportError, TransportErrorKind,
+    CachingTransport, ChainTransport, DefaultTransport, FetchEvent, FetchOutcome,
+    FilesystemTransport, MeteredTransport, ThrottledTransport, Transport, TransportError,
+    TransportErrorKind,
 };
 use chrono::{DateTime, Utc};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::HashMap;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Formatter};
 use std::io::Read;
-use std::path::PathBuf;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
 use url::Url;
 
 /// Represents whether a Repository should fail to load when metadata is expired (`Safe`) or whether
@@ -140,7 +195,7 @@ impl From<ExpirationEnforcement> for bool {
 ///     metadata_base_url,
 ///     targets_base_url,
 /// )
-/// .transport(FilesystemTransport)
+/// .transport(FilesystemTransport::new())
 /// .expiration_enforcement(ExpirationEnforcement::Unsafe)
 /// .load()
 /// .unwrap();
@@ -155,9 +210,17 @@ where
     metadata_base_url: Url,
     targets_base_url: Url,
     transport: Option<Box<dyn Transport>>,
+    targets_transport: Option<Box<dyn Transport>>,
+    #[cfg(feature = "async")]
+    async_transport: Option<Box<dyn AsyncTransport>>,
     limits: Option<Limits>,
-    datastore: Option<PathBuf>,
+    datastore: Option<Box<dyn Datastore>>,
+    clock: Option<Box<dyn Clock>>,
     expiration_enforcement: Option<ExpirationEnforcement>,
+    mirrors: bool,
+    on_metadata_fetched: Option<MetadataCallback>,
+    signature_verifier: Option<SharedVerifier>,
+    lazy_delegated_roles: bool,
 }
 
 impl<R: Read> RepositoryLoader<R> {
@@ -176,9 +239,17 @@ impl<R: Read> RepositoryLoader<R> {
             metadata_base_url,
             targets_base_url,
             transport: None,
+            targets_transport: None,
+            #[cfg(feature = "async")]
+            async_transport: None,
             limits: None,
             datastore: None,
+            clock: None,
             expiration_enforcement: None,
+            mirrors: false,
+            on_metadata_fetched: None,
+            signature_verifier: None,
+            lazy_delegated_roles: false,
         }
     }
 
@@ -193,6 +264,25 @@ impl<R: Read> RepositoryLoader<R> {
         self
     }
 
+    /// Set a separate transport for fetching targets, e.g. to fetch metadata from one origin and
+    /// targets from a bulk object store with different credentials. If no targets transport has
+    /// been set, targets are fetched with the same transport as metadata.
+    pub fn targets_transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.targets_transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Set an [`AsyncTransport`] to fetch metadata with in [`RepositoryLoader::load_async`], in
+    /// place of the [`Transport`]. The returned [`Repository`] fetches targets, and refreshes its
+    /// metadata, with the [`Transport`] as usual, since its methods block.
+    ///
+    /// Requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub fn async_transport<T: AsyncTransport + 'static>(mut self, transport: T) -> Self {
+        self.async_transport = Some(Box::new(transport));
+        self
+    }
+
     /// Set a the repository [`Limits`].
     pub fn limits(mut self, limits: Limits) -> Self {
         self.limits = Some(limits);
@@ -207,7 +297,23 @@ impl<R: Read> RepositoryLoader<R> {
     /// exist prior to calling [`RepositoryLoader::load`]. If no datastore is provided, a temporary
     /// directory will be created and cleaned up for for you.
     pub fn datastore<P: Into<PathBuf>>(mut self, datastore: P) -> Self {
-        self.datastore = Some(datastore.into());
+        self.datastore = Some(Box::new(FilesystemDatastore::new(datastore)));
+        self
+    }
+
+    /// Set the [`Datastore`] that stores the most recently fetched timestamp, snapshot, and targets
+    /// metadata files, in place of a directory set with [`RepositoryLoader::datastore`]. For
+    /// example, a [`MemoryDatastore`] can be used where there is no writable filesystem.
+    pub fn datastore_backend<D: Datastore + 'static>(mut self, datastore: D) -> Self {
+        self.datastore = Some(Box::new(datastore));
+        self
+    }
+
+    /// Set the [`Clock`] that gives the current time when checking whether metadata has expired,
+    /// both while loading and when reading targets. If no clock has been set, [`SystemClock`] will
+    /// be used.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Box::new(clock));
         self
     }
 
@@ -220,6 +326,156 @@ impl<R: Read> RepositoryLoader<R> {
         self.expiration_enforcement = Some(exp);
         self
     }
+
+    /// Set whether to load `mirrors.json`, the optional metadata file that lists mirrors of the
+    /// repository. If enabled, `mirrors.json` is fetched from the metadata base URL after root.json
+    /// has been updated, and must be signed by the mirrors role in root.json. Each later fetch is
+    /// then made from the mirrors that serve the file, in the order they are listed.
+    ///
+    /// If the repository has no `mirrors.json`, or loading it is not enabled (the default), files
+    /// are fetched from `metadata_base_url` and `targets_base_url`.
+    pub fn mirrors(mut self, mirrors: bool) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Set a callback that is called with a [`MetadataEvent`] for each metadata file that is
+    /// fetched, once it has been verified, e.g. to archive the exact bytes that were trusted. This
+    /// includes each new version of root.json, and the metadata of delegated roles, which is
+    /// fetched as it is needed and when refreshing, after loading has finished.
+    ///
+    /// The callback does not change how metadata is verified. A file is reported as soon as its
+    /// own checks pass, so a file may be reported even if loading fails at a later step.
+    pub fn on_metadata_fetched<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&MetadataEvent<'_>) + Send + Sync + 'static,
+    {
+        self.on_metadata_fetched = Some(MetadataCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Set whether to load the metadata of delegated targets roles lazily. By default, the whole
+    /// delegation tree is loaded and verified along with the top-level roles.
+    ///
+    /// If enabled, only the top-level roles are loaded, and the metadata of a delegated role is
+    /// fetched and verified the first time that the search for a target reaches it, e.g. with
+    /// [`Repository::read_target`]. This avoids fetching every role of a repository with many
+    /// delegated roles, such as hashed bins. Until [`Repository::load_delegated_roles`] is called,
+    /// the methods that list every role or target, such as [`Repository::targets`],
+    /// [`Repository::delegated_roles`] and [`Repository::target_names`], only include the roles
+    /// that have been loaded.
+    pub fn lazy_delegated_roles(mut self, lazy: bool) -> Self {
+        self.lazy_delegated_roles = lazy;
+        self
+    }
+
+    /// Set a [`SignatureVerifier`] to check the signatures of metadata, e.g. with an HSM, in place
+    /// of checking them in process. It is used for every role, both while loading and for the
+    /// delegated roles that are loaded later, and the signatures of each role are checked
+    /// concurrently.
+    pub fn signature_verifier<V: SignatureVerifier + Send + 'static>(
+        mut self,
+        verifier: V,
+    ) -> Self {
+        self.signature_verifier = Some(SharedVerifier(Arc::new(verifier)));
+        self
+    }
+}
+
+/// The settings of a [`RepositoryLoader`], with defaults in place of the ones that were not set, as
+/// they are used while loading a [`Repository`].
+#[derive(Debug)]
+pub(crate) struct LoadSettings {
+    transport: Box<dyn Transport>,
+    targets_transport: Option<Box<dyn Transport>>,
+    datastore: Box<dyn Datastore>,
+    clock: Box<dyn Clock>,
+    limits: Limits,
+    expiration_enforcement: ExpirationEnforcement,
+    metadata_base_url: Url,
+    targets_base_url: Url,
+    mirrors: bool,
+    on_metadata_fetched: Option<MetadataCallback>,
+    signature_verifier: Option<SharedVerifier>,
+    lazy_delegated_roles: bool,
+}
+
+impl LoadSettings {
+    /// Returns the trusted root metadata file of `loader`, and its other settings.
+    pub(crate) fn new<R: Read>(loader: RepositoryLoader<R>) -> Result<(R, Self)> {
+        let datastore: Box<dyn Datastore> = match loader.datastore {
+            Some(datastore) => datastore,
+            None => Box::new(FilesystemDatastore::temporary()?),
+        };
+        let settings = Self {
+            transport: loader
+                .transport
+                .unwrap_or_else(|| Box::new(DefaultTransport::new())),
+            targets_transport: loader.targets_transport,
+            datastore,
+            clock: loader.clock.unwrap_or_else(|| Box::new(SystemClock)),
+            limits: loader.limits.unwrap_or_default(),
+            expiration_enforcement: loader.expiration_enforcement.unwrap_or_default(),
+            metadata_base_url: parse_url(loader.metadata_base_url)?,
+            targets_base_url: parse_url(loader.targets_base_url)?,
+            mirrors: loader.mirrors,
+            on_metadata_fetched: loader.on_metadata_fetched,
+            signature_verifier: loader.signature_verifier,
+            lazy_delegated_roles: loader.lazy_delegated_roles,
+        };
+        Ok((loader.root, settings))
+    }
+
+    /// Makes the transports fetch files from the mirrors listed in `mirrors`.
+    pub(crate) fn use_mirrors(mut self, mirrors: &Signed<Mirrors>) -> Self {
+        let mirror_urls = MirrorUrls::new(
+            mirrors.signed.mirrors.clone(),
+            self.metadata_base_url.clone(),
+            self.targets_base_url.clone(),
+        );
+        let mirrored = |inner: Box<dyn Transport>| -> Box<dyn Transport> {
+            Box::new(MirrorTransport::new(inner, mirror_urls.clone()))
+        };
+        self.transport = mirrored(self.transport);
+        self.targets_transport = self.targets_transport.map(mirrored);
+        self
+    }
+
+    /// Returns a `Repository` with the verified metadata of the top-level roles. The metadata of
+    /// delegated roles has not been loaded yet.
+    pub(crate) fn into_repository(
+        self,
+        root: Signed<Root>,
+        mirrors: Option<Signed<Mirrors>>,
+        timestamp: Signed<Timestamp>,
+        snapshot: Signed<Snapshot>,
+        targets: Signed<crate::schema::Targets>,
+    ) -> Repository {
+        let (earliest_expiration, earliest_expiration_role) =
+            earliest_expiration(&root, &timestamp, &snapshot, &targets);
+        Repository {
+            transport: self.transport,
+            targets_transport: self.targets_transport,
+            consistent_snapshot: root.signed.consistent_snapshot,
+            datastore: self.datastore,
+            clock: self.clock,
+            earliest_expiration,
+            earliest_expiration_role,
+            root,
+            delegated: delegated_metadata(&snapshot),
+            snapshot,
+            timestamp,
+            targets,
+            mirrors,
+            limits: self.limits,
+            metadata_base_url: self.metadata_base_url,
+            targets_base_url: self.targets_base_url,
+            expiration_enforcement: self.expiration_enforcement,
+            on_metadata_fetched: self.on_metadata_fetched,
+            signature_verifier: self.signature_verifier,
+            lazy_delegated_roles: self.lazy_delegated_roles,
+        }
+    }
 }
 
 /// Limits used when fetching repository metadata.
@@ -228,8 +484,9 @@ impl<R: Read> RepositoryLoader<R> {
 /// are set higher than what would reasonably be expected by a repository, but not so high that the
 /// amount of data could interfere with the system.
 ///
-/// `max_root_size` and `max_timestamp_size` are the maximum size for the `root.json` and
-/// `timestamp.json` files, respectively, downloaded from the repository. These must be
+/// `max_root_size`, `max_timestamp_size` and `max_snapshot_size` are the maximum size for the
+/// `root.json`, `timestamp.json` and `snapshot.json` files, respectively, downloaded from the
+/// repository. These must be
 /// sufficiently large such that future updates to your repository's key management strategy
 /// will still be supported, but sufficiently small such that you are protected against an
 /// endless data attack (defined by TUF as an attacker responding to clients with extremely
@@ -239,8 +496,25 @@ impl<R: Read> RepositoryLoader<R> {
 /// * `max_root_size`: 1 MiB
 /// * `max_targets_size`: 10 MiB
 /// * `max_timestamp_size`: 1 MiB
+/// * `max_snapshot_size`: 10 MiB
 /// * `max_root_updates`: 1024
-#[derive(Debug, Clone, Copy)]
+/// * `max_delegation_depth`: 32
+/// * `max_mirrors_size`: 1 MiB
+///
+/// A role whose metadata exceeds its limit is reported as [`error::Error::MetadataSizeExceeded`].
+///
+/// New limits may be added in later versions, so `Limits` can't be constructed with a struct
+/// literal outside of this crate. Use [`Limits::builder`] to change only the limits you need:
+///
+/// ```rust
+/// # use tough::Limits;
+/// let limits = Limits::builder()
+///     .max_timestamp_size(64 * 1024)
+///     .max_delegation_depth(4)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct Limits {
     /// The maximum allowable size in bytes for downloaded root.json files.
     pub max_root_size: u64,
@@ -253,122 +527,408 @@ pub struct Limits {
     /// The maximum allowable size in bytes for the downloaded timestamp.json file.
     pub max_timestamp_size: u64,
 
+    /// The maximum allowable size in bytes for the downloaded snapshot.json file. The snapshot is
+    /// also bounded by the size listed in the signed timestamp.json file; a listed size larger
+    /// than this limit is rejected before downloading.
+    pub max_snapshot_size: u64,
+
     /// The maximum number of updates to root.json to download.
     pub max_root_updates: u64,
+
+    /// The maximum depth of delegated targets roles to load. Roles delegated by the top-level
+    /// targets role are at depth 1, roles they delegate to are at depth 2, and so on.
+    pub max_delegation_depth: usize,
+
+    /// The maximum allowable size in bytes for the downloaded mirrors.json file, if loading it is
+    /// enabled with [`RepositoryLoader::mirrors`].
+    pub max_mirrors_size: u64,
 }
 
 impl Default for Limits {
     fn default() -> Self {
         Self {
-            max_root_size: 1024 * 1024,         // 1 MiB
-            max_targets_size: 1024 * 1024 * 10, // 10 MiB
-            max_timestamp_size: 1024 * 1024,    // 1 MiB
+            max_root_size: 1024 * 1024,          // 1 MiB
+            max_targets_size: 1024 * 1024 * 10,  // 10 MiB
+            max_timestamp_size: 1024 * 1024,     // 1 MiB
+            max_snapshot_size: 1024 * 1024 * 10, // 10 MiB
             max_root_updates: 1024,
+            max_delegation_depth: 32,
+            max_mirrors_size: 1024 * 1024, // 1 MiB
         }
     }
 }
 
+impl Limits {
+    /// Returns a [`LimitsBuilder`] that starts from the [`Default`] limits.
+    pub fn builder() -> LimitsBuilder {
+        LimitsBuilder::default()
+    }
+}
+
+/// A builder for [`Limits`]. Any limit that is not set keeps its [`Default`] value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LimitsBuilder {
+    limits: Limits,
+}
+
+impl LimitsBuilder {
+    /// Set [`Limits::max_root_size`].
+    pub fn max_root_size(mut self, max_root_size: u64) -> Self {
+        self.limits.max_root_size = max_root_size;
+        self
+    }
+
+    /// Set [`Limits::max_targets_size`].
+    pub fn max_targets_size(mut self, max_targets_size: u64) -> Self {
+        self.limits.max_targets_size = max_targets_size;
+        self
+    }
+
+    /// Set [`Limits::max_timestamp_size`].
+    pub fn max_timestamp_size(mut self, max_timestamp_size: u64) -> Self {
+        self.limits.max_timestamp_size = max_timestamp_size;
+        self
+    }
+
+    /// Set [`Limits::max_snapshot_size`].
+    pub fn max_snapshot_size(mut self, max_snapshot_size: u64) -> Self {
+        self.limits.max_snapshot_size = max_snapshot_size;
+        self
+    }
+
+    /// Set [`Limits::max_root_updates`].
+    pub fn max_root_updates(mut self, max_root_updates: u64) -> Self {
+        self.limits.max_root_updates = max_root_updates;
+        self
+    }
+
+    /// Set [`Limits::max_delegation_depth`].
+    pub fn max_delegation_depth(mut self, max_delegation_depth: usize) -> Self {
+        self.limits.max_delegation_depth = max_delegation_depth;
+        self
+    }
+
+    /// Set [`Limits::max_mirrors_size`].
+    pub fn max_mirrors_size(mut self, max_mirrors_size: u64) -> Self {
+        self.limits.max_mirrors_size = max_mirrors_size;
+        self
+    }
+
+    /// Returns the [`Limits`].
+    pub fn build(self) -> Limits {
+        self.limits
+    }
+}
+
+/// Describes a metadata file that was fetched and verified, passed to the callback set with
+/// [`RepositoryLoader::on_metadata_fetched`].
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataEvent<'a> {
+    /// The type of the role. Delegated roles have the type [`RoleType::Targets`].
+    pub role: RoleType,
+    /// The name of the role, e.g. `snapshot`, or the name of a delegated role.
+    pub name: &'a str,
+    /// The version of the metadata.
+    pub version: NonZeroU64,
+    /// The bytes of the metadata file, exactly as they were fetched.
+    pub bytes: &'a [u8],
+}
+
+/// The callback set with [`RepositoryLoader::on_metadata_fetched`].
+#[derive(Clone)]
+pub(crate) struct MetadataCallback(Arc<dyn Fn(&MetadataEvent<'_>) + Send + Sync>);
+
+impl Debug for MetadataCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<callback>")
+    }
+}
+
+/// Calls `callback`, if there is one, with the metadata of `role` that was read through `reader`.
+fn report_fetched<R: Read>(
+    callback: Option<&MetadataCallback>,
+    role: RoleType,
+    name: &str,
+    version: NonZeroU64,
+    reader: &CaptureAdapter<R>,
+) {
+    if let Some(callback) = callback {
+        (callback.0)(&MetadataEvent {
+            role,
+            name,
+            version,
+            bytes: reader.bytes(),
+        });
+    }
+}
+
+/// The verifier set with [`RepositoryLoader::signature_verifier`].
+#[derive(Clone)]
+pub(crate) struct SharedVerifier(Arc<dyn SignatureVerifier + Send>);
+
+impl Debug for SharedVerifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<verifier>")
+    }
+}
+
+/// Checks that `role` is signed by a threshold of its keys in `root`, with `verifier` if one has
+/// been set.
+fn verify_signed<T: Role + Serialize>(
+    root: &Root,
+    role: &Signed<T>,
+    verifier: Option<&SharedVerifier>,
+) -> crate::schema::Result<()> {
+    match verifier {
+        Some(verifier) => root.verify_role_with(role, verifier.0.as_ref()),
+        None => root.verify_role(role),
+    }
+}
+
+/// Checks that the delegated role `name` is signed by a threshold of its keys in `delegations`,
+/// with `verifier` if one has been set.
+pub(crate) fn verify_delegated(
+    delegations: &Delegations,
+    role: &Signed<crate::schema::Targets>,
+    name: &str,
+    verifier: Option<&SharedVerifier>,
+) -> crate::schema::Result<()> {
+    match verifier {
+        Some(verifier) => delegations.verify_role_with(role, name, verifier.0.as_ref()),
+        None => delegations.verify_role(role, name),
+    }
+}
+
 /// A TUF repository.
 ///
 /// You can create a `Repository` using a [`RepositoryLoader`].
 #[derive(Debug, Clone)]
 pub struct Repository {
     transport: Box<dyn Transport>,
+    targets_transport: Option<Box<dyn Transport>>,
     consistent_snapshot: bool,
-    datastore: Datastore,
+    datastore: Box<dyn Datastore>,
+    clock: Box<dyn Clock>,
     earliest_expiration: DateTime<Utc>,
     earliest_expiration_role: RoleType,
     root: Signed<Root>,
     snapshot: Signed<Snapshot>,
     timestamp: Signed<Timestamp>,
     targets: Signed<crate::schema::Targets>,
+    delegated: DelegatedMetadata,
+    mirrors: Option<Signed<Mirrors>>,
     limits: Limits,
     metadata_base_url: Url,
     targets_base_url: Url,
     expiration_enforcement: ExpirationEnforcement,
+    on_metadata_fetched: Option<MetadataCallback>,
+    signature_verifier: Option<SharedVerifier>,
+    lazy_delegated_roles: bool,
 }
 
 impl Repository {
     /// Load and verify TUF repository metadata using a [`RepositoryLoader`] for the settings.
     fn load<R: Read>(loader: RepositoryLoader<R>) -> Result<Self> {
-        let datastore = Datastore::new(loader.datastore)?;
-        let transport = loader
-            .transport
-            .unwrap_or_else(|| Box::new(DefaultTransport::new()));
-        let limits = loader.limits.unwrap_or_default();
-        let expiration_enforcement = loader.expiration_enforcement.unwrap_or_default();
-        let metadata_base_url = parse_url(loader.metadata_base_url)?;
-        let targets_base_url = parse_url(loader.targets_base_url)?;
+        let (root, mut settings) = LoadSettings::new(loader)?;
+        let transport = settings.transport.as_ref();
+        let datastore = settings.datastore.as_ref();
+        let clock = settings.clock.as_ref();
+        let limits = settings.limits;
+        let expiration_enforcement = settings.expiration_enforcement;
+        let on_metadata_fetched = settings.on_metadata_fetched.as_ref();
+        let signature_verifier = settings.signature_verifier.as_ref();
 
         // 0. Load the trusted root metadata file + 1. Update the root metadata file
         let root = load_root(
-            transport.as_ref(),
-            loader.root,
-            &datastore,
+            transport,
+            root,
+            datastore,
+            clock,
             limits.max_root_size,
             limits.max_root_updates,
-            &metadata_base_url,
+            &settings.metadata_base_url,
             expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
         )?;
 
+        // If enabled, load the mirrors metadata file, and fetch everything else from the mirrors.
+        let mirrors = if settings.mirrors {
+            load_mirrors(
+                transport,
+                &root,
+                datastore,
+                clock,
+                limits.max_mirrors_size,
+                &settings.metadata_base_url,
+                expiration_enforcement,
+                on_metadata_fetched,
+                signature_verifier,
+            )?
+        } else {
+            None
+        };
+        if let Some(mirrors) = &mirrors {
+            settings = settings.use_mirrors(mirrors);
+        }
+        let transport = settings.transport.as_ref();
+        let datastore = settings.datastore.as_ref();
+        let clock = settings.clock.as_ref();
+        let on_metadata_fetched = settings.on_metadata_fetched.as_ref();
+        let signature_verifier = settings.signature_verifier.as_ref();
+
         // 2. Download the timestamp metadata file
         let timestamp = load_timestamp(
-            transport.as_ref(),
+            transport,
             &root,
-            &datastore,
+            datastore,
+            clock,
             limits.max_timestamp_size,
-            &metadata_base_url,
+            &settings.metadata_base_url,
             expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
         )?;
 
         // 3. Download the snapshot metadata file
         let snapshot = load_snapshot(
-            transport.as_ref(),
+            transport,
             &root,
             &timestamp,
-            &datastore,
-            &metadata_base_url,
+            datastore,
+            clock,
+            limits.max_snapshot_size,
+            &settings.metadata_base_url,
             expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
         )?;
 
         // 4. Download the targets metadata file
         let targets = load_targets(
-            transport.as_ref(),
+            transport,
             &root,
             &snapshot,
-            &datastore,
+            datastore,
+            clock,
             limits.max_targets_size,
-            &metadata_base_url,
+            &settings.metadata_base_url,
             expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
         )?;
 
-        let expires_iter = [
-            (root.signed.expires, RoleType::Root),
-            (timestamp.signed.expires, RoleType::Timestamp),
-            (snapshot.signed.expires, RoleType::Snapshot),
-            (targets.signed.expires, RoleType::Targets),
-        ];
-        let (earliest_expiration, earliest_expiration_role) =
-            expires_iter.iter().min_by_key(|tup| tup.0).unwrap();
+        let mut repository = settings.into_repository(root, mirrors, timestamp, snapshot, targets);
+        if !repository.lazy_delegated_roles {
+            repository.load_delegated_roles()?;
+        }
+        Ok(repository)
+    }
+
+    /// Checks the repository for new metadata, and updates this `Repository` if there is any.
+    ///
+    /// This runs the same update workflow as [`RepositoryLoader::load`], starting from the metadata
+    /// that is already trusted: the root metadata is updated, then `timestamp.json` is fetched. If
+    /// neither has a new version, nothing else is fetched. Otherwise the snapshot and targets
+    /// metadata are fetched and verified, with the same rollback checks as loading, along with the
+    /// metadata of every delegated role. If [`RepositoryLoader::lazy_delegated_roles`] was enabled,
+    /// the metadata of delegated roles that was loaded before is dropped instead, and is loaded
+    /// again as it is needed.
+    ///
+    /// Returns whether new metadata was loaded, and the targets that it added, removed or changed,
+    /// including delegated targets. If [`RepositoryLoader::lazy_delegated_roles`] was enabled,
+    /// only the targets listed by the top-level targets role are compared, since the metadata of
+    /// delegated roles is not loaded again.
+    ///
+    /// If any check fails, `Err` is returned and this `Repository` is unchanged.
+    pub fn refresh(&mut self) -> Result<RefreshOutcome> {
+        let transport = self.transport.as_ref();
+        let root = update_root(
+            transport,
+            self.root.clone(),
+            self.datastore.as_ref(),
+            self.clock.as_ref(),
+            self.limits.max_root_size,
+            self.limits.max_root_updates,
+            &self.metadata_base_url,
+            self.expiration_enforcement,
+            self.on_metadata_fetched.as_ref(),
+            self.signature_verifier.as_ref(),
+        )?;
+        let timestamp = load_timestamp(
+            transport,
+            &root,
+            self.datastore.as_ref(),
+            self.clock.as_ref(),
+            self.limits.max_timestamp_size,
+            &self.metadata_base_url,
+            self.expiration_enforcement,
+            self.on_metadata_fetched.as_ref(),
+            self.signature_verifier.as_ref(),
+        )?;
+        if root.signed.version == self.root.signed.version
+            && timestamp.signed.version == self.timestamp.signed.version
+        {
+            return Ok(RefreshOutcome::default());
+        }
 
-        Ok(Self {
+        let snapshot = load_snapshot(
+            transport,
+            &root,
+            &timestamp,
+            self.datastore.as_ref(),
+            self.clock.as_ref(),
+            self.limits.max_snapshot_size,
+            &self.metadata_base_url,
+            self.expiration_enforcement,
+            self.on_metadata_fetched.as_ref(),
+            self.signature_verifier.as_ref(),
+        )?;
+        let targets = load_targets(
             transport,
+            &root,
+            &snapshot,
+            self.datastore.as_ref(),
+            self.clock.as_ref(),
+            self.limits.max_targets_size,
+            &self.metadata_base_url,
+            self.expiration_enforcement,
+            self.on_metadata_fetched.as_ref(),
+            self.signature_verifier.as_ref(),
+        )?;
+
+        let (earliest_expiration, earliest_expiration_role) =
+            earliest_expiration(&root, &timestamp, &snapshot, &targets);
+        let mut refreshed = Self {
             consistent_snapshot: root.signed.consistent_snapshot,
-            datastore,
-            earliest_expiration: earliest_expiration.to_owned(),
-            earliest_expiration_role: *earliest_expiration_role,
+            earliest_expiration,
+            earliest_expiration_role,
             root,
-            snapshot,
             timestamp,
+            delegated: delegated_metadata(&snapshot),
+            snapshot,
             targets,
-            limits,
-            metadata_base_url,
-            targets_base_url,
-            expiration_enforcement,
+            ..self.clone()
+        };
+        if !refreshed.lazy_delegated_roles {
+            refreshed.load_delegated_roles()?;
+        }
+        let changes = TargetChanges::between(
+            &self.targets.signed,
+            &refreshed.targets.signed,
+            !refreshed.lazy_delegated_roles,
+        );
+        *self = refreshed;
+        Ok(RefreshOutcome {
+            updated: true,
+            changes,
         })
     }
 
-    /// Returns the list of targets present in the repository.
+    /// Returns the top-level targets metadata, with the metadata of its delegated roles.
+    ///
+    /// If [`RepositoryLoader::lazy_delegated_roles`] was enabled, delegated roles only include
+    /// their metadata once it has been loaded with [`Repository::load_delegated_roles`].
     pub fn targets(&self) -> &Signed<crate::schema::Targets> {
         &self.targets
     }
@@ -378,6 +938,12 @@ impl Repository {
         &self.root
     }
 
+    /// Returns whether the repository uses consistent snapshots, as set in its root metadata. If
+    /// so, metadata files are fetched as `VERSION.ROLE.json` and targets as `HASH.NAME`.
+    pub fn consistent_snapshot(&self) -> bool {
+        self.consistent_snapshot
+    }
+
     /// Returns a reference to the signed snapshot
     pub fn snapshot(&self) -> &Signed<Snapshot> {
         &self.snapshot
@@ -388,32 +954,84 @@ impl Repository {
         &self.timestamp
     }
 
+    /// Returns a reference to the signed mirrors, if loading `mirrors.json` was enabled with
+    /// [`RepositoryLoader::mirrors`] and the repository has one.
+    pub fn mirrors(&self) -> Option<&Signed<Mirrors>> {
+        self.mirrors.as_ref()
+    }
+
+    /// Returns when each of the loaded top-level roles expires. Clients can use this to warn when
+    /// the repository is close to expiring, before [`ExpirationEnforcement::Safe`] would reject it.
+    pub fn expirations(&self) -> Expirations {
+        Expirations {
+            root: self.root.signed.expires,
+            timestamp: self.timestamp.signed.expires,
+            snapshot: self.snapshot.signed.expires,
+            targets: self.targets.signed.expires,
+        }
+    }
+
     ///return a vec of all targets including all target files delegated by targets
+    ///
+    /// If [`RepositoryLoader::lazy_delegated_roles`] was enabled, delegated targets are only
+    /// included once they have been loaded with [`Repository::load_delegated_roles`].
     pub fn all_targets(&self) -> impl Iterator + '_ {
         self.targets.signed.targets_iter()
     }
 
+    /// Returns the names of all targets in the repository, including the targets of delegated
+    /// roles.
+    ///
+    /// If [`RepositoryLoader::lazy_delegated_roles`] was enabled, the targets of delegated roles
+    /// are only included once they have been loaded with [`Repository::load_delegated_roles`].
+    ///
+    /// The names are sorted, and each name appears once even if more than one role lists it.
+    pub fn target_names(&self) -> Vec<&str> {
+        self.targets.signed.target_names()
+    }
+
+    /// Returns the names of the targets that start with `prefix`, such as `linux/`, with the targets
+    /// that they resolve to, sorted by name.
+    ///
+    /// Only the metadata of the delegated roles whose paths could match a target with the prefix is
+    /// loaded, if it has not been already. Each target is then found with the TUF delegation
+    /// search, as with [`Repository::read_target`], so a target that a role lists outside of its
+    /// delegated paths, or that is hidden by a terminating role, is not included.
+    ///
+    /// If the repository metadata is expired, or the metadata of a delegated role cannot be loaded,
+    /// `Err` is returned.
+    pub fn targets_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<impl Iterator<Item = (&str, &Target)> + '_> {
+        self.check_expiration()?;
+        let mut names = BTreeSet::new();
+        self.collect_prefixed_names(&self.targets.signed, prefix, &mut Vec::new(), &mut names)?;
+        let mut targets = Vec::new();
+        for name in names {
+            if let Some((_, target)) = self.resolve_target(name)? {
+                targets.push((name, target));
+            }
+        }
+        Ok(targets.into_iter())
+    }
+
     /// Fetches a target from the repository.
     ///
     /// If the repository metadata is expired or there is an issue making the request, `Err` is
-    /// returned.
+    /// returned. `Err` is also returned if `name` is not a relative path, or has a `..` component,
+    /// since it could then refer to something outside of the repository's targets.
     ///
     /// If the requested target is not listed in the repository metadata, `Ok(None)` is returned.
     ///
     /// Otherwise, a reader is returned, which provides streaming access to the target contents
-    /// before its checksum is validated. If the maximum size is reached or there is a checksum
-    /// mismatch, the reader returns a [`std::io::Error`]. **Consumers of this library must not use
-    /// data from the reader if it returns an error.**
+    /// before its checksum is validated. If the target is longer than its signed length, the reader
+    /// returns a [`std::io::Error`] as soon as too much is read; if the target is shorter than its
+    /// signed length or there is a checksum mismatch, the reader returns an error at the end of the
+    /// target. **Consumers of this library must not use data from the reader if it returns an
+    /// error.**
     pub fn read_target(&self, name: &str) -> Result<Option<impl Read + Send>> {
-        // Check for repository metadata expiration.
-        if self.expiration_enforcement == ExpirationEnforcement::Safe {
-            ensure!(
-                system_time(&self.datastore)? < self.earliest_expiration,
-                error::ExpiredMetadata {
-                    role: self.earliest_expiration_role
-                }
-            );
-        }
+        self.check_expiration()?;
 
         // 5. Verify the desired target against its targets metadata.
         //
@@ -432,110 +1050,568 @@ impl Repository {
         //   HASH is one of the hashes of the targets file listed in the targets metadata file
         //   found earlier in step 4. In either case, the client MUST write the file to
         //   non-volatile storage as FILENAME.EXT.
-        Ok(if let Ok(target) = self.targets.signed.find_target(name) {
-            let (sha256, file) = self.target_digest_and_filename(target, name);
-            Some(self.fetch_target(target, &sha256, file.as_str())?)
+        //
+        // The metadata of the delegated roles that the search reaches is loaded now if it has not
+        // been already.
+        check_target_name(name)?;
+        Ok(if let Some((_, target)) = self.resolve_target(name)? {
+            let file = self.target_filename(target, name);
+            Some(self.fetch_target(target, file.as_str())?)
         } else {
             None
         })
     }
 
-    /// Return the named `DelegatedRole` if found.
-    pub fn delegated_role(&self, name: &str) -> Option<&DelegatedRole> {
-        self.targets.signed.delegated_role(name).ok()
+    /// Fetches the target `target_name` as it is listed by the role `role_name`, rather than by the
+    /// role that the TUF delegation search would choose. `role_name` is `targets` for the top-level
+    /// targets role, or the name of a delegated role anywhere in the delegation tree.
+    ///
+    /// The target is verified against the metadata of that role, like [`Repository::read_target`],
+    /// and the same cautions apply to the returned reader. The metadata of the delegated roles that
+    /// must be searched to find the role is loaded if it has not been already.
+    ///
+    /// If the repository metadata is expired, `Err` is returned. `Err` is also returned if no role
+    /// delegates to `role_name`, if the role does not list the target, or if the target is outside
+    /// of the paths that were delegated to the role.
+    pub fn read_target_from_role(
+        &self,
+        role_name: &str,
+        target_name: &str,
+    ) -> Result<impl Read + Send> {
+        self.check_expiration()?;
+        check_target_name(target_name)?;
+        let targets = if role_name == "targets" {
+            &self.targets
+        } else {
+            let (role, targets) = self
+                .find_delegated_role(role_name)?
+                .context(error::DelegateNotFound { name: role_name })?;
+            ensure!(
+                role.paths.matched_target(target_name),
+                error::UndelegatedTarget {
+                    role: role_name,
+                    target: target_name,
+                }
+            );
+            targets
+        };
+        let target =
+            targets
+                .signed
+                .targets
+                .get(target_name)
+                .context(error::RoleTargetNotFound {
+                    role: role_name,
+                    target: target_name,
+                })?;
+        let file = self.target_filename(target, target_name);
+        self.fetch_target(target, file.as_str())
     }
-}
 
-/// Ensures that system time has not stepped backward since it was last sampled
-fn system_time(datastore: &Datastore) -> Result<DateTime<Utc>> {
-    let file = "latest_known_time.json";
-    // Get 'current' system time
-    let sys_time = Utc::now();
-    // Load the latest known system time, if it exists
-    if let Some(Ok(latest_known_time)) = datastore
-        .reader(file)?
-        .map(serde_json::from_reader::<_, DateTime<Utc>>)
-    {
-        // Make sure the sampled system time did not go back in time
-        ensure!(
-            sys_time >= latest_known_time,
-            error::SystemTimeSteppedBackward {
-                sys_time,
-                latest_known_time
-            }
-        );
+    /// Fetches a target from the repository and reads it to the end, verifying it against the
+    /// targets metadata.
+    ///
+    /// This is the same as [`Repository::read_target`] followed by `read_to_end`, except that an
+    /// error while reading (such as the target being larger than its signed length, or a checksum
+    /// mismatch) is returned as `Err`, so the returned bytes are always verified. The target is
+    /// never read beyond its signed length.
+    ///
+    /// If the requested target is not listed in the repository metadata, `Ok(None)` is returned.
+    pub fn read_target_to_vec(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let mut reader = match self.read_target(name)? {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+        let mut buf = Vec::new();
+        reader
+            .read_to_end(&mut buf)
+            .context(error::TargetRead { name })?;
+        Ok(Some(buf))
     }
-    // Store the latest known time
-    // Serializes RFC3339 time string and store to datastore
-    datastore.create(file, &sys_time)?;
-    Ok(sys_time)
-}
-
-/// TUF v1.0.16, 5.2.9, 5.3.3, 5.4.5, 5.5.4, The expiration timestamp in the `[metadata]` file MUST
-/// be higher than the fixed update start time.
-fn check_expired<T: Role>(datastore: &Datastore, role: &T) -> Result<()> {
-    ensure!(
-        system_time(datastore)? <= role.expires(),
-        error::ExpiredMetadata { role: T::TYPE }
-    );
-    Ok(())
-}
 
-/// Checks to see if the `Url` has a trailing slash and adds one if not. Without a trailing slash,
-/// the last component of a `Url` is considered to be a file. `metadata_url` and `targets_url`
-/// must refer to a base (i.e. directory), so we need them to end with a slash.
-fn parse_url(url: Url) -> Result<Url> {
-    if url.as_str().ends_with('/') {
-        Ok(url)
-    } else {
-        let mut s = url.to_string();
-        s.push('/');
-        Url::parse(&s).context(error::ParseUrl { url: s })
+    /// Fetches a target from the repository and saves it to `dest`, verifying it against the
+    /// targets metadata.
+    ///
+    /// The target is written to a temporary file in the same directory as `dest`, and is only
+    /// renamed to `dest` once its length and hash have been verified, so a partial or unverified
+    /// target is never observed at `dest`. If `dest` already exists, it is replaced.
+    ///
+    /// If the requested target is not listed in the repository metadata, `Err` is returned and
+    /// nothing is written.
+    pub fn save_target<P: AsRef<Path>>(&self, name: &str, dest: P) -> Result<()> {
+        let dest = dest.as_ref();
+        let mut reader = self
+            .read_target(name)?
+            .context(error::SaveTargetNotFound { name })?;
+        let dir = match dest.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        // The temporary file is deleted when dropped if we return early.
+        let mut staging =
+            NamedTempFile::new_in(dir).context(error::SaveTarget { name, path: dest })?;
+        // The reader checks the length and hash as it goes, and returns an error at the end of the
+        // stream if the hash does not match.
+        std::io::copy(&mut reader, &mut staging).context(error::SaveTarget { name, path: dest })?;
+        set_default_permissions(&staging).context(error::SaveTarget { name, path: dest })?;
+        staging
+            .persist(dest)
+            .map_err(|e| e.error)
+            .context(error::SaveTarget { name, path: dest })?;
+        Ok(())
     }
-}
+
+    /// Returns whether the target `name` is listed in the targets metadata (including the metadata
+    /// of delegated roles). The target is not fetched, and metadata expiration is not checked; use
+    /// [`Repository::read_target`] to fetch and verify the target itself.
+    ///
+    /// The metadata of delegated roles that the search reaches is loaded if it has not been
+    /// already. Returns `false` if it cannot be loaded.
+    pub fn target_exists(&self, name: &str) -> bool {
+        matches!(self.resolve_target(name), Ok(Some(_)))
+    }
+
+    /// Returns the length in bytes of the target `name`, as listed in the targets metadata
+    /// (including the metadata of delegated roles). The target is not fetched.
+    ///
+    /// If the repository metadata is expired, or the metadata of a delegated role cannot be loaded,
+    /// `Err` is returned. If the target is not listed in the repository metadata, `Ok(None)` is
+    /// returned.
+    pub fn target_length(&self, name: &str) -> Result<Option<u64>> {
+        self.check_expiration()?;
+        Ok(self.resolve_target(name)?.map(|(_, target)| target.length))
+    }
+
+    /// Returns the hashes of the target `name`, as listed in the targets metadata (including the
+    /// metadata of delegated roles). The target is not fetched.
+    ///
+    /// If the repository metadata is expired, or the metadata of a delegated role cannot be loaded,
+    /// `Err` is returned. If the target is not listed in the repository metadata, `Ok(None)` is
+    /// returned.
+    pub fn target_hashes(&self, name: &str) -> Result<Option<&Hashes>> {
+        self.check_expiration()?;
+        Ok(self.resolve_target(name)?.map(|(_, target)| &target.hashes))
+    }
+
+    /// Returns the custom metadata field `key` of the target `name`, deserialized as a `T`. The
+    /// target is not fetched.
+    ///
+    /// If the repository metadata is expired, or the field cannot be deserialized as a `T`, `Err` is
+    /// returned. If the target is not listed in the repository metadata, or does not have the field,
+    /// `Ok(None)` is returned.
+    pub fn target_custom<T: DeserializeOwned>(&self, name: &str, key: &str) -> Result<Option<T>> {
+        self.check_expiration()?;
+        let value = match self
+            .resolve_target(name)?
+            .and_then(|(_, target)| target.custom.get(key))
+        {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        T::deserialize(value)
+            .map(Some)
+            .context(error::TargetCustom { name, key })
+    }
+
+    /// Returns an error if any of the repository metadata has expired, unless expiration
+    /// enforcement is disabled.
+    fn check_expiration(&self) -> Result<()> {
+        if self.expiration_enforcement == ExpirationEnforcement::Safe {
+            ensure!(
+                system_time(self.datastore.as_ref(), self.clock.as_ref())?
+                    < self.earliest_expiration,
+                error::ExpiredMetadata {
+                    role: self.earliest_expiration_role
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Return the named `DelegatedRole` if found.
+    ///
+    /// If [`RepositoryLoader::lazy_delegated_roles`] was enabled, roles that are delegated by other
+    /// delegated roles are only found once their metadata has been loaded with
+    /// [`Repository::load_delegated_roles`].
+    pub fn delegated_role(&self, name: &str) -> Option<&DelegatedRole> {
+        self.targets.signed.delegated_role(name).ok()
+    }
+
+    /// Returns all of the delegated targets roles, in the order that they are visited by a
+    /// depth-first search of the delegation tree.
+    ///
+    /// If [`RepositoryLoader::lazy_delegated_roles`] was enabled, roles that are delegated by other
+    /// delegated roles are only included once their metadata has been loaded with
+    /// [`Repository::load_delegated_roles`].
+    pub fn delegated_roles(&self) -> Vec<&DelegatedRole> {
+        self.targets.signed.delegated_roles()
+    }
+
+    /// Returns the name of the role that signs for the target `name`: `targets` for the top-level
+    /// targets role, or the name of a delegated role. Returns `None` if no role signs for the
+    /// target.
+    ///
+    /// This follows the TUF delegation search: a delegated role is only searched if its paths match
+    /// the target, roles are searched in the order that they are listed, and a matching terminating
+    /// role stops the search. The metadata of the delegated roles that the search reaches is loaded
+    /// if it has not been already; `None` is also returned if it cannot be loaded.
+    pub fn role_for_target(&self, name: &str) -> Option<&str> {
+        let (chain, _) = self.resolve_target(name).ok()??;
+        Some(chain.last().map_or("targets", |role| role.name.as_str()))
+    }
+
+    /// Returns the chain of roles that authorize the target `name`, starting with the top-level
+    /// `targets` role and ending with the role that lists the target. Returns `None` if the target
+    /// is not found, or if the metadata of a delegated role that the search reaches cannot be
+    /// loaded.
+    pub fn target_provenance(&self, name: &str) -> Option<Vec<RoleRef>> {
+        let (delegation_chain, _) = self.resolve_target(name).ok()??;
+        let targets_keys = self.root.signed.roles.get(&RoleType::Targets)?;
+        let mut provenance = vec![RoleRef {
+            name: "targets".to_owned(),
+            keyids: targets_keys.keyids.clone(),
+            threshold: targets_keys.threshold,
+            terminating: false,
+        }];
+        provenance.extend(delegation_chain.into_iter().map(|role| RoleRef {
+            name: role.name.clone(),
+            keyids: role.keyids.clone(),
+            threshold: role.threshold,
+            terminating: role.terminating,
+        }));
+        Some(provenance)
+    }
+}
+
+/// A role in the chain of roles that authorize a target. See [`Repository::target_provenance`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleRef {
+    /// The name of the role, `targets` for the top-level targets role.
+    pub name: String,
+    /// The IDs of the keys that may sign the role.
+    pub keyids: Vec<Decoded<Hex>>,
+    /// The number of signatures required to validate the role.
+    pub threshold: NonZeroU64,
+    /// Whether the role is terminating. Always `false` for the top-level targets role.
+    pub terminating: bool,
+}
+
+/// The expiration times of the top-level roles of a repository. See [`Repository::expirations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expirations {
+    /// When the root role expires.
+    pub root: DateTime<Utc>,
+    /// When the timestamp role expires.
+    pub timestamp: DateTime<Utc>,
+    /// When the snapshot role expires.
+    pub snapshot: DateTime<Utc>,
+    /// When the top-level targets role expires.
+    pub targets: DateTime<Utc>,
+}
+
+impl Expirations {
+    /// Returns the earliest expiration, and the role that expires then.
+    pub fn earliest(&self) -> (DateTime<Utc>, RoleType) {
+        let expires_iter = [
+            (self.root, RoleType::Root),
+            (self.timestamp, RoleType::Timestamp),
+            (self.snapshot, RoleType::Snapshot),
+            (self.targets, RoleType::Targets),
+        ];
+        // the array is not empty, so there is a minimum.
+        *expires_iter.iter().min_by_key(|tup| tup.0).unwrap()
+    }
+}
+
+/// Returns the earliest expiration of the top-level roles, and the role that expires then.
+fn earliest_expiration(
+    root: &Signed<Root>,
+    timestamp: &Signed<Timestamp>,
+    snapshot: &Signed<Snapshot>,
+    targets: &Signed<crate::schema::Targets>,
+) -> (DateTime<Utc>, RoleType) {
+    Expirations {
+        root: root.signed.expires,
+        timestamp: timestamp.signed.expires,
+        snapshot: snapshot.signed.expires,
+        targets: targets.signed.expires,
+    }
+    .earliest()
+}
+
+/// Ensures that system time has not stepped backward since it was last sampled
+fn system_time(datastore: &dyn Datastore, clock: &dyn Clock) -> Result<DateTime<Utc>> {
+    let file = "latest_known_time.json";
+    // Get 'current' system time
+    let sys_time = clock.now();
+    // Load the latest known system time, if it exists
+    if let Some(Ok(latest_known_time)) = datastore
+        .reader(file)?
+        .map(serde_json::from_reader::<_, DateTime<Utc>>)
+    {
+        // Make sure the sampled system time did not go back in time
+        ensure!(
+            sys_time >= latest_known_time,
+            error::SystemTimeSteppedBackward {
+                sys_time,
+                latest_known_time
+            }
+        );
+    }
+    // Store the latest known time
+    // Serializes RFC3339 time string and store to datastore
+    datastore.create(file, &sys_time)?;
+    Ok(sys_time)
+}
+
+/// Parses `role` metadata from `reader`. If the reader stopped because the file exceeded its size
+/// limit, the error names the role rather than reporting a parse failure. Otherwise a parse failure
+/// names the role, and the line and column where parsing failed.
+pub(crate) fn parse_metadata<T: DeserializeOwned>(reader: impl Read, role: RoleType) -> Result<T> {
+    serde_json::from_reader(reader).or_else(|source| {
+        let cause = std::error::Error::source(&source)
+            .and_then(|err| err.downcast_ref::<std::io::Error>())
+            .and_then(std::io::Error::get_ref)
+            .and_then(|err| err.downcast_ref::<error::Error>());
+        match cause {
+            Some(error::Error::MaxSizeExceeded {
+                max_size,
+                specifier,
+                ..
+            }) => {
+                return error::MetadataSizeExceeded {
+                    role,
+                    max_size: *max_size,
+                    specifier: *specifier,
+                }
+                .fail();
+            }
+            // The hash of the snapshot is checked as it is read, against the hash in the timestamp.
+            Some(error::Error::HashMismatch {
+                calculated,
+                expected,
+                ..
+            }) if role == RoleType::Snapshot => {
+                return error::SnapshotHashMismatch {
+                    expected: expected.clone(),
+                    actual: calculated.clone(),
+                }
+                .fail();
+            }
+            _ => {}
+        }
+        let (line, column) = (source.line(), source.column());
+        Err(source).context(error::ParseMetadata { role, line, column })
+    })
+}
+
+/// TUF v1.0.16, 5.2.9, 5.3.3, 5.4.5, 5.5.4, The expiration timestamp in the `[metadata]` file MUST
+/// be higher than the fixed update start time.
+fn check_expired<T: Role>(datastore: &dyn Datastore, clock: &dyn Clock, role: &T) -> Result<()> {
+    ensure!(
+        system_time(datastore, clock)? <= role.expires(),
+        error::ExpiredMetadata { role: T::TYPE }
+    );
+    Ok(())
+}
+
+/// The major and minor version of the TUF specification that this library implements.
+const SPEC_MAJOR_VERSION: u64 = 1;
+const SPEC_MINOR_VERSION: u64 = 0;
+
+/// Checks that metadata was written for a major version of the TUF specification that we support.
+/// Minor versions are backward compatible, so metadata for a later minor version is accepted, but
+/// a warning is logged.
+fn check_spec_version<T: Role>(role: &T) -> Result<()> {
+    let spec_version = role.spec_version();
+    let mut parts = spec_version.split('.').map(str::parse::<u64>);
+    ensure!(
+        matches!(parts.next(), Some(Ok(SPEC_MAJOR_VERSION))),
+        error::UnsupportedSpecVersion {
+            role: T::TYPE,
+            found: spec_version,
+            supported_major: SPEC_MAJOR_VERSION,
+        }
+    );
+    if let Some(Ok(minor)) = parts.next() {
+        if minor > SPEC_MINOR_VERSION {
+            warn!(
+                "{} metadata has spec version '{}', newer than supported version {}.{}",
+                T::TYPE,
+                spec_version,
+                SPEC_MAJOR_VERSION,
+                SPEC_MINOR_VERSION
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a target name is a relative path that stays within the directory it is joined to,
+/// so that a malicious targets role can't make us fetch from outside the targets base URL or write
+/// outside of an output directory. Both `/` and `\` are treated as separators, and a leading
+/// Windows drive letter (such as `C:`) is rejected, whatever platform we are running on.
+pub(crate) fn check_target_name(name: &str) -> Result<()> {
+    let mut components = name.split(&['/', '\\'][..]);
+    // `split` always returns at least one component.
+    let first = components.next().unwrap_or_default();
+    let mut first_chars = first.chars();
+    let drive_letter = matches!(
+        (first_chars.next(), first_chars.next()),
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic()
+    );
+    ensure!(
+        !first.is_empty() && first != ".." && !drive_letter && components.all(|c| c != ".."),
+        error::UnsafeTargetName { name }
+    );
+    Ok(())
+}
+
+/// Joins the target or metadata `name` to `base`, a directory URL. Each `/`-separated segment of
+/// `name` is percent-encoded, so that a name with spaces or characters such as `#`, `?`, or `%` is
+/// fetched as named: `my file.txt` becomes `my%20file.txt`, which [`FilesystemTransport`] decodes
+/// back to the file name.
+pub(crate) fn join_url(base: &Url, name: &str) -> Result<Url> {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .map_err(|()| url::ParseError::RelativeUrlWithCannotBeABaseBase)
+        .context(error::JoinUrl {
+            path: name,
+            url: base.clone(),
+        })?
+        .pop_if_empty()
+        .extend(name.split('/'));
+    Ok(url)
+}
+
+/// Checks to see if the `Url` has a trailing slash and adds one if not. Without a trailing slash,
+/// the last component of a `Url` is considered to be a file. `metadata_url` and `targets_url`
+/// must refer to a base (i.e. directory), so we need them to end with a slash.
+fn parse_url(url: Url) -> Result<Url> {
+    if url.as_str().ends_with('/') {
+        Ok(url)
+    } else {
+        let mut s = url.to_string();
+        s.push('/');
+        Url::parse(&s).context(error::ParseUrl { url: s })
+    }
+}
+
+/// Step 0 of the client application, which loads the trusted root metadata file.
+pub(crate) fn load_trusted_root<R: Read>(
+    root: R,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<Root>> {
+    // 0. Load the trusted root metadata file. We assume that a good, trusted copy of this file was
+    //    shipped with the package manager or software updater using an out-of-band process. Note
+    //    that the expiration of the trusted root metadata file does not matter, because we will
+    //    attempt to update it in the next step.
+    let root: Signed<Root> = serde_json::from_reader(root).context(error::ParseTrustedMetadata)?;
+    verify_signed(&root.signed, &root, verifier).context(error::VerifyTrustedMetadata)?;
+    check_spec_version(&root.signed)?;
+    Ok(root)
+}
 
 /// Steps 0 and 1 of the client application, which load the current root metadata file based on a
 /// trusted root metadata file.
+#[allow(clippy::too_many_arguments)]
 fn load_root<R: Read>(
     transport: &dyn Transport,
     root: R,
-    datastore: &Datastore,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
     max_root_size: u64,
     max_root_updates: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
 ) -> Result<Signed<Root>> {
-    // 0. Load the trusted root metadata file. We assume that a good, trusted copy of this file was
-    //    shipped with the package manager or software updater using an out-of-band process. Note
-    //    that the expiration of the trusted root metadata file does not matter, because we will
-    //    attempt to update it in the next step.
-    let mut root: Signed<Root> =
-        serde_json::from_reader(root).context(error::ParseTrustedMetadata)?;
-    root.signed
-        .verify_role(&root)
-        .context(error::VerifyTrustedMetadata)?;
+    update_root(
+        transport,
+        load_trusted_root(root, verifier)?,
+        datastore,
+        clock,
+        max_root_size,
+        max_root_updates,
+        metadata_base_url,
+        expiration_enforcement,
+        on_fetched,
+        verifier,
+    )
+}
 
-    // Used in step 1.2
-    let original_root_version = root.signed.version.get();
+/// Step 1 of the client application, which updates the trusted root metadata file to the latest
+/// version.
+#[allow(clippy::too_many_arguments)]
+fn update_root(
+    transport: &dyn Transport,
+    root: Signed<Root>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    max_root_size: u64,
+    max_root_updates: u64,
+    metadata_base_url: &Url,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<Root>> {
+    let mut update = RootUpdate::new(root, max_root_updates);
+    loop {
+        let url = update.next_url(metadata_base_url)?;
+        // The next root usually doesn't exist, so transports that can check cheaply, e.g. with
+        // an HTTP `HEAD` request, check before fetching it. Others fetch it only once.
+        if transport.exists_is_cheap() && !transport.exists(url.clone()).unwrap_or(false) {
+            break; // If this file is not available, then go to step 1.8.
+        }
+        match fetch_metadata(
+            transport,
+            &MetadataFetch::max_size(url, max_root_size, "max_root_size argument"),
+        ) {
+            Err(_) => break, // If this file is not available, then go to step 1.8.
+            Ok(reader) => {
+                if !update.verify_next(reader, on_fetched, verifier)? {
+                    break;
+                }
+            }
+        }
+    }
+    update.finish(datastore, clock, expiration_enforcement)
+}
 
+/// The state of step 1 of the client application, which updates the trusted root metadata file to
+/// the latest version, one version at a time. The files are fetched by the caller, so that this
+/// can be shared by the blocking and async load paths.
+pub(crate) struct RootUpdate {
+    root: Signed<Root>,
+    max_root_updates: u64,
+    // Used in step 1.2
+    original_root_version: u64,
     // Used in step 1.9
-    let original_timestamp_keys = root
-        .signed
-        .keys(RoleType::Timestamp)
-        .cloned()
-        .collect::<Vec<_>>();
-    let original_snapshot_keys = root
-        .signed
-        .keys(RoleType::Snapshot)
-        .cloned()
-        .collect::<Vec<_>>();
-
-    // 1. Update the root metadata file. Since it may now be signed using entirely different keys,
-    //    the client must somehow be able to establish a trusted line of continuity to the latest
-    //    set of keys. To do so, the client MUST download intermediate root metadata files, until
-    //    the latest available one is reached. Therefore, it MUST temporarily turn on consistent
-    //    snapshots in order to download versioned root metadata files as described next.
-    loop {
+    original_timestamp_keys: Vec<Key>,
+    original_snapshot_keys: Vec<Key>,
+}
+
+impl RootUpdate {
+    pub(crate) fn new(root: Signed<Root>, max_root_updates: u64) -> Self {
+        Self {
+            original_root_version: root.signed.version.get(),
+            original_timestamp_keys: root.signed.keys(RoleType::Timestamp).cloned().collect(),
+            original_snapshot_keys: root.signed.keys(RoleType::Snapshot).cloned().collect(),
+            root,
+            max_root_updates,
+        }
+    }
+
+    /// Returns the URL of the next version of the root metadata file.
+    pub(crate) fn next_url(&self, metadata_base_url: &Url) -> Result<Url> {
+        // 1. Update the root metadata file. Since it may now be signed using entirely different
+        //    keys, the client must somehow be able to establish a trusted line of continuity to the
+        //    latest set of keys. To do so, the client MUST download intermediate root metadata
+        //    files, until the latest available one is reached. Therefore, it MUST temporarily turn
+        //    on consistent snapshots in order to download versioned root metadata files as
+        //    described next.
+        //
         // 1.1. Let N denote the version number of the trusted root metadata file.
         //
         // 1.2. Try downloading version N+1 of the root metadata file, up to some X number of bytes
@@ -546,157 +1622,200 @@ fn load_root<R: Read>(
         //   number of root metadata files (because the exact number is as yet unknown), then go to
         //   step 1.8. The value for Y is set by the authors of the application using TUF. For
         //   example, Y may be 2^10.
+        let max_root_updates = self.max_root_updates;
         ensure!(
-            root.signed.version.get() < original_root_version + max_root_updates,
+            self.root.signed.version.get() < self.original_root_version + max_root_updates,
             error::MaxUpdatesExceeded { max_root_updates }
         );
-        let path = format!("{}.root.json", root.signed.version.get() + 1);
-        match fetch_max_size(
-            transport,
-            metadata_base_url.join(&path).context(error::JoinUrl {
-                path,
-                url: metadata_base_url.to_owned(),
-            })?,
-            max_root_size,
-            "max_root_size argument",
-        ) {
-            Err(_) => break, // If this file is not available, then go to step 1.8.
-            Ok(reader) => {
-                let new_root: Signed<Root> =
-                    serde_json::from_reader(reader).context(error::ParseMetadata {
-                        role: RoleType::Root,
-                    })?;
-
-                // 1.3. Check signatures. Version N+1 of the root metadata file MUST have been
-                //   signed by: (1) a threshold of keys specified in the trusted root metadata file
-                //   (version N), and (2) a threshold of keys specified in the new root metadata
-                //   file being validated (version N+1). If version N+1 is not signed as required,
-                //   discard it, abort the update cycle, and report the signature failure. On the
-                //   next update cycle, begin at step 0 and version N of the root metadata file.
-                root.signed
-                    .verify_role(&new_root)
-                    .context(error::VerifyMetadata {
-                        role: RoleType::Root,
-                    })?;
-                new_root
-                    .signed
-                    .verify_role(&new_root)
-                    .context(error::VerifyMetadata {
-                        role: RoleType::Root,
-                    })?;
-
-                // 1.4. Check for a rollback attack. The version number of the trusted root
-                //   metadata file (version N) must be less than or equal to the version number of
-                //   the new root metadata file (version N+1). Effectively, this means checking
-                //   that the version number signed in the new root metadata file is indeed N+1. If
-                //   the version of the new root metadata file is less than the trusted metadata
-                //   file, discard it, abort the update cycle, and report the rollback attack. On
-                //   the next update cycle, begin at step 0 and version N of the root metadata
-                //   file.
-                ensure!(
-                    root.signed.version <= new_root.signed.version,
-                    error::OlderMetadata {
-                        role: RoleType::Root,
-                        current_version: root.signed.version,
-                        new_version: new_root.signed.version
-                    }
-                );
-
-                // Off-spec: 1.4 specifies that the version number of the trusted root metadata
-                // file must be less than or equal to the version number of the new root metadata
-                // file. If they are equal, this will create an infinite loop, so we ignore the new
-                // root metadata file but do not report an error. This could only happen if the
-                // path we built above, referencing N+1, has a filename that doesn't match its
-                // contents, which would have to list version N.
-                if root.signed.version == new_root.signed.version {
-                    break;
-                }
-
-                // 1.5. Note that the expiration of the new (intermediate) root metadata file does
-                //   not matter yet, because we will check for it in step 1.8.
-                //
-                // 1.6. Set the trusted root metadata file to the new root metadata file.
-                //
-                // (This is where version N+1 becomes version N.)
-                root = new_root;
+        join_url(
+            metadata_base_url,
+            &format!("{}.root.json", self.root.signed.version.get() + 1),
+        )
+    }
 
-                // 1.7. Repeat steps 1.1 to 1.7.
-                continue;
+    /// Verifies the next version of the root metadata file, read from `reader`, and makes it the
+    /// trusted root metadata file. Returns `false` if there are no more versions to update to.
+    pub(crate) fn verify_next<R: Read>(
+        &mut self,
+        reader: R,
+        on_fetched: Option<&MetadataCallback>,
+        verifier: Option<&SharedVerifier>,
+    ) -> Result<bool> {
+        let root = &self.root;
+        let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
+        let new_root: Signed<Root> = parse_metadata(&mut reader, RoleType::Root)?;
+
+        // 1.3. Check signatures. Version N+1 of the root metadata file MUST have been signed by:
+        //   (1) a threshold of keys specified in the trusted root metadata file (version N), and
+        //   (2) a threshold of keys specified in the new root metadata file being validated
+        //   (version N+1). If version N+1 is not signed as required, discard it, abort the update
+        //   cycle, and report the signature failure. On the next update cycle, begin at step 0 and
+        //   version N of the root metadata file.
+        verify_signed(&root.signed, &new_root, verifier).context(error::VerifyMetadata {
+            role: RoleType::Root,
+        })?;
+        new_root
+            .signed
+            .verify_role(&new_root)
+            .context(error::VerifyMetadata {
+                role: RoleType::Root,
+            })?;
+        check_spec_version(&new_root.signed)?;
+
+        // 1.4. Check for a rollback attack. The version number of the trusted root metadata file
+        //   (version N) must be less than or equal to the version number of the new root metadata
+        //   file (version N+1). Effectively, this means checking that the version number signed in
+        //   the new root metadata file is indeed N+1. If the version of the new root metadata file
+        //   is less than the trusted metadata file, discard it, abort the update cycle, and report
+        //   the rollback attack. On the next update cycle, begin at step 0 and version N of the
+        //   root metadata file.
+        ensure!(
+            root.signed.version <= new_root.signed.version,
+            error::RollbackDetected {
+                role: RoleType::Root,
+                trusted_version: root.signed.version,
+                offered_version: new_root.signed.version
             }
+        );
+
+        // Off-spec: 1.4 specifies that the version number of the trusted root metadata file must
+        // be less than or equal to the version number of the new root metadata file. If they are
+        // equal, this will create an infinite loop, so we ignore the new root metadata file but do
+        // not report an error. This could only happen if the path we built above, referencing N+1,
+        // has a filename that doesn't match its contents, which would have to list version N.
+        if root.signed.version == new_root.signed.version {
+            return Ok(false);
         }
+
+        report_fetched(
+            on_fetched,
+            RoleType::Root,
+            "root",
+            new_root.signed.version,
+            &reader,
+        );
+
+        // 1.5. Note that the expiration of the new (intermediate) root metadata file does not
+        //   matter yet, because we will check for it in step 1.8.
+        //
+        // 1.6. Set the trusted root metadata file to the new root metadata file.
+        //
+        // (This is where version N+1 becomes version N.)
+        self.root = new_root;
+
+        // 1.7. Repeat steps 1.1 to 1.7.
+        Ok(true)
     }
 
-    // TUF v1.0.16, 5.2.9. Check for a freeze attack. The expiration timestamp in the trusted root
-    // metadata file MUST be higher than the fixed update start time. If the trusted root metadata
-    // file has expired, abort the update cycle, report the potential freeze attack. On the next
-    // update cycle, begin at step 5.1 and version N of the root metadata file.
-    if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &root.signed)?;
-    }
-
-    // 1.9. If the timestamp and / or snapshot keys have been rotated, then delete the trusted
-    //   timestamp and snapshot metadata files. This is done in order to recover from fast-forward
-    //   attacks after the repository has been compromised and recovered. A fast-forward attack
-    //   happens when attackers arbitrarily increase the version numbers of: (1) the timestamp
-    //   metadata, (2) the snapshot metadata, and / or (3) the targets, or a delegated targets,
-    //   metadata file in the snapshot metadata.
-    if original_timestamp_keys
-        .iter()
-        .ne(root.signed.keys(RoleType::Timestamp))
-        || original_snapshot_keys
+    /// Finishes the update once there are no more versions of the root metadata file.
+    pub(crate) fn finish(
+        self,
+        datastore: &dyn Datastore,
+        clock: &dyn Clock,
+        expiration_enforcement: ExpirationEnforcement,
+    ) -> Result<Signed<Root>> {
+        let root = self.root;
+
+        // TUF v1.0.16, 5.2.9. Check for a freeze attack. The expiration timestamp in the trusted
+        // root metadata file MUST be higher than the fixed update start time. If the trusted root
+        // metadata file has expired, abort the update cycle, report the potential freeze attack.
+        // On the next update cycle, begin at step 5.1 and version N of the root metadata file.
+        if expiration_enforcement == ExpirationEnforcement::Safe {
+            check_expired(datastore, clock, &root.signed)?;
+        }
+
+        // 1.9. If the timestamp and / or snapshot keys have been rotated, then delete the trusted
+        //   timestamp and snapshot metadata files. This is done in order to recover from
+        //   fast-forward attacks after the repository has been compromised and recovered. A
+        //   fast-forward attack happens when attackers arbitrarily increase the version numbers
+        //   of: (1) the timestamp metadata, (2) the snapshot metadata, and / or (3) the targets, or
+        //   a delegated targets, metadata file in the snapshot metadata.
+        if self
+            .original_timestamp_keys
             .iter()
-            .ne(root.signed.keys(RoleType::Snapshot))
-    {
-        let r1 = datastore.remove("timestamp.json");
-        let r2 = datastore.remove("snapshot.json");
-        r1.and(r2)?;
-    }
+            .ne(root.signed.keys(RoleType::Timestamp))
+            || self
+                .original_snapshot_keys
+                .iter()
+                .ne(root.signed.keys(RoleType::Snapshot))
+        {
+            let r1 = datastore.remove("timestamp.json");
+            let r2 = datastore.remove("snapshot.json");
+            r1.and(r2)?;
+        }
 
-    // 1.10. Set whether consistent snapshots are used as per the trusted root metadata file (see
-    //   Section 4.3).
-    //
-    // (This is done by checking the value of root.signed.consistent_snapshot throughout this
-    // library.)
+        // 1.10. Set whether consistent snapshots are used as per the trusted root metadata file
+        //   (see Section 4.3).
+        //
+        // (This is done by checking the value of root.signed.consistent_snapshot throughout this
+        // library.)
 
-    Ok(root)
+        Ok(root)
+    }
 }
 
 /// Step 2 of the client application, which loads the timestamp metadata file.
+#[allow(clippy::too_many_arguments)]
 fn load_timestamp(
     transport: &dyn Transport,
     root: &Signed<Root>,
-    datastore: &Datastore,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
     max_timestamp_size: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
 ) -> Result<Signed<Timestamp>> {
+    let fetch = timestamp_fetch(metadata_base_url, max_timestamp_size)?;
+    verify_timestamp(
+        fetch_metadata(transport, &fetch)?,
+        root,
+        datastore,
+        clock,
+        expiration_enforcement,
+        on_fetched,
+        verifier,
+    )
+}
+
+/// Returns how to fetch the timestamp metadata file.
+pub(crate) fn timestamp_fetch(
+    metadata_base_url: &Url,
+    max_timestamp_size: u64,
+) -> Result<MetadataFetch<'_>> {
     // 2. Download the timestamp metadata file, up to Y number of bytes (because the size is
     //    unknown.) The value for Y is set by the authors of the application using TUF. For
     //    example, Y may be tens of kilobytes. The filename used to download the timestamp metadata
     //    file is of the fixed form FILENAME.EXT (e.g., timestamp.json).
-    let path = "timestamp.json";
-    let reader = fetch_max_size(
-        transport,
-        metadata_base_url.join(path).context(error::JoinUrl {
-            path,
-            url: metadata_base_url.to_owned(),
-        })?,
+    Ok(MetadataFetch::max_size(
+        join_url(metadata_base_url, "timestamp.json")?,
         max_timestamp_size,
         "max_timestamp_size argument",
-    )?;
-    let timestamp: Signed<Timestamp> =
-        serde_json::from_reader(reader).context(error::ParseMetadata {
-            role: RoleType::Timestamp,
-        })?;
+    ))
+}
+
+/// Steps 2.1 to 2.3 of the client application, which verify the timestamp metadata file that was
+/// read from `reader`.
+pub(crate) fn verify_timestamp<R: Read>(
+    reader: R,
+    root: &Signed<Root>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<Timestamp>> {
+    let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
+    let timestamp: Signed<Timestamp> = parse_metadata(&mut reader, RoleType::Timestamp)?;
 
     // 2.1. Check signatures. The new timestamp metadata file must have been signed by a threshold
     //   of keys specified in the trusted root metadata file. If the new timestamp metadata file is
     //   not properly signed, discard it, abort the update cycle, and report the signature failure.
-    root.signed
-        .verify_role(&timestamp)
-        .context(error::VerifyMetadata {
-            role: RoleType::Timestamp,
-        })?;
+    verify_signed(&root.signed, &timestamp, verifier).context(error::VerifyMetadata {
+        role: RoleType::Timestamp,
+    })?;
+    check_spec_version(&timestamp.signed)?;
 
     // 2.2. Check for a rollback attack. The version number of the trusted timestamp metadata file,
     //   if any, must be less than or equal to the version number of the new timestamp metadata
@@ -706,13 +1825,13 @@ fn load_timestamp(
         .reader("timestamp.json")?
         .map(serde_json::from_reader::<_, Signed<Timestamp>>)
     {
-        if root.signed.verify_role(&old_timestamp).is_ok() {
+        if verify_signed(&root.signed, &old_timestamp, verifier).is_ok() {
             ensure!(
                 old_timestamp.signed.version <= timestamp.signed.version,
-                error::OlderMetadata {
+                error::RollbackDetected {
                     role: RoleType::Timestamp,
-                    current_version: old_timestamp.signed.version,
-                    new_version: timestamp.signed.version
+                    trusted_version: old_timestamp.signed.version,
+                    offered_version: timestamp.signed.version
                 }
             );
         }
@@ -723,24 +1842,164 @@ fn load_timestamp(
     // metadata file becomes the trusted timestamp metadata file. If the new timestamp metadata file
     // has expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &timestamp.signed)?;
+        check_expired(datastore, clock, &timestamp.signed)?;
     }
 
     // Now that everything seems okay, write the timestamp file to the datastore.
+    report_fetched(
+        on_fetched,
+        RoleType::Timestamp,
+        "timestamp",
+        timestamp.signed.version,
+        &reader,
+    );
     datastore.create("timestamp.json", &timestamp)?;
 
     Ok(timestamp)
 }
 
+/// Loads the optional mirrors metadata file, if the repository has one. This isn't a step of the
+/// client application in the current TUF specification; it follows the timestamp steps, as
+/// mirrors.json is also fetched with a fixed name and an unknown size.
+#[allow(clippy::too_many_arguments)]
+fn load_mirrors(
+    transport: &dyn Transport,
+    root: &Signed<Root>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    max_mirrors_size: u64,
+    metadata_base_url: &Url,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Option<Signed<Mirrors>>> {
+    let fetch = MetadataFetch::max_size(
+        join_url(metadata_base_url, "mirrors.json")?,
+        max_mirrors_size,
+        "max_mirrors_size argument",
+    );
+    match fetch_metadata(transport, &fetch) {
+        Ok(reader) => verify_mirrors(
+            reader,
+            root,
+            datastore,
+            clock,
+            expiration_enforcement,
+            on_fetched,
+            verifier,
+        )
+        .map(Some),
+        Err(err) if is_file_not_found(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns `true` if `err` is a transport error for a file that doesn't exist.
+pub(crate) fn is_file_not_found(err: &error::Error) -> bool {
+    matches!(err, error::Error::Transport { source, .. } if source.kind() == TransportErrorKind::FileNotFound)
+}
+
+/// Verifies the mirrors metadata file that was read from `reader`.
+pub(crate) fn verify_mirrors<R: Read>(
+    reader: R,
+    root: &Signed<Root>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<Mirrors>> {
+    let path = "mirrors.json";
+    let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
+    let mirrors: Signed<Mirrors> = parse_metadata(&mut reader, RoleType::Mirrors)?;
+
+    // Check signatures, which must have been made by a threshold of the mirrors keys listed in the
+    // trusted root metadata file.
+    verify_signed(&root.signed, &mirrors, verifier).context(error::VerifyMetadata {
+        role: RoleType::Mirrors,
+    })?;
+    check_spec_version(&mirrors.signed)?;
+
+    // Check for a rollback attack against the trusted mirrors metadata file, if any.
+    if let Some(Ok(old_mirrors)) = datastore
+        .reader(path)?
+        .map(serde_json::from_reader::<_, Signed<Mirrors>>)
+    {
+        if verify_signed(&root.signed, &old_mirrors, verifier).is_ok() {
+            ensure!(
+                old_mirrors.signed.version <= mirrors.signed.version,
+                error::RollbackDetected {
+                    role: RoleType::Mirrors,
+                    trusted_version: old_mirrors.signed.version,
+                    offered_version: mirrors.signed.version
+                }
+            );
+        }
+    }
+
+    // Check for a freeze attack.
+    if expiration_enforcement == ExpirationEnforcement::Safe {
+        check_expired(datastore, clock, &mirrors.signed)?;
+    }
+
+    report_fetched(
+        on_fetched,
+        RoleType::Mirrors,
+        "mirrors",
+        mirrors.signed.version,
+        &reader,
+    );
+    datastore.create(path, &mirrors)?;
+
+    Ok(mirrors)
+}
+
 /// Step 3 of the client application, which loads the snapshot metadata file.
+#[allow(clippy::too_many_arguments)]
 fn load_snapshot(
     transport: &dyn Transport,
     root: &Signed<Root>,
     timestamp: &Signed<Timestamp>,
-    datastore: &Datastore,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    max_snapshot_size: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
 ) -> Result<Signed<Snapshot>> {
+    let fetch = snapshot_fetch(root, timestamp, max_snapshot_size, metadata_base_url)?;
+    verify_snapshot(
+        fetch_metadata(transport, &fetch)?,
+        root,
+        timestamp,
+        datastore,
+        clock,
+        expiration_enforcement,
+        on_fetched,
+        verifier,
+    )
+}
+
+/// Returns the snapshot's entry in the timestamp metadata file.
+fn snapshot_meta(timestamp: &Signed<Timestamp>) -> Result<&TimestampMeta> {
+    timestamp
+        .signed
+        .meta
+        .get("snapshot.json")
+        .context(error::MetaMissing {
+            file: "snapshot.json",
+            role: RoleType::Timestamp,
+        })
+}
+
+/// Returns how to fetch the snapshot metadata file that `timestamp` lists.
+pub(crate) fn snapshot_fetch<'a>(
+    root: &Signed<Root>,
+    timestamp: &'a Signed<Timestamp>,
+    max_snapshot_size: u64,
+    metadata_base_url: &Url,
+) -> Result<MetadataFetch<'a>> {
     // 3. Download snapshot metadata file, up to the number of bytes specified in the timestamp
     //    metadata file. If consistent snapshots are not used (see Section 7), then the filename
     //    used to download the snapshot metadata file is of the fixed form FILENAME.EXT (e.g.,
@@ -748,46 +2007,57 @@ fn load_snapshot(
     //    42.snapshot.json), where VERSION_NUMBER is the version number of the snapshot metadata
     //    file listed in the timestamp metadata file. In either case, the client MUST write the
     //    file to non-volatile storage as FILENAME.EXT.
-    let snapshot_meta = timestamp
-        .signed
-        .meta
-        .get("snapshot.json")
-        .context(error::MetaMissing {
-            file: "snapshot.json",
-            role: RoleType::Timestamp,
-        })?;
+    let snapshot_meta = snapshot_meta(timestamp)?;
     let path = if root.signed.consistent_snapshot {
         format!("{}.snapshot.json", snapshot_meta.version)
     } else {
         "snapshot.json".to_owned()
     };
-    let reader = fetch_sha256(
-        transport,
-        metadata_base_url.join(&path).context(error::JoinUrl {
-            path,
-            url: metadata_base_url.to_owned(),
-        })?,
+    ensure!(
+        snapshot_meta.length <= max_snapshot_size,
+        error::MetadataSizeExceeded {
+            role: RoleType::Snapshot,
+            max_size: max_snapshot_size,
+            specifier: "max_snapshot_size argument",
+        }
+    );
+    Ok(MetadataFetch::hashed(
+        join_url(metadata_base_url, &path)?,
         snapshot_meta.length,
         "timestamp.json",
-        &snapshot_meta.hashes.sha256,
-    )?;
-    let snapshot: Signed<Snapshot> =
-        serde_json::from_reader(reader).context(error::ParseMetadata {
-            role: RoleType::Snapshot,
-        })?;
+        &snapshot_meta.hashes,
+    ))
+}
+
+/// Steps 3.1 to 3.4 of the client application, which verify the snapshot metadata file that was
+/// read from `reader`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_snapshot<R: Read>(
+    reader: R,
+    root: &Signed<Root>,
+    timestamp: &Signed<Timestamp>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<Snapshot>> {
+    let snapshot_meta = snapshot_meta(timestamp)?;
+    let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
+    let snapshot: Signed<Snapshot> = parse_metadata(&mut reader, RoleType::Snapshot)?;
 
     // 3.1. Check against timestamp metadata. The hashes and version number of the new snapshot
     //   metadata file MUST match the hashes and version number listed in timestamp metadata. If
     //   hashes and version do not match, discard the new snapshot metadata, abort the update
     //   cycle, and report the failure.
     //
-    // (We already checked the hash in `fetch_sha256` above.)
+    // (The hash was checked as the file was read.)
     ensure!(
         snapshot.signed.version == snapshot_meta.version,
-        error::VersionMismatch {
+        error::MetaVersionMismatch {
             role: RoleType::Snapshot,
-            fetched: snapshot.signed.version,
-            expected: snapshot_meta.version
+            expected: snapshot_meta.version,
+            actual: snapshot.signed.version,
         }
     );
 
@@ -795,11 +2065,10 @@ fn load_snapshot(
     //   of keys specified in the trusted root metadata file. If the new snapshot metadata file is
     //   not signed as required, discard it, abort the update cycle, and report the signature
     //   failure.
-    root.signed
-        .verify_role(&snapshot)
-        .context(error::VerifyMetadata {
-            role: RoleType::Snapshot,
-        })?;
+    verify_signed(&root.signed, &snapshot, verifier).context(error::VerifyMetadata {
+        role: RoleType::Snapshot,
+    })?;
+    check_spec_version(&snapshot.signed)?;
 
     // 3.3. Check for a rollback attack.
     //
@@ -813,13 +2082,13 @@ fn load_snapshot(
         //   than or equal to the version number of the new snapshot metadata file. If the new
         //   snapshot metadata file is older than the trusted metadata file, discard it, abort the
         //   update cycle, and report the potential rollback attack.
-        if root.signed.verify_role(&old_snapshot).is_ok() {
+        if verify_signed(&root.signed, &old_snapshot, verifier).is_ok() {
             ensure!(
                 old_snapshot.signed.version <= snapshot.signed.version,
-                error::OlderMetadata {
+                error::RollbackDetected {
                     role: RoleType::Snapshot,
-                    current_version: old_snapshot.signed.version,
-                    new_version: snapshot.signed.version
+                    trusted_version: old_snapshot.signed.version,
+                    offered_version: snapshot.signed.version
                 }
             );
 
@@ -842,10 +2111,10 @@ fn load_snapshot(
                         })?;
                 ensure!(
                     old_targets_meta.version <= targets_meta.version,
-                    error::OlderMetadata {
+                    error::RollbackDetected {
                         role: RoleType::Targets,
-                        current_version: old_targets_meta.version,
-                        new_version: targets_meta.version,
+                        trusted_version: old_targets_meta.version,
+                        offered_version: targets_meta.version,
                     }
                 );
             }
@@ -857,25 +2126,68 @@ fn load_snapshot(
     // metadata file becomes the trusted snapshot metadata file. If the new snapshot metadata file
     // is expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &snapshot.signed)?;
+        check_expired(datastore, clock, &snapshot.signed)?;
     }
 
     // Now that everything seems okay, write the snapshot file to the datastore.
+    report_fetched(
+        on_fetched,
+        RoleType::Snapshot,
+        "snapshot",
+        snapshot.signed.version,
+        &reader,
+    );
     datastore.create("snapshot.json", &snapshot)?;
 
     Ok(snapshot)
 }
 
 /// Step 4 of the client application, which loads the targets metadata file.
+#[allow(clippy::too_many_arguments)]
 fn load_targets(
     transport: &dyn Transport,
     root: &Signed<Root>,
     snapshot: &Signed<Snapshot>,
-    datastore: &Datastore,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
     max_targets_size: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
 ) -> Result<Signed<crate::schema::Targets>> {
+    let fetch = targets_fetch(root, snapshot, max_targets_size, metadata_base_url)?;
+    verify_targets(
+        fetch_metadata(transport, &fetch)?,
+        root,
+        snapshot,
+        datastore,
+        clock,
+        expiration_enforcement,
+        on_fetched,
+        verifier,
+    )
+}
+
+/// Returns the top-level targets role's entry in the snapshot metadata file.
+fn targets_meta(snapshot: &Signed<Snapshot>) -> Result<&SnapshotMeta> {
+    snapshot
+        .signed
+        .meta
+        .get("targets.json")
+        .context(error::MetaMissing {
+            file: "targets.json",
+            role: RoleType::Timestamp,
+        })
+}
+
+/// Returns how to fetch the top-level targets metadata file that `snapshot` lists.
+pub(crate) fn targets_fetch<'a>(
+    root: &Signed<Root>,
+    snapshot: &'a Signed<Snapshot>,
+    max_targets_size: u64,
+    metadata_base_url: &Url,
+) -> Result<MetadataFetch<'a>> {
     // 4. Download the top-level targets metadata file, up to either the number of bytes specified
     //    in the snapshot metadata file, or some Z number of bytes. The value for Z is set by the
     //    authors of the application using TUF. For example, Z may be tens of kilobytes. If
@@ -885,60 +2197,53 @@ fn load_targets(
     //    VERSION_NUMBER is the version number of the targets metadata file listed in the snapshot
     //    metadata file. In either case, the client MUST write the file to non-volatile storage as
     //    FILENAME.EXT.
-    let targets_meta = snapshot
-        .signed
-        .meta
-        .get("targets.json")
-        .context(error::MetaMissing {
-            file: "targets.json",
-            role: RoleType::Timestamp,
-        })?;
+    let targets_meta = targets_meta(snapshot)?;
     let path = if root.signed.consistent_snapshot {
         format!("{}.targets.json", targets_meta.version)
     } else {
         "targets.json".to_owned()
     };
-    let targets_url = metadata_base_url.join(&path).context(error::JoinUrl {
-        path,
-        url: metadata_base_url.to_owned(),
-    })?;
+    let targets_url = join_url(metadata_base_url, &path)?;
     let (max_targets_size, specifier) = match targets_meta.length {
         Some(length) => (length, "snapshot.json"),
         None => (max_targets_size, "max_targets_size parameter"),
     };
-    let reader = if let Some(hashes) = &targets_meta.hashes {
-        Box::new(fetch_sha256(
-            transport,
-            targets_url,
-            max_targets_size,
-            specifier,
-            &hashes.sha256,
-        )?) as Box<dyn Read>
-    } else {
-        Box::new(fetch_max_size(
-            transport,
-            targets_url,
-            max_targets_size,
-            specifier,
-        )?)
-    };
+    Ok(match &targets_meta.hashes {
+        Some(hashes) => MetadataFetch::hashed(targets_url, max_targets_size, specifier, hashes),
+        None => MetadataFetch::max_size(targets_url, max_targets_size, specifier),
+    })
+}
+
+/// Steps 4.1 to 4.5 of the client application, which verify the top-level targets metadata file
+/// that was read from `reader`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_targets<R: Read>(
+    reader: R,
+    root: &Signed<Root>,
+    snapshot: &Signed<Snapshot>,
+    datastore: &dyn Datastore,
+    clock: &dyn Clock,
+    expiration_enforcement: ExpirationEnforcement,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<crate::schema::Targets>> {
+    let targets_meta = targets_meta(snapshot)?;
+    let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
     let mut targets: Signed<crate::schema::Targets> =
-        serde_json::from_reader(reader).context(error::ParseMetadata {
-            role: RoleType::Targets,
-        })?;
+        parse_metadata(&mut reader, RoleType::Targets)?;
 
     // 4.1. Check against snapshot metadata. The hashes (if any), and version number of the new
     //   targets metadata file MUST match the trusted snapshot metadata. This is done, in part, to
     //   prevent a mix-and-match attack by man-in-the-middle attackers. If the new targets metadata
     //   file does not match, discard it, abort the update cycle, and report the failure.
     //
-    // (We already checked the hash in `fetch_sha256` above.)
+    // (The hashes, if any, were checked as the file was read.)
     ensure!(
         targets.signed.version == targets_meta.version,
-        error::VersionMismatch {
+        error::MetaVersionMismatch {
             role: RoleType::Targets,
-            fetched: targets.signed.version,
-            expected: targets_meta.version
+            expected: targets_meta.version,
+            actual: targets.signed.version,
         }
     );
 
@@ -946,11 +2251,10 @@ fn load_targets(
     //   signed by a threshold of keys specified in the trusted root metadata file. If the new
     //   targets metadata file is not signed as required, discard it, abort the update cycle, and
     //   report the failure.
-    root.signed
-        .verify_role(&targets)
-        .context(error::VerifyMetadata {
-            role: RoleType::Targets,
-        })?;
+    verify_signed(&root.signed, &targets, verifier).context(error::VerifyMetadata {
+        role: RoleType::Targets,
+    })?;
+    check_spec_version(&targets.signed)?;
 
     // 4.3. Check for a rollback attack. The version number of the trusted targets metadata file,
     //   if any, MUST be less than or equal to the version number of the new targets metadata file.
@@ -960,13 +2264,13 @@ fn load_targets(
         .reader("targets.json")?
         .map(serde_json::from_reader::<_, Signed<crate::schema::Targets>>)
     {
-        if root.signed.verify_role(&old_targets).is_ok() {
+        if verify_signed(&root.signed, &old_targets, verifier).is_ok() {
             ensure!(
                 old_targets.signed.version <= targets.signed.version,
-                error::OlderMetadata {
+                error::RollbackDetected {
                     role: RoleType::Targets,
-                    current_version: old_targets.signed.version,
-                    new_version: targets.signed.version
+                    trusted_version: old_targets.signed.version,
+                    offered_version: targets.signed.version
                 }
             );
         }
@@ -977,114 +2281,197 @@ fn load_targets(
     // metadata file becomes the trusted targets metadata file. If the new targets metadata file is
     // expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &targets.signed)?;
+        check_expired(datastore, clock, &targets.signed)?;
     }
 
     // Now that everything seems okay, write the targets file to the datastore.
+    report_fetched(
+        on_fetched,
+        RoleType::Targets,
+        "targets",
+        targets.signed.version,
+        &reader,
+    );
     datastore.create("targets.json", &targets)?;
 
     // 4.5. Perform a preorder depth-first search for metadata about the desired target, beginning
     //   with the top-level targets role.
+    //
+    // (The metadata of delegated roles is loaded by `Repository::load_delegated_roles`, or as the
+    // search reaches them, in `Repository::delegated_targets`.)
     if let Some(delegations) = &mut targets.signed.delegations {
-        load_delegations(
-            transport,
-            snapshot,
-            root.signed.consistent_snapshot,
-            metadata_base_url,
-            max_targets_size,
-            delegations,
-            &datastore,
-        )?;
+        add_succinct_bins(delegations, snapshot)?;
     }
 
     Ok(targets)
 }
 
-// Follow the paths of delegations starting with the top level targets.json delegation
-fn load_delegations(
+/// Loads the metadata of `delegated_role`, which is listed in `delegation`, verifying it against
+/// the snapshot and the keys in `delegation`, and checking that any roles it delegates to are
+/// within the paths that were delegated to it.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn load_delegated_role(
     transport: &dyn Transport,
     snapshot: &Signed<Snapshot>,
     consistent_snapshot: bool,
     metadata_base_url: &Url,
     max_targets_size: u64,
+    delegation: &Delegations,
+    delegated_role: &DelegatedRole,
+    datastore: &dyn Datastore,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<crate::schema::Targets>> {
+    let fetch = delegated_role_fetch(
+        snapshot,
+        consistent_snapshot,
+        metadata_base_url,
+        max_targets_size,
+        delegated_role,
+    )?;
+    verify_delegated_role(
+        fetch_metadata(transport, &fetch)?,
+        snapshot,
+        consistent_snapshot,
+        delegation,
+        delegated_role,
+        datastore,
+        on_fetched,
+        verifier,
+    )
+}
+
+/// Returns the delegated role's entry in the snapshot metadata file.
+fn delegated_role_meta<'a>(
+    snapshot: &'a Signed<Snapshot>,
+    delegated_role: &DelegatedRole,
+) -> Result<&'a SnapshotMeta> {
+    snapshot
+        .signed
+        .meta
+        .get(&format!("{}.json", &delegated_role.name))
+        .context(error::RoleNotInMeta {
+            name: delegated_role.name.clone(),
+        })
+}
+
+/// Returns the path of the metadata file of `delegated_role`.
+fn delegated_role_path(
+    snapshot: &Signed<Snapshot>,
+    consistent_snapshot: bool,
+    delegated_role: &DelegatedRole,
+) -> Result<String> {
+    let role_meta = delegated_role_meta(snapshot, delegated_role)?;
+    Ok(if consistent_snapshot {
+        format!("{}.{}.json", &role_meta.version, &delegated_role.name)
+    } else {
+        format!("{}.json", &delegated_role.name)
+    })
+}
+
+/// Returns how to fetch the metadata of `delegated_role`.
+pub(crate) fn delegated_role_fetch<'a>(
+    snapshot: &Signed<Snapshot>,
+    consistent_snapshot: bool,
+    metadata_base_url: &Url,
+    max_targets_size: u64,
+    delegated_role: &DelegatedRole,
+) -> Result<MetadataFetch<'a>> {
+    let path = delegated_role_path(snapshot, consistent_snapshot, delegated_role)?;
+    Ok(MetadataFetch::max_size(
+        join_url(metadata_base_url, &path)?,
+        max_targets_size,
+        "max_targets_size parameter",
+    ))
+}
+
+/// Verifies the metadata of `delegated_role` that was read from `reader`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_delegated_role<R: Read>(
+    reader: R,
+    snapshot: &Signed<Snapshot>,
+    consistent_snapshot: bool,
+    delegation: &Delegations,
+    delegated_role: &DelegatedRole,
+    datastore: &dyn Datastore,
+    on_fetched: Option<&MetadataCallback>,
+    verifier: Option<&SharedVerifier>,
+) -> Result<Signed<crate::schema::Targets>> {
+    let role_meta = delegated_role_meta(snapshot, delegated_role)?;
+    let mut reader = CaptureAdapter::new(reader, on_fetched.is_some());
+    // since each role is a targets, we load them as such
+    let mut role: Signed<crate::schema::Targets> = parse_metadata(&mut reader, RoleType::Targets)?;
+    // verify each role with the delegation
+    verify_delegated(delegation, &role, &delegated_role.name, verifier).context(
+        error::VerifyMetadata {
+            role: RoleType::Targets,
+        },
+    )?;
+    check_spec_version(&role.signed)?;
+    ensure!(
+        role.signed.version == role_meta.version,
+        error::MetaVersionMismatch {
+            role: RoleType::Targets,
+            expected: role_meta.version,
+            actual: role.signed.version,
+        }
+    );
+    if let Some(delegations) = role.signed.delegations.as_mut() {
+        delegations.verify_paths().context(error::InvalidPath {})?;
+        add_succinct_bins(delegations, snapshot)?;
+        // A role may only delegate paths that fall within the paths that its parent delegated to
+        // it.
+        for child in &delegations.roles {
+            delegated_role
+                .verify_paths(&child.paths)
+                .context(error::UnauthorizedDelegation {
+                    parent: delegated_role.name.clone(),
+                    child: child.name.clone(),
+                })?;
+        }
+    }
+
+    report_fetched(
+        on_fetched,
+        RoleType::Targets,
+        &delegated_role.name,
+        role.signed.version,
+        &reader,
+    );
+    datastore.create(
+        &delegated_role_path(snapshot, consistent_snapshot, delegated_role)?,
+        &role,
+    )?;
+    Ok(role)
+}
+
+/// Adds the bins of a succinct hash delegation in `delegation`, if it has one, to its delegated
+/// roles, so that they are loaded like the roles that are listed. Each bin must be listed in the
+/// snapshot; this is checked as the bins are added, so that a large `bit_length` is rejected
+/// before it can exhaust memory.
+pub(crate) fn add_succinct_bins(
     delegation: &mut Delegations,
-    datastore: &Datastore,
+    snapshot: &Signed<Snapshot>,
 ) -> Result<()> {
-    let mut delegated_roles: HashMap<String, Option<Signed<crate::schema::Targets>>> =
-        HashMap::new();
-    for delegated_role in &delegation.roles {
-        // find the role file metadata
-        let role_meta = snapshot
-            .signed
-            .meta
-            .get(&format!("{}.json", &delegated_role.name))
-            .context(error::RoleNotInMeta {
-                name: delegated_role.name.clone(),
-            })?;
-
-        let path = if consistent_snapshot {
-            format!("{}.{}.json", &role_meta.version, &delegated_role.name)
-        } else {
-            format!("{}.json", &delegated_role.name)
-        };
-        let role_url = metadata_base_url.join(&path).context(error::JoinUrl {
-            path: path.clone(),
-            url: metadata_base_url.to_owned(),
-        })?;
-        let specifier = "max_targets_size parameter";
-        // load the role json file
-        let reader = Box::new(fetch_max_size(
-            transport,
-            role_url,
-            max_targets_size,
-            specifier,
-        )?);
-        // since each role is a targets, we load them as such
-        let role: Signed<crate::schema::Targets> =
-            serde_json::from_reader(reader).context(error::ParseMetadata {
-                role: RoleType::Targets,
-            })?;
-        // verify each role with the delegation
-        delegation
-            .verify_role(&role, &delegated_role.name)
-            .context(error::VerifyMetadata {
-                role: RoleType::Targets,
-            })?;
+    if let Some(succinct_roles) = &mut delegation.succinct_roles {
         ensure!(
-            role.signed.version == role_meta.version,
-            error::VersionMismatch {
-                role: RoleType::Targets,
-                fetched: role.signed.version,
-                expected: role_meta.version
+            (1..=32).contains(&succinct_roles.bit_length),
+            error::SuccinctBitLength {
+                name_prefix: succinct_roles.name_prefix.clone(),
+                bit_length: succinct_roles.bit_length,
             }
         );
-        {
-            if let Some(delegations) = role.signed.delegations.as_ref() {
-                delegations.verify_paths().context(error::InvalidPath {})?
-            }
-        }
-
-        datastore.create(&path, &role)?;
-        delegated_roles.insert(delegated_role.name.clone(), Some(role));
-    }
-    // load all roles delegated by this role
-    for delegated_role in &mut delegation.roles {
-        delegated_role.targets = delegated_roles.remove(&delegated_role.name).context(
-            error::DelegatedRolesNotConsistent {
-                name: delegated_role.name.clone(),
-            },
-        )?;
-        if let Some(targets) = &mut delegated_role.targets {
-            if let Some(delegations) = &mut targets.signed.delegations {
-                load_delegations(
-                    transport,
-                    snapshot,
-                    consistent_snapshot,
-                    metadata_base_url,
-                    max_targets_size,
-                    delegations,
-                    datastore,
-                )?;
+        if succinct_roles.bins.is_empty() {
+            for number in 0..succinct_roles.bin_count() {
+                let bin = succinct_roles.bin(number);
+                ensure!(
+                    snapshot
+                        .signed
+                        .meta
+                        .contains_key(&format!("{}.json", bin.name)),
+                    error::RoleNotInMeta { name: bin.name }
+                );
+                succinct_roles.bins.push(bin);
             }
         }
     }
@@ -1095,6 +2482,22 @@ fn load_delegations(
 mod tests {
     use super::*;
 
+    // A builder with no limits set builds the default limits, and each setter only changes its own
+    // limit.
+    #[test]
+    fn limits_builder() {
+        assert_eq!(Limits::builder().build(), Limits::default());
+        let limits = Limits::builder().max_delegation_depth(4).build();
+        assert_eq!(limits.max_delegation_depth, 4);
+        assert_eq!(
+            limits,
+            Limits {
+                max_delegation_depth: 4,
+                ..Limits::default()
+            }
+        );
+    }
+
     // Check if a url with a trailing slash and one without trailing slash can both be parsed
     #[test]
     fn url_missing_trailing_slash() {
@@ -1108,6 +2511,99 @@ mod tests {
         )
     }
 
+    // Metadata for a later minor version is accepted, but not a different major version.
+    #[test]
+    fn spec_version_major() {
+        let snapshot = |spec_version: &str| {
+            Snapshot::new(
+                spec_version.to_owned(),
+                NonZeroU64::new(1).unwrap(),
+                Utc::now(),
+            )
+        };
+        for supported in &["1", "1.0", "1.0.0", "1.0.31", "1.1.0"] {
+            check_spec_version(&snapshot(supported)).unwrap();
+        }
+        for unsupported in &["", "0.9.0", "2.0.0", "v1.0.0", "one"] {
+            assert!(matches!(
+                check_spec_version(&snapshot(unsupported)),
+                Err(error::Error::UnsupportedSpecVersion {
+                    role: RoleType::Snapshot,
+                    found,
+                    ..
+                }) if found == *unsupported
+            ));
+        }
+    }
+
+    // A truncated metadata file is reported with its role, and where parsing failed.
+    #[test]
+    fn parse_metadata_truncated() {
+        let truncated = b"{\n  \"signed\": {\n    \"_type\": \"snap";
+        let result = parse_metadata::<Signed<Snapshot>>(&truncated[..], RoleType::Snapshot);
+        assert!(matches!(
+            result,
+            Err(error::Error::ParseMetadata {
+                role: RoleType::Snapshot,
+                line: 3,
+                column,
+                ..
+            }) if column > 0
+        ));
+    }
+
+    #[test]
+    fn target_name_safety() {
+        for safe in &[
+            "file.txt",
+            "dir/file.txt",
+            "a..b",
+            "..file",
+            "dir/.../file",
+            "./file",
+        ] {
+            check_target_name(safe).unwrap();
+        }
+        for unsafe_name in &[
+            "",
+            "..",
+            "../file.txt",
+            "dir/../../file.txt",
+            "dir/..",
+            "..\\file.txt",
+            "dir\\..\\..\\file.txt",
+            "/etc/passwd",
+            "\\windows\\system32",
+            "C:\\windows\\system32",
+            "c:file.txt",
+        ] {
+            assert!(
+                matches!(
+                    check_target_name(unsafe_name),
+                    Err(error::Error::UnsafeTargetName { .. })
+                ),
+                "{}",
+                unsafe_name
+            );
+        }
+    }
+
+    #[test]
+    fn join_url_encodes_segments() {
+        let base = Url::parse("https://example.org/targets/").unwrap();
+        for (name, expected) in &[
+            ("file.txt", "https://example.org/targets/file.txt"),
+            ("dir/file.txt", "https://example.org/targets/dir/file.txt"),
+            ("my file.txt", "https://example.org/targets/my%20file.txt"),
+            (
+                "50% off #1?.txt",
+                "https://example.org/targets/50%25%20off%20%231%3F.txt",
+            ),
+        ] {
+            assert_eq!(join_url(&base, name).unwrap().as_str(), *expected);
+        }
+    }
+
     // Ensure that the `ExpirationEnforcement` traits are not changed by mistake.
     #[test]
     fn expiration_enforcement_traits() {
@@ -0,0 +1,196 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `RepositoryLoader::load_async`, which loads a repository with an `AsyncTransport`.
+//!
+//! Each metadata file is fetched by awaiting the `AsyncTransport`, and is then checked with the
+//! same functions as `RepositoryLoader::load`, so the two load paths only differ in how files are
+//! fetched.
+
+use crate::error::Result;
+use crate::fetch::{fetch_metadata_async, MetadataFetch};
+use crate::mirrors::{AsyncMirrorTransport, MirrorUrls};
+use crate::schema::{Mirrors, Root, Signed};
+use crate::transport::AsyncTransport;
+use crate::{
+    is_file_not_found, join_url, load_trusted_root, snapshot_fetch, targets_fetch, timestamp_fetch,
+    verify_mirrors, verify_snapshot, verify_targets, verify_timestamp, LoadSettings, Repository,
+    RepositoryLoader, RootUpdate,
+};
+use std::io::Read;
+
+impl<R: Read> RepositoryLoader<R> {
+    /// Load and verify TUF repository metadata without blocking the async runtime.
+    ///
+    /// Metadata files are fetched with the [`AsyncTransport`] set with
+    /// [`RepositoryLoader::async_transport`], so requests and the pauses between retries do not
+    /// occupy a thread, and are verified as they arrive. If no `AsyncTransport` has been set, the
+    /// repository is loaded with the [`Transport`](crate::Transport) in
+    /// `tokio::task::block_in_place`, which must be called from within a multi-threaded Tokio
+    /// runtime.
+    ///
+    /// A [`Transport`](crate::Transport) need not be `Send`, so the returned future is not `Send`
+    /// either: await it from the task that needs the repository rather than spawning it.
+    ///
+    /// Requires the `async` feature.
+    pub async fn load_async(mut self) -> Result<Repository> {
+        let mut transport = match self.async_transport.take() {
+            Some(transport) => transport,
+            None => return tokio::task::block_in_place(|| self.load()),
+        };
+        let (root, settings) = LoadSettings::new(self)?;
+        let datastore = settings.datastore.as_ref();
+        let clock = settings.clock.as_ref();
+        let limits = settings.limits;
+        let expiration_enforcement = settings.expiration_enforcement;
+        let on_metadata_fetched = settings.on_metadata_fetched.as_ref();
+        let signature_verifier = settings.signature_verifier.as_ref();
+
+        // 0. Load the trusted root metadata file + 1. Update the root metadata file
+        let root = update_root(
+            transport.as_ref(),
+            load_trusted_root(root, signature_verifier)?,
+            &settings,
+        )
+        .await?;
+
+        // If enabled, load the mirrors metadata file, and fetch everything else from the mirrors.
+        let mirrors = if settings.mirrors {
+            load_mirrors(transport.as_ref(), &root, &settings).await?
+        } else {
+            None
+        };
+        if let Some(mirrors) = &mirrors {
+            transport = Box::new(AsyncMirrorTransport::new(
+                transport,
+                MirrorUrls::new(
+                    mirrors.signed.mirrors.clone(),
+                    settings.metadata_base_url.clone(),
+                    settings.targets_base_url.clone(),
+                ),
+            ));
+        }
+
+        // 2. Download the timestamp metadata file
+        let fetch = timestamp_fetch(&settings.metadata_base_url, limits.max_timestamp_size)?;
+        let timestamp = verify_timestamp(
+            fetch_metadata_async(transport.as_ref(), &fetch).await?,
+            &root,
+            datastore,
+            clock,
+            expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
+        )?;
+
+        // 3. Download the snapshot metadata file
+        let fetch = snapshot_fetch(
+            &root,
+            &timestamp,
+            limits.max_snapshot_size,
+            &settings.metadata_base_url,
+        )?;
+        let snapshot = verify_snapshot(
+            fetch_metadata_async(transport.as_ref(), &fetch).await?,
+            &root,
+            &timestamp,
+            datastore,
+            clock,
+            expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
+        )?;
+
+        // 4. Download the targets metadata file
+        let fetch = targets_fetch(
+            &root,
+            &snapshot,
+            limits.max_targets_size,
+            &settings.metadata_base_url,
+        )?;
+        let targets = verify_targets(
+            fetch_metadata_async(transport.as_ref(), &fetch).await?,
+            &root,
+            &snapshot,
+            datastore,
+            clock,
+            expiration_enforcement,
+            on_metadata_fetched,
+            signature_verifier,
+        )?;
+
+        let settings = match &mirrors {
+            Some(mirrors) => settings.use_mirrors(mirrors),
+            None => settings,
+        };
+        let mut repository = settings.into_repository(root, mirrors, timestamp, snapshot, targets);
+        if !repository.lazy_delegated_roles {
+            repository
+                .fetch_delegated_roles_async(transport.as_ref())
+                .await?;
+            repository.load_delegated_roles()?;
+        }
+        Ok(repository)
+    }
+}
+
+/// Step 1 of the client application, which updates the trusted root metadata file to the latest
+/// version.
+async fn update_root(
+    transport: &dyn AsyncTransport,
+    root: Signed<Root>,
+    settings: &LoadSettings,
+) -> Result<Signed<Root>> {
+    let mut update = RootUpdate::new(root, settings.limits.max_root_updates);
+    loop {
+        let fetch = MetadataFetch::max_size(
+            update.next_url(&settings.metadata_base_url)?,
+            settings.limits.max_root_size,
+            "max_root_size argument",
+        );
+        match fetch_metadata_async(transport, &fetch).await {
+            Err(_) => break, // If this file is not available, then go to step 1.8.
+            Ok(reader) => {
+                if !update.verify_next(
+                    reader,
+                    settings.on_metadata_fetched.as_ref(),
+                    settings.signature_verifier.as_ref(),
+                )? {
+                    break;
+                }
+            }
+        }
+    }
+    update.finish(
+        settings.datastore.as_ref(),
+        settings.clock.as_ref(),
+        settings.expiration_enforcement,
+    )
+}
+
+/// Loads the optional mirrors metadata file, if the repository has one.
+async fn load_mirrors(
+    transport: &dyn AsyncTransport,
+    root: &Signed<Root>,
+    settings: &LoadSettings,
+) -> Result<Option<Signed<Mirrors>>> {
+    let fetch = MetadataFetch::max_size(
+        join_url(&settings.metadata_base_url, "mirrors.json")?,
+        settings.limits.max_mirrors_size,
+        "max_mirrors_size argument",
+    );
+    match fetch_metadata_async(transport, &fetch).await {
+        Ok(reader) => verify_mirrors(
+            reader,
+            root,
+            settings.datastore.as_ref(),
+            settings.clock.as_ref(),
+            settings.expiration_enforcement,
+            settings.on_metadata_fetched.as_ref(),
+            settings.signature_verifier.as_ref(),
+        )
+        .map(Some),
+        Err(err) if is_file_not_found(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `MirrorTransport`, which resolves fetches to the mirrors listed in `mirrors.json`.
+
+use crate::schema::Mirror;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncFetch, AsyncTransport};
+use crate::transport::{Transport, TransportError};
+use std::io::Read;
+use url::Url;
+
+/// Resolves URLs under the metadata and targets base URLs to the mirrors listed in
+/// `mirrors.json`.
+#[derive(Debug, Clone)]
+pub(crate) struct MirrorUrls {
+    mirrors: Vec<Mirror>,
+    metadata_base_url: Url,
+    targets_base_url: Url,
+}
+
+impl MirrorUrls {
+    /// Creates a `MirrorUrls` for `mirrors`. `metadata_base_url` and `targets_base_url` must end
+    /// with a slash.
+    pub(crate) fn new(mirrors: Vec<Mirror>, metadata_base_url: Url, targets_base_url: Url) -> Self {
+        Self {
+            mirrors,
+            metadata_base_url,
+            targets_base_url,
+        }
+    }
+
+    /// Returns the URLs of `url` on each of the mirrors that serve it, in order.
+    fn mirror_urls(&self, url: &Url) -> Vec<Url> {
+        if let Some(name) = url.as_str().strip_prefix(self.metadata_base_url.as_str()) {
+            self.mirrors
+                .iter()
+                .filter(|mirror| mirror.serves_metadata(name))
+                .filter_map(|mirror| mirror_url(&mirror.urlbase, &mirror.metapath, name))
+                .collect()
+        } else if let Some(name) = url.as_str().strip_prefix(self.targets_base_url.as_str()) {
+            self.mirrors
+                .iter()
+                .filter(|mirror| mirror.serves_target(name))
+                .filter_map(|mirror| mirror_url(&mirror.urlbase, &mirror.targetspath, name))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// A [`Transport`] that fetches files from the mirrors listed in `mirrors.json`.
+///
+/// A URL under the metadata base URL is fetched from each mirror whose `metacontent` patterns
+/// match the file name, and a URL under the targets base URL from each mirror whose
+/// `targetscontent` patterns match, trying the mirrors in order until one of them succeeds. If
+/// every mirror fails, the last error is returned. URLs that no mirror serves are fetched as-is.
+#[derive(Debug, Clone)]
+pub(crate) struct MirrorTransport {
+    inner: Box<dyn Transport>,
+    urls: MirrorUrls,
+}
+
+impl MirrorTransport {
+    /// Creates a `MirrorTransport` that fetches files from the mirrors in `urls` with `inner`.
+    pub(crate) fn new(inner: Box<dyn Transport>, urls: MirrorUrls) -> Self {
+        Self { inner, urls }
+    }
+}
+
+impl Transport for MirrorTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let mut last_error = None;
+        for mirror_url in self.urls.mirror_urls(&url) {
+            match self.inner.fetch(mirror_url) {
+                Ok(read) => return Ok(read),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        match last_error {
+            Some(err) => Err(err),
+            None => self.inner.fetch(url),
+        }
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        let mirror_urls = self.urls.mirror_urls(&url);
+        if mirror_urls.is_empty() {
+            return self.inner.exists(url);
+        }
+        let mut last_error = None;
+        for mirror_url in mirror_urls {
+            match self.inner.exists(mirror_url) {
+                Ok(true) => return Ok(true),
+                Ok(false) => {}
+                Err(err) => last_error = Some(err),
+            }
+        }
+        match last_error {
+            Some(err) => Err(err),
+            None => Ok(false),
+        }
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        self.inner.exists_is_cheap()
+    }
+}
+
+/// The [`AsyncTransport`] counterpart of [`MirrorTransport`].
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub(crate) struct AsyncMirrorTransport {
+    inner: Box<dyn AsyncTransport>,
+    urls: MirrorUrls,
+}
+
+#[cfg(feature = "async")]
+impl AsyncMirrorTransport {
+    /// Creates an `AsyncMirrorTransport` that fetches files from the mirrors in `urls` with
+    /// `inner`.
+    pub(crate) fn new(inner: Box<dyn AsyncTransport>, urls: MirrorUrls) -> Self {
+        Self { inner, urls }
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransport for AsyncMirrorTransport {
+    fn fetch(&self, url: Url) -> AsyncFetch<'_> {
+        Box::pin(async move {
+            let mut last_error = None;
+            for mirror_url in self.urls.mirror_urls(&url) {
+                match self.inner.fetch(mirror_url).await {
+                    Ok(read) => return Ok(read),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+            match last_error {
+                Some(err) => Err(err),
+                None => self.inner.fetch(url).await,
+            }
+        })
+    }
+}
+
+/// Returns the URL of the file `name`, which is already percent-encoded, in the directory `path`
+/// relative to `urlbase`. Returns `None` if `urlbase` is not a valid base URL.
+fn mirror_url(urlbase: &str, path: &str, name: &str) -> Option<Url> {
+    let mut url = Url::parse(urlbase).ok()?;
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(path.split('/').filter(|segment| !segment.is_empty()))
+        .push("");
+    Url::parse(&format!("{}{}", url, name)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn mirror(urlbase: &str, metacontent: &[&str], targetscontent: &[&str]) -> Mirror {
+        Mirror {
+            urlbase: urlbase.to_owned(),
+            metapath: "metadata".to_owned(),
+            targetspath: "/targets/".to_owned(),
+            metacontent: metacontent.iter().map(|s| (*s).to_owned()).collect(),
+            targetscontent: targetscontent.iter().map(|s| (*s).to_owned()).collect(),
+            _extra: HashMap::new(),
+        }
+    }
+
+    // Files are fetched from each mirror that serves them, in order, and from the base URLs if no
+    // mirror serves them.
+    #[test]
+    fn mirror_urls() {
+        let mirror_urls = MirrorUrls::new(
+            vec![
+                mirror("https://a.example.com", &["*.json"], &[]),
+                mirror("https://b.example.com/repo/", &["*"], &["*.txt"]),
+            ],
+            Url::parse("https://example.com/metadata/").unwrap(),
+            Url::parse("https://example.com/targets/").unwrap(),
+        );
+        let urls = |url: &str| -> Vec<String> {
+            mirror_urls
+                .mirror_urls(&Url::parse(url).unwrap())
+                .iter()
+                .map(Url::to_string)
+                .collect()
+        };
+        assert_eq!(
+            urls("https://example.com/metadata/timestamp.json"),
+            vec![
+                "https://a.example.com/metadata/timestamp.json",
+                "https://b.example.com/repo/metadata/timestamp.json",
+            ]
+        );
+        assert_eq!(
+            urls("https://example.com/targets/dir/file%201.txt"),
+            vec!["https://b.example.com/repo/targets/dir/file%201.txt"]
+        );
+        assert!(urls("https://example.com/targets/file.tar").is_empty());
+        assert!(urls("https://example.org/metadata/root.json").is_empty());
+    }
+}
@@ -0,0 +1,747 @@
+//! The `oci` module provides `OciTransport`, which enables `Repository` objects to be loaded from
+//! artifacts in an OCI registry.
+use crate::{HttpTransport, HttpTransportBuilder, Transport, TransportError, TransportErrorKind};
+use log::debug;
+use reqwest::blocking::Client;
+use reqwest::header::{self, HeaderValue};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The manifest media types that are accepted when resolving a tag.
+const MANIFEST_MEDIA_TYPES: &str =
+    "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+/// How long a registry token is valid for if the token endpoint does not say, per the Docker
+/// registry token specification.
+const DEFAULT_TOKEN_EXPIRES_IN_SECONDS: u64 = 60;
+/// Registry tokens are requested again when they are this close to expiring.
+const TOKEN_REFRESH_MARGIN_SECONDS: u64 = 10;
+
+/// A [`Transport`] for files that are stored as blobs in an OCI registry.
+///
+/// The transport handles two forms of URL:
+/// - `oci://<registry>/<repository>@<digest>`, e.g. `oci://registry.example.com/tuf@sha256:...`,
+///   which pulls the blob with that digest.
+/// - `oci://<registry>/<repository>/<tag>`, which pulls the single layer of the artifact with that
+///   tag. A repository whose metadata files are pushed as artifacts tagged with their file names,
+///   e.g. `timestamp.json`, can be loaded with a metadata base URL of
+///   `oci://registry.example.com/tuf/metadata/`.
+///
+/// Credentials are found the way that `docker` finds them: from the credential helper for the
+/// registry, the credential store, or the `auths` in `config.json` (in `$DOCKER_CONFIG`, or
+/// `~/.docker`). Registries that do not require credentials are accessed anonymously. Blobs are
+/// fetched with an [`HttpTransport`](crate::HttpTransport), so they are retried per its settings,
+/// and a missing blob or tag results in [`TransportErrorKind::FileNotFound`].
+///
+/// The authorization for each registry and repository is cached until it expires, and is shared
+/// by clones of the transport, so the registry is only asked how to authenticate, and the
+/// credentials are only looked up, when there is no valid authorization.
+///
+/// Requires the `oci` feature.
+#[derive(Debug, Clone)]
+pub struct OciTransport {
+    settings: HttpTransportBuilder,
+    docker_config: Option<PathBuf>,
+    plain_http: bool,
+    /// Provides the HTTP client, built from `settings`, that every request is sent with.
+    http: HttpTransport,
+    /// The authorization for each registry and repository.
+    authorizations: Arc<Mutex<HashMap<(String, String), CachedAuthorization>>>,
+}
+
+/// The `Authorization` header for a registry and repository, or `None` if the registry does not
+/// require one.
+#[derive(Debug)]
+struct CachedAuthorization {
+    value: Option<HeaderValue>,
+    /// When a token expires; `None` for credentials that do not.
+    expires: Option<Instant>,
+}
+
+impl Default for OciTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OciTransport {
+    /// Create a new `OciTransport` with default settings.
+    pub fn new() -> Self {
+        Self {
+            settings: HttpTransportBuilder::default(),
+            docker_config: None,
+            plain_http: false,
+            http: HttpTransport::default(),
+            authorizations: Arc::default(),
+        }
+    }
+
+    /// Set the HTTP settings used to fetch manifests and blobs, e.g. the number of tries. Any
+    /// bearer token in the settings is replaced by the registry's credentials.
+    pub fn http_settings(mut self, settings: HttpTransportBuilder) -> Self {
+        self.http = settings.clone().build();
+        self.settings = settings;
+        self
+    }
+
+    /// Set the directory that contains the docker `config.json`. Defaults to `$DOCKER_CONFIG`, or
+    /// `~/.docker`.
+    pub fn docker_config<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.docker_config = Some(dir.into());
+        self
+    }
+
+    /// Connect to registries over HTTP instead of HTTPS, e.g. for a local registry. Defaults to
+    /// `false`.
+    pub fn plain_http(mut self, value: bool) -> Self {
+        self.plain_http = value;
+        self
+    }
+
+    /// Parses `url` and authorizes requests to its registry.
+    fn registry_request(&self, url: &Url) -> Result<RegistryRequest, FetchError> {
+        let reference = Reference::parse(url)?;
+        let base = self.registry_url(&reference)?;
+        let client = self
+            .http
+            .client()
+            .map_err(|e| TransportError::from((base.clone(), e)))?;
+        let mut settings = self.settings.clone();
+        if let Some(authorization) = self.authorization(&client, &reference, &base)? {
+            settings = settings.header(header::AUTHORIZATION, authorization);
+        }
+        Ok(RegistryRequest {
+            reference,
+            base,
+            settings,
+            client,
+        })
+    }
+
+    fn fetch_reference(&self, url: &Url) -> Result<Box<dyn Read + Send>, FetchError> {
+        let request = self.registry_request(url)?;
+        let digest = match &request.reference.target {
+            Target::Digest(digest) => digest.clone(),
+            Target::Tag(tag) => {
+                let (manifest_url, transport) = request.manifest(tag)?;
+                let reader = transport.fetch(manifest_url)?;
+                let mut manifest: Manifest =
+                    serde_json::from_reader(reader).context(ManifestParse)?;
+                ensure!(
+                    manifest.layers.len() == 1,
+                    ManifestLayers {
+                        count: manifest.layers.len()
+                    }
+                );
+                manifest.layers.remove(0).digest
+            }
+        };
+
+        let (blob_url, transport) = request.blob(&digest)?;
+        debug!("fetching '{}' from '{}'", url, blob_url);
+        Ok(transport.fetch(blob_url)?)
+    }
+
+    /// Checks for the blob with a `HEAD` request, or for a tag, its manifest. The layer of a
+    /// tagged artifact is only checked when it is fetched.
+    fn reference_exists(&self, url: &Url) -> Result<bool, FetchError> {
+        let request = self.registry_request(url)?;
+        let (url, transport) = match &request.reference.target {
+            Target::Digest(digest) => request.blob(digest)?,
+            Target::Tag(tag) => request.manifest(tag)?,
+        };
+        Ok(transport.exists(url)?)
+    }
+
+    /// Returns the base URL of the registry API, e.g. `https://registry.example.com/v2/`.
+    fn registry_url(&self, reference: &Reference) -> Result<Url, FetchError> {
+        let scheme = if self.plain_http { "http" } else { "https" };
+        Ok(Url::parse(&format!("{}://{}/v2/", scheme, reference.registry)).context(JoinUrl)?)
+    }
+
+    /// Returns the `Authorization` header to send to the registry for `reference`, or `None` if the
+    /// registry does not require one, from the cache if it has not expired.
+    fn authorization(
+        &self,
+        client: &Client,
+        reference: &Reference,
+        base: &Url,
+    ) -> Result<Option<HeaderValue>, FetchError> {
+        let key = (reference.registry.clone(), reference.repository.clone());
+        // a poisoned lock only means that another thread panicked while authenticating.
+        let mut authorizations = self
+            .authorizations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = authorizations.get(&key) {
+            let expired = match cached.expires {
+                Some(expires) => expires <= Instant::now(),
+                None => false,
+            };
+            if !expired {
+                return Ok(cached.value.clone());
+            }
+        }
+        let cached = self.authenticate(client, reference, base)?;
+        let value = cached.value.clone();
+        authorizations.insert(key, cached);
+        Ok(value)
+    }
+
+    /// Asks the registry how to authenticate, and returns the `Authorization` header for
+    /// `reference`.
+    fn authenticate(
+        &self,
+        client: &Client,
+        reference: &Reference,
+        base: &Url,
+    ) -> Result<CachedAuthorization, FetchError> {
+        let response = client.get(base.as_str()).send().context(Challenge)?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(CachedAuthorization {
+                value: None,
+                expires: None,
+            });
+        }
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        let credentials = self.credentials(&reference.registry)?;
+        let (scheme, params) = parse_challenge(&challenge);
+
+        let (value, expires) = if scheme.eq_ignore_ascii_case("basic") {
+            let credentials = credentials.context(MissingCredentials {
+                registry: &reference.registry,
+            })?;
+            (format!("Basic {}", credentials.basic()), None)
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            let realm = params.get("realm").context(UnsupportedChallenge {
+                challenge: &challenge,
+            })?;
+            let mut request = client
+                .get(realm.as_str())
+                .query(&[("scope", format!("repository:{}:pull", reference.repository))]);
+            if let Some(service) = params.get("service") {
+                request = request.query(&[("service", service)]);
+            }
+            if let Some(credentials) = &credentials {
+                request = request.header(
+                    header::AUTHORIZATION,
+                    format!("Basic {}", credentials.basic()),
+                );
+            }
+            let response = request
+                .send()
+                .and_then(reqwest::blocking::Response::error_for_status)
+                .context(Token)?;
+            let token: TokenResponse = serde_json::from_reader(response).context(TokenParse)?;
+            let expires_in = token
+                .expires_in
+                .unwrap_or(DEFAULT_TOKEN_EXPIRES_IN_SECONDS)
+                .saturating_sub(TOKEN_REFRESH_MARGIN_SECONDS);
+            (
+                format!(
+                    "Bearer {}",
+                    token.token.or(token.access_token).unwrap_or_default()
+                ),
+                Some(Instant::now() + Duration::from_secs(expires_in)),
+            )
+        } else {
+            return UnsupportedChallenge {
+                challenge: &challenge,
+            }
+            .fail()
+            .map_err(FetchError::from);
+        };
+        let mut value = HeaderValue::from_str(&value).context(InvalidCredentials {
+            registry: &reference.registry,
+        })?;
+        value.set_sensitive(true);
+        Ok(CachedAuthorization {
+            value: Some(value),
+            expires,
+        })
+    }
+
+    /// Finds the credentials for `registry` the way that `docker` does.
+    fn credentials(&self, registry: &str) -> Result<Option<Credentials>, OciError> {
+        let dir = match self.docker_config.clone().or_else(default_docker_config) {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+        let path = dir.join("config.json");
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let file = std::fs::File::open(&path).context(DockerConfigRead { path: &path })?;
+        let config: DockerConfig =
+            serde_json::from_reader(file).context(DockerConfigParse { path: &path })?;
+
+        let helper = config
+            .cred_helpers
+            .iter()
+            .find(|(key, _)| registry_matches(key, registry))
+            .map(|(_, helper)| helper)
+            .or_else(|| config.creds_store.as_ref());
+        if let Some(helper) = helper {
+            if let Some(credentials) = credential_helper(helper, registry)? {
+                return Ok(Some(credentials));
+            }
+        }
+        config
+            .auths
+            .iter()
+            .find(|(key, _)| registry_matches(key, registry))
+            .map(|(_, auth)| auth.credentials(registry))
+            .transpose()
+    }
+}
+
+impl Transport for OciTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        self.fetch_reference(&url)
+            .map_err(|e| e.into_transport_error(url))
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        self.reference_exists(&url)
+            .map_err(|e| e.into_transport_error(url))
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
+
+/// A reference, with the settings and client that send authorized requests to its registry.
+struct RegistryRequest {
+    reference: Reference,
+    /// The base URL of the registry API.
+    base: Url,
+    settings: HttpTransportBuilder,
+    client: Client,
+}
+
+impl RegistryRequest {
+    /// Returns the URL of the manifest for `tag`, and the transport to fetch it with.
+    fn manifest(&self, tag: &str) -> Result<(Url, HttpTransport), FetchError> {
+        let url = self
+            .base
+            .join(&format!("{}/manifests/{}", self.reference.repository, tag))
+            .context(JoinUrl)?;
+        let settings = self.settings.clone().header(
+            header::ACCEPT,
+            HeaderValue::from_static(MANIFEST_MEDIA_TYPES),
+        );
+        Ok((
+            url,
+            HttpTransport::with_client(settings, self.client.clone()),
+        ))
+    }
+
+    /// Returns the URL of the blob with `digest`, and the transport to fetch it with.
+    fn blob(&self, digest: &str) -> Result<(Url, HttpTransport), FetchError> {
+        let url = self
+            .base
+            .join(&format!("{}/blobs/{}", self.reference.repository, digest))
+            .context(JoinUrl)?;
+        Ok((
+            url,
+            HttpTransport::with_client(self.settings.clone(), self.client.clone()),
+        ))
+    }
+}
+
+/// An error from `fetch_reference`, which is either from the `HttpTransport` or from this module.
+#[derive(Debug)]
+enum FetchError {
+    Transport(TransportError),
+    Oci(OciError),
+}
+
+impl FetchError {
+    /// Keeps the kind, e.g. `FileNotFound`, but reports the `oci` URL that was requested.
+    fn into_transport_error(self, url: Url) -> TransportError {
+        match self {
+            FetchError::Transport(e) => TransportError::new_with_cause(e.kind(), url, e),
+            FetchError::Oci(e) => TransportError::new_with_cause(TransportErrorKind::Other, url, e),
+        }
+    }
+}
+
+impl From<TransportError> for FetchError {
+    fn from(e: TransportError) -> Self {
+        FetchError::Transport(e)
+    }
+}
+
+impl From<OciError> for FetchError {
+    fn from(e: OciError) -> Self {
+        FetchError::Oci(e)
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A blob in a registry, parsed from an `oci` URL.
+#[derive(Debug, PartialEq, Eq)]
+struct Reference {
+    /// The registry host, and port if there is one.
+    registry: String,
+    repository: String,
+    target: Target,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Digest(String),
+    Tag(String),
+}
+
+impl Reference {
+    fn parse(url: &Url) -> Result<Self, FetchError> {
+        if url.scheme() != "oci" {
+            return Err(TransportError::new(TransportErrorKind::UnsupportedUrlScheme, url).into());
+        }
+        let host = url
+            .host_str()
+            .context(InvalidReference { url: url.as_str() })?;
+        let registry = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_owned(),
+        };
+        let path = url.path().trim_start_matches('/');
+        let (repository, target) = if let Some((repository, digest)) = split_once(path, '@') {
+            (repository, digest)
+        } else {
+            rsplit_once(path, '/').context(InvalidReference { url: url.as_str() })?
+        };
+        ensure!(
+            !repository.is_empty() && !target.is_empty(),
+            InvalidReference { url: url.as_str() }
+        );
+        Ok(Self {
+            registry,
+            repository: repository.to_owned(),
+            target: if path.contains('@') {
+                Target::Digest(target.to_owned())
+            } else {
+                Target::Tag(target.to_owned())
+            },
+        })
+    }
+}
+
+fn split_once(s: &str, delimiter: char) -> Option<(&str, &str)> {
+    let i = s.find(delimiter)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+fn rsplit_once(s: &str, delimiter: char) -> Option<(&str, &str)> {
+    let i = s.rfind(delimiter)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+/// An OCI image manifest, of which only the layers are needed.
+#[derive(Deserialize)]
+struct Manifest {
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+}
+
+/// The response from a registry's token endpoint, which uses either field name for the token.
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+    /// The number of seconds that the token is valid for.
+    expires_in: Option<u64>,
+}
+
+/// Parses a `WWW-Authenticate` header, e.g. `Bearer realm="https://auth.example.com/token",
+/// service="registry.example.com"`, into its scheme and parameters.
+fn parse_challenge(challenge: &str) -> (&str, HashMap<String, String>) {
+    let challenge = challenge.trim();
+    let (scheme, mut rest) = split_once(challenge, ' ').unwrap_or((challenge, ""));
+    let mut params = HashMap::new();
+    while let Some((key, after_key)) = split_once(rest, '=') {
+        let key = key.trim().trim_start_matches(',').trim();
+        let after_key = after_key.trim_start();
+        let (value, after_value) = if let Some(quoted) = after_key.strip_prefix('"') {
+            // quoted values may contain commas, e.g. a scope with several actions.
+            split_once(quoted, '"').unwrap_or((quoted, ""))
+        } else {
+            split_once(after_key, ',').unwrap_or((after_key, ""))
+        };
+        params.insert(key.to_ascii_lowercase(), value.to_owned());
+        rest = after_value;
+    }
+    (scheme, params)
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A username and password for a registry.
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    /// Returns the credentials encoded for HTTP basic authentication.
+    fn basic(&self) -> String {
+        base64::encode(format!("{}:{}", self.username, self.password))
+    }
+}
+
+/// The parts of the docker `config.json` that hold credentials.
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+    #[serde(default, rename = "credsStore")]
+    creds_store: Option<String>,
+    #[serde(default, rename = "credHelpers")]
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl DockerAuth {
+    fn credentials(&self, registry: &str) -> Result<Credentials, OciError> {
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            return Ok(Credentials {
+                username: username.clone(),
+                password: password.clone(),
+            });
+        }
+        // `auth` is the base64 encoding of `username:password`.
+        let auth = self
+            .auth
+            .as_ref()
+            .and_then(|auth| base64::decode(auth).ok())
+            .and_then(|auth| String::from_utf8(auth).ok())
+            .context(InvalidDockerAuth { registry })?;
+        let (username, password) =
+            split_once(&auth, ':').context(InvalidDockerAuth { registry })?;
+        Ok(Credentials {
+            username: username.to_owned(),
+            password: password.to_owned(),
+        })
+    }
+}
+
+/// The response from `docker-credential-<helper> get`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+    username: String,
+    secret: String,
+}
+
+fn default_docker_config() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("DOCKER_CONFIG") {
+        return Some(PathBuf::from(dir));
+    }
+    let home = if cfg!(windows) {
+        std::env::var_os("USERPROFILE")?
+    } else {
+        std::env::var_os("HOME")?
+    };
+    Some(Path::new(&home).join(".docker"))
+}
+
+/// Checks whether a key in the docker config, which may be a URL such as
+/// `https://index.docker.io/v1/`, refers to `registry`.
+fn registry_matches(key: &str, registry: &str) -> bool {
+    let host = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    host == registry
+}
+
+/// Gets credentials from `docker-credential-<helper>`. Returns `None` if the helper has no
+/// credentials for the registry.
+fn credential_helper(helper: &str, registry: &str) -> Result<Option<Credentials>, OciError> {
+    let program = format!("docker-credential-{}", helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context(CredentialHelper { program: &program })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(registry.as_bytes())
+            .context(CredentialHelper { program: &program })?;
+    }
+    let output = child
+        .wait_with_output()
+        .context(CredentialHelper { program: &program })?;
+    if !output.status.success() {
+        debug!("'{}' has no credentials for '{}'", program, registry);
+        return Ok(None);
+    }
+    let credentials: HelperCredentials =
+        serde_json::from_slice(&output.stdout).context(CredentialHelperParse { program })?;
+    Ok(Some(Credentials {
+        username: credentials.username,
+        password: credentials.secret,
+    }))
+}
+
+/// The error type for the OCI transport module.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum OciError {
+    #[snafu(display("Unable to reach the registry: {}", source))]
+    Challenge { source: reqwest::Error },
+
+    #[snafu(display("Unable to run credential helper '{}': {}", program, source))]
+    CredentialHelper {
+        program: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display(
+        "Unable to parse output of credential helper '{}': {}",
+        program,
+        source
+    ))]
+    CredentialHelperParse {
+        program: String,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Unable to read docker config '{}': {}", path.display(), source))]
+    DockerConfigRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to parse docker config '{}': {}", path.display(), source))]
+    DockerConfigParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    // The credentials are deliberately left out of the messages.
+    #[snafu(display("Invalid credentials for registry '{}' in docker config", registry))]
+    InvalidDockerAuth { registry: String },
+
+    #[snafu(display("Credentials for registry '{}' are not a valid header value", registry))]
+    InvalidCredentials {
+        registry: String,
+        source: reqwest::header::InvalidHeaderValue,
+    },
+
+    #[snafu(display(
+        "Invalid OCI reference '{}', expected oci://<registry>/<repository>@<digest> or \
+        oci://<registry>/<repository>/<tag>",
+        url
+    ))]
+    InvalidReference { url: String },
+
+    #[snafu(display("Unable to create registry URL: {}", source))]
+    JoinUrl { source: url::ParseError },
+
+    #[snafu(display("Expected an artifact with exactly one layer, found {} layers", count))]
+    ManifestLayers { count: usize },
+
+    #[snafu(display("Unable to parse manifest: {}", source))]
+    ManifestParse { source: serde_json::Error },
+
+    #[snafu(display("Registry '{}' requires credentials, but none were found", registry))]
+    MissingCredentials { registry: String },
+
+    #[snafu(display("Unable to get a registry token: {}", source))]
+    Token { source: reqwest::Error },
+
+    #[snafu(display("Unable to parse registry token response: {}", source))]
+    TokenParse { source: serde_json::Error },
+
+    #[snafu(display("Unsupported registry authentication challenge '{}'", challenge))]
+    UnsupportedChallenge { challenge: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_references() {
+        let url = Url::parse("oci://registry.example.com:5000/tuf/repo@sha256:abc").unwrap();
+        assert_eq!(
+            Reference::parse(&url).unwrap(),
+            Reference {
+                registry: "registry.example.com:5000".to_owned(),
+                repository: "tuf/repo".to_owned(),
+                target: Target::Digest("sha256:abc".to_owned()),
+            }
+        );
+
+        let url = Url::parse("oci://registry.example.com/tuf/repo/1.root.json").unwrap();
+        assert_eq!(
+            Reference::parse(&url).unwrap(),
+            Reference {
+                registry: "registry.example.com".to_owned(),
+                repository: "tuf/repo".to_owned(),
+                target: Target::Tag("1.root.json".to_owned()),
+            }
+        );
+
+        let url = Url::parse("oci://registry.example.com/timestamp.json").unwrap();
+        assert!(Reference::parse(&url).is_err());
+    }
+
+    #[test]
+    fn parse_bearer_challenge() {
+        let (scheme, params) = parse_challenge(
+            r#"Bearer realm="https://auth.example.com/token",service="registry.example.com",scope="repository:tuf:pull,push""#,
+        );
+        assert_eq!(scheme, "Bearer");
+        assert_eq!(params["realm"], "https://auth.example.com/token");
+        assert_eq!(params["service"], "registry.example.com");
+        assert_eq!(params["scope"], "repository:tuf:pull,push");
+    }
+
+    #[test]
+    fn docker_auth() {
+        let auth = DockerAuth {
+            auth: Some(base64::encode("user:pass:word")),
+            username: None,
+            password: None,
+        };
+        let credentials = auth.credentials("registry.example.com").unwrap();
+        assert_eq!(credentials.username, "user");
+        assert_eq!(credentials.password, "pass:word");
+        assert!(registry_matches(
+            "https://registry.example.com/v1/",
+            "registry.example.com"
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! The `s3` module provides `S3Transport`, which enables `Repository` objects to be loaded from an
+//! Amazon S3 bucket.
+use crate::{Transport, TransportError, TransportErrorKind};
+use log::debug;
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{
+    GetObjectError, GetObjectRequest, HeadObjectError, HeadObjectRequest, S3Client, S3,
+};
+use std::fmt::{Debug, Formatter};
+use std::io::{self, Read};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// A [`Transport`] for files in an Amazon S3 bucket, which are fetched with `GetObject`.
+///
+/// The transport handles URLs of the form `s3://<bucket>/<key>` for the bucket that it was created
+/// for, so a repository in the `my-repo` bucket under the `prod` prefix would be loaded with a
+/// metadata base URL of `s3://my-repo/prod/metadata`. Credentials are found with the default AWS
+/// credential provider chain, unless a client is given with [`S3Transport::new_with_client`].
+///
+/// A missing object (`NoSuchKey`, or a `404` response) results in
+/// [`TransportErrorKind::FileNotFound`]. If reading an object fails partway, the read is resumed
+/// with a byte-range `GetObject` request, up to the number of [`S3Transport::tries`].
+///
+/// Requires the `s3` feature.
+#[derive(Clone)]
+pub struct S3Transport {
+    bucket: String,
+    client: S3Client,
+    tries: u32,
+    /// The runtime that drives requests, created on the first fetch and shared by clones.
+    runtime: Arc<OnceLock<Runtime>>,
+}
+
+impl Debug for S3Transport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Transport")
+            .field("bucket", &self.bucket)
+            .field("tries", &self.tries)
+            .finish()
+    }
+}
+
+impl S3Transport {
+    /// Create a new `S3Transport` for `bucket`, which is in `region`.
+    pub fn new<S: Into<String>>(bucket: S, region: Region) -> Self {
+        Self::new_with_client(bucket, S3Client::new(region))
+    }
+
+    /// Create a new `S3Transport` for `bucket` that uses `client`, e.g. a client with credentials
+    /// for a specific profile.
+    pub fn new_with_client<S: Into<String>>(bucket: S, client: S3Client) -> Self {
+        Self {
+            bucket: bucket.into(),
+            client,
+            tries: 4,
+            runtime: Arc::default(),
+        }
+    }
+
+    /// Set the total number of times that reading an object will be tried, in case reads fail
+    /// partway. Defaults to 4.
+    pub fn tries(mut self, value: u32) -> Self {
+        self.tries = value;
+        self
+    }
+
+    /// Returns the object key for `url`, or an error if the URL is not in this transport's bucket.
+    fn key(&self, url: &Url) -> Result<String, TransportError> {
+        if url.scheme() != "s3" {
+            return Err(TransportError::new(
+                TransportErrorKind::UnsupportedUrlScheme,
+                url,
+            ));
+        }
+        if url.host_str() != Some(self.bucket.as_str()) {
+            return Err(TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                format!("URL is not in the S3 bucket '{}'", self.bucket),
+            ));
+        }
+        Ok(url.path().trim_start_matches('/').to_owned())
+    }
+
+    /// Returns the runtime, creating it if this is the first fetch.
+    fn runtime(&self, url: &Url) -> Result<&Runtime, TransportError> {
+        if let Some(runtime) = self.runtime.get() {
+            return Ok(runtime);
+        }
+        let runtime = Runtime::new()
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))?;
+        Ok(self.runtime.get_or_init(|| runtime))
+    }
+
+    /// Sends a `GetObject` request for `key`, starting at `next_byte`, and returns the body.
+    fn get_object(
+        &self,
+        url: &Url,
+        key: &str,
+        next_byte: u64,
+    ) -> Result<Pin<Box<dyn AsyncRead + Send + Sync>>, TransportError> {
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key: key.to_owned(),
+            range: if next_byte > 0 {
+                Some(format!("bytes={}-", next_byte))
+            } else {
+                None
+            },
+            ..GetObjectRequest::default()
+        };
+        // block on the request without starting a runtime, so that this works within a runtime.
+        let _runtime = self.runtime(url)?.enter();
+        let output = futures_executor::block_on(self.client.get_object(request)).map_err(|e| {
+            let kind = match &e {
+                RusotoError::Service(GetObjectError::NoSuchKey(_)) => {
+                    TransportErrorKind::FileNotFound
+                }
+                RusotoError::Unknown(response) if response.status.as_u16() == 404 => {
+                    TransportErrorKind::FileNotFound
+                }
+                _ => TransportErrorKind::Other,
+            };
+            TransportError::new_with_cause(kind, url, e)
+        })?;
+        let body = output.body.ok_or_else(|| {
+            TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                "GetObject response has no body",
+            )
+        })?;
+        Ok(Box::pin(body.into_async_read()))
+    }
+}
+
+impl Transport for S3Transport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let key = self.key(&url)?;
+        let body = self.get_object(&url, &key, 0)?;
+        Ok(Box::new(S3Read {
+            transport: self.clone(),
+            url,
+            key,
+            body,
+            next_byte: 0,
+            current_try: 0,
+        }))
+    }
+
+    /// Sends a `HeadObject` request, so that the object is not downloaded.
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        let request = HeadObjectRequest {
+            bucket: self.bucket.clone(),
+            key: self.key(&url)?,
+            ..HeadObjectRequest::default()
+        };
+        let _runtime = self.runtime(&url)?.enter();
+        match futures_executor::block_on(self.client.head_object(request)) {
+            Ok(_) => Ok(true),
+            // a `HEAD` response has no body, so a missing object is usually only a `404`.
+            Err(RusotoError::Service(HeadObjectError::NoSuchKey(_))) => Ok(false),
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 404 => Ok(false),
+            Err(e) => Err(TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                e,
+            )),
+        }
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
+
+/// Reads the body of an object, resuming with a byte-range request if a read fails.
+struct S3Read {
+    transport: S3Transport,
+    url: Url,
+    key: String,
+    body: Pin<Box<dyn AsyncRead + Send + Sync>>,
+    /// The next byte that we should read. e.g. the last read byte + 1.
+    next_byte: u64,
+    /// The current try we are on. First try is zero.
+    current_try: u32,
+}
+
+impl Read for S3Read {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // retry loop
+        loop {
+            let result = {
+                let _runtime = self
+                    .transport
+                    .runtime(&self.url)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+                    .enter();
+                futures_executor::block_on(self.body.read(buf))
+            };
+            let retry_err = match result {
+                Ok(sz) => {
+                    self.next_byte += sz as u64;
+                    return Ok(sz);
+                }
+                Err(err) => err,
+            };
+            debug!("error during read of '{}': {:?}", self.url, retry_err);
+            self.current_try += 1;
+            if self.current_try >= self.transport.tries {
+                // we are out of retries, so return the last known error.
+                return Err(retry_err);
+            }
+            self.body = self
+                .transport
+                .get_object(&self.url, &self.key, self.next_byte)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides an exporter that describes the targets of a loaded (and therefore verified)
+//! [`Repository`] as a software bill of materials (SBOM) in the [CycloneDX] JSON format.
+//!
+//! [CycloneDX]: https://cyclonedx.org/
+
+use crate::schema::{Hashes, Target};
+use crate::Repository;
+use serde_json::{json, Value};
+
+/// The CycloneDX specification version of the documents produced by [`cyclonedx`].
+pub const CYCLONEDX_SPEC_VERSION: &str = "1.4";
+
+/// Returns a CycloneDX document that lists each target of `repository`, including delegated
+/// targets, as a `file` component with the target's SHA-256 hash. If
+/// [`RepositoryLoader::lazy_delegated_roles`] was enabled, delegated targets are only included once
+/// they have been loaded with [`Repository::load_delegated_roles`].
+///
+/// The `version` and `license` custom fields of a target, if they are strings, become the
+/// component's version and license expression. Other custom fields become component properties.
+/// Components are sorted by target name.
+///
+/// [`RepositoryLoader::lazy_delegated_roles`]: crate::RepositoryLoader::lazy_delegated_roles
+pub fn cyclonedx(repository: &Repository) -> Value {
+    let targets = repository.targets().signed.targets_map();
+    let mut names: Vec<&String> = targets.keys().collect();
+    names.sort();
+    let components: Vec<Value> = names
+        .into_iter()
+        .map(|name| component(name, targets[name]))
+        .collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": CYCLONEDX_SPEC_VERSION,
+        "version": 1,
+        "components": components,
+    })
+}
+
+/// Lists the hashes of a target that CycloneDX can describe.
+fn hashes(hashes: &Hashes) -> Value {
+    let mut list = Vec::new();
+    if let Some(sha256) = &hashes.sha256 {
+        list.push(json!({ "alg": "SHA-256", "content": hex::encode(&sha256[..]) }));
+    }
+    if let Some(sha512) = &hashes.sha512 {
+        list.push(json!({ "alg": "SHA-512", "content": hex::encode(&sha512[..]) }));
+    }
+    Value::Array(list)
+}
+
+/// Describes a single target as a CycloneDX component.
+fn component(name: &str, target: &Target) -> Value {
+    let mut component = json!({
+        "type": "file",
+        "name": name,
+        "hashes": hashes(&target.hashes),
+    });
+    let mut custom: Vec<(&String, &Value)> = target.custom.iter().collect();
+    custom.sort_by_key(|(key, _)| *key);
+    let mut properties = Vec::new();
+    for (key, value) in custom {
+        match (key.as_str(), value) {
+            ("version", Value::String(version)) => component["version"] = json!(version),
+            ("license", Value::String(license)) => {
+                component["licenses"] = json!([{ "expression": license }]);
+            }
+            // property values are strings, so other JSON values are included as JSON text.
+            _ => properties.push(json!({
+                "name": key,
+                "value": value.as_str().map_or_else(|| value.to_string(), str::to_owned),
+            })),
+        }
+    }
+    if !properties.is_empty() {
+        component["properties"] = Value::Array(properties);
+    }
+    component
+}
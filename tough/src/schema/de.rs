@@ -2,11 +2,14 @@ use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::error;
 use crate::schema::key::Key;
 use serde::{de::Error as _, Deserialize, Deserializer};
-use snafu::ensure;
+use serde_json::Value;
+use snafu::{ensure, ResultExt};
 use std::collections::HashMap;
 use std::fmt;
 
-/// Validates the key ID for each key during deserialization and fails if any don't match.
+/// Validates the key ID for each key during deserialization and fails if any don't match. Keys
+/// with a signature scheme we don't support are rejected with an error naming the scheme and key
+/// ID.
 pub(super) fn deserialize_keys<'de, D>(
     deserializer: D,
 ) -> Result<HashMap<Decoded<Hex>, Key>, D::Error>
@@ -38,6 +41,26 @@ where
         Ok(())
     }
 
+    // Parses a key, first checking that its scheme is one we support for its key type, so that
+    // the error names the key and scheme rather than just the unknown enum variant.
+    fn parse_key(keyid: &Decoded<Hex>, value: Value) -> Result<Key, error::Error> {
+        let keyid_hex = hex::encode(keyid);
+        if let (Some(keytype), Some(scheme)) = (
+            value.get("keytype").and_then(Value::as_str),
+            value.get("scheme").and_then(Value::as_str),
+        ) {
+            ensure!(
+                Key::supports_scheme(keytype, scheme),
+                error::UnsupportedKeyScheme {
+                    keyid: &keyid_hex,
+                    keytype,
+                    scheme,
+                }
+            );
+        }
+        Key::deserialize(value).context(error::KeyParse { keyid: keyid_hex })
+    }
+
     // The rest of this is fitting the above function into serde and doing error type conversion.
     struct Visitor;
 
@@ -53,7 +76,8 @@ where
             M: serde::de::MapAccess<'de>,
         {
             let mut map = HashMap::new();
-            while let Some((keyid, key)) = access.next_entry()? {
+            while let Some((keyid, value)) = access.next_entry::<Decoded<Hex>, Value>()? {
+                let key = parse_key(&keyid, value).map_err(M::Error::custom)?;
                 validate_and_insert_entry(keyid, key, &mut map).map_err(M::Error::custom)?;
             }
             Ok(map)
@@ -86,4 +110,19 @@ mod tests {
         ))
         .is_err());
     }
+
+    #[test]
+    fn unsupported_key_scheme() {
+        let err = serde_json::from_str::<Signed<Root>>(include_str!(
+            "../../tests/data/unsupported-key-scheme/root.json"
+        ))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("rsassa-pkcs1v15-sha256"), "{}", err);
+        assert!(
+            err.contains("8ec3a843a0f9328c863cac4046ab1cacbbc67888476ac7acf73d9bcd9a223ada"),
+            "{}",
+            err
+        );
+    }
 }
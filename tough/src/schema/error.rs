@@ -3,6 +3,7 @@
 #![allow(clippy::default_trait_access)]
 
 use crate::schema::RoleType;
+use chrono::{DateTime, Utc};
 use snafu::{Backtrace, Snafu};
 use std::fmt::{self, Debug, Display};
 use std::path::PathBuf;
@@ -24,6 +25,14 @@ pub enum Error {
     #[snafu(display("Duplicate role name: {}", name))]
     DuplicateRoleName { name: String },
 
+    /// Metadata that was verified against a time had expired by then.
+    #[snafu(display("{} metadata expired at {}", role, expires))]
+    Expired {
+        role: RoleType,
+        expires: DateTime<Utc>,
+        backtrace: Backtrace,
+    },
+
     /// Unable to open a file
     #[snafu(display("Failed to open '{}': {}", path.display(), source))]
     FileOpen {
@@ -64,6 +73,14 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A key in the metadata could not be parsed.
+    #[snafu(display("Failed to parse key {}: {}", keyid, source))]
+    KeyParse {
+        keyid: String,
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
     /// A required role is missing from the root metadata file.
     #[snafu(display("Role {} missing from root metadata", role))]
     MissingRole {
@@ -79,16 +96,23 @@ pub enum Error {
     },
 
     /// A signature threshold specified in root.json was not met when verifying a signature.
+    ///
+    /// `invalid` lists the IDs of the role's keys whose signatures failed to verify, and
+    /// `unrecognized` lists the key IDs of signatures made by keys that are not the role's keys.
     #[snafu(display(
-        "Signature threshold of {} not met for role {} ({} valid signatures)",
+        "Signature threshold of {} not met for role {} ({} valid signatures; invalid signatures from keys [{}]; signatures from unrecognized keys [{}])",
         threshold,
         role,
         valid,
+        invalid.join(", "),
+        unrecognized.join(", "),
     ))]
     SignatureThreshold {
         role: RoleType,
         threshold: u64,
         valid: u64,
+        invalid: Vec<String>,
+        unrecognized: Vec<String>,
         backtrace: Backtrace,
     },
 
@@ -96,6 +120,30 @@ pub enum Error {
     #[snafu(display("Invalid SubjectPublicKeyInfo document"))]
     SpkiDecode { backtrace: Backtrace },
 
+    /// A PEM-encoded ECDSA public key uses a curve other than NIST P-256.
+    #[snafu(display("Unsupported ECDSA curve; only NIST P-256 (secp256r1) keys are supported"))]
+    UnsupportedCurve { backtrace: Backtrace },
+
+    /// A PEM-encoded public key uses an algorithm that TUF keys can't be made from.
+    #[snafu(display(
+        "Unsupported public key algorithm; RSA, NIST P-256 ECDSA, and Ed25519 keys are supported"
+    ))]
+    UnsupportedKeyAlgorithm { backtrace: Backtrace },
+
+    /// A key in the metadata uses a signature scheme that is not supported for its key type.
+    #[snafu(display(
+        "Unsupported signature scheme '{}' for {} key {}",
+        scheme,
+        keytype,
+        keyid
+    ))]
+    UnsupportedKeyScheme {
+        keyid: String,
+        keytype: String,
+        scheme: String,
+        backtrace: Backtrace,
+    },
+
     /// Unable to create a TUF target from anything but a file
     #[snafu(display("TUF targets must be files, given: '{}'", path.display()))]
     TargetNotAFile { path: PathBuf, backtrace: Backtrace },
@@ -4,6 +4,7 @@
 
 use crate::schema::decoded::{Decoded, EcdsaPem, Hex, RsaPem};
 use crate::schema::error::{self, Result};
+use crate::schema::spki;
 use olpc_cjson::CanonicalFormatter;
 use ring::digest::{digest, SHA256};
 use ring::signature::VerificationAlgorithm;
@@ -128,7 +129,9 @@ pub struct EcdsaKey {
 }
 
 impl Key {
-    /// Calculate the key ID for this key.
+    /// Calculate the key ID for this key: the SHA-256 digest of the key's canonical JSON form,
+    /// including any extra fields it was read with. This is the same ID that tough uses to match
+    /// signatures to keys, and the ID that keys are listed under in root and delegations metadata.
     pub fn key_id(&self) -> Result<Decoded<Hex>> {
         let mut buf = Vec::new();
         let mut ser = serde_json::Serializer::with_formatter(&mut buf, CanonicalFormatter::new());
@@ -138,8 +141,60 @@ impl Key {
         Ok(digest(&SHA256, &buf).as_ref().to_vec().into())
     }
 
-    /// Verify a signature of an object made with this key.
-    pub(super) fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
+    /// Creates a key from a PEM-encoded `SubjectPublicKeyInfo` document ("PUBLIC KEY"), such as the
+    /// output of `openssl pkey -pubout`. RSA keys use the `rsassa-pss-sha256` scheme, and ECDSA keys
+    /// must be on the NIST P-256 curve. The TUF key ID of the result is given by [`Key::key_id`].
+    pub fn from_public_pem(pem: &str) -> Result<Key> {
+        let info = spki::decode_any(pem)?;
+        if info.is(spki::OID_RSA_ENCRYPTION, None) {
+            Ok(Key::Rsa {
+                keyval: RsaKey {
+                    public: info.key.into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: RsaScheme::RsassaPssSha256,
+                _extra: HashMap::new(),
+            })
+        } else if info.is(spki::OID_EC_PUBLIC_KEY, Some(spki::OID_EC_PARAM_SECP256R1)) {
+            Ok(Key::Ecdsa {
+                keyval: EcdsaKey {
+                    public: info.key.into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                _extra: HashMap::new(),
+            })
+        } else if info.is_algorithm(spki::OID_EC_PUBLIC_KEY) {
+            error::UnsupportedCurve.fail()
+        } else if info.is(spki::OID_ED25519, None) {
+            Ok(Key::Ed25519 {
+                keyval: Ed25519Key {
+                    public: info.key.into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: Ed25519Scheme::Ed25519,
+                _extra: HashMap::new(),
+            })
+        } else {
+            error::UnsupportedKeyAlgorithm.fail()
+        }
+    }
+
+    /// Returns whether `scheme` is a supported signature scheme for keys of type `keytype`, as
+    /// named in metadata. Key types that aren't known are reported as supported, so that parsing
+    /// fails on the key type instead.
+    pub(super) fn supports_scheme(keytype: &str, scheme: &str) -> bool {
+        match keytype {
+            "rsa" => serde_plain::from_str::<RsaScheme>(scheme).is_ok(),
+            "ed25519" => serde_plain::from_str::<Ed25519Scheme>(scheme).is_ok(),
+            "ecdsa" => serde_plain::from_str::<EcdsaScheme>(scheme).is_ok(),
+            _ => true,
+        }
+    }
+
+    /// Verify a signature of an object made with this key. The verification algorithm is chosen by
+    /// the key's declared scheme; `rsassa-pss-sha256` signatures are checked with PSS padding.
+    pub(crate) fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
         let (alg, public_key): (&dyn VerificationAlgorithm, untrusted::Input<'_>) = match self {
             Key::Ecdsa {
                 scheme: EcdsaScheme::EcdsaSha2Nistp256,
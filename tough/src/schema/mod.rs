@@ -14,17 +14,20 @@ use crate::schema::decoded::{Decoded, Hex};
 pub use crate::schema::error::{Error, Result};
 use crate::schema::iter::KeysIter;
 use crate::schema::key::Key;
+pub use crate::schema::verify::SignatureVerifier;
 use crate::sign::Sign;
 pub use crate::transport::{FilesystemTransport, Transport};
 use chrono::{DateTime, Utc};
 use globset::Glob;
 use olpc_cjson::CanonicalFormatter;
 use ring::digest::{digest, Context, SHA256};
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::Value;
 use serde_plain::{forward_display_to_serde, forward_from_str_to_serde};
 use snafu::ResultExt;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::{Infallible, TryFrom};
 use std::fs::File;
 use std::io::Read;
 use std::num::NonZeroU64;
@@ -49,6 +52,9 @@ pub enum RoleType {
     Timestamp,
     /// A delegated targets role
     DelegatedTargets,
+    /// The mirrors role signs an optional metadata file that lists mirrors from which metadata and
+    /// targets can be downloaded.
+    Mirrors,
 }
 
 forward_display_to_serde!(RoleType);
@@ -75,6 +81,14 @@ pub trait Role: Serialize {
     /// number less than the one currently trusted.
     fn version(&self) -> NonZeroU64;
 
+    /// The version of the TUF specification that the metadata was written for, such as `1.0.0`.
+    ///
+    /// Defaults to `1.0.0`, the version that this library implements, for roles that don't record
+    /// it.
+    fn spec_version(&self) -> &str {
+        "1.0.0"
+    }
+
     /// The filename that the role metadata should be written to
     fn filename(&self, consistent_snapshot: bool) -> String;
 
@@ -86,14 +100,26 @@ pub trait Role: Serialize {
     /// A deterministic JSON serialization used when calculating the digest of a metadata object.
     /// [More info on canonical JSON](http://wiki.laptop.org/go/Canonical_JSON)
     fn canonical_form(&self) -> Result<Vec<u8>> {
-        let mut data = Vec::new();
-        let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
-        self.serialize(&mut ser)
-            .context(error::JsonSerialization { what: "role" })?;
-        Ok(data)
+        canonical_json(self)
     }
 }
 
+/// Serializes `value` as [canonical JSON](http://wiki.laptop.org/go/Canonical_JSON), the
+/// deterministic form of metadata that is hashed and signed.
+///
+/// The signature of a role in a [`Signed`] is made over `canonical_json(&signed.signed)`, so
+/// metadata can be signed outside of tough by signing these bytes and adding the result to
+/// [`Signed::signatures`]. The bytes must not be modified (e.g. pretty-printed or re-encoded) before
+/// signing, or the signature won't verify.
+pub fn canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+    value
+        .serialize(&mut ser)
+        .context(error::JsonSerialization { what: "value" })?;
+    Ok(data)
+}
+
 /// A signed metadata object.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Signed<T> {
@@ -227,6 +253,10 @@ impl Role for Root {
         self.version
     }
 
+    fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
     fn filename(&self, _consistent_snapshot: bool) -> String {
         format!("{}.root.json", self.version())
     }
@@ -317,10 +347,19 @@ pub struct SnapshotMeta {
 }
 
 /// Represents the hash dictionary in a `snapshot.json` file.
+///
+/// tough verifies files with the strongest of the hashes that it supports (SHA-512, then SHA-256);
+/// hashes for other algorithms are kept in `_extra`. A file whose hashes are all for unsupported
+/// algorithms can't be verified, and is rejected.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Hashes {
     /// The SHA 256 digest of a metadata file.
-    pub sha256: Decoded<Hex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<Decoded<Hex>>,
+
+    /// The SHA 512 digest of a metadata file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<Decoded<Hex>>,
 
     /// Extra arguments found during deserialization.
     ///
@@ -331,6 +370,14 @@ pub struct Hashes {
     pub _extra: HashMap<String, Value>,
 }
 
+impl Hashes {
+    /// Returns the digest to use in the file name of a target when consistent snapshots are used:
+    /// the SHA-256 digest if there is one, otherwise the SHA-512 digest.
+    pub fn consistent_snapshot_digest(&self) -> Option<&Decoded<Hex>> {
+        self.sha256.as_ref().or_else(|| self.sha512.as_ref())
+    }
+}
+
 impl Snapshot {
     /// Create a new `Snapshot` object.
     pub fn new(spec_version: String, version: NonZeroU64, expires: DateTime<Utc>) -> Self {
@@ -354,6 +401,10 @@ impl Role for Snapshot {
         self.version
     }
 
+    fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
     fn filename(&self, consistent_snapshot: bool) -> String {
         if consistent_snapshot {
             format!("{}.snapshot.json", self.version())
@@ -479,7 +530,8 @@ impl Target {
         Ok(Target {
             length,
             hashes: Hashes {
-                sha256: Decoded::from(digest.finish().as_ref().to_vec()),
+                sha256: Some(Decoded::from(digest.finish().as_ref().to_vec())),
+                sha512: None,
                 _extra: HashMap::new(),
             },
             custom: HashMap::new(),
@@ -502,22 +554,135 @@ impl Targets {
     }
 
     /// Given a target url, returns a reference to the Target struct or error if the target is unreachable
+    ///
+    /// Delegated roles are searched as described for [`Targets::find_signing_role_chain`], so a
+    /// target that is only listed by a role that the search never reaches, e.g. one after a
+    /// matching terminating role, is not found.
     pub fn find_target(&self, target_name: &str) -> Result<&Target> {
+        match self.search_signing_role(target_name) {
+            RoleSearch::Found(_, target) => Ok(target),
+            RoleSearch::NotFound | RoleSearch::Terminated => Err(Error::TargetNotFound {
+                target_file: target_name.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the chain of delegated roles through which [`Targets::find_target`] finds
+    /// `target_name`, ending with the role that lists it. The chain is empty if this role lists the
+    /// target itself, and `None` if the target is not found.
+    pub fn find_target_delegation_chain(&self, target_name: &str) -> Option<Vec<&DelegatedRole>> {
+        self.find_signing_role_chain(target_name)
+    }
+
+    /// Returns the chain of delegated roles that sign for `target_name`, ending with the role that
+    /// lists it, following the TUF delegation search.
+    ///
+    /// Delegated roles are visited depth-first in the order that they are listed, and a role is only
+    /// visited if its `paths` or `path_hash_prefixes` match the target. Once a terminating role that
+    /// matches the target has been visited, no other roles are considered. The chain is empty if
+    /// this role lists the target itself, and `None` if no role signs for it.
+    pub fn find_signing_role_chain(&self, target_name: &str) -> Option<Vec<&DelegatedRole>> {
+        match self.search_signing_role(target_name) {
+            RoleSearch::Found(chain, _) => Some(chain),
+            RoleSearch::NotFound | RoleSearch::Terminated => None,
+        }
+    }
+
+    /// Searches the delegated roles that have been loaded into this role's `delegations`.
+    fn search_signing_role(&self, target_name: &str) -> RoleSearch<'_> {
+        let search: std::result::Result<_, Infallible> =
+            self.search_signing_role_with(target_name, &mut Vec::new(), &mut |_, role, _| {
+                Ok(role.targets.as_ref())
+            });
+        match search {
+            Ok(search) => search,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Searches the delegation tree for the role that signs for `target_name`, as described for
+    /// [`Targets::find_signing_role_chain`].
+    ///
+    /// `load` is called with each delegated role that is visited, the delegations that list it, and
+    /// the names of its ancestors below the top-level `targets` role, and returns the role's
+    /// metadata, or `None` if the role is not searched. `ancestors` holds the names of the ancestors
+    /// of this role.
+    pub(crate) fn search_signing_role_with<'a, E>(
+        &'a self,
+        target_name: &str,
+        ancestors: &mut Vec<&'a str>,
+        load: &mut dyn FnMut(
+            &'a Delegations,
+            &'a DelegatedRole,
+            &[&'a str],
+        ) -> std::result::Result<Option<&'a Signed<Targets>>, E>,
+    ) -> std::result::Result<RoleSearch<'a>, E> {
         if let Some(target) = self.targets.get(target_name) {
-            return Ok(target);
+            return Ok(RoleSearch::Found(Vec::new(), target));
         }
         if let Some(delegations) = &self.delegations {
             for role in &delegations.roles {
-                if let Some(targets) = &role.targets {
-                    if let Ok(target) = targets.signed.find_target(target_name) {
-                        return Ok(target);
+                if !role.paths.matched_target(target_name) {
+                    continue;
+                }
+                if let Some(targets) = load(delegations, role, ancestors)? {
+                    ancestors.push(&role.name);
+                    let search =
+                        targets
+                            .signed
+                            .search_signing_role_with(target_name, ancestors, load);
+                    ancestors.pop();
+                    match search? {
+                        RoleSearch::Found(mut chain, target) => {
+                            chain.insert(0, role);
+                            return Ok(RoleSearch::Found(chain, target));
+                        }
+                        RoleSearch::Terminated => return Ok(RoleSearch::Terminated),
+                        RoleSearch::NotFound => {}
                     }
                 }
+                if role.terminating {
+                    return Ok(RoleSearch::Terminated);
+                }
+            }
+            if let Some(succinct_roles) = &delegations.succinct_roles {
+                // Each target belongs to exactly one bin, so no other roles are considered.
+                let bin = usize::try_from(succinct_roles.bin_number(target_name))
+                    .ok()
+                    .and_then(|number| succinct_roles.bins.get(number));
+                if let Some(bin) = bin {
+                    if let Some(targets) = load(delegations, bin, ancestors)? {
+                        ancestors.push(&bin.name);
+                        let search =
+                            targets
+                                .signed
+                                .search_signing_role_with(target_name, ancestors, load);
+                        ancestors.pop();
+                        if let RoleSearch::Found(mut chain, target) = search? {
+                            chain.insert(0, bin);
+                            return Ok(RoleSearch::Found(chain, target));
+                        }
+                    }
+                }
+                return Ok(RoleSearch::Terminated);
             }
         }
-        Err(Error::TargetNotFound {
-            target_file: target_name.to_string(),
-        })
+        Ok(RoleSearch::NotFound)
+    }
+
+    /// Returns all of the delegated roles, recursively, in the order that they are visited by a
+    /// depth-first search of the delegation tree.
+    pub fn delegated_roles(&self) -> Vec<&DelegatedRole> {
+        let mut roles = Vec::new();
+        if let Some(delegations) = &self.delegations {
+            for role in delegations.roles_iter() {
+                roles.push(role);
+                if let Some(targets) = &role.targets {
+                    roles.append(&mut targets.signed.delegated_roles());
+                }
+            }
+        }
+        roles
     }
 
     /// Returns a hashmap of all targets and all delegated targets recursively
@@ -527,7 +692,7 @@ impl Targets {
             targets_map.insert(target.0.clone(), target.1);
         }
         if let Some(delegations) = &self.delegations {
-            for role in &delegations.roles {
+            for role in delegations.roles_iter() {
                 if let Some(targets) = &role.targets {
                     targets_map.extend(targets.signed.targets_map());
                 }
@@ -537,6 +702,25 @@ impl Targets {
         targets_map
     }
 
+    /// Returns the names of all targets and all delegated targets recursively, sorted by name and
+    /// without duplicates.
+    pub fn target_names(&self) -> Vec<&str> {
+        let mut names = BTreeSet::new();
+        self.collect_target_names(&mut names);
+        names.into_iter().collect()
+    }
+
+    fn collect_target_names<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+        names.extend(self.targets.keys().map(String::as_str));
+        if let Some(delegations) = &self.delegations {
+            for role in delegations.roles_iter() {
+                if let Some(targets) = &role.targets {
+                    targets.signed.collect_target_names(names);
+                }
+            }
+        }
+    }
+
     /// Returns an iterator of all targets delegated
     pub fn targets_iter(&self) -> impl Iterator + '_ {
         self.targets_map().into_iter()
@@ -546,7 +730,7 @@ impl Targets {
     pub fn clear_targets(&mut self) {
         self.targets = HashMap::new();
         if let Some(delegations) = &mut self.delegations {
-            for delegated_role in &mut delegations.roles {
+            for delegated_role in delegations.roles_iter_mut() {
                 if let Some(targets) = &mut delegated_role.targets {
                     targets.signed.clear_targets();
                 }
@@ -582,11 +766,11 @@ impl Targets {
 
     /// Returns the `&DelegatedRole` for `name`
     pub fn delegated_role(&self, name: &str) -> Result<&DelegatedRole> {
-        for role in &self
+        for role in self
             .delegations
             .as_ref()
             .ok_or(error::Error::NoDelegations)?
-            .roles
+            .roles_iter()
         {
             if role.name == name {
                 return Ok(role);
@@ -607,11 +791,11 @@ impl Targets {
 
     /// Returns a mutable `DelegatedRole` for `name`
     pub fn delegated_role_mut(&mut self, name: &str) -> Result<&mut DelegatedRole> {
-        for role in &mut self
+        for role in self
             .delegations
             .as_mut()
             .ok_or(error::Error::NoDelegations)?
-            .roles
+            .roles_iter_mut()
         {
             if role.name == name {
                 return Ok(role);
@@ -634,7 +818,7 @@ impl Targets {
     pub fn role_names(&self) -> Vec<&String> {
         let mut roles = Vec::new();
         if let Some(delelegations) = &self.delegations {
-            for role in &delelegations.roles {
+            for role in delelegations.roles_iter() {
                 roles.push(&role.name);
                 if let Some(targets) = &role.targets {
                     roles.append(&mut targets.signed.role_names())
@@ -648,7 +832,7 @@ impl Targets {
     /// Returns a reference to the parent delegation of `name`
     pub fn parent_of(&self, name: &str) -> Result<&Delegations> {
         if let Some(delegations) = &self.delegations {
-            for role in &delegations.roles {
+            for role in delegations.roles_iter() {
                 if role.name == name {
                     return Ok(&delegations);
                 }
@@ -668,7 +852,7 @@ impl Targets {
     pub fn signed_delegated_targets(&self) -> Vec<Signed<DelegatedTargets>> {
         let mut delegated_targets = Vec::new();
         if let Some(delegations) = &self.delegations {
-            for role in &delegations.roles {
+            for role in delegations.roles_iter() {
                 if let Some(targets) = &role.targets {
                     delegated_targets.push(targets.clone().delegated_targets(&role.name));
                     delegated_targets.extend(targets.signed.signed_delegated_targets());
@@ -711,6 +895,10 @@ impl Role for Targets {
         self.version
     }
 
+    fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
     fn filename(&self, consistent_snapshot: bool) -> String {
         if consistent_snapshot {
             format!("{}.targets.json", self.version())
@@ -756,6 +944,10 @@ impl Role for DelegatedTargets {
         self.targets.version
     }
 
+    fn spec_version(&self) -> &str {
+        &self.targets.spec_version
+    }
+
     fn filename(&self, consistent_snapshot: bool) -> String {
         if consistent_snapshot {
             format!("{}.{}.json", self.version(), self.name)
@@ -815,7 +1007,10 @@ impl Signed<Targets> {
 ///   }, ... ]
 /// }
 /// ```
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+///
+/// With succinct hash delegations (TAP 15), `"succinct_roles"` replaces `"roles"`, describing a
+/// number of bins without listing each of them; see [`SuccinctRoles`].
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
 pub struct Delegations {
     /// Lists the public keys to verify signatures of delegated targets roles. Revocation and
     /// replacement of delegated targets roles keys is done by changing the keys in this field in
@@ -823,8 +1018,83 @@ pub struct Delegations {
     #[serde(deserialize_with = "de::deserialize_keys")]
     pub keys: HashMap<Decoded<Hex>, Key>,
 
-    /// The list of delegated roles.
+    /// The list of delegated roles. Empty if the roles are described by `succinct_roles`.
+    #[serde(default)]
     pub roles: Vec<DelegatedRole>,
+
+    /// The bins of a succinct hash delegation, if the delegated roles are described this way.
+    #[serde(default)]
+    pub succinct_roles: Option<SuccinctRoles>,
+}
+
+// `roles` is left out when the roles are described by `succinct_roles`, so that the metadata is
+// serialized as it was signed.
+impl Serialize for Delegations {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Delegations", 3)?;
+        state.serialize_field("keys", &self.keys)?;
+        match &self.succinct_roles {
+            Some(succinct_roles) => {
+                if !self.roles.is_empty() {
+                    state.serialize_field("roles", &self.roles)?;
+                }
+                state.serialize_field("succinct_roles", succinct_roles)?;
+            }
+            None => state.serialize_field("roles", &self.roles)?,
+        }
+        state.end()
+    }
+}
+
+/// Succinct hash delegations, as described by
+/// [TAP 15](https://github.com/theupdateframework/taps/blob/master/tap15.md).
+///
+/// Targets are split into `2^bit_length` bins by the leading `bit_length` bits of the SHA-256
+/// digest of their names, and each bin is a delegated role named `NAME_PREFIX-HEX`, e.g.
+/// `bin-0a`, where `HEX` is the bin number in as many hex digits as the last bin needs. All of the
+/// bins are signed with the same keys and threshold. A target is only looked for in its bin.
+/// ```text
+/// { "keyids" : [ KEYID, ... ] ,
+///   "threshold" : THRESHOLD,
+///   "bit_length": BIT_LENGTH,
+///   "name_prefix": NAME_PREFIX
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SuccinctRoles {
+    /// The key IDs used by each bin.
+    pub keyids: Vec<Decoded<Hex>>,
+
+    /// The threshold of signatures required to validate each bin.
+    pub threshold: NonZeroU64,
+
+    /// The number of leading bits of a target name's digest that select its bin, from 1 to 32.
+    pub bit_length: u8,
+
+    /// The prefix of the bins' role names.
+    pub name_prefix: String,
+
+    /// Extra arguments found during deserialization.
+    ///
+    /// We must store these to correctly verify signatures for this object.
+    ///
+    /// If you're instantiating this struct, you should make this `HashMap::empty()`.
+    #[serde(flatten)]
+    pub _extra: HashMap<String, Value>,
+
+    /// The bins, in order, as delegated roles with their targets, once they have been loaded.
+    #[serde(skip)]
+    pub bins: Vec<DelegatedRole>,
+}
+
+/// The result of searching the delegation tree for the role that signs for a target.
+pub(crate) enum RoleSearch<'a> {
+    /// The chain of delegated roles that ends with the role that lists the target, and the target.
+    Found(Vec<&'a DelegatedRole>, &'a Target),
+    /// No role lists the target, and the search can go on to other roles.
+    NotFound,
+    /// A terminating role matched the target, so the search must stop.
+    Terminated,
 }
 
 /// Each role delegated in a targets file is considered a delegated role
@@ -881,7 +1151,7 @@ pub enum PathSet {
 
 impl PathSet {
     /// Given a target string determines if paths match
-    fn matched_target(&self, target: &str) -> bool {
+    pub(crate) fn matched_target(&self, target: &str) -> bool {
         match self {
             Self::Paths(paths) => {
                 for path in paths {
@@ -902,11 +1172,33 @@ impl PathSet {
         false
     }
 
-    /// Given a path hash prefix and a target path determines if target is delegated by prefix
+    /// Returns whether a target whose name starts with `prefix` could be delegated by these paths.
+    /// This may return `true` for paths that no such target matches, but never returns `false`
+    /// for paths that one does.
+    ///
+    /// Any target may be delegated by a path hash prefix. A path pattern can only match such a
+    /// target if the part of the pattern before its first wildcard is compatible with `prefix`.
+    pub(crate) fn could_match_prefix(&self, prefix: &str) -> bool {
+        match self {
+            Self::Paths(paths) => paths.iter().any(|path| {
+                let literal = path
+                    .find(|c| matches!(c, '*' | '?' | '[' | '{' | '\\'))
+                    .map_or(path.as_str(), |index| &path[..index]);
+                if literal.len() == path.len() {
+                    path.starts_with(prefix)
+                } else {
+                    literal.starts_with(prefix) || prefix.starts_with(literal)
+                }
+            }),
+            Self::PathHashPrefixes(_) => true,
+        }
+    }
+
+    /// Given a path hash prefix and a target path determines if target is delegated by prefix: the
+    /// hex-encoded SHA-256 digest of the target path must start with the prefix.
     fn matched_prefix(prefix: &str, target: &str) -> bool {
-        let temp_target = target.to_string();
-        let hash = digest(&SHA256, temp_target.as_bytes());
-        hash.as_ref().starts_with(prefix.as_bytes())
+        let hash = hex::encode(digest(&SHA256, target.as_bytes()));
+        hash.starts_with(&prefix.to_ascii_lowercase())
     }
 
     /// Given a shell style wildcard path determines if target matches the path
@@ -933,9 +1225,28 @@ impl Delegations {
         Delegations {
             keys: HashMap::new(),
             roles: Vec::new(),
+            succinct_roles: None,
         }
     }
 
+    /// Returns the delegated roles in `roles`, followed by the loaded bins of `succinct_roles`.
+    pub fn roles_iter(&self) -> impl Iterator<Item = &DelegatedRole> + '_ {
+        self.roles.iter().chain(
+            self.succinct_roles
+                .iter()
+                .flat_map(|succinct_roles| succinct_roles.bins.iter()),
+        )
+    }
+
+    /// Returns the delegated roles in `roles`, followed by the loaded bins of `succinct_roles`.
+    pub fn roles_iter_mut(&mut self) -> impl Iterator<Item = &mut DelegatedRole> + '_ {
+        self.roles.iter_mut().chain(
+            self.succinct_roles
+                .iter_mut()
+                .flat_map(|succinct_roles| succinct_roles.bins.iter_mut()),
+        )
+    }
+
     /// Determines if target passes pathset specific matching
     pub fn target_is_delegated(&self, target: &str) -> bool {
         for role in &self.roles {
@@ -949,8 +1260,10 @@ impl Delegations {
     /// Ensures that all delegated paths are allowed to be delegated
     pub fn verify_paths(&self) -> Result<()> {
         for sub_role in &self.roles {
+            // Hash prefixes are not target paths, so there is nothing to match them against.
             let pathset = match &sub_role.paths {
-                PathSet::Paths(paths) | PathSet::PathHashPrefixes(paths) => paths,
+                PathSet::Paths(paths) => paths,
+                PathSet::PathHashPrefixes(_) => continue,
             };
             for path in pathset {
                 if !self.target_is_delegated(&path) {
@@ -986,21 +1299,83 @@ impl DelegatedRole {
     }
 
     /// Verify that paths can be delegated by this role
+    ///
+    /// Paths must match this role's paths, and hash prefixes must start with one of this role's
+    /// hash prefixes. Paths and hash prefixes can't be compared with each other, so they are
+    /// allowed; a target is only found through a role if it matches the paths or hash prefixes of
+    /// every role that delegates to it.
     pub fn verify_paths(&self, paths: &PathSet) -> Result<()> {
-        let paths = match paths {
-            PathSet::Paths(x) | PathSet::PathHashPrefixes(x) => x,
-        };
-        for path in paths {
-            if !self.paths.matched_target(&path) {
-                return Err(Error::UnmatchedPath {
-                    child: path.to_string(),
-                });
+        match (&self.paths, paths) {
+            (PathSet::Paths(_), PathSet::Paths(paths)) => {
+                for path in paths {
+                    if !self.paths.matched_target(&path) {
+                        return Err(Error::UnmatchedPath {
+                            child: path.to_string(),
+                        });
+                    }
+                }
+            }
+            (PathSet::PathHashPrefixes(prefixes), PathSet::PathHashPrefixes(child_prefixes)) => {
+                for child_prefix in child_prefixes {
+                    let child = child_prefix.to_ascii_lowercase();
+                    if !prefixes
+                        .iter()
+                        .any(|prefix| child.starts_with(&prefix.to_ascii_lowercase()))
+                    {
+                        return Err(Error::UnmatchedPath {
+                            child: child_prefix.to_string(),
+                        });
+                    }
+                }
             }
+            _ => {}
         }
         Ok(())
     }
 }
 
+impl SuccinctRoles {
+    /// The number of bins, `2^bit_length`.
+    pub fn bin_count(&self) -> u64 {
+        1 << self.bit_length.min(32)
+    }
+
+    /// Returns the number of the bin that `target` belongs to: the leading `bit_length` bits of the
+    /// SHA-256 digest of its name.
+    pub fn bin_number(&self, target: &str) -> u64 {
+        let hash = digest(&SHA256, target.as_bytes());
+        let hash = hash.as_ref();
+        let leading = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+        let shift = 32 - u32::from(self.bit_length.min(32));
+        u64::from(leading.checked_shr(shift).unwrap_or(0))
+    }
+
+    /// Returns the role name of bin `number`.
+    pub fn bin_name(&self, number: u64) -> String {
+        let width = format!("{:x}", self.bin_count() - 1).len();
+        format!("{}-{:0width$x}", self.name_prefix, number, width = width)
+    }
+
+    /// Returns bin `number` as a delegated role, without its targets. Its `paths` are the hash
+    /// prefixes of the bin, and it is terminating, since no other role signs for its targets.
+    pub fn bin(&self, number: u64) -> DelegatedRole {
+        let bit_length = usize::from(self.bit_length.min(32));
+        let hex_digits = (bit_length + 3) / 4;
+        let padding = hex_digits * 4 - bit_length;
+        let prefixes = (0..1_u64 << padding)
+            .map(|low| format!("{:0width$x}", number << padding | low, width = hex_digits))
+            .collect();
+        DelegatedRole {
+            name: self.bin_name(number),
+            keyids: self.keyids.clone(),
+            threshold: self.threshold,
+            paths: PathSet::PathHashPrefixes(prefixes),
+            terminating: true,
+            targets: None,
+        }
+    }
+}
+
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
 /// Represents a `timestamp.json` file.
@@ -1083,7 +1458,299 @@ impl Role for Timestamp {
         self.version
     }
 
+    fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
     fn filename(&self, _consistent_snapshot: bool) -> String {
         "timestamp.json".to_string()
     }
 }
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// Represents a `mirrors.json` file.
+/// TUF 4.7: The optional mirrors file is signed by a mirrors key. It lists the mirrors from which
+/// metadata and targets can be downloaded, and which files each mirror serves.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "_type")]
+#[serde(rename = "mirrors")]
+pub struct Mirrors {
+    /// A string that contains the version number of the TUF specification. Its format follows the
+    /// Semantic Versioning 2.0.0 (semver) specification.
+    pub spec_version: String,
+
+    /// An integer that is greater than 0. Clients MUST NOT replace a metadata file with a version
+    /// number less than the one currently trusted.
+    pub version: NonZeroU64,
+
+    /// Determines when metadata should be considered expired and no longer trusted by clients.
+    pub expires: DateTime<Utc>,
+
+    /// The mirrors, in the order in which clients should try them.
+    pub mirrors: Vec<Mirror>,
+
+    /// Extra arguments found during deserialization.
+    ///
+    /// We must store these to correctly verify signatures for this object.
+    ///
+    /// If you're instantiating this struct, you should make this `HashMap::empty()`.
+    #[serde(flatten)]
+    #[serde(deserialize_with = "de::extra_skip_type")]
+    pub _extra: HashMap<String, Value>,
+}
+
+/// A mirror listed in `mirrors.json`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Mirror {
+    /// The URL of the mirror, which `metapath` and `targetspath` are relative to.
+    pub urlbase: String,
+
+    /// The path, relative to `urlbase`, of the directory where the mirror serves metadata.
+    pub metapath: String,
+
+    /// The path, relative to `urlbase`, of the directory where the mirror serves targets.
+    pub targetspath: String,
+
+    /// Shell-style wildcard patterns for the metadata files that the mirror serves.
+    #[serde(default)]
+    pub metacontent: Vec<String>,
+
+    /// Shell-style wildcard patterns for the targets that the mirror serves.
+    #[serde(default)]
+    pub targetscontent: Vec<String>,
+
+    /// Extra arguments found during deserialization, such as `custom`.
+    ///
+    /// We must store these to correctly verify signatures for this object.
+    ///
+    /// If you're instantiating this struct, you should make this `HashMap::empty()`.
+    #[serde(flatten)]
+    pub _extra: HashMap<String, Value>,
+}
+
+impl Mirrors {
+    /// Creates a new `Mirrors` object with no mirrors.
+    pub fn new(spec_version: String, version: NonZeroU64, expires: DateTime<Utc>) -> Self {
+        Mirrors {
+            spec_version,
+            version,
+            expires,
+            mirrors: Vec::new(),
+            _extra: HashMap::new(),
+        }
+    }
+}
+
+impl Mirror {
+    /// Returns whether the mirror serves the metadata file `name`, according to `metacontent`.
+    pub fn serves_metadata(&self, name: &str) -> bool {
+        self.metacontent
+            .iter()
+            .any(|pattern| PathSet::matched_path(pattern, name))
+    }
+
+    /// Returns whether the mirror serves the target `name`, according to `targetscontent`.
+    pub fn serves_target(&self, name: &str) -> bool {
+        self.targetscontent
+            .iter()
+            .any(|pattern| PathSet::matched_path(pattern, name))
+    }
+}
+
+impl Role for Mirrors {
+    const TYPE: RoleType = RoleType::Mirrors;
+
+    fn expires(&self) -> DateTime<Utc> {
+        self.expires
+    }
+
+    fn version(&self) -> NonZeroU64 {
+        self.version
+    }
+
+    fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
+    fn filename(&self, _consistent_snapshot: bool) -> String {
+        "mirrors.json".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DelegatedRole, Delegations, Hashes, PathSet, Signed, SuccinctRoles, Target, Targets,
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::num::NonZeroU64;
+
+    fn targets(names: &[&str], roles: Vec<DelegatedRole>) -> Targets {
+        let mut targets = Targets::new("1.0.0".to_owned(), NonZeroU64::new(1).unwrap(), Utc::now());
+        for name in names {
+            targets.add_target(
+                name,
+                Target {
+                    length: 0,
+                    hashes: Hashes {
+                        sha256: Some(Vec::new().into()),
+                        sha512: None,
+                        _extra: HashMap::new(),
+                    },
+                    custom: HashMap::new(),
+                    _extra: HashMap::new(),
+                },
+            );
+        }
+        targets.delegations = Some(Delegations {
+            keys: HashMap::new(),
+            roles,
+            succinct_roles: None,
+        });
+        targets
+    }
+
+    fn role(name: &str, paths: &[&str], terminating: bool, targets: Targets) -> DelegatedRole {
+        DelegatedRole {
+            name: name.to_owned(),
+            keyids: Vec::new(),
+            threshold: NonZeroU64::new(1).unwrap(),
+            paths: PathSet::Paths(paths.iter().map(|&path| path.to_owned()).collect()),
+            terminating,
+            targets: Some(Signed {
+                signed: targets,
+                signatures: Vec::new(),
+            }),
+        }
+    }
+
+    fn signing_role<'a>(targets: &'a Targets, name: &str) -> Option<Vec<&'a str>> {
+        targets
+            .find_signing_role_chain(name)
+            .map(|chain| chain.iter().map(|role| role.name.as_str()).collect())
+    }
+
+    #[test]
+    fn signing_role_chain() {
+        // targets -> a (*.txt) -> b (b/*), then c (*.txt), which also lists a.txt
+        let b = role("b", &["b/*"], false, targets(&["b/file.txt"], Vec::new()));
+        let a = role("a", &["*"], false, targets(&["a.txt"], vec![b]));
+        let c = role(
+            "c",
+            &["*.txt"],
+            false,
+            targets(&["a.txt", "c.txt"], Vec::new()),
+        );
+        let top = targets(&["top.txt"], vec![a, c]);
+
+        assert_eq!(signing_role(&top, "top.txt"), Some(Vec::new()));
+        // a is listed first, so it wins over c.
+        assert_eq!(signing_role(&top, "a.txt"), Some(vec!["a"]));
+        assert_eq!(signing_role(&top, "b/file.txt"), Some(vec!["a", "b"]));
+        assert_eq!(signing_role(&top, "c.txt"), Some(vec!["c"]));
+        assert_eq!(signing_role(&top, "d.txt"), None);
+
+        let names: Vec<_> = top
+            .delegated_roles()
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn path_hash_prefixes() {
+        // sha256("a.txt") = 18b7..., sha256("b.txt") = ffa0...
+        let bins = PathSet::PathHashPrefixes(vec!["1".to_owned(), "FF".to_owned()]);
+        assert!(bins.matched_target("a.txt"));
+        assert!(bins.matched_target("b.txt"));
+        assert!(!bins.matched_target("file3.txt"));
+
+        let parent = DelegatedRole {
+            paths: bins,
+            ..role("bins", &[], false, targets(&[], Vec::new()))
+        };
+        parent
+            .verify_paths(&PathSet::PathHashPrefixes(vec!["18".to_owned()]))
+            .unwrap();
+        assert!(parent
+            .verify_paths(&PathSet::PathHashPrefixes(vec!["2".to_owned()]))
+            .is_err());
+    }
+
+    #[test]
+    fn paths_could_match_prefix() {
+        let paths =
+            |paths: &[&str]| PathSet::Paths(paths.iter().map(|p| (*p).to_owned()).collect());
+        assert!(paths(&["linux/*"]).could_match_prefix("linux/"));
+        assert!(paths(&["linux/*"]).could_match_prefix("linux/x86_64/"));
+        assert!(paths(&["linux/x86_64/*"]).could_match_prefix("linux/"));
+        assert!(paths(&["*.txt"]).could_match_prefix("linux/"));
+        assert!(paths(&["linux/readme.txt"]).could_match_prefix("linux/"));
+        assert!(!paths(&["linux/readme.txt"]).could_match_prefix("linux/x86_64/"));
+        assert!(!paths(&["windows/*"]).could_match_prefix("linux/"));
+        assert!(!paths(&[]).could_match_prefix("linux/"));
+        assert!(PathSet::PathHashPrefixes(vec!["1".to_owned()]).could_match_prefix("linux/"));
+    }
+
+    #[test]
+    fn succinct_roles_bins() {
+        let succinct_roles = |bit_length| SuccinctRoles {
+            keyids: Vec::new(),
+            threshold: NonZeroU64::new(1).unwrap(),
+            bit_length,
+            name_prefix: "bin".to_owned(),
+            _extra: HashMap::new(),
+            bins: Vec::new(),
+        };
+
+        // sha256("b.txt") = ffa0...
+        let roles = succinct_roles(2);
+        assert_eq!(roles.bin_count(), 4);
+        assert_eq!(roles.bin_number("b.txt"), 3);
+        assert_eq!(roles.bin_name(3), "bin-3");
+        assert_eq!(
+            roles.bin(1).paths,
+            PathSet::PathHashPrefixes(
+                vec!["4", "5", "6", "7"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+
+        let roles = succinct_roles(9);
+        assert_eq!(roles.bin_number("b.txt"), 0x1ff);
+        assert_eq!(roles.bin_name(10), "bin-00a");
+        assert!(roles
+            .bin(roles.bin_number("b.txt"))
+            .paths
+            .matched_target("b.txt"));
+        assert!(!roles.bin(0x1fe).paths.matched_target("b.txt"));
+    }
+
+    #[test]
+    fn signing_role_chain_terminating() {
+        // a is terminating for *.txt, so c is never considered for *.txt, even though a does not
+        // list c.txt. a does not match other.bin, so c is considered for it.
+        let a = role("a", &["*.txt"], true, targets(&["a.txt"], Vec::new()));
+        let c = role(
+            "c",
+            &["*"],
+            false,
+            targets(&["c.txt", "other.bin"], Vec::new()),
+        );
+        let top = targets(&[], vec![a, c]);
+
+        assert_eq!(signing_role(&top, "a.txt"), Some(vec!["a"]));
+        assert_eq!(signing_role(&top, "c.txt"), None);
+        assert_eq!(signing_role(&top, "other.bin"), Some(vec!["c"]));
+
+        // find_target follows the same search, so c.txt isn't found in c.
+        assert!(top.find_target("a.txt").is_ok());
+        assert!(top.find_target("c.txt").is_err());
+        assert!(top.find_target("other.bin").is_ok());
+    }
+}
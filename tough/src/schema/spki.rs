@@ -24,6 +24,29 @@ use snafu::{OptionExt, ResultExt};
 pub(super) static OID_RSA_ENCRYPTION: &[u64] = &[1, 2, 840, 113_549, 1, 1, 1];
 pub(super) static OID_EC_PUBLIC_KEY: &[u64] = &[1, 2, 840, 10_045, 2, 1];
 pub(super) static OID_EC_PARAM_SECP256R1: &[u64] = &[1, 2, 840, 10_045, 3, 1, 7];
+pub(super) static OID_ED25519: &[u64] = &[1, 3, 101, 112];
+
+/// The contents of a `SubjectPublicKeyInfo` document whose algorithm isn't known in advance.
+pub(super) struct PublicKeyInfo {
+    /// The encoded algorithm OID.
+    pub(super) algorithm: Vec<u8>,
+    /// The encoded parameters OID, if the parameters are an OID (rather than NULL or absent).
+    pub(super) parameters: Option<Vec<u8>>,
+    /// The contents of the bit string.
+    pub(super) key: Vec<u8>,
+}
+
+impl PublicKeyInfo {
+    /// Returns whether the document is for `algorithm_oid`, with parameters `parameters_oid`.
+    pub(super) fn is(&self, algorithm_oid: &[u64], parameters_oid: Option<&[u64]>) -> bool {
+        self.is_algorithm(algorithm_oid) && self.parameters == parameters_oid.map(asn1_encode_oid)
+    }
+
+    /// Returns whether the document is for `algorithm_oid`, whatever its parameters.
+    pub(super) fn is_algorithm(&self, algorithm_oid: &[u64]) -> bool {
+        self.algorithm == asn1_encode_oid(algorithm_oid)
+    }
+}
 
 /// Wrap a bit string in a `SubjectPublicKeyInfo` document.
 pub(super) fn encode(algorithm_oid: &[u64], parameters_oid: Option<&[u64]>, b: &[u8]) -> String {
@@ -100,6 +123,49 @@ pub(super) fn decode(
         .to_owned())
 }
 
+/// Extract the algorithm, parameters, and bit string from a PEM-encoded `SubjectPublicKeyInfo`
+/// document, whatever its algorithm.
+pub(super) fn decode_any(input: &str) -> Result<PublicKeyInfo> {
+    let pem = pem::parse(input)
+        .map_err(Compat)
+        .context(error::PemDecode)?;
+    untrusted::Input::from(&pem.contents)
+        .read_all(ring::error::Unspecified, |input| {
+            der::expect_tag_and_get_value(input, der::Tag::Sequence).and_then(|spki| {
+                spki.read_all(ring::error::Unspecified, |input| {
+                    let (algorithm, parameters) = der::nested(
+                        input,
+                        der::Tag::Sequence,
+                        ring::error::Unspecified,
+                        |input| {
+                            let algorithm = der::expect_tag_and_get_value(input, der::Tag::OID)?;
+                            // RSA keys have NULL parameters and Ed25519 keys have none.
+                            let parameters = if input.at_end() {
+                                None
+                            } else {
+                                let (tag, value) = der::read_tag_and_get_value(input)?;
+                                if tag == der::Tag::OID as u8 {
+                                    Some(value.as_slice_less_safe().to_owned())
+                                } else {
+                                    None
+                                }
+                            };
+                            Ok((algorithm.as_slice_less_safe().to_owned(), parameters))
+                        },
+                    )?;
+                    let key = der::bit_string_with_no_unused_bits(input)?;
+                    Ok(PublicKeyInfo {
+                        algorithm,
+                        parameters,
+                        key: key.as_slice_less_safe().to_owned(),
+                    })
+                })
+            })
+        })
+        .ok()
+        .context(error::SpkiDecode)
+}
+
 fn asn1_tag(tag: der::Tag, data: Vec<u8>) -> Vec<u8> {
     let mut v = vec![tag as u8];
     v.extend(asn1_encode_len(data.len()));
@@ -1,9 +1,27 @@
+use super::decoded::{Decoded, Hex};
 use super::error::{self, Result};
-use super::{Delegations, Role, RoleType, Root, Signed, Targets};
+use super::key::Key;
+use super::{Delegations, Role, RoleType, Root, Signature, Signed, Targets};
+use chrono::{DateTime, Utc};
 use olpc_cjson::CanonicalFormatter;
 use serde::Serialize;
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU64;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Checks signatures on behalf of [`Root::verify_role_with`] and [`Delegations::verify_role_with`].
+///
+/// Implement this to check signatures with an external service such as an HSM, where each check
+/// is a remote call. The signatures of a role are checked concurrently on a small, fixed number of
+/// threads. Each key is checked at most until one of its signatures is valid, and signatures made
+/// by keys that are not the role's keys are not checked at all.
+pub trait SignatureVerifier: Sync {
+    /// Returns `true` if `signature` is a valid signature of `msg` made by `key`.
+    fn verify(&self, key: &Key, msg: &[u8], signature: &[u8]) -> bool;
+}
 
 impl Root {
     /// Checks that the given metadata role is valid based on a threshold of key signatures.
@@ -12,7 +30,6 @@ impl Root {
             .roles
             .get(&T::TYPE)
             .context(error::MissingRole { role: T::TYPE })?;
-        let mut valid = 0;
 
         let mut data = Vec::new();
         let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
@@ -22,30 +39,44 @@ impl Root {
                 what: format!("{} role", T::TYPE),
             })?;
 
-        let mut valid_keyids = HashSet::new();
-
-        for signature in &role.signatures {
-            if role_keys.keyids.contains(&signature.keyid) {
-                if let Some(key) = self.keys.get(&signature.keyid) {
-                    if key.verify(&data, &signature.sig) {
-                        // Ignore duplicate keyids.
-                        if valid_keyids.insert(&signature.keyid) {
-                            valid += 1;
-                        }
-                    }
-                }
-            }
-        }
+        verify_signatures(
+            T::TYPE,
+            &data,
+            &role.signatures,
+            &role_keys.keyids,
+            &self.keys,
+            role_keys.threshold,
+        )
+    }
 
-        ensure!(
-            valid >= u64::from(role_keys.threshold),
-            error::SignatureThreshold {
-                role: T::TYPE,
-                threshold: role_keys.threshold,
-                valid,
-            }
-        );
-        Ok(())
+    /// Checks that the given metadata role is valid based on a threshold of key signatures, using
+    /// `verifier` to check each signature. The signatures are checked concurrently.
+    pub fn verify_role_with<T, V>(&self, role: &Signed<T>, verifier: &V) -> Result<()>
+    where
+        T: Role + Serialize,
+        V: SignatureVerifier + ?Sized,
+    {
+        let role_keys = self
+            .roles
+            .get(&T::TYPE)
+            .context(error::MissingRole { role: T::TYPE })?;
+
+        let mut data = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+        role.signed
+            .serialize(&mut ser)
+            .context(error::JsonSerialization {
+                what: format!("{} role", T::TYPE),
+            })?;
+
+        tally_concurrently(
+            verifier,
+            &data,
+            &role.signatures,
+            &role_keys.keyids,
+            &self.keys,
+        )
+        .ensure_threshold(T::TYPE, role_keys.threshold)
     }
 }
 
@@ -53,13 +84,11 @@ impl Delegations {
     /// Verifies that roles matches contain valid keys
     pub fn verify_role(&self, role: &Signed<Targets>, name: &str) -> Result<()> {
         let role_keys =
-            self.roles
-                .iter()
+            self.roles_iter()
                 .find(|role| role.name == name)
                 .ok_or(error::Error::RoleNotFound {
                     name: name.to_string(),
                 })?;
-        let mut valid = 0;
 
         // serialize the role to verify the key ID by using the JSON representation
         let mut data = Vec::new();
@@ -69,22 +98,208 @@ impl Delegations {
             .context(error::JsonSerialization {
                 what: format!("{} role", name.to_string()),
             })?;
-        for signature in &role.signatures {
-            if role_keys.keyids.contains(&signature.keyid) {
-                if let Some(key) = self.keys.get(&signature.keyid) {
-                    if key.verify(&data, &signature.sig) {
-                        valid += 1;
-                    }
+
+        verify_signatures(
+            RoleType::Targets,
+            &data,
+            &role.signatures,
+            &role_keys.keyids,
+            &self.keys,
+            role_keys.threshold,
+        )
+    }
+
+    /// Verifies that roles matches contain valid keys, using `verifier` to check each signature.
+    /// The signatures are checked concurrently.
+    pub fn verify_role_with<V: SignatureVerifier + ?Sized>(
+        &self,
+        role: &Signed<Targets>,
+        name: &str,
+        verifier: &V,
+    ) -> Result<()> {
+        let role_keys =
+            self.roles_iter()
+                .find(|role| role.name == name)
+                .ok_or(error::Error::RoleNotFound {
+                    name: name.to_string(),
+                })?;
+
+        let mut data = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+        role.signed
+            .serialize(&mut ser)
+            .context(error::JsonSerialization {
+                what: format!("{} role", name.to_string()),
+            })?;
+
+        tally_concurrently(
+            verifier,
+            &data,
+            &role.signatures,
+            &role_keys.keyids,
+            &self.keys,
+        )
+        .ensure_threshold(RoleType::Targets, role_keys.threshold)
+    }
+}
+
+impl<T: Role> Signed<T> {
+    /// Checks that the role is signed by at least `threshold` of `keys`, which are keyed by their
+    /// key IDs, with the same checks that are made when a repository is loaded. This can verify
+    /// metadata that is received on its own, without loading a repository, e.g. a targets.json
+    /// from a third party whose keys are known.
+    ///
+    /// If `now` is given, the role must also not have expired by then.
+    ///
+    /// A role that is not signed by enough of `keys` is reported as [`Error::SignatureThreshold`],
+    /// with the keys whose signatures did not count.
+    ///
+    /// [`Error::SignatureThreshold`]: crate::schema::Error::SignatureThreshold
+    pub fn verify(
+        &self,
+        keys: &HashMap<Decoded<Hex>, Key>,
+        threshold: NonZeroU64,
+        now: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let data = self.signed.canonical_form()?;
+        let keyids: Vec<Decoded<Hex>> = keys.keys().cloned().collect();
+        verify_signatures(T::TYPE, &data, &self.signatures, &keyids, keys, threshold)?;
+        if let Some(now) = now {
+            let expires = self.signed.expires();
+            ensure!(
+                now <= expires,
+                error::Expired {
+                    role: T::TYPE,
+                    expires
+                }
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a threshold of `signatures` of `data` were made by keys in `keys` whose IDs are in
+/// `keyids`.
+fn verify_signatures(
+    role: RoleType,
+    data: &[u8],
+    signatures: &[Signature],
+    keyids: &[Decoded<Hex>],
+    keys: &HashMap<Decoded<Hex>, Key>,
+    threshold: NonZeroU64,
+) -> Result<()> {
+    let mut tally = Tally::default();
+    for signature in signatures {
+        let verified =
+            role_key(&signature.keyid, keyids, keys).map(|key| key.verify(data, &signature.sig));
+        tally.record(&signature.keyid, verified);
+    }
+    tally.ensure_threshold(role, threshold)
+}
+
+/// Returns the key that made a signature with `keyid`, if the key is one of the role's `keyids`.
+fn role_key<'a>(
+    keyid: &Decoded<Hex>,
+    keyids: &[Decoded<Hex>],
+    keys: &'a HashMap<Decoded<Hex>, Key>,
+) -> Option<&'a Key> {
+    if keyids.contains(keyid) {
+        keys.get(keyid)
+    } else {
+        None
+    }
+}
+
+/// The most signatures that [`tally_concurrently`] checks at once.
+const MAX_CONCURRENT_CHECKS: usize = 4;
+
+/// Checks `signatures` with `verifier` on a small pool of threads, and tallies the results.
+/// Signatures not made by one of `keyids` are recorded as unrecognized without a check. The
+/// signatures made by each key are checked on one thread, one at a time, until one of them is
+/// valid, as a key only counts once toward the threshold.
+fn tally_concurrently<'a, V: SignatureVerifier + ?Sized>(
+    verifier: &V,
+    data: &[u8],
+    signatures: &'a [Signature],
+    keyids: &[Decoded<Hex>],
+    keys: &HashMap<Decoded<Hex>, Key>,
+) -> Tally<'a> {
+    let mut tally = Tally::default();
+    let mut by_key: Vec<(&'a Decoded<Hex>, &Key, Vec<&'a Signature>)> = Vec::new();
+    for signature in signatures {
+        match role_key(&signature.keyid, keyids, keys) {
+            None => tally.record(&signature.keyid, None),
+            Some(key) => match by_key
+                .iter_mut()
+                .find(|(keyid, ..)| **keyid == signature.keyid)
+            {
+                Some((_, _, made)) => made.push(signature),
+                None => by_key.push((&signature.keyid, key, vec![signature])),
+            },
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(by_key.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..by_key.len().min(MAX_CONCURRENT_CHECKS) {
+            scope.spawn(|| {
+                while let Some((keyid, key, made)) = by_key.get(next.fetch_add(1, Ordering::SeqCst))
+                {
+                    let valid = made.iter().any(|signature| {
+                        // A verifier that panics has not verified the signature.
+                        panic::catch_unwind(AssertUnwindSafe(|| {
+                            verifier.verify(key, data, &signature.sig)
+                        }))
+                        .unwrap_or(false)
+                    });
+                    results
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((*keyid, valid));
                 }
+            });
+        }
+    });
+    for (keyid, valid) in results.into_inner().unwrap_or_else(PoisonError::into_inner) {
+        tally.record(keyid, Some(valid));
+    }
+    tally
+}
+
+/// The results of checking the signatures on a role, kept so that a role that doesn't meet its
+/// threshold can be reported with the keys whose signatures didn't count.
+#[derive(Debug, Default)]
+struct Tally<'a> {
+    valid: HashSet<&'a Decoded<Hex>>,
+    invalid: Vec<String>,
+    unrecognized: Vec<String>,
+}
+
+impl<'a> Tally<'a> {
+    /// Records the result of checking a signature made with `keyid`: `None` if the key is not one
+    /// of the role's keys, otherwise whether the signature was valid.
+    fn record(&mut self, keyid: &'a Decoded<Hex>, verified: Option<bool>) {
+        match verified {
+            // Duplicate keyids are only counted once.
+            Some(true) => {
+                self.valid.insert(keyid);
             }
+            Some(false) => self.invalid.push(hex::encode(keyid)),
+            None => self.unrecognized.push(hex::encode(keyid)),
         }
+    }
 
+    fn ensure_threshold(self, role: RoleType, threshold: NonZeroU64) -> Result<()> {
+        let valid = self.valid.len() as u64;
         ensure!(
-            valid >= u64::from(role_keys.threshold),
+            valid >= threshold.get(),
             error::SignatureThreshold {
-                role: RoleType::Targets,
-                threshold: role_keys.threshold,
+                role,
+                threshold,
                 valid,
+                invalid: self.invalid,
+                unrecognized: self.unrecognized,
             }
         );
         Ok(())
@@ -93,7 +308,15 @@ impl Delegations {
 
 #[cfg(test)]
 mod tests {
-    use super::{Root, Signed};
+    use super::{Root, SignatureVerifier, Signed, Targets};
+    use crate::schema::decoded::{Decoded, Hex};
+    use crate::schema::key::Key;
+    use crate::schema::{Error, RoleType};
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+    use std::num::NonZeroU64;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
 
     #[test]
     fn simple_rsa() {
@@ -171,4 +394,169 @@ mod tests {
             .verify_role(&root)
             .expect_err("expired root signature should not verify");
     }
+
+    #[test]
+    fn signature_threshold_breakdown() {
+        let mut root: Signed<Root> = serde_json::from_str(include_str!(
+            "../../tests/data/invalid-root-json-signature/root.json"
+        ))
+        .unwrap();
+        let mut unrecognized = root.signatures[0].clone();
+        unrecognized.keyid = vec![0; 32].into();
+        root.signatures.push(unrecognized);
+
+        match root.signed.verify_role(&root) {
+            Err(Error::SignatureThreshold {
+                role: RoleType::Root,
+                threshold: 1,
+                valid: 0,
+                invalid,
+                unrecognized,
+                ..
+            }) => {
+                assert_eq!(invalid, vec![hex::encode(&root.signatures[0].keyid)]);
+                assert_eq!(unrecognized, vec!["00".repeat(32)]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    // A role received on its own is verified with the keys and threshold that are given for it.
+    #[test]
+    fn verify_standalone_role() {
+        let root: Signed<Root> = serde_json::from_str(include_str!(
+            "../../tests/data/tuf-reference-impl/metadata/1.root.json"
+        ))
+        .unwrap();
+        let targets: Signed<Targets> = serde_json::from_str(include_str!(
+            "../../tests/data/tuf-reference-impl/metadata/targets.json"
+        ))
+        .unwrap();
+        let role_keys = |role: RoleType| -> (HashMap<Decoded<Hex>, Key>, NonZeroU64) {
+            let keys = &root.signed.roles[&role];
+            let map = keys
+                .keyids
+                .iter()
+                .map(|keyid| (keyid.clone(), root.signed.keys[keyid].clone()))
+                .collect();
+            (map, keys.threshold)
+        };
+
+        let (keys, threshold) = role_keys(RoleType::Root);
+        root.verify(&keys, threshold, None).unwrap();
+        root.verify(&keys, threshold, Some(Utc.ymd(2020, 1, 1).and_hms(0, 0, 0)))
+            .unwrap();
+        assert!(matches!(
+            root.verify(&keys, threshold, Some(Utc.ymd(2100, 1, 1).and_hms(0, 0, 0))),
+            Err(Error::Expired {
+                role: RoleType::Root,
+                ..
+            })
+        ));
+
+        let (keys, threshold) = role_keys(RoleType::Targets);
+        targets.verify(&keys, threshold, None).unwrap();
+        assert!(matches!(
+            targets.verify(&keys, NonZeroU64::new(2).unwrap(), None),
+            Err(Error::SignatureThreshold {
+                role: RoleType::Targets,
+                threshold: 2,
+                valid: 1,
+                ..
+            })
+        ));
+        // The targets role is not signed by the root keys.
+        let (keys, threshold) = role_keys(RoleType::Root);
+        assert!(matches!(
+            targets.verify(&keys, threshold, None),
+            Err(Error::SignatureThreshold { valid: 0, .. })
+        ));
+    }
+
+    /// Stands in for a remote verifier, recording how many calls are made, and how many are in
+    /// flight at once.
+    #[derive(Default)]
+    struct RemoteVerifier {
+        valid: bool,
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl SignatureVerifier for RemoteVerifier {
+        fn verify(&self, key: &Key, msg: &[u8], signature: &[u8]) -> bool {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.valid && key.verify(msg, signature)
+        }
+    }
+
+    #[test]
+    fn verify_with_remote_verifier() {
+        let mut root: Signed<Root> =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        // The same signature twice is only checked once, as it is made by the same key.
+        let signature = root.signatures[0].clone();
+        let signatures = root.signatures.len();
+        root.signatures.push(signature.clone());
+        // A signature by a key that isn't one of the role's keys isn't checked.
+        let mut unrecognized = signature;
+        unrecognized.keyid = vec![0; 32].into();
+        root.signatures.push(unrecognized);
+
+        let verifier = RemoteVerifier {
+            valid: true,
+            ..RemoteVerifier::default()
+        };
+        root.signed.verify_role_with(&root, &verifier).unwrap();
+        assert_eq!(verifier.calls.load(Ordering::SeqCst), signatures);
+
+        let verifier = RemoteVerifier::default();
+        match root.signed.verify_role_with(&root, &verifier) {
+            Err(Error::SignatureThreshold {
+                invalid,
+                unrecognized,
+                ..
+            }) => {
+                assert_eq!(invalid.len(), signatures);
+                assert_eq!(unrecognized, vec!["00".repeat(32)]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // Each key's signatures are checked until one is valid, so both copies were checked.
+        assert_eq!(verifier.calls.load(Ordering::SeqCst), signatures + 1);
+    }
+
+    #[test]
+    fn verify_with_remote_verifier_bounded() {
+        let mut root: Signed<Root> =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        // Add many more keys than the verifier may check at once. This changes the role, so none
+        // of the signatures are valid, and each of them is checked.
+        let signature = root.signatures[0].clone();
+        let key = root.signed.keys[&signature.keyid].clone();
+        let role = root.signed.roles.get_mut(&RoleType::Root).unwrap();
+        for i in 1..=16_u8 {
+            let keyid: Decoded<Hex> = vec![i; 32].into();
+            role.keyids.push(keyid.clone());
+            root.signed.keys.insert(keyid.clone(), key.clone());
+            let mut signature = signature.clone();
+            signature.keyid = keyid;
+            root.signatures.push(signature);
+        }
+
+        let verifier = RemoteVerifier {
+            valid: true,
+            ..RemoteVerifier::default()
+        };
+        root.signed
+            .verify_role_with(&root, &verifier)
+            .expect_err("the role was changed after it was signed");
+        assert_eq!(verifier.calls.load(Ordering::SeqCst), root.signatures.len());
+        assert!(verifier.max_in_flight.load(Ordering::SeqCst) <= super::MAX_CONCURRENT_CHECKS);
+        assert!(verifier.max_in_flight.load(Ordering::SeqCst) > 1);
+    }
 }
@@ -140,8 +140,11 @@ impl Sign for SignKeyPair {
 /// Parses a supplied keypair and if it is recognized, returns an object that
 /// implements the Sign trait
 /// Accepted Keys: ED25519 pkcs8, Ecdsa pkcs8, RSA
+///
+/// Keys may be DER-encoded PKCS#8, or PEM-encoded as PKCS#8 ("PRIVATE KEY") or PKCS#1
+/// ("RSA PRIVATE KEY"). ECDSA keys must use the NIST P-256 curve.
 pub fn parse_keypair(key: &[u8]) -> Result<impl Sign> {
-    if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8(key) {
+    if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(key) {
         Ok(SignKeyPair::ED25519(ed25519_key_pair))
     } else if let Ok(ecdsa_key_pair) =
         EcdsaKeyPair::from_pkcs8(&ring::signature::ECDSA_P256_SHA256_ASN1_SIGNING, key)
@@ -149,19 +152,33 @@ pub fn parse_keypair(key: &[u8]) -> Result<impl Sign> {
         Ok(SignKeyPair::ECDSA(ecdsa_key_pair))
     } else if let Ok(pem) = pem::parse(key) {
         match pem.tag.as_str() {
-            "PRIVATE KEY" => {
-                if let Ok(rsa_key_pair) = RsaKeyPair::from_pkcs8(&pem.contents) {
-                    Ok(SignKeyPair::RSA(rsa_key_pair))
-                } else {
-                    error::KeyUnrecognized.fail()
-                }
-            }
+            "PRIVATE KEY" => parse_pkcs8(&pem.contents),
             "RSA PRIVATE KEY" => Ok(SignKeyPair::RSA(
                 RsaKeyPair::from_der(&pem.contents).context(error::KeyRejected)?,
             )),
+            "EC PRIVATE KEY" => error::KeySec1Unsupported.fail(),
             _ => error::KeyUnrecognized.fail(),
         }
     } else {
         error::KeyUnrecognized.fail()
     }
 }
+
+/// Parses a PKCS#8 private key of any supported type.
+fn parse_pkcs8(der: &[u8]) -> Result<SignKeyPair> {
+    if let Ok(rsa_key_pair) = RsaKeyPair::from_pkcs8(der) {
+        Ok(SignKeyPair::RSA(rsa_key_pair))
+    } else if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+        Ok(SignKeyPair::ED25519(ed25519_key_pair))
+    } else if let Ok(ecdsa_key_pair) =
+        EcdsaKeyPair::from_pkcs8(&ring::signature::ECDSA_P256_SHA256_ASN1_SIGNING, der)
+    {
+        Ok(SignKeyPair::ECDSA(ecdsa_key_pair))
+    } else if EcdsaKeyPair::from_pkcs8(&ring::signature::ECDSA_P384_SHA384_ASN1_SIGNING, der)
+        .is_ok()
+    {
+        error::KeyUnsupportedCurve.fail()
+    } else {
+        error::KeyUnrecognized.fail()
+    }
+}
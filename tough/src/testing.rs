@@ -0,0 +1,97 @@
+//! The `testing` module provides types that make it easier to test code that uses tough, such as
+//! [`MockTransport`].
+//!
+//! Requires the `testing` feature.
+use crate::{Transport, TransportError, TransportErrorKind};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+use url::Url;
+
+/// An in-memory [`Transport`] that serves files from a map of URLs to their contents.
+///
+/// Fetching a URL that is not in the map results in [`TransportErrorKind::FileNotFound`]. A URL can
+/// also be made to fail with a specific kind of error with [`MockTransport::fail`], e.g. to
+/// simulate a missing `timestamp.json`, or a server error while fetching a target.
+///
+/// # Example
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use tough::testing::MockTransport;
+/// # use tough::{Transport, TransportErrorKind};
+/// # use url::Url;
+/// let timestamp = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+/// let snapshot = Url::parse("https://example.com/metadata/snapshot.json").unwrap();
+/// let transport = MockTransport::new(HashMap::new())
+///     .file(snapshot.clone(), b"{}".to_vec())
+///     .fail(timestamp.clone(), TransportErrorKind::Other);
+///
+/// assert!(transport.fetch(snapshot).is_ok());
+/// assert!(matches!(
+///     transport.fetch(timestamp).err().unwrap().kind(),
+///     TransportErrorKind::Other
+/// ));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    files: HashMap<Url, Arc<[u8]>>,
+    errors: HashMap<Url, TransportErrorKind>,
+}
+
+impl MockTransport {
+    /// Create a new `MockTransport` that serves `files`.
+    pub fn new(files: HashMap<Url, Vec<u8>>) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|(url, contents)| (url, contents.into()))
+                .collect(),
+            errors: HashMap::new(),
+        }
+    }
+
+    /// Add a file to serve at `url`, replacing any file that was already there.
+    pub fn file<C: Into<Vec<u8>>>(mut self, url: Url, contents: C) -> Self {
+        self.files.insert(url, contents.into().into());
+        self
+    }
+
+    /// Make fetching `url` fail with an error of `kind`, even if there is a file at `url`.
+    pub fn fail(mut self, url: Url, kind: TransportErrorKind) -> Self {
+        self.errors.insert(url, kind);
+        self
+    }
+}
+
+impl Transport for MockTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        if let Some(kind) = self.errors.get(&url) {
+            return Err(TransportError::new_with_cause(
+                *kind,
+                url,
+                "error configured in MockTransport",
+            ));
+        }
+        match self.files.get(&url) {
+            Some(contents) => Ok(Box::new(Cursor::new(Arc::clone(contents)))),
+            None => Err(TransportError::new(TransportErrorKind::FileNotFound, url)),
+        }
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        match self.errors.get(&url) {
+            Some(TransportErrorKind::FileNotFound) => Ok(false),
+            Some(kind) => Err(TransportError::new_with_cause(
+                *kind,
+                url,
+                "error configured in MockTransport",
+            )),
+            None => Ok(self.files.contains_key(&url)),
+        }
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
@@ -1,9 +1,20 @@
+use crate::io::{CacheAdapter, ThrottleAdapter};
 #[cfg(feature = "http")]
 use crate::{HttpTransport, HttpTransportBuilder};
 use dyn_clone::DynClone;
+use ring::digest::{digest, SHA256};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
 use std::io::{ErrorKind, Read};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+#[cfg(feature = "async")]
+use tokio::io::AsyncRead;
 use url::Url;
 
 /// A trait to abstract over the method/protocol by which files are obtained.
@@ -16,16 +27,61 @@ use url::Url;
 pub trait Transport: Debug + DynClone {
     /// Opens a `Read` object for the file specified by `url`.
     fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError>;
+
+    /// Checks whether the file specified by `url` exists, without reading it.
+    ///
+    /// The default implementation calls [`Transport::fetch`] and returns `Ok(false)` for
+    /// [`TransportErrorKind::FileNotFound`]. Transports that can check more cheaply, e.g. with an
+    /// HTTP `HEAD` request, should override it.
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        match self.fetch(url) {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.kind(), TransportErrorKind::FileNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether [`Transport::exists`] is cheaper than [`Transport::fetch`], i.e. whether it has
+    /// been overridden. Files that may be missing, e.g. the next version of `root.json`, are only
+    /// checked for before they are fetched if this returns `true`; otherwise they are fetched
+    /// directly, so that they are not downloaded twice.
+    ///
+    /// Returns `false` by default. A transport that overrides `exists` should return `true`, and
+    /// one that wraps another transport should return what the inner transport returns.
+    fn exists_is_cheap(&self) -> bool {
+        false
+    }
 }
 
 // Implements `Clone` for `Transport` trait objects (i.e. on `Box::<dyn Clone>`). To facilitate
 // this, `Clone` needs to be implemented for any `Transport`s. The compiler will enforce this.
 dyn_clone::clone_trait_object!(Transport);
 
+/// The future returned by [`AsyncTransport::fetch`].
+#[cfg(feature = "async")]
+pub type AsyncFetch<'a> = Pin<
+    Box<dyn Future<Output = Result<Pin<Box<dyn AsyncRead + Send>>, TransportError>> + Send + 'a>,
+>;
+
+/// The async counterpart of [`Transport`], for use with
+/// [`RepositoryLoader::load_async`](crate::RepositoryLoader::load_async).
+///
+/// Like [`Transport`], you will need to implement `Clone` when implementing an `AsyncTransport`.
+///
+/// Requires the `async` feature.
+#[cfg(feature = "async")]
+pub trait AsyncTransport: Debug + DynClone + Send + Sync {
+    /// Opens an `AsyncRead` object for the file specified by `url`.
+    fn fetch(&self, url: Url) -> AsyncFetch<'_>;
+}
+
+#[cfg(feature = "async")]
+dyn_clone::clone_trait_object!(AsyncTransport);
+
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
 /// The kind of error that the transport object experienced during `fetch`.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum TransportErrorKind {
     /// The [`Transport`] does not handle the URL scheme. e.g. `file://` or `http://`.
@@ -135,11 +191,84 @@ impl Error for TransportError {
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
 /// Provides a [`Transport`] for local files.
-#[derive(Debug, Clone, Copy)]
-pub struct FilesystemTransport;
+///
+/// By default, symlinks are followed wherever they point. For repositories assembled from
+/// untrusted sources, use [`FilesystemTransport::follow_symlinks`] and
+/// [`FilesystemTransport::base_dir`] to refuse files that resolve to a path outside of the
+/// repository's directory.
+#[derive(Debug, Clone)]
+pub struct FilesystemTransport {
+    follow_symlinks: bool,
+    /// The canonical form of the directory set with `base_dir`.
+    base_dir: Option<PathBuf>,
+}
 
-impl Transport for FilesystemTransport {
-    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+impl Default for FilesystemTransport {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: true,
+            base_dir: None,
+        }
+    }
+}
+
+impl FilesystemTransport {
+    /// Creates a new `FilesystemTransport`, which follows symlinks. Same as `default()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to follow symlinks that lead outside of the directory set with
+    /// [`FilesystemTransport::base_dir`]. If `false`, fetching a file whose path resolves, after
+    /// resolving symlinks in any of its components, to a path outside of the base directory fails
+    /// with [`TransportErrorKind::Other`], as does every fetch if no base directory is set.
+    /// Symlinks that stay within the base directory are still followed.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets the directory that files must resolve to a path within when symlinks aren't followed;
+    /// typically the directory that holds both the metadata and targets of a repository. Symlinks
+    /// in `base_dir` itself are resolved here, once, so this fails if it doesn't exist.
+    pub fn base_dir<P: AsRef<Path>>(mut self, base_dir: P) -> std::io::Result<Self> {
+        self.base_dir = Some(base_dir.as_ref().canonicalize()?);
+        Ok(self)
+    }
+
+    /// Fails if `file_path` resolves to a path outside of the base directory.
+    fn check_symlinks(&self, url: &Url, file_path: &Path) -> Result<(), TransportError> {
+        let base_dir = self.base_dir.as_ref().ok_or_else(|| {
+            TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                "symlinks are not followed, but no base directory is set".to_string(),
+            )
+        })?;
+        let resolved = file_path.canonicalize().map_err(|e| {
+            let kind = match e.kind() {
+                ErrorKind::NotFound => TransportErrorKind::FileNotFound,
+                _ => TransportErrorKind::Other,
+            };
+            TransportError::new_with_cause(kind, url, e)
+        })?;
+        if resolved.starts_with(base_dir) {
+            Ok(())
+        } else {
+            Err(TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                format!(
+                    "refusing to follow symlink to '{}', which is outside of '{}'",
+                    resolved.display(),
+                    base_dir.display()
+                ),
+            ))
+        }
+    }
+
+    /// Converts a `file://` URL into a file path.
+    fn file_path(url: &Url) -> Result<PathBuf, TransportError> {
         // If the scheme isn't "file://", reject
         if url.scheme() != "file" {
             return Err(TransportError::new(
@@ -149,13 +278,22 @@ impl Transport for FilesystemTransport {
         }
 
         // Convert the file URL into a file path
-        let file_path = &url.to_file_path().map_err(|_e| {
+        url.to_file_path().map_err(|_e| {
             TransportError::new_with_cause(
                 TransportErrorKind::Other,
-                &url,
+                url,
                 "unable to get filepath from URL".to_string(),
             )
-        })?;
+        })
+    }
+}
+
+impl Transport for FilesystemTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let file_path = &Self::file_path(&url)?;
+        if !self.follow_symlinks {
+            self.check_symlinks(&url, file_path)?;
+        }
 
         // And open the file
         let f = std::fs::File::open(file_path).map_err(|e| {
@@ -167,13 +305,365 @@ impl Transport for FilesystemTransport {
         })?;
         Ok(Box::new(f))
     }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        let file_path = Self::file_path(&url)?;
+        if !file_path.exists() {
+            return Ok(false);
+        }
+        if !self.follow_symlinks {
+            self.check_symlinks(&url, &file_path)?;
+        }
+        Ok(true)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A [`Transport`] that limits the bandwidth used by each file fetched with another `Transport`.
+///
+/// This is useful to avoid saturating a shared link when caching a large repository. The limit
+/// applies to any one-second window, and to each fetched file separately.
+///
+/// # Example
+///
+/// ```
+/// # use std::num::NonZeroU64;
+/// # use tough::{DefaultTransport, ThrottledTransport};
+/// // Fetch files at about 1 MiB per second.
+/// let transport = ThrottledTransport::new(
+///     Box::new(DefaultTransport::new()),
+///     NonZeroU64::new(1024 * 1024).unwrap(),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ThrottledTransport {
+    inner: Box<dyn Transport>,
+    bytes_per_second: NonZeroU64,
+}
+
+impl ThrottledTransport {
+    /// Creates a `ThrottledTransport` that reads files fetched by `inner` at no more than
+    /// `bytes_per_second`.
+    pub fn new(inner: Box<dyn Transport>, bytes_per_second: NonZeroU64) -> Self {
+        Self {
+            inner,
+            bytes_per_second,
+        }
+    }
+}
+
+impl Transport for ThrottledTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        Ok(Box::new(ThrottleAdapter::new(
+            self.inner.fetch(url)?,
+            self.bytes_per_second,
+        )))
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        self.inner.exists(url)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        self.inner.exists_is_cheap()
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A [`Transport`] that saves every file that it fetches to a local directory, and fetches files
+/// from that directory when they have been saved before.
+///
+/// This lets a run that has network access record what it fetched, so that a later offline run can
+/// replay it. Note that a cached file is never refetched, so a repository loaded through a
+/// `CachingTransport` will see cached metadata, e.g. `timestamp.json`, for as long as the cache
+/// directory is kept.
+///
+/// A file is saved in the cache once it has been read to the end. It is written to a temporary file
+/// and then renamed, so concurrent fetches of the same URL, or reads that fail partway, never leave
+/// a partial file in the cache.
+#[derive(Debug, Clone)]
+pub struct CachingTransport {
+    inner: Box<dyn Transport>,
+    cache_dir: PathBuf,
+}
+
+impl CachingTransport {
+    /// Creates a `CachingTransport` that fetches files with `inner` and saves them in `cache_dir`,
+    /// which is created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(inner: Box<dyn Transport>, cache_dir: P) -> Self {
+        Self {
+            inner,
+            cache_dir: cache_dir.into(),
+        }
+    }
+
+    /// Returns the path that the file for `url` is cached at. The file name is the SHA-256 of the
+    /// URL, so that any URL maps to a valid, unique file name.
+    fn cache_path(&self, url: &Url) -> PathBuf {
+        let name = hex::encode(digest(&SHA256, url.as_str().as_bytes()));
+        self.cache_dir.join(name)
+    }
+}
+
+impl Transport for CachingTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let path = self.cache_path(&url);
+        match File::open(&path) {
+            Ok(f) => return Ok(Box::new(f)),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(TransportError::new_with_cause(
+                    TransportErrorKind::Other,
+                    url,
+                    e,
+                ))
+            }
+        }
+
+        let read = self.inner.fetch(url.clone())?;
+        std::fs::create_dir_all(&self.cache_dir)
+            .and_then(|()| CacheAdapter::new(read, path))
+            .map(|adapter| Box::new(adapter) as Box<dyn Read + Send>)
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        if self.cache_path(&url).is_file() {
+            return Ok(true);
+        }
+        self.inner.exists(url)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        self.inner.exists_is_cheap()
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A [`Transport`] that tries each of a list of transports in order, e.g. one for each mirror of a
+/// repository, and returns the first file that is fetched successfully.
+///
+/// Any error, including [`TransportErrorKind::FileNotFound`], causes the next transport to be
+/// tried, since a mirror may be only partially populated. If every transport fails, the error from
+/// the last one is returned.
+///
+/// Note that only opening the file is retried with the next transport; an error while reading the
+/// file is returned to the caller.
+#[derive(Debug, Clone)]
+pub struct ChainTransport {
+    transports: Vec<Box<dyn Transport>>,
+}
+
+impl ChainTransport {
+    /// Creates a `ChainTransport` that tries each of `transports`, in order.
+    pub fn new(transports: Vec<Box<dyn Transport>>) -> Self {
+        Self { transports }
+    }
+}
+
+impl Transport for ChainTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let mut last_err = None;
+        for transport in &self.transports {
+            match transport.fetch(url.clone()) {
+                Ok(read) => return Ok(read),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                "ChainTransport has no transports to try",
+            )
+        }))
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        let mut last_err = None;
+        for transport in &self.transports {
+            match transport.exists(url.clone()) {
+                Ok(true) => return Ok(true),
+                Ok(false) => last_err = None,
+                Err(err) => last_err = Some(err),
+            }
+        }
+        // the file exists nowhere, unless the last transport failed to check.
+        last_err.map_or(Ok(false), Err)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        self.transports
+            .iter()
+            .all(|transport| transport.exists_is_cheap())
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// A [`Transport`] that wraps another `Transport` and reports a [`FetchEvent`] to a callback for
+/// each file fetched, e.g. to record bytes transferred and latency in metrics.
+///
+/// The event for a file is reported once: right away if the file could not be opened, or otherwise
+/// when its reader is dropped. Errors from the inner transport, and from reading the file, are
+/// returned unchanged. Retries are made inside the inner transport and are not counted here; for
+/// [`HttpTransport`] use [`HttpTransportBuilder::on_retry`] to count them.
+///
+/// # Example
+///
+/// ```
+/// # use tough::{DefaultTransport, MeteredTransport};
+/// let transport = MeteredTransport::new(Box::new(DefaultTransport::new()), |event| {
+///     println!("{} {:?} {} bytes", event.url, event.outcome, event.bytes);
+/// });
+/// ```
+#[derive(Clone)]
+pub struct MeteredTransport {
+    inner: Box<dyn Transport>,
+    callback: Arc<dyn Fn(&FetchEvent) + Send + Sync>,
+}
+
+impl MeteredTransport {
+    /// Creates a `MeteredTransport` that fetches files with `inner` and calls `callback` with a
+    /// [`FetchEvent`] for each of them. The callback may be called from any thread that reads a
+    /// fetched file, so it should return quickly.
+    pub fn new<F>(inner: Box<dyn Transport>, callback: F) -> Self
+    where
+        F: Fn(&FetchEvent) + Send + Sync + 'static,
+    {
+        Self {
+            inner,
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl Debug for MeteredTransport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeteredTransport")
+            .field("inner", &self.inner)
+            .field("callback", &"<callback>")
+            .finish()
+    }
+}
+
+impl Transport for MeteredTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let start = Instant::now();
+        match self.inner.fetch(url.clone()) {
+            Ok(inner) => Ok(Box::new(MeteredRead {
+                inner,
+                callback: Arc::clone(&self.callback),
+                url: Some(url),
+                start,
+                bytes: 0,
+                outcome: FetchOutcome::Incomplete,
+            })),
+            Err(err) => {
+                (self.callback)(&FetchEvent {
+                    url,
+                    bytes: 0,
+                    elapsed: start.elapsed(),
+                    outcome: FetchOutcome::FetchFailed(err.kind()),
+                });
+                Err(err)
+            }
+        }
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        self.inner.exists(url)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        self.inner.exists_is_cheap()
+    }
+}
+
+/// Describes a file fetched by a [`MeteredTransport`], passed to its callback.
+#[derive(Debug, Clone)]
+pub struct FetchEvent {
+    /// The URL that was fetched.
+    pub url: Url,
+    /// The number of bytes of the file that were read.
+    pub bytes: u64,
+    /// The time from the start of the fetch until the file could not be opened, or until its
+    /// reader was dropped.
+    pub elapsed: Duration,
+    /// How the fetch ended.
+    pub outcome: FetchOutcome,
+}
+
+/// How a fetch made by a [`MeteredTransport`] ended, see [`FetchEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchOutcome {
+    /// The file was read to the end.
+    Complete,
+    /// The reader was dropped before the end of the file was reached.
+    Incomplete,
+    /// The inner transport failed to open the file.
+    FetchFailed(TransportErrorKind),
+    /// Reading the file failed.
+    ReadFailed(ErrorKind),
+}
+
+/// A `Read` that counts the bytes read for [`MeteredTransport`], and reports them when dropped.
+struct MeteredRead {
+    inner: Box<dyn Read + Send>,
+    callback: Arc<dyn Fn(&FetchEvent) + Send + Sync>,
+    url: Option<Url>,
+    start: Instant,
+    bytes: u64,
+    outcome: FetchOutcome,
+}
+
+impl Read for MeteredRead {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(0) if !buf.is_empty() => {
+                self.outcome = FetchOutcome::Complete;
+                Ok(0)
+            }
+            Ok(size) => {
+                self.bytes += size as u64;
+                Ok(size)
+            }
+            Err(err) => {
+                // an interrupted read is retried by the caller, so it doesn't end the fetch.
+                if err.kind() != ErrorKind::Interrupted {
+                    self.outcome = FetchOutcome::ReadFailed(err.kind());
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Drop for MeteredRead {
+    fn drop(&mut self) {
+        if let Some(url) = self.url.take() {
+            (self.callback)(&FetchEvent {
+                url,
+                bytes: self.bytes,
+                elapsed: self.start.elapsed(),
+                outcome: self.outcome,
+            });
+        }
+    }
 }
 
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
 /// A Transport that provides support for both local files and, if the `http` feature is enabled,
 /// HTTP-transported files.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct DefaultTransport {
     file: FilesystemTransport,
     #[cfg(feature = "http")]
@@ -183,7 +673,7 @@ pub struct DefaultTransport {
 impl Default for DefaultTransport {
     fn default() -> Self {
         Self {
-            file: FilesystemTransport,
+            file: FilesystemTransport::new(),
             #[cfg(feature = "http")]
             http: HttpTransport::default(),
         }
@@ -202,7 +692,7 @@ impl DefaultTransport {
     /// Create a new `DefaultTransport` with potentially customized settings.
     pub fn new_with_http_settings(builder: HttpTransportBuilder) -> Self {
         Self {
-            file: FilesystemTransport,
+            file: FilesystemTransport::new(),
             http: builder.build(),
         }
     }
@@ -219,6 +709,20 @@ impl Transport for DefaultTransport {
             )),
         }
     }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        match url.scheme() {
+            "file" => self.file.exists(url),
+            #[cfg(feature = "http")]
+            "http" | "https" => self.http.exists(url),
+            // fails, e.g. for an unsupported URL scheme.
+            _ => self.fetch(url).map(|_| true),
+        }
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
 }
 
 impl DefaultTransport {
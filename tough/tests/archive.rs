@@ -0,0 +1,107 @@
+#![cfg(feature = "archive")]
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{Cursor, Write};
+use tempfile::TempDir;
+use test_utils::{read_to_end, test_data};
+use tough::{ArchiveTransport, RepositoryLoader, Transport, TransportErrorKind};
+use url::Url;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+mod test_utils;
+
+/// Returns a tar archive of the tuf-reference-impl repository, under a `./repo` directory.
+fn tar() -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    builder
+        .append_dir_all("./repo", test_data().join("tuf-reference-impl"))
+        .unwrap();
+    builder.into_inner().unwrap()
+}
+
+/// Returns a zip archive of the tuf-reference-impl repository, with metadata that is stored and
+/// targets that are compressed.
+fn zip() -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (dir, method) in &[
+        ("metadata", CompressionMethod::Stored),
+        ("targets", CompressionMethod::Deflated),
+    ] {
+        let mut entries = std::fs::read_dir(test_data().join("tuf-reference-impl").join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for path in entries {
+            let name = format!(
+                "repo/{}/{}",
+                dir,
+                path.file_name().unwrap().to_str().unwrap()
+            );
+            zip.start_file(name, FileOptions::default().compression_method(*method))
+                .unwrap();
+            zip.write_all(&std::fs::read(&path).unwrap()).unwrap();
+        }
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Loads the repository in the archive and reads a target.
+fn load(transport: ArchiveTransport) {
+    let root = transport
+        .fetch(Url::parse("file:///repo/metadata/1.root.json").unwrap())
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        root,
+        Url::parse("file:///repo/metadata/").unwrap(),
+        Url::parse("file:///repo/targets/").unwrap(),
+    )
+    .transport(transport)
+    .load()
+    .unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        b"This is an example target file."
+    );
+}
+
+#[test]
+fn archive_transport_tar() {
+    load(ArchiveTransport::from_tar(Cursor::new(tar())).unwrap());
+}
+
+#[test]
+fn archive_transport_tar_gz() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&tar()).unwrap();
+    let archive = encoder.finish().unwrap();
+    load(ArchiveTransport::from_tar_gz(Cursor::new(archive)).unwrap());
+}
+
+#[test]
+fn archive_transport_zip() {
+    load(ArchiveTransport::from_zip(Cursor::new(zip())).unwrap());
+}
+
+#[test]
+fn archive_transport_open() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("repo.zip");
+    File::create(&path).unwrap().write_all(&zip()).unwrap();
+    let transport = ArchiveTransport::open(&path).unwrap();
+
+    let missing = Url::parse("file:///repo/metadata/2.root.json").unwrap();
+    let err = transport.fetch(missing.clone()).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::FileNotFound));
+    assert!(!transport.exists(missing).unwrap());
+    assert!(transport
+        .exists(Url::parse("file:///repo/targets/file2.txt").unwrap())
+        .unwrap());
+
+    let path = dir.path().join("repo.rar");
+    File::create(&path).unwrap();
+    assert!(ArchiveTransport::open(&path).is_err());
+}
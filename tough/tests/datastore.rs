@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::sync::{Arc, Mutex};
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::{Datastore, MemoryDatastore, RepositoryLoader};
+
+mod test_utils;
+
+/// A `Datastore` that keeps values in a shared map, so the test can look at them.
+#[derive(Debug, Clone, Default)]
+struct MapDatastore(Arc<Mutex<HashMap<String, Vec<u8>>>>);
+
+impl Datastore for MapDatastore {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    fn write(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        self.0.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Test that a custom `Datastore` is used to store the trusted metadata.
+#[test]
+fn custom_datastore() {
+    let base = test_data().join("tuf-reference-impl");
+    let datastore = MapDatastore::default();
+    let load = || {
+        RepositoryLoader::new(
+            File::open(base.join("metadata").join("1.root.json")).unwrap(),
+            dir_url(base.join("metadata")),
+            dir_url(base.join("targets")),
+        )
+        .datastore_backend(datastore.clone())
+        .load()
+        .unwrap()
+    };
+
+    load();
+    let mut keys: Vec<_> = datastore.0.lock().unwrap().keys().cloned().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "latest_known_time.json",
+            "role1.json",
+            "role2.json",
+            "snapshot.json",
+            "targets.json",
+            "timestamp.json",
+        ]
+    );
+
+    // Loading again reads the trusted metadata back from the datastore.
+    load();
+}
+
+/// Test that a repository can be loaded with a `MemoryDatastore`, and its targets read.
+#[test]
+fn memory_datastore() {
+    let base = test_data().join("tuf-reference-impl");
+    let datastore = MemoryDatastore::new();
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .datastore_backend(datastore.clone())
+    .load()
+    .unwrap();
+
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This is an example target file."[..]
+    );
+    let timestamp: serde_json::Value =
+        serde_json::from_slice(&datastore.read("timestamp.json").unwrap().unwrap()).unwrap();
+    assert_eq!(timestamp["signed"]["version"], 1);
+
+    datastore.delete("timestamp.json").unwrap();
+    assert!(datastore.read("timestamp.json").unwrap().is_none());
+}
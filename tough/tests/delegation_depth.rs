@@ -0,0 +1,87 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::error::{Error, Result};
+use tough::{Limits, Repository, RepositoryLoader};
+
+// The reference implementation delegates from targets to role1, and from role1 to role2.
+fn load_with_max_delegation_depth(max_delegation_depth: usize) -> Result<Repository> {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .limits(
+        Limits::builder()
+            .max_delegation_depth(max_delegation_depth)
+            .build(),
+    )
+    .load()
+}
+
+/// Test that a delegation chain deeper than `max_delegation_depth` is rejected.
+#[test]
+fn max_delegation_depth_exceeded() {
+    let result = load_with_max_delegation_depth(1);
+    assert!(matches!(
+        result,
+        Err(Error::MaxDelegationDepthExceeded {
+            ref name,
+            max_delegation_depth: 1,
+            ..
+        }) if name == "role2"
+    ));
+}
+
+/// Test that a delegation chain as deep as `max_delegation_depth` is loaded.
+#[test]
+fn max_delegation_depth_reached() {
+    let repo = load_with_max_delegation_depth(2).unwrap();
+    assert!(repo.delegated_role("role2").is_some());
+}
+
+// The delegation-cycle repository delegates from targets to role1, from role1 to role2, and from
+// role2 back to role1. role1 lists file1.txt.
+fn delegation_cycle_loader() -> RepositoryLoader<File> {
+    let base = test_data().join("delegation-cycle");
+    RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+}
+
+/// Test that loading the whole delegation tree rejects a cycle.
+#[test]
+fn delegation_cycle() {
+    let result = delegation_cycle_loader().load();
+    assert!(matches!(
+        result,
+        Err(Error::DelegationCycle { ref parent, ref name, .. })
+            if parent == "role2" && name == "role1"
+    ));
+}
+
+/// Test that with lazy delegated roles, a search that reaches the cycle is rejected, and a target
+/// found before it is not.
+#[test]
+fn delegation_cycle_lazy() {
+    let repo = delegation_cycle_loader()
+        .lazy_delegated_roles(true)
+        .load()
+        .unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"Signed by role1.\n"[..]
+    );
+    assert!(matches!(
+        repo.read_target("missing.txt"),
+        Err(Error::DelegationCycle { ref parent, ref name, .. })
+            if parent == "role2" && name == "role1"
+    ));
+}
@@ -0,0 +1,33 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::schema::key::Key;
+use tough::RepositoryLoader;
+
+/// Test a repository whose metadata is signed with an `ed25519` key.
+#[test]
+fn ed25519_signed_repository() {
+    let base = test_data().join("ed25519");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let keys = &repo.root().signed.keys;
+    assert_eq!(keys.len(), 1);
+    for (keyid, key) in keys {
+        assert!(matches!(key, Key::Ed25519 { .. }));
+        assert_eq!(&key.key_id().unwrap(), keyid);
+    }
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This repository is signed with an Ed25519 key.\n"[..]
+    );
+}
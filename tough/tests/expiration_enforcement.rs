@@ -1,11 +1,13 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::fs::File;
 use test_utils::{dir_url, test_data};
 use tough::error::Error::ExpiredMetadata;
+use tough::error::Result;
 use tough::schema::RoleType;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use tough::{ExpirationEnforcement, FixedClock, Repository, RepositoryLoader};
 
 mod test_utils;
 
@@ -51,3 +53,31 @@ fn test_expiration_enforcement_unsafe() {
     .load();
     assert!(result.is_ok())
 }
+
+/// Loads `tuf-reference-impl`, all of whose metadata expires at 2030-01-01T00:00:00Z, as if the
+/// current time were `now`.
+fn load_reference_impl_at(now: DateTime<Utc>) -> Result<Repository> {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        File::open(base.join("metadata").join("root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .clock(FixedClock(now))
+    .load()
+}
+
+/// Test that the expiration boundary is checked against the loader's clock: a repository that
+/// expires one second from now loads, and one that expired one second ago does not.
+#[test]
+fn test_expiration_enforcement_clock() {
+    let expires = Utc.ymd(2030, 1, 1).and_hms(0, 0, 0);
+
+    let repo = load_reference_impl_at(expires - Duration::seconds(1)).unwrap();
+    assert!(repo.read_target("file1.txt").unwrap().is_some());
+
+    assert!(matches!(
+        load_reference_impl_at(expires + Duration::seconds(1)),
+        Err(ExpiredMetadata { .. })
+    ));
+}
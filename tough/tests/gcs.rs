@@ -0,0 +1,129 @@
+#![cfg(feature = "gcs")]
+
+use mockito::{mock, Matcher};
+use std::str::FromStr;
+use tempfile::TempDir;
+use test_utils::{read_to_end, test_data};
+use tough::{GcsTransport, HttpTransportBuilder, Transport, TransportErrorKind};
+use url::Url;
+
+mod test_utils;
+
+fn endpoint() -> Url {
+    Url::from_str(&format!("{}/", mockito::server_url())).unwrap()
+}
+
+#[test]
+fn gcs_transport_anonymous() {
+    let mock_object = mock("GET", "/my-repo/metadata/timestamp.json")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(200)
+        .with_body("timestamp")
+        .create();
+
+    let transport = GcsTransport::anonymous().endpoint(endpoint());
+    let reader = transport
+        .fetch(Url::parse("gs://my-repo/metadata/timestamp.json").unwrap())
+        .unwrap();
+    assert_eq!(read_to_end(reader), b"timestamp");
+
+    mock_object.assert();
+}
+
+#[test]
+fn gcs_transport_file_not_found() {
+    let mock_object = mock("GET", "/my-repo/metadata/2.root.json")
+        .with_status(404)
+        .create();
+
+    let transport = GcsTransport::anonymous()
+        .endpoint(endpoint())
+        .http_settings(HttpTransportBuilder::new().tries(1));
+    let url = Url::parse("gs://my-repo/metadata/2.root.json").unwrap();
+    let err = transport.fetch(url.clone()).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::FileNotFound));
+    assert_eq!(err.url(), url.as_str());
+
+    mock_object.assert();
+}
+
+/// Objects are checked for with a `HEAD` request, so nothing is downloaded.
+#[test]
+fn gcs_transport_exists() {
+    let mock_missing = mock("HEAD", "/my-repo/metadata/3.root.json")
+        .with_status(404)
+        .create();
+    let mock_present = mock("HEAD", "/my-repo/metadata/1.root.json")
+        .with_status(200)
+        .create();
+
+    let transport = GcsTransport::anonymous()
+        .endpoint(endpoint())
+        .http_settings(HttpTransportBuilder::new().tries(1));
+    assert!(transport.exists_is_cheap());
+    assert!(!transport
+        .exists(Url::parse("gs://my-repo/metadata/3.root.json").unwrap())
+        .unwrap());
+    assert!(transport
+        .exists(Url::parse("gs://my-repo/metadata/1.root.json").unwrap())
+        .unwrap());
+
+    mock_missing.assert();
+    mock_present.assert();
+}
+
+#[test]
+fn gcs_transport_unsupported_scheme() {
+    let transport = GcsTransport::anonymous();
+    let err = transport
+        .fetch(Url::parse("s3://my-repo/metadata/timestamp.json").unwrap())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err.kind(),
+        TransportErrorKind::UnsupportedUrlScheme
+    ));
+}
+
+/// Test that a service account's access token is requested once and sent with each fetch.
+#[test]
+fn gcs_transport_service_account() {
+    let token_uri = format!("{}/token", mockito::server_url());
+    let credentials = serde_json::json!({
+        "type": "service_account",
+        "client_email": "tough@example.iam.gserviceaccount.com",
+        "private_key": std::fs::read_to_string(test_data().join("snakeoil_2.pem")).unwrap(),
+        "token_uri": token_uri,
+    });
+    let dir = TempDir::new().unwrap();
+    let credentials_path = dir.path().join("credentials.json");
+    std::fs::write(&credentials_path, credentials.to_string()).unwrap();
+
+    let mock_token = mock("POST", "/token")
+        .match_body(Matcher::UrlEncoded(
+            "grant_type".into(),
+            "urn:ietf:params:oauth:grant-type:jwt-bearer".into(),
+        ))
+        .with_status(200)
+        .with_body(r#"{"access_token":"abc123","expires_in":3600,"token_type":"Bearer"}"#)
+        .expect(1)
+        .create();
+    let mock_object = mock("GET", "/my-repo/metadata/snapshot.json")
+        .match_header("authorization", "Bearer abc123")
+        .with_status(200)
+        .with_body("snapshot")
+        .expect(2)
+        .create();
+
+    let transport = GcsTransport::from_credentials_file(credentials_path).endpoint(endpoint());
+    let url = Url::parse("gs://my-repo/metadata/snapshot.json").unwrap();
+    for _ in 0..2 {
+        assert_eq!(
+            read_to_end(transport.fetch(url.clone()).unwrap()),
+            b"snapshot"
+        );
+    }
+
+    mock_token.assert();
+    mock_object.assert();
+}
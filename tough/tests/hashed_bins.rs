@@ -0,0 +1,38 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::RepositoryLoader;
+
+/// Test a repository where `targets` delegates to hashed bins with `path_hash_prefixes`: `00-7f`
+/// for targets whose SHA-256 name digest starts with `0` to `7`, and `80-ff` for the rest.
+#[test]
+fn hashed_bins() {
+    let base = test_data().join("hashed-bins");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    // sha256("a.txt") starts with 18, and sha256("b.txt") with ff.
+    assert_eq!(
+        read_to_end(repo.read_target("a.txt").unwrap().unwrap()),
+        &b"a.txt is in bin 00-7f.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("a.txt"), Some("00-7f"));
+    assert_eq!(
+        read_to_end(repo.read_target("b.txt").unwrap().unwrap()),
+        &b"b.txt is in bin 80-ff.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("b.txt"), Some("80-ff"));
+
+    // sha256("file3.txt") starts with 45, so it is not found in 80-ff, which lists it.
+    assert!(repo.read_target("file3.txt").unwrap().is_none());
+    assert_eq!(repo.role_for_target("file3.txt"), None);
+}
@@ -4,14 +4,22 @@ mod test_utils;
 #[cfg(feature = "http")]
 mod http_happy {
     use crate::test_utils::{read_to_end, test_data};
-    use mockito::mock;
+    use mockito::{mock, Matcher};
     use std::fs::File;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
     use std::str::FromStr;
-    use tough::{DefaultTransport, HttpTransport, RepositoryLoader, Transport};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tough::{
+        DefaultTransport, HttpTransport, HttpTransportBuilder, RedirectPolicy, RepositoryLoader,
+        RetryEvent, RetryReason, Transport, TransportErrorKind,
+    };
     use url::Url;
 
     /// Create a path in a mock HTTP server which serves a file from `tuf-reference-impl`.
-    fn create_successful_get_mock(relative_path: &str) -> mockito::Mock {
+    pub(crate) fn create_successful_get_mock(relative_path: &str) -> mockito::Mock {
         let repo_dir = test_data().join("tuf-reference-impl");
         let file_bytes = std::fs::read(&repo_dir.join(relative_path)).unwrap();
         mock("GET", ("/".to_owned() + relative_path).as_str())
@@ -81,6 +89,612 @@ mod http_happy {
         mock_file1_txt.assert();
         mock_file2_txt.assert();
     }
+
+    /// Test that a 429 response is retried after the delay given by its `Retry-After` header.
+    #[test]
+    fn test_http_retry_after() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_throttled = mock("GET", "/metadata/snapshot.json")
+            .with_status(429)
+            .with_header("retry-after", "2")
+            .expect(2)
+            .create();
+
+        let transport = HttpTransportBuilder::new()
+            .tries(2)
+            .initial_backoff(Duration::from_millis(10))
+            .max_backoff(Duration::from_secs(5))
+            .build();
+        let start = Instant::now();
+        assert!(transport
+            .fetch(base_url.join("metadata/snapshot.json").unwrap())
+            .is_err());
+        assert!(start.elapsed() >= Duration::from_secs(2));
+
+        mock_throttled.assert();
+    }
+
+    /// Test that the `on_retry` callback is called for each retry.
+    #[test]
+    fn test_http_on_retry() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_unavailable = mock("GET", "/metadata/targets.json")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let events: Arc<Mutex<Vec<RetryEvent>>> = Arc::default();
+        let recorded = Arc::clone(&events);
+        let transport = HttpTransportBuilder::new()
+            .tries(3)
+            .initial_backoff(Duration::from_millis(10))
+            .on_retry(move |event| recorded.lock().unwrap().push(event.clone()))
+            .build();
+        let url = base_url.join("metadata/targets.json").unwrap();
+        assert!(transport.fetch(url.clone()).is_err());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        for (event, attempt) in events.iter().zip(2..) {
+            assert_eq!(event.url, url);
+            assert_eq!(event.attempt, attempt);
+            assert_eq!(event.reason, RetryReason::Status(503));
+        }
+        mock_unavailable.assert();
+    }
+
+    /// Test that a custom `User-Agent` is sent to the server.
+    #[test]
+    fn test_http_user_agent() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_user_agent = mock("GET", "/metadata/root.json")
+            .match_header("user-agent", "my-updater/1.0")
+            .with_status(200)
+            .with_body("{}")
+            .expect(1)
+            .create();
+
+        let transport = HttpTransportBuilder::new()
+            .user_agent("my-updater/1.0")
+            .build();
+        let read = transport
+            .fetch(base_url.join("metadata/root.json").unwrap())
+            .unwrap();
+        assert_eq!(read_to_end(read), b"{}");
+
+        mock_user_agent.assert();
+    }
+
+    /// Test that a response with a `Content-Length` over `max_response_bytes` fails right away.
+    #[test]
+    fn test_http_max_response_bytes_content_length() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_large = mock("GET", "/targets/large.bin")
+            .with_status(200)
+            .with_body(vec![0; 100])
+            .expect(1)
+            .create();
+
+        let transport = HttpTransportBuilder::new().max_response_bytes(50).build();
+        let err = transport
+            .fetch(base_url.join("targets/large.bin").unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+
+        mock_large.assert();
+    }
+
+    /// Test that a streamed response fails once more than `max_response_bytes` have been read.
+    #[test]
+    fn test_http_max_response_bytes_streamed() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        // a body written by a function is sent with chunked encoding, without a `Content-Length`.
+        let mock_endless = mock("GET", "/targets/endless.bin")
+            .with_status(200)
+            .with_body_from_fn(|w| {
+                for _ in 0..10 {
+                    w.write_all(&[0; 1024])?;
+                }
+                Ok(())
+            })
+            .expect(1)
+            .create();
+
+        let transport = HttpTransportBuilder::new().max_response_bytes(4096).build();
+        let mut read = transport
+            .fetch(base_url.join("targets/endless.bin").unwrap())
+            .unwrap();
+        let mut buf = Vec::new();
+        assert!(read.read_to_end(&mut buf).is_err());
+        assert!(buf.len() <= 4096 + 1024);
+
+        mock_endless.assert();
+    }
+
+    /// Test that `RetryRead::bytes_read` reports how much of the response has been read.
+    #[test]
+    fn test_http_bytes_read() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_file = mock("GET", "/targets/progress.bin")
+            .with_status(200)
+            .with_body(&[7; 3000][..])
+            .expect(1)
+            .create();
+
+        let mut read = HttpTransport::default()
+            .fetch_retry_read(base_url.join("targets/progress.bin").unwrap())
+            .unwrap();
+        assert_eq!(read.bytes_read(), 0);
+        let mut buf = [0; 1000];
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!(read.bytes_read(), 1000);
+        let mut rest = Vec::new();
+        read.read_to_end(&mut rest).unwrap();
+        assert_eq!(read.bytes_read(), 3000);
+
+        mock_file.assert();
+    }
+
+    /// Test that a gzip-compressed response is decompressed when `accept_compression` is set.
+    #[test]
+    fn test_http_accept_compression() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let compressed = std::fs::read(test_data().join("gzip").join("timestamp.json.gz")).unwrap();
+        let mock_gzip = mock("GET", "/metadata/timestamp.json")
+            .match_header("accept-encoding", Matcher::Regex("gzip".to_string()))
+            .with_status(200)
+            .with_header("content-encoding", "gzip")
+            .with_body(compressed)
+            .expect(1)
+            .create();
+
+        let transport = HttpTransportBuilder::new().accept_compression(true).build();
+        let read = transport
+            .fetch(base_url.join("metadata/timestamp.json").unwrap())
+            .unwrap();
+        let expected = std::fs::read(
+            test_data()
+                .join("tuf-reference-impl")
+                .join("metadata")
+                .join("timestamp.json"),
+        )
+        .unwrap();
+        assert_eq!(read_to_end(read), expected);
+
+        mock_gzip.assert();
+    }
+
+    /// Serves `body` for every request, keeping connections alive, and counts the connections.
+    fn start_counting_server(body: &'static [u8]) -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    loop {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => return,
+                            Ok(size) => request.extend_from_slice(&buf[..size]),
+                        }
+                        // each GET request ends with a blank line.
+                        while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            request.drain(..end + 4);
+                            let header = format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                                body.len()
+                            );
+                            stream.write_all(header.as_bytes()).unwrap();
+                            stream.write_all(body).unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    /// Serves `body` in two parts: the first response claims the whole body but is cut off halfway
+    /// through, and a range request for the rest is answered with the rest.
+    fn start_truncating_server(body: &'static [u8]) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(size) => request.extend_from_slice(&buf[..size]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let half = body.len() / 2;
+                if let Some(start) = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+                {
+                    let header = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\
+                         Content-Range: bytes {}-{}/{}\r\n\r\n",
+                        body.len() - start,
+                        start,
+                        body.len() - 1,
+                        body.len()
+                    );
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&body[start..]).unwrap();
+                } else {
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&body[..half]).unwrap();
+                }
+            }
+        });
+        url
+    }
+
+    /// Test that the pause before retrying a read that failed partway through is jittered, like
+    /// the pause before retrying a fetch.
+    #[test]
+    fn test_http_read_retry_jitter() {
+        let base_url = start_truncating_server(b"helloworld");
+        let events: Arc<Mutex<Vec<RetryEvent>>> = Arc::default();
+        let recorded = Arc::clone(&events);
+        let transport = HttpTransportBuilder::new()
+            .initial_backoff(Duration::from_millis(20))
+            .jitter_factor(0.5)
+            .jitter_source(|| 1.0)
+            .on_retry(move |event| recorded.lock().unwrap().push(event.clone()))
+            .build();
+        let read = transport
+            .fetch(base_url.join("targets/file1.txt").unwrap())
+            .unwrap();
+        assert_eq!(read_to_end(read), b"helloworld");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason, RetryReason::Read);
+        assert_eq!(events[0].wait, Duration::from_millis(30));
+    }
+
+    /// Test that connections are reused across fetches made with the same transport.
+    #[test]
+    fn test_http_connection_reuse() {
+        let (base_url, connections) = start_counting_server(b"hello");
+        let transport = HttpTransport::default();
+        for _ in 0..3 {
+            let read = transport
+                .fetch(base_url.join("targets/file1.txt").unwrap())
+                .unwrap();
+            assert_eq!(read_to_end(read), b"hello");
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    /// Creates a mock at `from` that redirects to `to` on the mock server.
+    fn create_redirect_mock(from: &str, to: &str) -> mockito::Mock {
+        let location = Url::from_str(mockito::server_url().as_str())
+            .unwrap()
+            .join(to)
+            .unwrap();
+        mock("GET", from)
+            .with_status(302)
+            .with_header("location", location.as_str())
+            .create()
+    }
+
+    /// Test that a redirect is an error when redirects are disabled.
+    #[test]
+    fn test_http_redirects_disabled() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let _mock_redirect = create_redirect_mock("/disabled/start.json", "/disabled/end.json");
+        let mock_end = mock("GET", "/disabled/end.json")
+            .with_status(200)
+            .expect(0)
+            .create();
+
+        let transport = HttpTransportBuilder::new()
+            .redirect_policy(RedirectPolicy::None)
+            .build();
+        let err = transport
+            .fetch(base_url.join("disabled/start.json").unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+
+        mock_end.assert();
+    }
+
+    /// Test that the number of redirects that are followed is limited.
+    #[test]
+    fn test_http_redirects_limited() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let _mock_first = create_redirect_mock("/limited/first.json", "/limited/second.json");
+        let _mock_second = create_redirect_mock("/limited/second.json", "/limited/end.json");
+        let _mock_end = mock("GET", "/limited/end.json")
+            .with_status(200)
+            .with_body("end")
+            .create();
+        let url = base_url.join("limited/first.json").unwrap();
+
+        let transport = HttpTransportBuilder::new()
+            .redirect_policy(RedirectPolicy::Limited(1))
+            .build();
+        assert!(transport.fetch(url.clone()).is_err());
+
+        let transport = HttpTransportBuilder::new()
+            .redirect_policy(RedirectPolicy::Limited(2))
+            .build();
+        assert_eq!(read_to_end(transport.fetch(url).unwrap()), b"end");
+    }
+
+    /// Test that a redirect to a different host is rejected, without retries, when
+    /// `restrict_redirects_to_same_host` is enabled.
+    #[test]
+    fn test_http_cross_host_redirect_rejected() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mut other_host = base_url.join("metadata/timestamp.json").unwrap();
+        other_host.set_host(Some("example.com")).unwrap();
+        let mock_redirect = mock("GET", "/metadata/timestamp.json")
+            .with_status(302)
+            .with_header("location", other_host.as_str())
+            .expect(1)
+            .create();
+
+        let transport = HttpTransportBuilder::new()
+            .restrict_redirects_to_same_host(true)
+            .build();
+        let err = transport
+            .fetch(base_url.join("metadata/timestamp.json").unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+        assert!(format!("{:?}", err).contains("is not allowed"));
+
+        mock_redirect.assert();
+    }
+
+    /// Test that `exists` sends a `HEAD` request rather than downloading the file.
+    #[test]
+    fn test_http_exists() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_head = mock("HEAD", "/exists/targets.json")
+            .with_status(200)
+            .expect(1)
+            .create();
+        let mock_get = mock("GET", "/exists/targets.json").expect(0).create();
+        let _mock_missing = mock("HEAD", "/exists/missing.json")
+            .with_status(404)
+            .create();
+        let _mock_error = mock("HEAD", "/exists/error.json").with_status(400).create();
+
+        let transport = HttpTransportBuilder::new().tries(1).build();
+        assert!(transport
+            .exists(base_url.join("exists/targets.json").unwrap())
+            .unwrap());
+        assert!(!transport
+            .exists(base_url.join("exists/missing.json").unwrap())
+            .unwrap());
+        let err = transport
+            .exists(base_url.join("exists/error.json").unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+
+        mock_head.assert();
+        mock_get.assert();
+    }
+}
+
+/// Tests of the async transport and repository load path.
+#[cfg(feature = "async")]
+mod http_async {
+    use crate::http_happy::create_successful_get_mock;
+    use crate::test_utils::{read_to_end, test_data};
+    use mockito::mock;
+    use std::fs::File;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tough::{
+        AsyncHttpTransport, AsyncTransport, HttpTransportBuilder, RepositoryLoader,
+        TransportErrorKind,
+    };
+    use url::Url;
+
+    /// Test that a repository can be loaded with `load_async` and an `AsyncHttpTransport`.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_http_load() {
+        let repo_dir = test_data().join("tuf-reference-impl");
+        let mock_timestamp = create_successful_get_mock("metadata/timestamp.json");
+        let mock_snapshot = create_successful_get_mock("metadata/snapshot.json");
+        let mock_targets = create_successful_get_mock("metadata/targets.json");
+        let mock_role1 = create_successful_get_mock("metadata/role1.json");
+        let mock_role2 = create_successful_get_mock("metadata/role2.json");
+        let mock_file1_txt = create_successful_get_mock("targets/file1.txt");
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let repo = RepositoryLoader::new(
+            File::open(repo_dir.join("metadata").join("1.root.json")).unwrap(),
+            base_url.join("metadata").unwrap(),
+            base_url.join("targets").unwrap(),
+        )
+        .async_transport(AsyncHttpTransport::default())
+        .load_async()
+        .await
+        .unwrap();
+
+        // reading a target blocks, so the runtime is told to move other tasks off this thread.
+        let file1 = tokio::task::block_in_place(|| {
+            read_to_end(repo.read_target("file1.txt").unwrap().unwrap())
+        });
+        assert_eq!(file1, &b"This is an example target file."[..]);
+
+        mock_timestamp.assert();
+        mock_snapshot.assert();
+        mock_targets.assert();
+        mock_role1.assert();
+        mock_role2.assert();
+        mock_file1_txt.assert();
+    }
+
+    /// Test that a file can be read from an `AsyncHttpTransport`.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_http_fetch() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_file = mock("GET", "/async/file.txt")
+            .with_status(200)
+            .with_body("async file")
+            .create();
+
+        let transport = AsyncHttpTransport::default();
+        let mut reader = transport
+            .fetch(base_url.join("async/file.txt").unwrap())
+            .await
+            .unwrap();
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "async file");
+
+        mock_file.assert();
+    }
+
+    /// Test that a 404 is `FileNotFound`.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_http_file_not_found() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_missing = mock("GET", "/async/missing.txt").with_status(404).create();
+
+        let err = AsyncHttpTransport::default()
+            .fetch(base_url.join("async/missing.txt").unwrap())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::FileNotFound));
+
+        mock_missing.assert();
+    }
+
+    /// Test that a server error is retried per the settings.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_http_retries() {
+        let base_url = Url::from_str(mockito::server_url().as_str()).unwrap();
+        let mock_unavailable = mock("GET", "/async/unavailable.txt")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let retries = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&retries);
+        let transport = HttpTransportBuilder::new()
+            .tries(3)
+            .initial_backoff(Duration::from_millis(10))
+            .on_retry(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build_async();
+        let err = transport
+            .fetch(base_url.join("async/unavailable.txt").unwrap())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), TransportErrorKind::Other));
+        assert_eq!(retries.load(Ordering::SeqCst), 2);
+
+        mock_unavailable.assert();
+    }
+}
+
+/// Tests that need a TLS backend.
+#[cfg(feature = "native-tls")]
+mod http_tls {
+    use crate::test_utils::{read_to_end, test_data};
+    use native_tls::{Identity, TlsAcceptor};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use tough::{HttpTransportBuilder, Transport};
+    use url::Url;
+
+    /// Serves `body` over TLS, with a certificate signed by the test CA, for every request.
+    fn start_tls_server(body: &'static [u8]) -> Url {
+        let tls_dir = test_data().join("tls");
+        let identity =
+            Identity::from_pkcs12(&std::fs::read(tls_dir.join("server.p12")).unwrap(), "tough")
+                .unwrap();
+        let acceptor = Arc::new(TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let acceptor = Arc::clone(&acceptor);
+                std::thread::spawn(move || {
+                    // the handshake fails when the client does not trust the test CA.
+                    let mut stream = match acceptor.accept(stream.unwrap()) {
+                        Ok(stream) => stream,
+                        Err(_) => return,
+                    };
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => return,
+                            Ok(size) => request.extend_from_slice(&buf[..size]),
+                        }
+                    }
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(header.as_bytes());
+                    let _ = stream.write_all(body);
+                });
+            }
+        });
+        Url::parse(&format!("https://localhost:{}/", port)).unwrap()
+    }
+
+    /// Test that a server with a certificate from a private CA is only trusted when the CA
+    /// certificate is added.
+    #[test]
+    fn test_http_root_certificate() {
+        let base_url = start_tls_server(b"hello");
+        let url = base_url.join("metadata/timestamp.json").unwrap();
+
+        let untrusted = HttpTransportBuilder::new().tries(1).build();
+        assert!(untrusted.fetch(url.clone()).is_err());
+
+        let ca = std::fs::read(test_data().join("tls").join("ca.pem")).unwrap();
+        let trusted = HttpTransportBuilder::new()
+            .tries(1)
+            .root_certificate(ca)
+            .build();
+        assert_eq!(read_to_end(trusted.fetch(url).unwrap()), b"hello");
+    }
+
+    /// Test that a certificate that cannot be read results in an error.
+    #[test]
+    fn test_http_invalid_root_certificate() {
+        let transport = HttpTransportBuilder::new()
+            .root_certificate(b"-----BEGIN CERTIFICATE-----".to_vec())
+            .build();
+        let url = Url::parse("https://localhost/metadata/timestamp.json").unwrap();
+        assert!(transport.fetch(url).is_err());
+    }
 }
 
 #[cfg(feature = "http")]
@@ -1,10 +1,19 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use chrono::{TimeZone, Utc};
 use std::fs::File;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 use test_utils::{dir_url, read_to_end, test_data};
-use tough::{FilesystemTransport, Limits, Repository, RepositoryLoader};
+use tough::error::Error;
+use tough::schema::RoleType;
+use tough::{
+    Expirations, FilesystemTransport, Limits, Repository, RepositoryLoader, Transport,
+    TransportError,
+};
+use url::Url;
 
 mod test_utils;
 
@@ -26,6 +35,85 @@ fn test_tuf_reference_impl() {
     assert_tuf_reference_impl(&repo);
 }
 
+/// Test that `tough` fetches versioned metadata (`N.role.json`) and hash-prefixed targets from a
+/// repository whose root sets `consistent_snapshot`. The fixture has no unversioned metadata
+/// files other than `timestamp.json`, and no unprefixed targets.
+#[test]
+fn test_consistent_snapshot() {
+    let base = test_data().join("consistent-snapshot");
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+    assert!(repo.root().signed.consistent_snapshot);
+    assert!(repo.consistent_snapshot());
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This target is listed by the targets role.\n"[..]
+    );
+    assert_eq!(
+        read_to_end(repo.read_target("file2.txt").unwrap().unwrap()),
+        &b"This target is listed by role1.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("file2.txt"), Some("role1"));
+}
+
+/// Test that targets whose names have spaces or URL delimiters are fetched by name, and are saved
+/// under their names by `cache`.
+#[test]
+fn test_target_names_with_special_characters() {
+    let base = test_data().join("consistent-snapshot");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let names: &[(&str, &[u8])] = &[
+        ("my file.txt", b"This target has a space in its name.\n"),
+        (
+            "50% off #1.txt",
+            b"This target has URL delimiters in its name.\n",
+        ),
+    ];
+    let outdir = TempDir::new().unwrap();
+    for (name, contents) in names {
+        assert_eq!(
+            read_to_end(repo.read_target(name).unwrap().unwrap()),
+            *contents
+        );
+        repo.save_target(name, outdir.path().join(name)).unwrap();
+        assert_eq!(std::fs::read(outdir.path().join(name)).unwrap(), *contents);
+    }
+
+    let targets_outdir = outdir.path().join("targets");
+    repo.cache(
+        outdir.path().join("metadata"),
+        &targets_outdir,
+        Some(&["my file.txt"]),
+        false,
+    )
+    .unwrap();
+    let digest = hex::encode(
+        repo.target_hashes("my file.txt")
+            .unwrap()
+            .unwrap()
+            .sha256
+            .as_ref()
+            .unwrap(),
+    );
+    assert_eq!(
+        std::fs::read(targets_outdir.join(format!("{}.my file.txt", digest))).unwrap(),
+        names[0].1
+    );
+}
+
 fn assert_tuf_reference_impl(repo: &Repository) {
     assert_eq!(
         read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
@@ -46,6 +134,20 @@ fn assert_tuf_reference_impl(repo: &Repository) {
             .unwrap(),
         "0644"
     );
+    assert_eq!(
+        repo.target_custom::<String>("file1.txt", "file_permissions")
+            .unwrap(),
+        Some("0644".to_owned())
+    );
+    assert_eq!(
+        repo.target_custom::<String>("file1.txt", "missing")
+            .unwrap(),
+        None
+    );
+    assert!(matches!(
+        repo.target_custom::<u32>("file1.txt", "file_permissions"),
+        Err(tough::error::Error::TargetCustom { .. })
+    ));
 
     assert!(repo
         .targets()
@@ -54,6 +156,158 @@ fn assert_tuf_reference_impl(repo: &Repository) {
         .as_ref()
         .unwrap()
         .target_is_delegated(&"file3.txt".to_string()));
+
+    assert_eq!(
+        repo.read_target_to_vec("file3.txt").unwrap().unwrap(),
+        &b"This is role1's target file."[..]
+    );
+    assert!(repo.read_target_to_vec("file4.txt").unwrap().is_none());
+
+    assert!(repo.target_exists("file1.txt"));
+    assert!(repo.target_exists("file3.txt"));
+    assert!(!repo.target_exists("file4.txt"));
+
+    assert_eq!(repo.target_length("file1.txt").unwrap(), Some(31));
+    assert_eq!(repo.target_length("file3.txt").unwrap(), Some(28));
+    assert_eq!(repo.target_length("file4.txt").unwrap(), None);
+
+    assert_eq!(
+        hex::encode(
+            repo.target_hashes("file1.txt")
+                .unwrap()
+                .unwrap()
+                .sha256
+                .as_ref()
+                .unwrap()
+        ),
+        "65b8c67f51c993d898250f40aa57a317d854900b3a04895464313e48785440da"
+    );
+    assert!(repo.target_hashes("file4.txt").unwrap().is_none());
+
+    assert_eq!(repo.timestamp().signed.version.get(), 1);
+    assert_eq!(
+        repo.timestamp().signed.expires.to_rfc3339(),
+        "2030-01-01T00:00:00+00:00"
+    );
+    assert_eq!(repo.snapshot().signed.version.get(), 1);
+    assert!(!repo.consistent_snapshot());
+
+    assert_eq!(
+        repo.target_names(),
+        vec!["file1.txt", "file2.txt", "file3.txt"]
+    );
+
+    let roles: Vec<_> = repo
+        .delegated_roles()
+        .iter()
+        .map(|role| role.name.as_str())
+        .collect();
+    assert_eq!(roles, vec!["role1", "role2"]);
+    assert_eq!(repo.role_for_target("file1.txt"), Some("targets"));
+    assert_eq!(repo.role_for_target("file3.txt"), Some("role1"));
+    assert_eq!(repo.role_for_target("file4.txt"), None);
+}
+
+/// Test that enabling mirrors falls back to the base URLs when the repository has no mirrors.json.
+#[test]
+fn test_tuf_reference_impl_no_mirrors() {
+    let base = test_data().join("tuf-reference-impl");
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .mirrors(true)
+    .load()
+    .unwrap();
+    assert!(repo.mirrors().is_none());
+    assert_tuf_reference_impl(&repo);
+}
+
+/// A `Transport` that serves `https://<host>/<path>` from `<path>` in the directory named after
+/// `<host>` in the mirrors fixture, and records the URLs that it fetches.
+#[derive(Debug, Clone, Default)]
+struct MirrorHostsTransport {
+    fetched: Arc<Mutex<Vec<String>>>,
+}
+
+impl Transport for MirrorHostsTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        self.fetched.lock().unwrap().push(url.to_string());
+        let path = test_data()
+            .join("mirrors")
+            .join(url.host_str().unwrap())
+            .join(url.path().trim_start_matches('/'));
+        FilesystemTransport::new().fetch(Url::from_file_path(path).unwrap())
+    }
+}
+
+fn mirrors_loader(metadata_host: &str) -> RepositoryLoader<File> {
+    RepositoryLoader::new(
+        File::open(
+            test_data()
+                .join("mirrors")
+                .join("origin.example.com")
+                .join("metadata")
+                .join("1.root.json"),
+        )
+        .unwrap(),
+        Url::parse(&format!("https://{}/metadata/", metadata_host)).unwrap(),
+        Url::parse("https://origin.example.com/targets/").unwrap(),
+    )
+    .mirrors(true)
+}
+
+/// Test a repository whose mirrors.json, signed with the mirrors key in root.json, lists two
+/// mirrors. The origin only serves root.json and mirrors.json, so everything else must be fetched
+/// from the mirrors. Only the first mirror serves metadata, and only the second has the target,
+/// so fetching the target falls back from the first mirror to the second.
+#[test]
+fn test_mirrors() {
+    let transport = MirrorHostsTransport::default();
+    let repo = mirrors_loader("origin.example.com")
+        .transport(transport.clone())
+        .load()
+        .unwrap();
+
+    let mirrors = &repo.mirrors().unwrap().signed.mirrors;
+    assert_eq!(mirrors.len(), 2);
+    assert_eq!(mirrors[0].urlbase, "https://mirror1.example.com/");
+    assert_eq!(mirrors[1].urlbase, "https://mirror2.example.com/");
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"Served by mirror2.\n"[..]
+    );
+
+    let fetched = transport.fetched.lock().unwrap();
+    for url in &[
+        "https://origin.example.com/metadata/mirrors.json",
+        "https://mirror1.example.com/metadata/timestamp.json",
+        "https://mirror1.example.com/metadata/snapshot.json",
+        "https://mirror1.example.com/metadata/targets.json",
+    ] {
+        assert!(fetched.iter().any(|fetched| fetched == url), "{}", url);
+    }
+    assert!(fetched.ends_with(&[
+        "https://mirror1.example.com/targets/file1.txt".to_owned(),
+        "https://mirror2.example.com/targets/file1.txt".to_owned(),
+    ]));
+}
+
+/// Test that a mirrors.json that isn't signed with the mirrors key in root.json is rejected.
+#[test]
+fn test_mirrors_wrong_key() {
+    let result = mirrors_loader("wrong-key.example.com")
+        .transport(MirrorHostsTransport::default())
+        .load();
+    assert!(matches!(
+        result,
+        Err(Error::VerifyMetadata {
+            role: RoleType::Mirrors,
+            ..
+        })
+    ));
 }
 
 /// Test that `tough` can process repositories generated by [`tuf`], the reference Python
@@ -68,15 +322,272 @@ fn test_tuf_reference_impl_default_transport() {
         dir_url(base.join("metadata")),
         dir_url(base.join("targets")),
     )
-    .transport(FilesystemTransport)
-    .limits(Limits {
-        max_root_size: 1000,
-        max_targets_size: 2000,
-        max_timestamp_size: 3000,
-        max_root_updates: 1,
-    })
+    .transport(FilesystemTransport::new())
+    .limits(
+        Limits::builder()
+            .max_root_size(1000)
+            .max_targets_size(2000)
+            .max_timestamp_size(3000)
+            .max_snapshot_size(4000)
+            .max_root_updates(1)
+            .max_delegation_depth(2)
+            .max_mirrors_size(5000)
+            .build(),
+    )
     .datastore(datastore.path())
     .load()
     .unwrap();
     assert_tuf_reference_impl(&repo);
 }
+
+/// Test that `read_target_to_vec` returns an error, rather than the data, for a target that is
+/// larger than its signed length.
+#[test]
+fn test_read_target_to_vec_too_large() {
+    let base = test_data().join("tuf-reference-impl");
+    let targets = TempDir::new().unwrap();
+    std::fs::write(
+        targets.path().join("file1.txt"),
+        "This is an example target file, with more data.",
+    )
+    .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(targets.path()),
+    )
+    .load()
+    .unwrap();
+    assert!(matches!(
+        repo.read_target_to_vec("file1.txt"),
+        Err(tough::error::Error::TargetRead { .. })
+    ));
+}
+
+/// Returns the `tough` error inside an error from a target reader.
+fn target_read_error(err: tough::error::Error) -> tough::error::Error {
+    match err {
+        tough::error::Error::TargetRead { source, .. } => *source
+            .into_inner()
+            .unwrap()
+            .downcast::<tough::error::Error>()
+            .unwrap(),
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+/// Test that a target that is shorter or longer than its signed length is rejected for its length,
+/// rather than only for its hash.
+#[test]
+fn test_read_target_length_mismatch() {
+    let base = test_data().join("tuf-reference-impl");
+    let targets = TempDir::new().unwrap();
+    // file1.txt is 31 bytes.
+    std::fs::write(
+        targets.path().join("file1.txt"),
+        "This is an example target",
+    )
+    .unwrap();
+    std::fs::write(
+        targets.path().join("file2.txt"),
+        "This is an another example target file.\0\0\0\0",
+    )
+    .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(targets.path()),
+    )
+    .load()
+    .unwrap();
+    assert!(matches!(
+        target_read_error(repo.read_target_to_vec("file1.txt").unwrap_err()),
+        tough::error::Error::LengthMismatch {
+            length: 31,
+            read: 25,
+            ..
+        }
+    ));
+    assert!(matches!(
+        target_read_error(repo.read_target_to_vec("file2.txt").unwrap_err()),
+        tough::error::Error::MaxSizeExceeded { max_size: 39, .. }
+    ));
+}
+
+/// Test that `save_target` saves a verified target, and leaves nothing behind for a missing target
+/// or one that does not match its metadata.
+#[test]
+fn test_save_target() {
+    let base = test_data().join("tuf-reference-impl");
+    let targets = TempDir::new().unwrap();
+    std::fs::copy(
+        base.join("targets").join("file2.txt"),
+        targets.path().join("file2.txt"),
+    )
+    .unwrap();
+    std::fs::write(
+        targets.path().join("file1.txt"),
+        "This is an example target file, with more data.",
+    )
+    .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(targets.path()),
+    )
+    .load()
+    .unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    let dest = outdir.path().join("saved.txt");
+    repo.save_target("file2.txt", &dest).unwrap();
+    assert_eq!(
+        std::fs::read(&dest).unwrap(),
+        &b"This is an another example target file."[..]
+    );
+    #[cfg(unix)]
+    test_utils::assert_default_permissions(&dest);
+
+    assert!(matches!(
+        repo.save_target("file4.txt", outdir.path().join("file4.txt")),
+        Err(tough::error::Error::SaveTargetNotFound { .. })
+    ));
+    assert!(matches!(
+        repo.save_target("file1.txt", outdir.path().join("file1.txt")),
+        Err(tough::error::Error::SaveTarget { .. })
+    ));
+    let saved: Vec<_> = std::fs::read_dir(outdir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(saved, vec!["saved.txt"]);
+}
+
+/// Test that `verify_metadata` succeeds for an untouched repository, and finds a delegated role
+/// that was tampered with after the repository was loaded.
+#[test]
+fn test_verify_metadata_tampered_delegated_role() {
+    let base = test_data().join("tuf-reference-impl");
+    let metadata = TempDir::new().unwrap();
+    for entry in std::fs::read_dir(base.join("metadata")).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, metadata.path().join(path.file_name().unwrap())).unwrap();
+    }
+
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(metadata.path()),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+    repo.verify_metadata().unwrap();
+
+    let role1_path = metadata.path().join("role1.json");
+    let role1 = std::fs::read_to_string(&role1_path).unwrap();
+    std::fs::write(
+        &role1_path,
+        role1.replace("\"length\": 28", "\"length\": 29"),
+    )
+    .unwrap();
+    assert!(matches!(
+        repo.verify_metadata(),
+        Err(tough::error::Error::VerifyDelegatedRole { name, .. }) if name == "role1"
+    ));
+}
+
+/// Test that the key IDs calculated by `Key::key_id` match the IDs that the reference
+/// implementation listed the keys under, in both root and delegations metadata.
+#[test]
+fn test_tuf_reference_impl_key_ids() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let root_keys = &repo.root().signed.keys;
+    assert_eq!(root_keys.len(), 4);
+    let delegation_keys = &repo.targets().signed.delegations.as_ref().unwrap().keys;
+    assert!(!delegation_keys.is_empty());
+    for (keyid, key) in root_keys.iter().chain(delegation_keys) {
+        assert_eq!(&key.key_id().unwrap(), keyid);
+    }
+}
+
+/// Test that `expirations` returns the expiration of each top-level role.
+#[test]
+fn test_tuf_reference_impl_expirations() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let expires = Utc.ymd(2030, 1, 1).and_hms(0, 0, 0);
+    let expirations = repo.expirations();
+    assert_eq!(
+        expirations,
+        Expirations {
+            root: expires,
+            timestamp: expires,
+            snapshot: expires,
+            targets: expires,
+        }
+    );
+    assert_eq!(expirations.earliest(), (expires, RoleType::Root));
+}
+
+/// Test that target names that could escape the targets base URL or an output directory are
+/// rejected by `read_target`, `save_target`, and `cache`.
+#[test]
+fn test_unsafe_target_names() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    for name in &[
+        "../targets/file1.txt",
+        "../../etc/passwd",
+        "/etc/passwd",
+        "..\\targets\\file1.txt",
+        "C:\\Windows\\win.ini",
+    ] {
+        assert!(
+            matches!(
+                repo.read_target(name),
+                Err(tough::error::Error::UnsafeTargetName { .. })
+            ),
+            "{}",
+            name
+        );
+        assert!(matches!(
+            repo.save_target(name, outdir.path().join("saved.txt")),
+            Err(tough::error::Error::UnsafeTargetName { .. })
+        ));
+        assert!(matches!(
+            repo.cache(
+                outdir.path().join("metadata"),
+                outdir.path().join("targets"),
+                Some(&[name]),
+                false,
+            ),
+            Err(tough::error::Error::UnsafeTargetName { .. })
+        ));
+    }
+}
@@ -0,0 +1,395 @@
+#![cfg(feature = "testing")]
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use test_utils::{read_to_end, test_data};
+use tough::error::Error;
+use tough::schema::key::Key;
+use tough::schema::{RoleType, SignatureVerifier};
+use tough::testing::MockTransport;
+use tough::{
+    FilesystemTransport, MeteredTransport, RepositoryLoader, Transport, TransportError,
+    TransportErrorKind,
+};
+use url::Url;
+
+mod test_utils;
+
+fn metadata_base_url() -> Url {
+    Url::parse("https://example.com/metadata/").unwrap()
+}
+
+fn targets_base_url() -> Url {
+    Url::parse("https://example.com/targets/").unwrap()
+}
+
+/// Returns a `MockTransport` that serves the tuf-reference-impl repository.
+fn reference_impl() -> MockTransport {
+    let base = test_data().join("tuf-reference-impl");
+    let mut files = HashMap::new();
+    for (dir, base_url) in &[
+        ("metadata", metadata_base_url()),
+        ("targets", targets_base_url()),
+    ] {
+        for entry in fs::read_dir(base.join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            files.insert(base_url.join(name).unwrap(), fs::read(&path).unwrap());
+        }
+    }
+    MockTransport::new(files)
+}
+
+fn root() -> Vec<u8> {
+    fs::read(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("1.root.json"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn mock_transport_load() {
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(reference_impl())
+        .load()
+        .unwrap();
+    let target = repo.read_target("file1.txt").unwrap().unwrap();
+    assert_eq!(
+        read_to_end(target),
+        b"This is an example target file.".to_vec()
+    );
+}
+
+/// The next root.json is checked for with `Transport::exists`, so it isn't fetched if it doesn't
+/// exist.
+#[test]
+fn mock_transport_next_root_not_fetched() {
+    let fetched: Arc<Mutex<Vec<Url>>> = Arc::default();
+    let recorded = Arc::clone(&fetched);
+    let transport = MeteredTransport::new(Box::new(reference_impl()), move |event| {
+        recorded.lock().unwrap().push(event.url.clone());
+    });
+    RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .load()
+        .unwrap();
+    let next_root = metadata_base_url().join("2.root.json").unwrap();
+    let fetched = fetched.lock().unwrap();
+    assert!(fetched.contains(&metadata_base_url().join("timestamp.json").unwrap()));
+    assert!(!fetched.contains(&next_root));
+}
+
+/// A transport that can only fetch files, so checking whether a file exists would download it.
+#[derive(Debug, Clone)]
+struct FetchOnlyTransport(MockTransport);
+
+impl Transport for FetchOnlyTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        self.0.fetch(url)
+    }
+}
+
+/// With a transport that doesn't override `Transport::exists`, the next root.json is fetched once,
+/// without checking that it exists first.
+#[test]
+fn mock_transport_next_root_fetched_once() {
+    let fetched: Arc<Mutex<Vec<Url>>> = Arc::default();
+    let recorded = Arc::clone(&fetched);
+    let transport = MeteredTransport::new(
+        Box::new(FetchOnlyTransport(reference_impl())),
+        move |event| {
+            recorded.lock().unwrap().push(event.url.clone());
+        },
+    );
+    assert!(!transport.exists_is_cheap());
+    RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .load()
+        .unwrap();
+    let next_root = metadata_base_url().join("2.root.json").unwrap();
+    let fetched = fetched.lock().unwrap();
+    assert_eq!(fetched.iter().filter(|url| **url == next_root).count(), 1);
+}
+
+/// With lazy loading, delegated roles are only fetched when the search for a target reaches them,
+/// and are fetched at most once.
+#[test]
+fn mock_transport_delegated_roles_loaded_lazily() {
+    let fetched: Arc<Mutex<Vec<Url>>> = Arc::default();
+    let recorded = Arc::clone(&fetched);
+    let transport = MeteredTransport::new(Box::new(reference_impl()), move |event| {
+        recorded.lock().unwrap().push(event.url.clone());
+    });
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .lazy_delegated_roles(true)
+        .load()
+        .unwrap();
+    let role1 = metadata_base_url().join("role1.json").unwrap();
+    let role2 = metadata_base_url().join("role2.json").unwrap();
+    let fetches = |url: &Url| fetched.lock().unwrap().iter().filter(|u| *u == url).count();
+    assert_eq!(fetches(&role1), 0);
+    assert_eq!(fetches(&role2), 0);
+
+    // file1.txt is listed by the top-level targets role.
+    read_to_end(repo.read_target("file1.txt").unwrap().unwrap());
+    assert_eq!(fetches(&role1), 0);
+
+    // file3.txt is listed by role1, so the role that it delegates to isn't needed.
+    for _ in 0..2 {
+        assert_eq!(
+            read_to_end(repo.read_target("file3.txt").unwrap().unwrap()),
+            b"This is role1's target file.".to_vec()
+        );
+    }
+    assert_eq!(repo.role_for_target("file3.txt"), Some("role1"));
+    assert_eq!(fetches(&role1), 1);
+    assert_eq!(fetches(&role2), 0);
+}
+
+/// A target can be read as it is listed by a specific role, which must list it and must have been
+/// delegated its path.
+#[test]
+fn mock_transport_read_target_from_role() {
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(reference_impl())
+        .load()
+        .unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target_from_role("role1", "file3.txt").unwrap()),
+        b"This is role1's target file.".to_vec()
+    );
+    assert_eq!(
+        read_to_end(repo.read_target_from_role("targets", "file1.txt").unwrap()),
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap())
+    );
+    assert!(matches!(
+        repo.read_target_from_role("targets", "file3.txt"),
+        Err(Error::RoleTargetNotFound { role, target, .. }) if role == "targets" && target == "file3.txt"
+    ));
+    assert!(matches!(
+        repo.read_target_from_role("role2", "file3.txt"),
+        Err(Error::UndelegatedTarget { role, .. }) if role == "role2"
+    ));
+    assert!(matches!(
+        repo.read_target_from_role("role3", "file3.txt"),
+        Err(Error::DelegateNotFound { name }) if name == "role3"
+    ));
+}
+
+/// The metadata fetched callback is called with the exact bytes of each metadata file that is
+/// verified, including delegated roles as they are lazily loaded.
+#[test]
+fn mock_transport_on_metadata_fetched() {
+    let events: Arc<Mutex<Vec<(RoleType, String, u64, Vec<u8>)>>> = Arc::default();
+    let recorded = Arc::clone(&events);
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(reference_impl())
+        .lazy_delegated_roles(true)
+        .on_metadata_fetched(move |event| {
+            recorded.lock().unwrap().push((
+                event.role,
+                event.name.to_owned(),
+                event.version.get(),
+                event.bytes.to_vec(),
+            ));
+        })
+        .load()
+        .unwrap();
+    read_to_end(repo.read_target("file3.txt").unwrap().unwrap());
+
+    let metadata = |file: &str| {
+        fs::read(
+            test_data()
+                .join("tuf-reference-impl")
+                .join("metadata")
+                .join(file),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            (
+                RoleType::Timestamp,
+                "timestamp".to_owned(),
+                1,
+                metadata("timestamp.json")
+            ),
+            (
+                RoleType::Snapshot,
+                "snapshot".to_owned(),
+                1,
+                metadata("snapshot.json")
+            ),
+            (
+                RoleType::Targets,
+                "targets".to_owned(),
+                1,
+                metadata("targets.json")
+            ),
+            (
+                RoleType::Targets,
+                "role1".to_owned(),
+                1,
+                metadata("role1.json")
+            ),
+        ]
+    );
+}
+
+/// Stands in for an external verifier that accepts or rejects every signature, counting the
+/// checks.
+#[derive(Clone)]
+struct CountingVerifier {
+    valid: bool,
+    checks: Arc<AtomicUsize>,
+}
+
+impl SignatureVerifier for CountingVerifier {
+    fn verify(&self, _key: &Key, _msg: &[u8], _signature: &[u8]) -> bool {
+        self.checks.fetch_add(1, Ordering::SeqCst);
+        self.valid
+    }
+}
+
+/// The signature verifier set on the loader checks the signatures of every role, including
+/// delegated roles that are loaded later.
+#[test]
+fn mock_transport_signature_verifier() {
+    let verifier = CountingVerifier {
+        valid: true,
+        checks: Arc::default(),
+    };
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(reference_impl())
+        .lazy_delegated_roles(true)
+        .signature_verifier(verifier.clone())
+        .load()
+        .unwrap();
+    let loaded = verifier.checks.load(Ordering::SeqCst);
+    // The trusted root, timestamp, snapshot and targets each have one signature.
+    assert_eq!(loaded, 4);
+    read_to_end(repo.read_target("file3.txt").unwrap().unwrap());
+    assert_eq!(verifier.checks.load(Ordering::SeqCst), loaded + 1);
+
+    let verifier = CountingVerifier {
+        valid: false,
+        checks: Arc::default(),
+    };
+    let result = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(reference_impl())
+        .signature_verifier(verifier)
+        .load();
+    assert!(matches!(result, Err(Error::VerifyTrustedMetadata { .. })));
+}
+
+#[test]
+fn mock_transport_timestamp_failure() {
+    let timestamp = metadata_base_url().join("timestamp.json").unwrap();
+    let transport = reference_impl().fail(timestamp, TransportErrorKind::Other);
+    let result = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .load();
+    match result {
+        Err(Error::Transport { source, .. }) => {
+            assert!(matches!(source.kind(), TransportErrorKind::Other));
+        }
+        _ => panic!("expected a transport error"),
+    }
+}
+
+/// A snapshot that differs from the one that the timestamp lists, e.g. from a stale or corrupted
+/// mirror, is reported with both hashes.
+#[test]
+fn mock_transport_snapshot_hash_mismatch() {
+    let snapshot = fs::read_to_string(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("snapshot.json"),
+    )
+    .unwrap()
+    .replace("2030-01-01", "2031-01-01");
+    let transport =
+        reference_impl().file(metadata_base_url().join("snapshot.json").unwrap(), snapshot);
+    let result = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .load();
+    match result {
+        Err(Error::SnapshotHashMismatch {
+            expected, actual, ..
+        }) => {
+            assert_eq!(
+                expected,
+                "1f72d907a95612ecbea8b6f7c7e88c2782a2e0a6e9ac6332e8ec89edb9d83a06"
+            );
+            assert_ne!(actual, expected);
+        }
+        _ => panic!("expected a snapshot hash mismatch"),
+    }
+}
+
+/// A targets role whose version isn't the one that the snapshot lists is reported with both
+/// versions.
+#[test]
+fn mock_transport_targets_version_mismatch() {
+    let targets = fs::read_to_string(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("targets.json"),
+    )
+    .unwrap()
+    .replace("\"version\": 1", "\"version\": 2");
+    let transport =
+        reference_impl().file(metadata_base_url().join("targets.json").unwrap(), targets);
+    let result = RepositoryLoader::new(root().as_slice(), metadata_base_url(), targets_base_url())
+        .transport(transport)
+        .load();
+    assert!(matches!(
+        result,
+        Err(Error::MetaVersionMismatch {
+            role: RoleType::Targets,
+            expected: 1,
+            actual: 2,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn mock_transport_separate_targets_transport() {
+    let base = test_data().join("tuf-reference-impl");
+    let metadata_base_url = Url::from_directory_path(base.join("metadata")).unwrap();
+    let target = targets_base_url().join("file1.txt").unwrap();
+    let targets_transport = MockTransport::default().file(
+        target,
+        fs::read(base.join("targets").join("file1.txt")).unwrap(),
+    );
+    let repo = RepositoryLoader::new(root().as_slice(), metadata_base_url, targets_base_url())
+        .transport(FilesystemTransport::new())
+        .targets_transport(targets_transport)
+        .load()
+        .unwrap();
+    let target = repo.read_target("file1.txt").unwrap().unwrap();
+    assert_eq!(
+        read_to_end(target),
+        b"This is an example target file.".to_vec()
+    );
+
+    // targets that the targets transport doesn't have are not fetched with the metadata transport.
+    let error = repo.read_target("file2.txt").err().unwrap();
+    match error {
+        Error::Transport { source, .. } => {
+            assert!(matches!(source.kind(), TransportErrorKind::FileNotFound));
+        }
+        _ => panic!("expected a transport error"),
+    }
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "oci")]
+
+use mockito::{mock, Matcher};
+use test_utils::read_to_end;
+use tough::{HttpTransportBuilder, OciTransport, Transport, TransportErrorKind};
+use url::Url;
+
+mod test_utils;
+
+/// Returns an `oci` URL for `path` in the mock registry.
+fn oci_url(path: &str) -> Url {
+    let server = Url::parse(&mockito::server_url()).unwrap();
+    Url::parse(&format!(
+        "oci://{}:{}/{}",
+        server.host_str().unwrap(),
+        server.port().unwrap(),
+        path
+    ))
+    .unwrap()
+}
+
+fn transport() -> OciTransport {
+    OciTransport::new()
+        .plain_http(true)
+        .http_settings(HttpTransportBuilder::new().tries(1))
+}
+
+#[test]
+fn oci_transport_digest() {
+    let mock_base = mock("GET", "/v2/").with_status(200).create();
+    let mock_blob = mock("GET", "/v2/tuf/repo/blobs/sha256:abc")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(200)
+        .with_body("timestamp")
+        .create();
+
+    let reader = transport().fetch(oci_url("tuf/repo@sha256:abc")).unwrap();
+    assert_eq!(read_to_end(reader), b"timestamp");
+
+    mock_base.assert();
+    mock_blob.assert();
+}
+
+#[test]
+fn oci_transport_tag() {
+    let mock_base = mock("GET", "/v2/").with_status(200).create();
+    let mock_manifest = mock("GET", "/v2/tuf/metadata/manifests/snapshot.json")
+        .with_status(200)
+        .with_body(r#"{"schemaVersion":2,"layers":[{"digest":"sha256:def","size":8}]}"#)
+        .create();
+    let mock_blob = mock("GET", "/v2/tuf/metadata/blobs/sha256:def")
+        .with_status(200)
+        .with_body("snapshot")
+        .create();
+
+    let reader = transport()
+        .fetch(oci_url("tuf/metadata/snapshot.json"))
+        .unwrap();
+    assert_eq!(read_to_end(reader), b"snapshot");
+
+    mock_base.assert();
+    mock_manifest.assert();
+    mock_blob.assert();
+}
+
+/// The token is requested once, and is reused for later fetches from the same repository until it
+/// expires.
+#[test]
+fn oci_transport_bearer_token() {
+    let realm = format!("{}/token", mockito::server_url());
+    let mock_base = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "www-authenticate",
+            &format!(r#"Bearer realm="{}",service="registry""#, realm),
+        )
+        .expect(1)
+        .create();
+    let mock_token = mock("GET", "/token")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("scope".into(), "repository:tuf/repo:pull".into()),
+            Matcher::UrlEncoded("service".into(), "registry".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"token":"abc123","expires_in":300}"#)
+        .expect(1)
+        .create();
+    let mock_blob = mock("GET", "/v2/tuf/repo/blobs/sha256:abc")
+        .match_header("authorization", "Bearer abc123")
+        .with_status(200)
+        .with_body("root")
+        .expect(2)
+        .create();
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let transport = transport().docker_config(dir.path());
+    for _ in 0..2 {
+        let reader = transport.fetch(oci_url("tuf/repo@sha256:abc")).unwrap();
+        assert_eq!(read_to_end(reader), b"root");
+    }
+
+    mock_base.assert();
+    mock_token.assert();
+    mock_blob.assert();
+}
+
+#[test]
+fn oci_transport_file_not_found() {
+    let mock_base = mock("GET", "/v2/").with_status(200).create();
+    let mock_manifest = mock("GET", "/v2/tuf/metadata/manifests/2.root.json")
+        .with_status(404)
+        .create();
+
+    let url = oci_url("tuf/metadata/2.root.json");
+    let err = transport().fetch(url.clone()).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::FileNotFound));
+    assert_eq!(err.url(), url.as_str());
+
+    mock_base.assert();
+    mock_manifest.assert();
+}
+
+/// A tag is checked for with a `HEAD` request for its manifest, and a digest with one for its blob,
+/// so nothing is downloaded.
+#[test]
+fn oci_transport_exists() {
+    let mock_base = mock("GET", "/v2/").with_status(200).create();
+    let mock_manifest = mock("HEAD", "/v2/tuf/metadata/manifests/3.root.json")
+        .with_status(404)
+        .create();
+    let mock_blob = mock("HEAD", "/v2/tuf/metadata/blobs/sha256:fed")
+        .with_status(200)
+        .create();
+
+    let transport = transport();
+    assert!(transport.exists_is_cheap());
+    assert!(!transport
+        .exists(oci_url("tuf/metadata/3.root.json"))
+        .unwrap());
+    assert!(transport
+        .exists(oci_url("tuf/metadata@sha256:fed"))
+        .unwrap());
+
+    mock_base.assert();
+    mock_manifest.assert();
+    mock_blob.assert();
+}
+
+#[test]
+fn oci_transport_unsupported_scheme() {
+    let err = OciTransport::new()
+        .fetch(Url::parse("https://example.com/v2/tuf/blobs/sha256:abc").unwrap())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err.kind(),
+        TransportErrorKind::UnsupportedUrlScheme
+    ));
+}
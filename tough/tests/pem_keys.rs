@@ -0,0 +1,123 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::{Duration, Utc};
+use ring::rand::SystemRandom;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::num::NonZeroU64;
+use test_utils::test_data;
+use tough::editor::signed::SignedRole;
+use tough::error::Error;
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::schema::key::Key;
+use tough::schema::{KeyHolder, RoleKeys, RoleType, Root};
+
+fn key_source(name: &str) -> Box<dyn KeySource> {
+    Box::new(LocalKeySource {
+        path: test_data().join(name),
+    })
+}
+
+fn public_key(name: &str) -> tough::schema::Result<Key> {
+    Key::from_public_pem(&read_to_string(test_data().join(name)).unwrap())
+}
+
+// A root that lists only `key`, for every role
+fn root_with_key(key: Key) -> Root {
+    let keyid = key.key_id().unwrap();
+    let roles = [
+        RoleType::Root,
+        RoleType::Snapshot,
+        RoleType::Targets,
+        RoleType::Timestamp,
+    ]
+    .iter()
+    .map(|role| {
+        (
+            *role,
+            RoleKeys {
+                keyids: vec![keyid.clone()],
+                threshold: NonZeroU64::new(1).unwrap(),
+                _extra: HashMap::new(),
+            },
+        )
+    })
+    .collect();
+    let mut keys = HashMap::new();
+    keys.insert(keyid, key);
+    Root {
+        spec_version: "1.0.0".to_owned(),
+        consistent_snapshot: true,
+        version: NonZeroU64::new(1).unwrap(),
+        expires: Utc::now() + Duration::days(7),
+        keys,
+        roles,
+        _extra: HashMap::new(),
+    }
+}
+
+/// Test that a private key and its public key give the same TUF key and key ID, and that
+/// signatures made with the private key verify against the public key.
+fn assert_round_trip(private: &str, public: &str) {
+    let private = key_source(private);
+    let public = public_key(public).unwrap();
+    let tuf_key = private.as_sign().unwrap().tuf_key();
+    assert_eq!(tuf_key, public);
+    assert_eq!(tuf_key.key_id().unwrap(), public.key_id().unwrap());
+
+    let root = root_with_key(public);
+    let signed = SignedRole::new(
+        root.clone(),
+        &KeyHolder::Root(root.clone()),
+        &[private],
+        &SystemRandom::new(),
+    )
+    .unwrap();
+    root.verify_role(signed.signed()).unwrap();
+}
+
+#[test]
+fn rsa_pem_round_trip() {
+    assert_round_trip("snakeoil.pem", "snakeoil.pub.pem");
+}
+
+#[test]
+fn ecdsa_pem_round_trip() {
+    assert_round_trip("snakeoil_ecdsa.pem", "snakeoil_ecdsa.pub.pem");
+}
+
+#[test]
+fn ed25519_pem_round_trip() {
+    assert_round_trip("snakeoil_ed25519.pem", "snakeoil_ed25519.pub.pem");
+}
+
+/// Test that ECDSA keys on curves other than P-256 are rejected with a clear error.
+#[test]
+fn p384_keys_unsupported() {
+    assert!(matches!(
+        public_key("snakeoil_p384.pub.pem"),
+        Err(tough::schema::Error::UnsupportedCurve { .. })
+    ));
+    assert!(matches!(
+        key_source("snakeoil_p384.pem").as_sign(),
+        Err(e) if matches!(
+            e.downcast_ref::<Error>(),
+            Some(Error::KeyUnsupportedCurve { .. })
+        )
+    ));
+}
+
+/// Test that SEC1 ("EC PRIVATE KEY") keys are rejected with an error suggesting conversion.
+#[test]
+fn sec1_key_unsupported() {
+    assert!(matches!(
+        key_source("snakeoil_ecdsa_sec1.pem").as_sign(),
+        Err(e) if matches!(
+            e.downcast_ref::<Error>(),
+            Some(Error::KeySec1Unsupported { .. })
+        )
+    ));
+}
@@ -0,0 +1,60 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use test_utils::{dir_url, test_data};
+use tough::{FilesystemTransport, MeteredTransport, RepositoryLoader};
+use url::Url;
+
+/// Test a repository where `targets` lists `linux/readme.txt` and `windows/readme.txt`, and
+/// delegates `linux/*` to `linux` and `windows/*` to `windows`. Listing the targets under `linux/`
+/// includes those of `linux`, and with lazy loading, does not load `windows`.
+#[test]
+fn targets_with_prefix() {
+    let base = test_data().join("prefix-delegation");
+    let fetched: Arc<Mutex<Vec<Url>>> = Arc::default();
+    let recorded = Arc::clone(&fetched);
+    let transport = MeteredTransport::new(Box::new(FilesystemTransport::new()), move |event| {
+        recorded.lock().unwrap().push(event.url.clone());
+    });
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .transport(transport)
+    .lazy_delegated_roles(true)
+    .load()
+    .unwrap();
+
+    let names = |prefix: &str| -> Vec<String> {
+        repo.targets_with_prefix(prefix)
+            .unwrap()
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    };
+    assert_eq!(
+        names("linux/"),
+        vec![
+            "linux/aarch64/tool",
+            "linux/readme.txt",
+            "linux/x86_64/tool"
+        ]
+    );
+    let windows = dir_url(base.join("metadata")).join("windows.json").unwrap();
+    assert!(!fetched.lock().unwrap().contains(&windows));
+
+    let (name, target) = repo
+        .targets_with_prefix("linux/x86_64/")
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(name, "linux/x86_64/tool");
+    assert_eq!(target.length, 43);
+    assert!(names("macos/").is_empty());
+    assert_eq!(names("").len(), 5);
+    assert!(fetched.lock().unwrap().contains(&windows));
+}
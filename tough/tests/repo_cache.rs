@@ -2,11 +2,12 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use tempfile::TempDir;
 use test_utils::{dir_url, test_data};
-use tough::{Repository, RepositoryLoader};
+use tough::{FilesystemTransport, Repository, RepositoryLoader, Transport, TransportError};
 use url::Url;
 
 mod test_utils;
@@ -42,6 +43,25 @@ fn load_tuf_reference_impl(paths: &RepoPaths) -> Repository {
     .unwrap()
 }
 
+/// Test that cached targets get the same permissions as other new files, rather than the owner-only
+/// permissions of the temporary files they are staged in.
+#[cfg(unix)]
+#[test]
+fn test_repo_cache_target_permissions() {
+    let repo_paths = RepoPaths::new();
+    let repo = load_tuf_reference_impl(&repo_paths);
+    let destination = TempDir::new().unwrap();
+    let targets_destination = destination.as_ref().join("targets");
+    repo.cache(
+        destination.as_ref().join("metadata"),
+        &targets_destination,
+        Some(&["file1.txt"]),
+        true,
+    )
+    .unwrap();
+    test_utils::assert_default_permissions(targets_destination.join("file1.txt"));
+}
+
 /// Test that the repo.cache() function works when given a list of multiple targets.
 #[test]
 fn test_repo_cache_all_targets() {
@@ -181,3 +201,169 @@ fn test_repo_cache_some() {
         .unwrap();
     assert_eq!(39, file_size);
 }
+
+/// A `Transport` that fails partway through reading any target named `file1.txt`.
+#[derive(Debug, Clone, Copy)]
+struct FailingTransport;
+
+/// Returns some bytes and then an error.
+struct FailingRead {
+    inner: Box<dyn Read + Send>,
+    remaining: usize,
+}
+
+impl Read for FailingRead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "connection reset"));
+        }
+        let len = buf.len().min(self.remaining);
+        let size = self.inner.read(&mut buf[..len])?;
+        self.remaining -= size;
+        Ok(size)
+    }
+}
+
+impl Transport for FailingTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        let fail = url.path().ends_with("file1.txt");
+        let inner = FilesystemTransport::new().fetch(url)?;
+        if fail {
+            Ok(Box::new(FailingRead {
+                inner,
+                remaining: 4,
+            }))
+        } else {
+            Ok(inner)
+        }
+    }
+}
+
+/// Test that a target which fails partway through its download is not left at its final path.
+#[test]
+fn test_repo_cache_failed_download() {
+    let repo_paths = RepoPaths::new();
+    let repo = RepositoryLoader::new(
+        repo_paths.root(),
+        repo_paths.metadata_base_url.clone(),
+        repo_paths.targets_base_url.clone(),
+    )
+    .transport(FailingTransport)
+    .load()
+    .unwrap();
+
+    let destination = TempDir::new().unwrap();
+    let metadata_destination = destination.as_ref().join("metadata");
+    let targets_destination = destination.as_ref().join("targets");
+    let targets_subset = vec!["file2.txt".to_string(), "file1.txt".to_string()];
+    assert!(repo
+        .cache(
+            &metadata_destination,
+            &targets_destination,
+            Some(&targets_subset),
+            true,
+        )
+        .is_err());
+
+    // file2 was verified and moved into place; file1 and its staging file are gone.
+    let cached: Vec<_> = std::fs::read_dir(&targets_destination)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(cached, vec!["file2.txt"]);
+}
+
+/// Test that the repo.cache_with_progress() function reports the progress of each target.
+#[test]
+fn test_repo_cache_with_progress() {
+    let mut repo_paths = RepoPaths::new();
+    let repo = load_tuf_reference_impl(&mut repo_paths);
+
+    let destination = TempDir::new().unwrap();
+    let mut events = Vec::new();
+    repo.cache_with_progress(
+        destination.as_ref().join("metadata"),
+        destination.as_ref().join("targets"),
+        Some(&["file1.txt"]),
+        false,
+        |name, done, total| events.push((name.to_owned(), done, total)),
+    )
+    .unwrap();
+
+    assert_eq!(
+        events.first(),
+        Some(&("file1.txt".to_owned(), 0, 31)),
+        "progress starts at zero"
+    );
+    assert_eq!(events.last(), Some(&("file1.txt".to_owned(), 31, 31)));
+}
+
+/// Test that the repo.cache_parallel() function caches all of the targets.
+#[test]
+fn test_repo_cache_parallel() {
+    let repo_paths = RepoPaths::new();
+    let repo = load_tuf_reference_impl(&repo_paths);
+
+    let destination = TempDir::new().unwrap();
+    let metadata_destination = destination.as_ref().join("metadata");
+    let targets_destination = destination.as_ref().join("targets");
+    repo.cache_parallel(
+        &metadata_destination,
+        &targets_destination,
+        None::<&[&str]>,
+        true,
+        NonZeroUsize::new(2).unwrap(),
+    )
+    .unwrap();
+
+    let copied_repo = RepositoryLoader::new(
+        repo_paths.root(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .unwrap();
+    for (name, length) in &[("file1.txt", 31), ("file2.txt", 39), ("file3.txt", 28)] {
+        let mut file_data = Vec::new();
+        let file_size = copied_repo
+            .read_target(name)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut file_data)
+            .unwrap();
+        assert_eq!(*length, file_size);
+    }
+}
+
+/// Test that an error from any worker of repo.cache_parallel() is returned, and that no metadata is
+/// saved.
+#[test]
+fn test_repo_cache_parallel_failed_download() {
+    let repo_paths = RepoPaths::new();
+    let repo = RepositoryLoader::new(
+        repo_paths.root(),
+        repo_paths.metadata_base_url.clone(),
+        repo_paths.targets_base_url.clone(),
+    )
+    .transport(FailingTransport)
+    .load()
+    .unwrap();
+
+    let destination = TempDir::new().unwrap();
+    let metadata_destination = destination.as_ref().join("metadata");
+    let targets_destination = destination.as_ref().join("targets");
+    let targets_subset = vec!["file2.txt".to_string(), "file1.txt".to_string()];
+    assert!(repo
+        .cache_parallel(
+            &metadata_destination,
+            &targets_destination,
+            Some(&targets_subset),
+            true,
+            NonZeroUsize::new(2).unwrap(),
+        )
+        .is_err());
+
+    // file1 and its staging file are gone, and no metadata was saved.
+    assert!(!targets_destination.join("file1.txt").exists());
+    assert_eq!(std::fs::read_dir(&metadata_destination).unwrap().count(), 0);
+}
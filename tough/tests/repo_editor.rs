@@ -1,23 +1,25 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::test_utils::{dir_url, read_to_end, test_data};
+use crate::test_utils::{dir_url, local_key_sources, read_to_end, test_data};
 use chrono::{Duration, Utc};
+use ring::rand::SystemRandom;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::Write;
 use std::num::NonZeroU64;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
-use tough::editor::signed::PathExists;
+use tough::editor::signed::{PathExists, SignedRepository};
 use tough::editor::{targets::TargetsEditor, RepositoryEditor};
+use tough::error::Error;
 use tough::key_source::KeySource;
 use tough::key_source::LocalKeySource;
 use tough::schema::decoded::Decoded;
 use tough::schema::decoded::Hex;
 use tough::schema::key::Key;
-use tough::schema::PathSet;
-use tough::{Repository, RepositoryLoader};
+use tough::schema::{PathSet, RoleType, Root, Signed, Target};
+use tough::{RefreshOutcome, Repository, RepositoryLoader, TargetChanges};
 use url::Url;
 
 mod test_utils;
@@ -60,6 +62,20 @@ fn targets_key_path1() -> PathBuf {
     test_data().join("targetskey-1")
 }
 
+// Path to a root.json that requires signatures from both snakeoil.pem and snakeoil_2.pem
+fn threshold_2_root_path() -> PathBuf {
+    test_data().join("threshold-2").join("root.json")
+}
+
+fn threshold_2_keys() -> Vec<Box<dyn KeySource>> {
+    vec![
+        Box::new(LocalKeySource { path: key_path() }),
+        Box::new(LocalKeySource {
+            path: test_data().join("snakeoil_2.pem"),
+        }),
+    ]
+}
+
 // Path to fake targets in the reference implementation
 fn targets_path() -> PathBuf {
     test_data().join("tuf-reference-impl").join("targets")
@@ -173,6 +189,10 @@ fn create_sign_write_reload_repo() {
         .unwrap();
     // switch repo owner to role1
     editor
+        .targets_version(NonZeroU64::new(2).unwrap())
+        .unwrap()
+        .targets_expires(Utc::now().checked_add_signed(Duration::days(13)).unwrap())
+        .unwrap()
         .sign_targets_editor(targets_key)
         .unwrap()
         .change_delegated_targets("role1")
@@ -804,3 +824,635 @@ fn update_targets_flow() {
         &b"Updated file1.txt"[..]
     );
 }
+
+#[test]
+/// A role must not delegate paths that are broader than the paths delegated to it. Here `role1` is
+/// delegated `file1.txt`, but it delegates `*.txt` to `role2`.
+fn over_broad_delegation_fails_to_load() {
+    let mut editor = test_repo_editor();
+
+    let targets_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+        &[Box::new(LocalKeySource { path: key_path() })];
+    let role1_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+        &[Box::new(LocalKeySource {
+            path: targets_key_path(),
+        })];
+    let role2_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+        &[Box::new(LocalKeySource {
+            path: targets_key_path1(),
+        })];
+    let expiration = Utc::now().checked_add_signed(Duration::days(21)).unwrap();
+    let version = NonZeroU64::new(1).unwrap();
+
+    editor
+        .delegate_role(
+            "role1",
+            role1_key,
+            PathSet::Paths(vec!["file1.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            expiration,
+            version,
+        )
+        .unwrap()
+        .sign_targets_editor(targets_key)
+        .unwrap()
+        .change_delegated_targets("role1")
+        .unwrap()
+        .delegate_role(
+            "role2",
+            role2_key,
+            PathSet::Paths(vec!["*.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            expiration,
+            version,
+        )
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .targets_expires(expiration)
+        .unwrap()
+        .sign_targets_editor(role1_key)
+        .unwrap();
+
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+    let targets_destination = repo_dir.path().join("targets");
+    editor
+        .sign(targets_key)
+        .unwrap()
+        .write(&metadata_destination)
+        .unwrap();
+
+    let error = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .err()
+    .unwrap();
+    match error {
+        Error::UnauthorizedDelegation { parent, child, .. } => {
+            assert_eq!(parent, "role1");
+            assert_eq!(child, "role2");
+        }
+        _ => panic!("expected UnauthorizedDelegation, got: {}", error),
+    }
+}
+
+#[test]
+/// Simulates signing a repository out-of-process: the signing payloads are exported, signed
+/// elsewhere, and the detached signatures are imported to assemble the signed metadata.
+fn offline_signing_round_trip() {
+    let mut editor = test_repo_editor();
+    let key_pair = LocalKeySource { path: key_path() }.as_sign().unwrap();
+    // The signature must name the key ID that root.json lists the key under.
+    let root: Signed<Root> = serde_json::from_reader(File::open(root_path()).unwrap()).unwrap();
+    let keyid = root.signed.key_id(key_pair.as_ref()).unwrap();
+    let rng = SystemRandom::new();
+
+    // A signature that wasn't made over the payload is rejected
+    assert!(editor
+        .add_signature(RoleType::Targets, keyid.clone(), vec![0; 256])
+        .is_err());
+
+    for role in &[RoleType::Targets, RoleType::Snapshot, RoleType::Timestamp] {
+        let payload = editor.signing_payload(*role).unwrap();
+        // This happens on the offline machine
+        let sig = key_pair.sign(&payload, &rng).unwrap();
+        editor.add_signature(*role, keyid.clone(), sig).unwrap();
+    }
+
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+    let targets_destination = repo_dir.path().join("targets");
+    let signed_repo = editor.assemble().unwrap();
+    signed_repo.write(&metadata_destination).unwrap();
+    signed_repo
+        .link_targets(&targets_path(), &targets_destination, PathExists::Skip)
+        .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target("file3.txt").unwrap().unwrap()),
+        &b"This is role1's target file."[..]
+    );
+}
+
+/// A delegated role that the targets editor holds can't be signed out-of-process, and the error
+/// names the role.
+#[test]
+fn signing_payload_rejects_delegated_role() {
+    let targets_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let role1_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path(),
+    })];
+    let mut editor = test_repo_editor();
+    editor
+        .delegate_role(
+            "role1",
+            role1_key,
+            PathSet::Paths(vec!["file?.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            Utc::now().checked_add_signed(Duration::days(21)).unwrap(),
+            NonZeroU64::new(1).unwrap(),
+        )
+        .unwrap()
+        .sign_targets_editor(targets_key)
+        .unwrap()
+        .change_delegated_targets("role1")
+        .unwrap()
+        .targets_version(NonZeroU64::new(2).unwrap())
+        .unwrap()
+        .targets_expires(Utc::now().checked_add_signed(Duration::days(13)).unwrap())
+        .unwrap();
+    assert!(matches!(
+        editor.signing_payload(RoleType::Targets),
+        Err(Error::DetachedDelegatedRole { name, .. }) if name == "role1"
+    ));
+}
+
+/// Sets versions of the top-level roles that are `n` more than those set by `test_repo_editor`.
+fn bump_versions(editor: &mut RepositoryEditor, n: u64) {
+    editor
+        .targets_version(NonZeroU64::new(789 + n).unwrap())
+        .unwrap()
+        .snapshot_version(NonZeroU64::new(5432 + n).unwrap())
+        .timestamp_version(NonZeroU64::new(1234 + n).unwrap());
+}
+
+/// Delegates `file1.txt` to `role1`, which lists it, then signs the repository and writes its
+/// metadata to `metadata_destination`.
+fn write_with_role1(mut editor: RepositoryEditor, metadata_destination: &Path) {
+    let targets_key = &local_key_sources(key_path());
+    let role1_key = &local_key_sources(targets_key_path());
+    let expiration = Utc::now().checked_add_signed(Duration::days(21)).unwrap();
+    editor
+        .delegate_role(
+            "role1",
+            role1_key,
+            PathSet::Paths(vec!["file1.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            expiration,
+            NonZeroU64::new(1).unwrap(),
+        )
+        .unwrap()
+        .sign_targets_editor(targets_key)
+        .unwrap()
+        .change_delegated_targets("role1")
+        .unwrap()
+        .add_target_path(targets_path().join("file1.txt"))
+        .unwrap()
+        .targets_version(NonZeroU64::new(1).unwrap())
+        .unwrap()
+        .targets_expires(expiration)
+        .unwrap()
+        .sign_targets_editor(role1_key)
+        .unwrap();
+    editor
+        .sign(targets_key)
+        .unwrap()
+        .write(metadata_destination)
+        .unwrap();
+}
+
+#[test]
+// Refreshing reports whether there was new metadata, and the targets that were added, removed and
+// changed, including delegated targets
+fn refresh_reports_target_changes() {
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+
+    // Before: file2.txt and file3.txt
+    let mut editor = test_repo_editor();
+    editor
+        .add_target_path(targets_path().join("file2.txt"))
+        .unwrap();
+    editor
+        .sign(&local_key_sources(key_path()))
+        .unwrap()
+        .write(&metadata_destination)
+        .unwrap();
+    let mut repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(targets_path()),
+    )
+    .load()
+    .unwrap();
+    assert_eq!(repo.refresh().unwrap(), RefreshOutcome::default());
+
+    // After: file1.txt is added by the delegated role1, file2.txt is removed, and file3.txt has
+    // different contents
+    let changed_editor = || {
+        let mut editor = test_repo_editor();
+        editor
+            .add_target(
+                "file3.txt",
+                Target::from_path(targets_path().join("file2.txt")).unwrap(),
+            )
+            .unwrap();
+        editor
+    };
+    let mut editor = changed_editor();
+    bump_versions(&mut editor, 1);
+    write_with_role1(editor, &metadata_destination);
+    assert_eq!(
+        repo.refresh().unwrap(),
+        RefreshOutcome {
+            updated: true,
+            changes: TargetChanges {
+                added: vec!["file1.txt".to_string()],
+                removed: vec!["file2.txt".to_string()],
+                changed: vec!["file3.txt".to_string()],
+            },
+        }
+    );
+    assert_eq!(repo.role_for_target("file1.txt"), Some("role1"));
+
+    // New metadata that lists the same targets
+    let mut editor = changed_editor();
+    bump_versions(&mut editor, 2);
+    write_with_role1(editor, &metadata_destination);
+    assert_eq!(
+        repo.refresh().unwrap(),
+        RefreshOutcome {
+            updated: true,
+            changes: TargetChanges::default(),
+        }
+    );
+    assert_eq!(repo.refresh().unwrap(), RefreshOutcome::default());
+}
+
+#[test]
+// With lazy delegated roles, refreshing only compares the top-level targets, so delegated targets
+// that were loaded before aren't reported as removed
+fn refresh_reports_top_level_target_changes_when_lazy() {
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+
+    write_with_role1(test_repo_editor(), &metadata_destination);
+    let mut repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(targets_path()),
+    )
+    .lazy_delegated_roles(true)
+    .load()
+    .unwrap();
+    repo.load_delegated_roles().unwrap();
+    assert_eq!(repo.target_names(), vec!["file1.txt", "file3.txt"]);
+
+    let mut editor = test_repo_editor();
+    bump_versions(&mut editor, 1);
+    editor
+        .add_target_path(targets_path().join("file2.txt"))
+        .unwrap();
+    write_with_role1(editor, &metadata_destination);
+    assert_eq!(
+        repo.refresh().unwrap(),
+        RefreshOutcome {
+            updated: true,
+            changes: TargetChanges {
+                added: vec!["file2.txt".to_string()],
+                ..TargetChanges::default()
+            },
+        }
+    );
+    assert_eq!(repo.role_for_target("file1.txt"), Some("role1"));
+}
+
+#[test]
+/// The provenance of a target lists each role from `targets` down to the role that lists it. Here
+/// `targets` delegates `*.txt` to `role1`, which delegates `file1.txt` to `role2`.
+fn target_provenance_two_level_delegation() {
+    let mut editor = test_repo_editor();
+
+    let targets_key = &local_key_sources(key_path());
+    let role1_key = &local_key_sources(targets_key_path());
+    let role2_key = &local_key_sources(targets_key_path1());
+    let expiration = Utc::now().checked_add_signed(Duration::days(21)).unwrap();
+    let version = NonZeroU64::new(1).unwrap();
+
+    editor
+        .delegate_role(
+            "role1",
+            role1_key,
+            PathSet::Paths(vec!["*.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            expiration,
+            version,
+        )
+        .unwrap()
+        .sign_targets_editor(targets_key)
+        .unwrap()
+        .change_delegated_targets("role1")
+        .unwrap()
+        .delegate_role(
+            "role2",
+            role2_key,
+            PathSet::Paths(vec!["file1.txt".to_string()]),
+            NonZeroU64::new(1).unwrap(),
+            expiration,
+            version,
+        )
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .targets_expires(expiration)
+        .unwrap()
+        .sign_targets_editor(role1_key)
+        .unwrap()
+        .change_delegated_targets("role2")
+        .unwrap()
+        .add_target_path(targets_path().join("file1.txt"))
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .targets_expires(expiration)
+        .unwrap()
+        .sign_targets_editor(role2_key)
+        .unwrap();
+
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+    editor
+        .sign(targets_key)
+        .unwrap()
+        .write(&metadata_destination)
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(targets_path()),
+    )
+    .load()
+    .unwrap();
+
+    let provenance = repo.target_provenance("file1.txt").unwrap();
+    let names: Vec<&str> = provenance.iter().map(|role| role.name.as_str()).collect();
+    assert_eq!(names, vec!["targets", "role1", "role2"]);
+    assert_eq!(
+        provenance[0].keyids,
+        repo.root().signed.roles[&RoleType::Targets].keyids
+    );
+    assert_eq!(
+        provenance[2].keyids,
+        key_hash_map(role2_key).keys().cloned().collect::<Vec<_>>()
+    );
+    assert!(provenance.iter().all(|role| !role.terminating));
+
+    // file3.txt is listed by the top-level targets role
+    let provenance = repo.target_provenance("file3.txt").unwrap();
+    assert_eq!(provenance.len(), 1);
+    assert_eq!(provenance[0].name, "targets");
+
+    assert!(repo.target_provenance("file2.txt").is_none());
+}
+
+#[test]
+/// Writes a repo, loads it, then writes new metadata and checks that `refresh` picks it up.
+fn refresh_picks_up_new_metadata() {
+    let create_dir = TempDir::new().unwrap();
+    let metadata_destination = create_dir.path().join("metadata");
+    let targets_destination = create_dir.path().join("targets");
+    let key_source: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+
+    let signed_repo = test_repo_editor().sign(key_source).unwrap();
+    signed_repo.write(&metadata_destination).unwrap();
+    signed_repo
+        .link_targets(&targets_path(), &targets_destination, PathExists::Skip)
+        .unwrap();
+
+    let mut repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .unwrap();
+    assert!(!repo.refresh().unwrap().updated);
+    assert_eq!(repo.target_names(), vec!["file3.txt"]);
+
+    let mut editor = test_repo_editor();
+    editor
+        .targets_version(NonZeroU64::new(790).unwrap())
+        .unwrap()
+        .snapshot_version(NonZeroU64::new(5433).unwrap())
+        .timestamp_version(NonZeroU64::new(1235).unwrap())
+        .add_target_paths(vec![targets_path().join("file1.txt")])
+        .unwrap();
+    let signed_repo = editor.sign(key_source).unwrap();
+    signed_repo.write(&metadata_destination).unwrap();
+    signed_repo
+        .link_targets(&targets_path(), &targets_destination, PathExists::Skip)
+        .unwrap();
+
+    let outcome = repo.refresh().unwrap();
+    assert!(outcome.updated);
+    assert_eq!(outcome.changes.added, vec!["file1.txt"]);
+    assert_eq!(repo.timestamp().signed.version.get(), 1235);
+    assert_eq!(repo.snapshot().signed.version.get(), 5433);
+    assert_eq!(repo.target_names(), vec!["file1.txt", "file3.txt"]);
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        std::fs::read(targets_path().join("file1.txt")).unwrap()
+    );
+    assert!(!repo.refresh().unwrap().updated);
+}
+
+/// Test adding a target with custom metadata and removing another target, then signing, writing,
+/// reloading the repository, and reading the targets.
+#[test]
+fn add_and_remove_targets_round_trip() {
+    let (name, mut target) =
+        RepositoryEditor::build_target(targets_path().join("file1.txt")).unwrap();
+    target
+        .custom
+        .insert("file_permissions".to_owned(), "0644".into());
+
+    // The test editor starts with file3.txt.
+    let mut editor = test_repo_editor();
+    editor
+        .add_target(&name, target)
+        .unwrap()
+        .add_target_path(targets_path().join("file2.txt"))
+        .unwrap()
+        .remove_target("file3.txt")
+        .unwrap();
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let signed_repo = editor.sign(&keys).unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    let metadata_dir = outdir.path().join("metadata");
+    let targets_dir = outdir.path().join("targets");
+    signed_repo.write(&metadata_dir).unwrap();
+    signed_repo
+        .copy_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(root_path()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(&targets_dir),
+    )
+    .load()
+    .unwrap();
+    assert_eq!(repo.target_names(), vec!["file1.txt", "file2.txt"]);
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This is an example target file."[..]
+    );
+    assert_eq!(
+        repo.targets().signed.targets["file1.txt"].custom["file_permissions"],
+        "0644"
+    );
+    assert!(repo.read_target("file3.txt").unwrap().is_none());
+}
+
+fn threshold_2_editor() -> RepositoryEditor {
+    let expires = Utc::now().checked_add_signed(Duration::days(7)).unwrap();
+    let version = NonZeroU64::new(1).unwrap();
+    let mut editor = RepositoryEditor::new(threshold_2_root_path()).unwrap();
+    editor
+        .targets_expires(expires)
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .snapshot_expires(expires)
+        .snapshot_version(version)
+        .timestamp_expires(expires)
+        .timestamp_version(version)
+        .add_target_path(targets_path().join("file1.txt"))
+        .unwrap();
+    editor
+}
+
+/// Writes a signed repository whose root is threshold-2/root.json, and loads it.
+fn write_and_load_threshold_2(signed_repo: SignedRepository, outdir: &Path) -> Repository {
+    let metadata_dir = outdir.join("metadata");
+    let targets_dir = outdir.join("targets");
+    signed_repo.write(&metadata_dir).unwrap();
+    signed_repo
+        .copy_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .unwrap();
+    RepositoryLoader::new(
+        File::open(threshold_2_root_path()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(&targets_dir),
+    )
+    .load()
+    .unwrap()
+}
+
+/// Test that signing a repository whose roles have a threshold of 2 adds a signature from each
+/// key, and that the repository loads.
+#[test]
+fn sign_with_threshold_of_two_keys() {
+    let keys = threshold_2_keys();
+    assert!(matches!(
+        threshold_2_editor().sign(&keys[..1]),
+        Err(Error::SigningKeysNotFound { .. })
+    ));
+
+    let outdir = TempDir::new().unwrap();
+    let repo = write_and_load_threshold_2(threshold_2_editor().sign(&keys).unwrap(), outdir.path());
+    assert_eq!(repo.targets().signatures.len(), 2);
+    assert_eq!(repo.snapshot().signatures.len(), 2);
+    assert_eq!(repo.timestamp().signatures.len(), 2);
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This is an example target file."[..]
+    );
+}
+
+/// Test signing a repository whose roles have a threshold of 2 one key at a time, as separate
+/// offline key holders would.
+#[test]
+fn sign_with_threshold_of_two_keys_in_stages() {
+    let mut editor = threshold_2_editor();
+    let rng = SystemRandom::new();
+    let key_pairs: Vec<_> = threshold_2_keys()
+        .iter()
+        .map(|key| key.as_sign().unwrap())
+        .collect();
+    for role in &[RoleType::Targets, RoleType::Snapshot, RoleType::Timestamp] {
+        let payload = editor.signing_payload(*role).unwrap();
+        for key_pair in &key_pairs {
+            let sig = key_pair.sign(&payload, &rng).unwrap();
+            editor
+                .add_signature(*role, key_pair.tuf_key().key_id().unwrap(), sig)
+                .unwrap();
+        }
+    }
+
+    let outdir = TempDir::new().unwrap();
+    let repo = write_and_load_threshold_2(editor.assemble().unwrap(), outdir.path());
+    assert_eq!(repo.targets().signatures.len(), 2);
+    assert!(repo.read_target("file1.txt").unwrap().is_some());
+}
+
+/// Test bumping the versions and expirations of a loaded repository, then re-signing and
+/// reloading it.
+#[test]
+fn bump_version_and_expirations() {
+    let outdir = TempDir::new().unwrap();
+    let metadata_dir = outdir.path().join("metadata");
+    let targets_dir = outdir.path().join("targets");
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let load = || {
+        RepositoryLoader::new(
+            File::open(root_path()).unwrap(),
+            dir_url(&metadata_dir),
+            dir_url(&targets_dir),
+        )
+        .load()
+        .unwrap()
+    };
+
+    let signed_repo = test_repo_editor().sign(&keys).unwrap();
+    signed_repo.write(&metadata_dir).unwrap();
+    signed_repo
+        .copy_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .unwrap();
+    let repo = load();
+
+    let mut editor = RepositoryEditor::from_repo(root_path(), repo).unwrap();
+    editor
+        .bump_version()
+        .unwrap()
+        .set_expiration_from_now(RoleType::Targets, Duration::days(30))
+        .unwrap()
+        .set_expiration_from_now(RoleType::Snapshot, Duration::days(30))
+        .unwrap()
+        .set_expiration_from_now(RoleType::Timestamp, Duration::days(1))
+        .unwrap();
+    assert!(matches!(
+        editor.set_expiration_from_now(RoleType::Root, Duration::days(1)),
+        Err(Error::UneditableRole { .. })
+    ));
+    let signed_repo = editor.sign(&keys).unwrap();
+    signed_repo.write(&metadata_dir).unwrap();
+
+    let repo = load();
+    assert_eq!(repo.targets().signed.version.get(), 790);
+    assert_eq!(repo.snapshot().signed.version.get(), 5433);
+    assert_eq!(repo.timestamp().signed.version.get(), 1235);
+    assert_eq!(
+        repo.snapshot().signed.meta["targets.json"].version.get(),
+        790
+    );
+    assert_eq!(
+        repo.timestamp().signed.meta["snapshot.json"].version.get(),
+        5433
+    );
+    assert!(repo.timestamp().signed.expires < Utc::now() + Duration::days(2));
+    assert!(repo.targets().signed.expires > Utc::now() + Duration::days(29));
+    assert_eq!(repo.target_names(), vec!["file3.txt"]);
+}
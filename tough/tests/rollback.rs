@@ -0,0 +1,103 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::{Duration, Utc};
+use std::fs::File;
+use std::num::NonZeroU64;
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{dir_url, test_data};
+use tough::editor::signed::PathExists;
+use tough::editor::RepositoryEditor;
+use tough::error::Error;
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::schema::RoleType;
+use tough::{Repository, RepositoryLoader};
+
+/// Writes a repository signed by snakeoil.pem with the given snapshot and timestamp versions.
+fn write_repo(outdir: &Path, snapshot_version: u64, timestamp_version: u64) {
+    let root_path = test_data().join("simple-rsa").join("root.json");
+    let targets_path = test_data().join("tuf-reference-impl").join("targets");
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource {
+        path: test_data().join("snakeoil.pem"),
+    })];
+    let expires = Utc::now().checked_add_signed(Duration::days(7)).unwrap();
+    let mut editor = RepositoryEditor::new(root_path).unwrap();
+    editor
+        .targets_expires(expires)
+        .unwrap()
+        .targets_version(NonZeroU64::new(1).unwrap())
+        .unwrap()
+        .snapshot_expires(expires)
+        .snapshot_version(NonZeroU64::new(snapshot_version).unwrap())
+        .timestamp_expires(expires)
+        .timestamp_version(NonZeroU64::new(timestamp_version).unwrap())
+        .add_target_path(targets_path.join("file1.txt"))
+        .unwrap();
+    let signed_repo = editor.sign(&keys).unwrap();
+    signed_repo.write(outdir.join("metadata")).unwrap();
+    signed_repo
+        .copy_targets(&targets_path, outdir.join("targets"), PathExists::Skip)
+        .unwrap();
+}
+
+fn load(repo_dir: &Path, datastore: &Path) -> Result<Repository, Error> {
+    RepositoryLoader::new(
+        File::open(test_data().join("simple-rsa").join("root.json")).unwrap(),
+        dir_url(repo_dir.join("metadata")),
+        dir_url(repo_dir.join("targets")),
+    )
+    .datastore(datastore)
+    .load()
+}
+
+/// Test that a snapshot whose version is lower than the trusted snapshot's is reported as a
+/// rollback, with the role and both versions, even though the timestamp is newer.
+#[test]
+fn snapshot_rollback_detected() {
+    let datastore = TempDir::new().unwrap();
+    let newer = TempDir::new().unwrap();
+    write_repo(newer.path(), 2, 2);
+    load(newer.path(), datastore.path()).unwrap();
+
+    let older = TempDir::new().unwrap();
+    write_repo(older.path(), 1, 3);
+    let err = load(older.path(), datastore.path()).err().unwrap();
+    assert!(
+        matches!(
+            err,
+            Error::RollbackDetected {
+                role: RoleType::Snapshot,
+                trusted_version: 2,
+                offered_version: 1,
+                ..
+            }
+        ),
+        "unexpected error: {}",
+        err
+    );
+}
+
+/// Test that a timestamp whose version is lower than the trusted timestamp's is reported as a
+/// rollback.
+#[test]
+fn timestamp_rollback_detected() {
+    let datastore = TempDir::new().unwrap();
+    let newer = TempDir::new().unwrap();
+    write_repo(newer.path(), 1, 2);
+    load(newer.path(), datastore.path()).unwrap();
+
+    let older = TempDir::new().unwrap();
+    write_repo(older.path(), 1, 1);
+    assert!(matches!(
+        load(older.path(), datastore.path()),
+        Err(Error::RollbackDetected {
+            role: RoleType::Timestamp,
+            trusted_version: 2,
+            offered_version: 1,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,161 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::{Duration, Utc};
+use std::fs::File;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use test_utils::{dir_url, test_data};
+use tough::editor::root::RootEditor;
+use tough::editor::signed::PathExists;
+use tough::editor::RepositoryEditor;
+use tough::error::Error;
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::schema::decoded::{Decoded, Hex};
+use tough::schema::key::Key;
+use tough::schema::{RoleType, Root, Signed};
+use tough::RepositoryLoader;
+
+const ALL_ROLES: &[RoleType] = &[
+    RoleType::Root,
+    RoleType::Snapshot,
+    RoleType::Targets,
+    RoleType::Timestamp,
+];
+
+fn root_path() -> PathBuf {
+    test_data().join("simple-rsa").join("root.json")
+}
+
+fn targets_path() -> PathBuf {
+    test_data().join("tuf-reference-impl").join("targets")
+}
+
+fn old_key() -> Box<dyn KeySource> {
+    Box::new(LocalKeySource {
+        path: test_data().join("snakeoil.pem"),
+    })
+}
+
+fn new_key() -> Box<dyn KeySource> {
+    Box::new(LocalKeySource {
+        path: test_data().join("snakeoil_2.pem"),
+    })
+}
+
+fn tuf_key(key_source: &dyn KeySource) -> Key {
+    key_source.as_sign().unwrap().tuf_key()
+}
+
+// The ID of the only key in simple-rsa/root.json
+fn old_keyid() -> Decoded<Hex> {
+    let root: Signed<Root> = serde_json::from_reader(File::open(root_path()).unwrap()).unwrap();
+    root.signed.keys.keys().next().unwrap().clone()
+}
+
+// Writes a repository signed with `keys` using the root at `root_path`, and returns the loaded
+// repository's root version as seen by a client that trusts simple-rsa/root.json.
+fn write_and_load(root_path: &Path, keys: &[Box<dyn KeySource>], outdir: &Path) -> u64 {
+    let metadata_dir = outdir.join("metadata");
+    let targets_dir = outdir.join("targets");
+    let expires = Utc::now().checked_add_signed(Duration::days(7)).unwrap();
+    let version = NonZeroU64::new(1).unwrap();
+    let mut editor = RepositoryEditor::new(root_path).unwrap();
+    editor
+        .targets_expires(expires)
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .snapshot_expires(expires)
+        .snapshot_version(version)
+        .timestamp_expires(expires)
+        .timestamp_version(version)
+        .add_target_path(targets_path().join("file1.txt"))
+        .unwrap();
+    let signed_repo = editor.sign(keys).unwrap();
+    signed_repo.write(&metadata_dir).unwrap();
+    signed_repo
+        .copy_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .unwrap();
+
+    let repo = RepositoryLoader::new(
+        File::open(self::root_path()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(&targets_dir),
+    )
+    .load()
+    .unwrap();
+    repo.root().signed.version.get()
+}
+
+/// Test rotating every role to a new key: the new root is signed with both the old and new keys,
+/// and a client that trusts the old root follows the rotation to a repository signed with only the
+/// new key.
+#[test]
+fn rotate_root_keys() {
+    let mut editor = RootEditor::new(root_path()).unwrap();
+    editor
+        .add_key(tuf_key(new_key().as_ref()), ALL_ROLES)
+        .unwrap()
+        .remove_key(&old_keyid(), None);
+    assert_eq!(editor.root().version.get(), 2);
+
+    let signed_root = editor.sign(&[old_key(), new_key()]).unwrap();
+    assert_eq!(signed_root.signed().signatures.len(), 2);
+
+    let outdir = TempDir::new().unwrap();
+    let metadata_dir = outdir.path().join("metadata");
+    signed_root.write(&metadata_dir, false).unwrap();
+    let new_root_path = metadata_dir.join("2.root.json");
+    assert_eq!(
+        write_and_load(&new_root_path, &[new_key()], outdir.path()),
+        2
+    );
+}
+
+/// Test that a rotated root isn't produced unless it's signed by a threshold of the previous
+/// root's keys.
+#[test]
+fn rotate_root_keys_without_previous_key() {
+    let mut editor = RootEditor::new(root_path()).unwrap();
+    editor
+        .add_key(tuf_key(new_key().as_ref()), ALL_ROLES)
+        .unwrap()
+        .remove_key(&old_keyid(), None);
+    assert!(editor.sign(&[new_key()]).is_err());
+}
+
+/// Test that raising a role's threshold requires enough keys for both the new threshold and for
+/// the previous root.
+#[test]
+fn raise_root_threshold() {
+    let mut editor = RootEditor::new(root_path()).unwrap();
+    editor
+        .add_key(tuf_key(new_key().as_ref()), &[RoleType::Root])
+        .unwrap()
+        .threshold(RoleType::Root, NonZeroU64::new(2).unwrap());
+    assert!(matches!(
+        editor.sign(&[old_key()]),
+        Err(Error::VerifyRoleMetadata { .. })
+    ));
+
+    let signed_root = editor.sign(&[old_key(), new_key()]).unwrap();
+    let outdir = TempDir::new().unwrap();
+    let metadata_dir = outdir.path().join("metadata");
+    signed_root.write(&metadata_dir, false).unwrap();
+    let new_root_path = metadata_dir.join("2.root.json");
+    assert_eq!(
+        write_and_load(&new_root_path, &[old_key()], outdir.path()),
+        2
+    );
+
+    // Three keys are needed, but only two are listed for the root role
+    editor.threshold(RoleType::Root, NonZeroU64::new(3).unwrap());
+    assert!(matches!(
+        editor.sign(&[old_key(), new_key()]),
+        Err(Error::UnstableRoot { .. })
+    ));
+}
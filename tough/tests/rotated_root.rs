@@ -5,7 +5,8 @@ mod test_utils;
 
 use std::fs::File;
 use test_utils::{dir_url, test_data};
-use tough::RepositoryLoader;
+use tough::error::Error;
+use tough::{Limits, RepositoryLoader};
 
 #[test]
 fn rotated_root() {
@@ -21,3 +22,26 @@ fn rotated_root() {
 
     assert_eq!(u64::from(repo.root().signed.version), 2);
 }
+
+/// Test that a chain of root updates longer than `max_root_updates` is rejected, rather than
+/// followed for as long as the repository serves new roots.
+#[test]
+fn rotated_root_max_updates_exceeded() {
+    let base = test_data().join("rotated-root");
+
+    let result = RepositoryLoader::new(
+        File::open(base.join("1.root.json")).unwrap(),
+        dir_url(&base),
+        dir_url(base.join("targets")),
+    )
+    .limits(Limits::builder().max_root_updates(1).build())
+    .load();
+
+    assert!(matches!(
+        result,
+        Err(Error::MaxUpdatesExceeded {
+            max_root_updates: 1,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "s3")]
+
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::Region;
+use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher};
+use rusoto_s3::S3Client;
+use test_utils::read_to_end;
+use tough::{S3Transport, Transport, TransportErrorKind};
+use url::Url;
+
+mod test_utils;
+
+fn transport(dispatcher: MockRequestDispatcher) -> S3Transport {
+    S3Transport::new_with_client(
+        "my-repo",
+        S3Client::new_with(dispatcher, MockCredentialsProvider, Region::UsEast1),
+    )
+}
+
+#[test]
+fn s3_transport_fetch() {
+    let dispatcher = MockRequestDispatcher::with_status(200)
+        .with_body("{\"signed\":{}}")
+        .with_request_checker(|request: &SignedRequest| {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.path, "/my-repo/metadata/timestamp.json");
+        });
+    let url = Url::parse("s3://my-repo/metadata/timestamp.json").unwrap();
+    let reader = transport(dispatcher).fetch(url).unwrap();
+    assert_eq!(read_to_end(reader), b"{\"signed\":{}}");
+}
+
+#[test]
+fn s3_transport_file_not_found() {
+    let dispatcher = MockRequestDispatcher::with_status(404).with_body(
+        "<Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>",
+    );
+    let url = Url::parse("s3://my-repo/metadata/2.root.json").unwrap();
+    let err = transport(dispatcher).fetch(url).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::FileNotFound));
+}
+
+#[test]
+fn s3_transport_failure() {
+    let dispatcher = MockRequestDispatcher::with_status(500);
+    let url = Url::parse("s3://my-repo/metadata/timestamp.json").unwrap();
+    let err = transport(dispatcher).fetch(url).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::Other));
+}
+
+#[test]
+fn s3_transport_rejects_other_urls() {
+    let transport = transport(MockRequestDispatcher::default());
+
+    let url = Url::parse("https://example.com/metadata/timestamp.json").unwrap();
+    let err = transport.fetch(url).err().unwrap();
+    assert!(matches!(
+        err.kind(),
+        TransportErrorKind::UnsupportedUrlScheme
+    ));
+
+    let url = Url::parse("s3://another-bucket/metadata/timestamp.json").unwrap();
+    let err = transport.fetch(url).err().unwrap();
+    assert!(matches!(err.kind(), TransportErrorKind::Other));
+}
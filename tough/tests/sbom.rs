@@ -0,0 +1,47 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::fs::File;
+use test_utils::{dir_url, test_data};
+use tough::sbom::cyclonedx;
+use tough::RepositoryLoader;
+
+mod test_utils;
+
+/// Test that the SBOM lists every target of the reference repo, including the delegated target,
+/// with its hash and custom metadata.
+#[test]
+fn cyclonedx_reference_impl() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let sbom = cyclonedx(&repo);
+    assert_eq!(sbom["bomFormat"], "CycloneDX");
+    let components = sbom["components"].as_array().unwrap();
+    let names: Vec<&str> = components
+        .iter()
+        .map(|component| component["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["file1.txt", "file2.txt", "file3.txt"]);
+
+    assert_eq!(
+        components[0]["hashes"][0]["content"],
+        "65b8c67f51c993d898250f40aa57a317d854900b3a04895464313e48785440da"
+    );
+    assert_eq!(components[0]["properties"][0]["name"], "file_permissions");
+    assert_eq!(components[0]["properties"][0]["value"], "0644");
+    assert_eq!(
+        components[1]["hashes"][0]["content"],
+        "452ce8308500d83ef44248d8e6062359211992fd837ea9e370e561efb1a4ca99"
+    );
+    assert_eq!(
+        components[2]["hashes"][0]["content"],
+        "141f740f53781d1ca54b8a50af22cbf74e44c21a998fa2a8a05aaac2c002886b"
+    );
+}
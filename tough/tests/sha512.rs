@@ -0,0 +1,41 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::error::Error;
+use tough::RepositoryLoader;
+
+/// Test a repository whose metadata and targets are listed with SHA-512 hashes, either alone or
+/// along with SHA-256 hashes.
+#[test]
+fn sha512_hashes() {
+    let base = test_data().join("sha512");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"This target only has a SHA-512 hash.\n"[..]
+    );
+    assert_eq!(
+        read_to_end(repo.read_target("file2.txt").unwrap().unwrap()),
+        &b"This target has SHA-256 and SHA-512 hashes.\n"[..]
+    );
+    let hashes = repo.target_hashes("file1.txt").unwrap().unwrap();
+    assert!(hashes.sha256.is_none());
+    assert!(hashes.sha512.is_some());
+
+    // A target whose hashes are all for unsupported algorithms can't be verified.
+    assert!(matches!(
+        repo.read_target("file3.txt"),
+        Err(Error::NoSupportedHash { .. })
+    ));
+}
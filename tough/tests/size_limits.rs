@@ -0,0 +1,63 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, test_data};
+use tough::error::{Error, Result};
+use tough::schema::RoleType;
+use tough::{Limits, Repository, RepositoryLoader};
+
+fn load_with_limits(limits: Limits) -> Result<Repository> {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .limits(limits)
+    .load()
+}
+
+/// Test that a timestamp.json larger than `max_timestamp_size` is rejected with an error naming
+/// the timestamp role.
+#[test]
+fn oversized_timestamp() {
+    let result = load_with_limits(Limits::builder().max_timestamp_size(100).build());
+    assert!(matches!(
+        result,
+        Err(Error::MetadataSizeExceeded {
+            role: RoleType::Timestamp,
+            max_size: 100,
+            ..
+        })
+    ));
+}
+
+/// Test that a snapshot.json whose signed length is larger than `max_snapshot_size` is rejected
+/// with an error naming the snapshot role.
+#[test]
+fn oversized_snapshot() {
+    let result = load_with_limits(Limits::builder().max_snapshot_size(100).build());
+    assert!(matches!(
+        result,
+        Err(Error::MetadataSizeExceeded {
+            role: RoleType::Snapshot,
+            max_size: 100,
+            ..
+        })
+    ));
+}
+
+/// Test that per-role limits only apply to their own role.
+#[test]
+fn per_role_limits() {
+    load_with_limits(
+        Limits::builder()
+            .max_timestamp_size(1000)
+            .max_snapshot_size(1000)
+            .build(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,45 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::RepositoryLoader;
+
+/// Test a repository where `targets` uses a succinct hash delegation with a `bit_length` of 2 and
+/// the `name_prefix` `bin`, so targets are split between the roles `bin-0` to `bin-3`.
+#[test]
+fn succinct_delegation() {
+    let base = test_data().join("succinct-delegation");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    let roles: Vec<_> = repo
+        .delegated_roles()
+        .iter()
+        .map(|role| role.name.as_str())
+        .collect();
+    assert_eq!(roles, vec!["bin-0", "bin-1", "bin-2", "bin-3"]);
+
+    // sha256("a.txt") starts with 0b00, and sha256("b.txt") with 0b11.
+    assert_eq!(
+        read_to_end(repo.read_target("a.txt").unwrap().unwrap()),
+        &b"a.txt is in bin-0.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("a.txt"), Some("bin-0"));
+    assert_eq!(
+        read_to_end(repo.read_target("b.txt").unwrap().unwrap()),
+        &b"b.txt is in bin-3.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("b.txt"), Some("bin-3"));
+
+    // sha256("file3.txt") starts with 0b01, so it is not found in bin-2, which lists it.
+    assert!(repo.read_target("file3.txt").unwrap().is_none());
+    assert_eq!(repo.role_for_target("file3.txt"), None);
+}
@@ -0,0 +1,39 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::fs::File;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::RepositoryLoader;
+
+/// Test a repository where `targets` delegates `*.txt` to the terminating `role1`, then `*` to
+/// `role2`. `role2` lists `file2.txt`, but it must not be found there, because `role1` is
+/// terminating for it.
+#[test]
+fn terminating_delegation() {
+    let base = test_data().join("terminating-delegation");
+    let repo = RepositoryLoader::new(
+        File::open(base.join("metadata").join("1.root.json")).unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .unwrap();
+
+    assert_eq!(
+        read_to_end(repo.read_target("file1.txt").unwrap().unwrap()),
+        &b"Signed by role1, which is terminating for *.txt.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("file1.txt"), Some("role1"));
+    assert_eq!(
+        read_to_end(repo.read_target("data.bin").unwrap().unwrap()),
+        &b"Signed by role2.\n"[..]
+    );
+    assert_eq!(repo.role_for_target("data.bin"), Some("role2"));
+
+    assert!(repo.read_target("file2.txt").unwrap().is_none());
+    assert!(!repo.target_exists("file2.txt"));
+    assert_eq!(repo.role_for_target("file2.txt"), None);
+    assert!(repo.target_provenance("file2.txt").is_none());
+}
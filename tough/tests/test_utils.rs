@@ -3,6 +3,7 @@
 
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use tough::key_source::{KeySource, LocalKeySource};
 use url::Url;
 
 /// Utilities for tests. Not every test module uses every function, so we suppress unused warnings.
@@ -28,3 +29,30 @@ pub fn read_to_end<R: Read>(mut reader: R) -> Vec<u8> {
     reader.read_to_end(&mut v).unwrap();
     v
 }
+
+/// Returns the private key at `path` as a list of key sources, ready to sign a role with.
+#[allow(unused)]
+pub fn local_key_sources<P: Into<PathBuf>>(path: P) -> Vec<Box<dyn KeySource>> {
+    vec![Box::new(LocalKeySource { path: path.into() })]
+}
+
+/// Asserts that `path` has the permissions that `File::create` gives a new file in the same
+/// directory, i.e. that it wasn't left readable only by its owner.
+#[cfg(unix)]
+#[allow(unused)]
+pub fn assert_default_permissions<P: AsRef<Path>>(path: P) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = path.as_ref();
+    let probe = path.with_file_name(".default-permissions");
+    std::fs::File::create(&probe).unwrap();
+    let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    let expected = mode(&probe);
+    std::fs::remove_file(&probe).unwrap();
+    assert_eq!(
+        format!("{:o}", mode(path)),
+        format!("{:o}", expected),
+        "{}",
+        path.display()
+    );
+}
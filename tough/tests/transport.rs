@@ -1,8 +1,17 @@
 use std::fs;
+use std::io::Read;
+use std::num::NonZeroU64;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 use test_utils::read_to_end;
-use tough::{DefaultTransport, Transport, TransportErrorKind};
+#[cfg(windows)]
+use test_utils::test_data;
+use tough::{
+    CachingTransport, ChainTransport, DefaultTransport, FetchEvent, FetchOutcome,
+    FilesystemTransport, MeteredTransport, ThrottledTransport, Transport, TransportError,
+    TransportErrorKind,
+};
 use url::Url;
 
 mod test_utils;
@@ -47,3 +56,230 @@ fn default_transport_file() {
     let contents = String::from_utf8_lossy(&temp_vec);
     assert_eq!(contents, "123123987");
 }
+
+#[test]
+fn throttled_transport_file() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, vec![b'a'; 2500]).unwrap();
+    // The rate is high enough that the file isn't delayed; how reads are throttled is tested with
+    // the adapter that does it.
+    let transport = ThrottledTransport::new(
+        Box::new(FilesystemTransport::new()),
+        NonZeroU64::new(1024 * 1024).unwrap(),
+    );
+    let url = Url::from_file_path(&filepath).unwrap();
+    assert!(transport.exists(url.clone()).unwrap());
+    let read = transport.fetch(url).unwrap();
+    assert_eq!(read_to_end(read).len(), 2500);
+}
+
+/// A transport that always fails with an error of the given kind.
+#[derive(Debug, Clone, Copy)]
+struct FailingTransport(TransportErrorKind);
+
+impl Transport for FailingTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        Err(TransportError::new(self.0, url))
+    }
+}
+
+#[test]
+fn chain_transport_falls_back() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, "mirrored").unwrap();
+    let transport = ChainTransport::new(vec![
+        Box::new(FailingTransport(TransportErrorKind::FileNotFound)),
+        Box::new(FailingTransport(TransportErrorKind::Other)),
+        Box::new(FilesystemTransport::new()),
+    ]);
+    let url = Url::from_file_path(filepath).unwrap();
+    let read = transport.fetch(url).unwrap();
+    assert_eq!(read_to_end(read), b"mirrored");
+}
+
+#[test]
+fn chain_transport_returns_last_error() {
+    let transport = ChainTransport::new(vec![
+        Box::new(FailingTransport(TransportErrorKind::Other)),
+        Box::new(FailingTransport(TransportErrorKind::FileNotFound)),
+    ]);
+    let url = Url::from_str("https://example.com/timestamp.json").unwrap();
+    let error = transport.fetch(url.clone()).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::FileNotFound));
+    assert_eq!(error.url(), url.as_str());
+
+    let error = ChainTransport::new(Vec::new()).fetch(url).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::Other));
+}
+
+#[test]
+fn metered_transport_reports_fetches() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, "metered").unwrap();
+    let events: Arc<Mutex<Vec<FetchEvent>>> = Arc::default();
+    let recorded = Arc::clone(&events);
+    let transport = MeteredTransport::new(Box::new(FilesystemTransport::new()), move |event| {
+        recorded.lock().unwrap().push(event.clone());
+    });
+
+    let url = Url::from_file_path(&filepath).unwrap();
+    assert_eq!(
+        read_to_end(transport.fetch(url.clone()).unwrap()),
+        b"metered"
+    );
+    let missing = Url::from_file_path(dir.path().join("missing.txt")).unwrap();
+    let error = transport.fetch(missing.clone()).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::FileNotFound));
+    assert_eq!(error.url(), missing.as_str());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].url, url);
+    assert_eq!(events[0].bytes, 7);
+    assert_eq!(events[0].outcome, FetchOutcome::Complete);
+    assert_eq!(events[1].url, missing);
+    assert_eq!(
+        events[1].outcome,
+        FetchOutcome::FetchFailed(TransportErrorKind::FileNotFound)
+    );
+}
+
+#[test]
+fn caching_transport_replays_files() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, "cached").unwrap();
+    let cache_dir = dir.path().join("cache");
+    let transport = CachingTransport::new(Box::new(FilesystemTransport::new()), &cache_dir);
+    let url = Url::from_file_path(&filepath).unwrap();
+    assert_eq!(
+        read_to_end(transport.fetch(url.clone()).unwrap()),
+        b"cached"
+    );
+
+    // once the original is gone, the file is served from the cache.
+    fs::remove_file(&filepath).unwrap();
+    assert_eq!(
+        read_to_end(transport.fetch(url.clone()).unwrap()),
+        b"cached"
+    );
+
+    // files that were never fetched are still not found.
+    let missing = Url::from_file_path(dir.path().join("missing.txt")).unwrap();
+    let error = transport.fetch(missing).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::FileNotFound));
+}
+
+#[test]
+fn filesystem_transport_exists() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, "exists").unwrap();
+    let transport = FilesystemTransport::new();
+    assert!(transport
+        .exists(Url::from_file_path(&filepath).unwrap())
+        .unwrap());
+    assert!(!transport
+        .exists(Url::from_file_path(dir.path().join("missing.txt")).unwrap())
+        .unwrap());
+}
+
+/// Test that a `FilesystemTransport` that doesn't follow symlinks refuses a symlink that leads
+/// outside of its base directory, but still follows one that stays inside it.
+#[cfg(unix)]
+#[test]
+fn filesystem_transport_escaping_symlink() {
+    let outside = TempDir::new().unwrap();
+    let secret = outside.path().join("secret.txt");
+    fs::write(&secret, "secret").unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("file.txt"), "inside").unwrap();
+    std::os::unix::fs::symlink(&secret, dir.path().join("escape.txt")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("file.txt"), dir.path().join("link.txt")).unwrap();
+    let escape = Url::from_file_path(dir.path().join("escape.txt")).unwrap();
+    let link = Url::from_file_path(dir.path().join("link.txt")).unwrap();
+
+    // Symlinks are followed by default.
+    assert_eq!(
+        read_to_end(FilesystemTransport::new().fetch(escape.clone()).unwrap()),
+        b"secret"
+    );
+
+    let transport = FilesystemTransport::new()
+        .follow_symlinks(false)
+        .base_dir(dir.path())
+        .unwrap();
+    let error = transport.fetch(escape.clone()).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::Other));
+    assert!(transport.exists(escape).is_err());
+    assert_eq!(
+        read_to_end(transport.fetch(link.clone()).unwrap()),
+        b"inside"
+    );
+    assert!(transport.exists(link).unwrap());
+
+    // Without a base directory, nothing can be checked, so nothing is fetched.
+    let transport = FilesystemTransport::new().follow_symlinks(false);
+    let error = transport
+        .fetch(Url::from_file_path(dir.path().join("file.txt")).unwrap())
+        .err()
+        .unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::Other));
+}
+
+/// Test that a symlinked directory within the base directory, rather than a symlinked file, can't
+/// be used to escape it.
+#[cfg(unix)]
+#[test]
+fn filesystem_transport_escaping_symlinked_directory() {
+    let outside = TempDir::new().unwrap();
+    fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("targets")).unwrap();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("targets").join("sub")).unwrap();
+    let escape =
+        Url::from_file_path(dir.path().join("targets").join("sub").join("secret.txt")).unwrap();
+    let missing = Url::from_file_path(dir.path().join("targets").join("missing.txt")).unwrap();
+
+    let transport = FilesystemTransport::new()
+        .follow_symlinks(false)
+        .base_dir(dir.path())
+        .unwrap();
+    let error = transport.fetch(escape.clone()).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::Other));
+    assert!(transport.exists(escape).is_err());
+    assert!(!transport.exists(missing.clone()).unwrap());
+    let error = transport.fetch(missing).err().unwrap();
+    assert!(matches!(error.kind(), TransportErrorKind::FileNotFound));
+}
+
+/// Test that a `file:` URL for a path with a drive letter, whose URL path looks like `/C:/...`, is
+/// converted to a path that can be opened.
+#[cfg(windows)]
+#[test]
+fn filesystem_transport_windows_drive_path() {
+    let path = test_data()
+        .join("tuf-reference-impl")
+        .join("targets")
+        .join("file1.txt");
+    let url = Url::from_file_path(&path).unwrap();
+    assert_eq!(url.path().chars().nth(2), Some(':'));
+    assert_eq!(
+        read_to_end(FilesystemTransport::new().fetch(url).unwrap()),
+        b"This is an example target file."
+    );
+}
+
+#[test]
+fn default_exists_uses_fetch() {
+    let url = Url::from_str("https://example.com/timestamp.json").unwrap();
+    assert!(!FailingTransport(TransportErrorKind::FileNotFound)
+        .exists(url.clone())
+        .unwrap());
+    assert!(FailingTransport(TransportErrorKind::Other)
+        .exists(url)
+        .is_err());
+}
@@ -0,0 +1,56 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::repository_loader;
+use crate::error::{self, Result};
+use snafu::ResultExt;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use url::Url;
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct CloneArgs {
+    /// Path to root.json file for the repository
+    #[structopt(short = "r", long = "root")]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[structopt(short = "m", long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// TUF repository targets base URL
+    #[structopt(short = "t", long = "targets-url")]
+    targets_base_url: Url,
+
+    /// Clone only these targets, if specified
+    #[structopt(short = "n", long = "target")]
+    targets: Vec<String>,
+
+    /// Output directory; metadata is written to `metadata` and targets to `targets` within it
+    outdir: PathBuf,
+}
+
+impl CloneArgs {
+    pub(crate) fn run(&self) -> Result<()> {
+        let repository = repository_loader(
+            &self.root,
+            self.metadata_base_url.clone(),
+            self.targets_base_url.clone(),
+        )?
+        .load()
+        .context(error::RepoLoad)?;
+
+        let metadata_outdir = self.outdir.join("metadata");
+        let targets_outdir = self.outdir.join("targets");
+        let targets_subset = if self.targets.is_empty() {
+            None
+        } else {
+            Some(self.targets.as_slice())
+        };
+        // Every version of root.json is kept so that clients that trust an older root can load
+        // the clone.
+        repository
+            .cache(&metadata_outdir, &targets_outdir, targets_subset, true)
+            .context(error::CloneRepository { path: &self.outdir })
+    }
+}
@@ -1,5 +1,6 @@
 /// This module is for code that is re-used by different `tuftool` subcommands.
 use crate::error::{self, Result};
+use crate::transport::SchemeTransport;
 use snafu::ResultExt;
 use std::fs::File;
 use std::path::Path;
@@ -18,18 +19,37 @@ pub(crate) const UNUSED_URL: &str = "file:///unused/url";
 /// - `metadata_url` can be local or remote.
 ///
 pub(crate) fn load_metadata_repo<P>(root: P, metadata_url: Url) -> Result<Repository>
+where
+    P: AsRef<Path>,
+{
+    // we don't do anything with the targets url for metadata operations
+    let targets_url = Url::parse(UNUSED_URL).with_context(|| error::UrlParse {
+        url: UNUSED_URL.to_owned(),
+    })?;
+    repository_loader(root, metadata_url, targets_url)?
+        .load()
+        .context(error::RepoLoad)
+}
+
+/// Creates a `RepositoryLoader` for the repository at `metadata_url` and `targets_url`, which
+/// fetches files with a transport chosen by URL scheme, so that `s3://` URLs can be used along with
+/// `file://`, `http://`, and `https://` URLs.
+///
+/// - `root` must be a path to a file that can be opened with `File::open`.
+pub(crate) fn repository_loader<P>(
+    root: P,
+    metadata_url: Url,
+    targets_url: Url,
+) -> Result<RepositoryLoader<File>>
 where
     P: AsRef<Path>,
 {
     let root = root.as_ref();
-    RepositoryLoader::new(
+    let transport = SchemeTransport::for_urls(&[&metadata_url, &targets_url]);
+    Ok(RepositoryLoader::new(
         File::open(root).context(error::OpenRoot { path: root })?,
         metadata_url,
-        // we don't do anything with the targets url for metadata operations
-        Url::parse(UNUSED_URL).with_context(|| error::UrlParse {
-            url: UNUSED_URL.to_owned(),
-        })?,
+        targets_url,
     )
-    .load()
-    .context(error::RepoLoad)
+    .transport(transport))
 }
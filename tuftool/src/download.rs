@@ -1,6 +1,7 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::common::repository_loader;
 use crate::error::{self, Result};
 use snafu::{OptionExt, ResultExt};
 use std::fs::File;
@@ -8,7 +9,7 @@ use std::io::{self};
 use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
-use tough::{ExpirationEnforcement, Repository, RepositoryLoader};
+use tough::{ExpirationEnforcement, Repository};
 use url::Url;
 
 #[derive(Debug, StructOpt)]
@@ -105,11 +106,11 @@ impl DownloadArgs {
         } else {
             ExpirationEnforcement::Safe
         };
-        let repository = RepositoryLoader::new(
-            File::open(&root_path).context(error::OpenRoot { path: &root_path })?,
+        let repository = repository_loader(
+            &root_path,
             self.metadata_base_url.clone(),
             self.targets_base_url.clone(),
-        )
+        )?
         .expiration_enforcement(expiration_enforcement)
         .load()
         .context(error::RepoLoad)?;
@@ -0,0 +1,52 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::repository_loader;
+use crate::error::{self, Result};
+use snafu::ResultExt;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use url::Url;
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct DownloadTargetArgs {
+    /// Path to root.json file for the repository
+    #[structopt(short = "r", long = "root")]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[structopt(short = "m", long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// TUF repository targets base URL
+    #[structopt(short = "t", long = "targets-url")]
+    targets_base_url: Url,
+
+    /// Name of the target to download
+    target_name: String,
+
+    /// Path to write the target to
+    output: PathBuf,
+}
+
+impl DownloadTargetArgs {
+    pub(crate) fn run(&self) -> Result<()> {
+        let repository = repository_loader(
+            &self.root,
+            self.metadata_base_url.clone(),
+            self.targets_base_url.clone(),
+        )?
+        .load()
+        .context(error::RepoLoad)?;
+
+        // The target's length and hashes are only checked as it's read, so `save_target` writes it
+        // to a temporary file first; unverified data is never left at the output path.
+        repository
+            .save_target(&self.target_name, &self.output)
+            .context(error::TargetSave {
+                target: &self.target_name,
+                path: &self.output,
+            })?;
+        Ok(())
+    }
+}
@@ -13,6 +13,13 @@ pub(crate) type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug, Snafu)]
 #[snafu(visibility = "pub(crate)")]
 pub(crate) enum Error {
+    #[snafu(display("Failed to clone repository to '{}': {}", path.display(), source))]
+    CloneRepository {
+        path: PathBuf,
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to run {}: {}", command_str, source))]
     CommandExec {
         command_str: String,
@@ -118,6 +125,9 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Unable to convert URL \"{}\" to a file path", url))]
+    FileUrl { url: String, backtrace: Backtrace },
+
     #[snafu(display("Failed to write to {}: {}", path.display(), source))]
     FileWrite {
         path: PathBuf,
@@ -286,12 +296,33 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to save target '{}' to '{}': {}", target, path.display(), source))]
+    TargetSave {
+        target: String,
+        path: PathBuf,
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to create temporary directory: {}", source))]
     TempDir {
         source: std::io::Error,
         backtrace: Backtrace,
     },
 
+    #[snafu(display("PKCS#11 URL \"{}\" is missing \"{}\"", url, attribute))]
+    Pkcs11UrlAttribute {
+        url: String,
+        attribute: &'static str,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Unrecognized AWS KMS signing algorithm \"{}\"", algorithm))]
+    UnrecognizedSigningAlgorithm {
+        algorithm: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unrecognized URL scheme \"{}\"", scheme))]
     UnrecognizedScheme {
         scheme: String,
@@ -351,6 +382,24 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to verify repository: {}", source))]
+    VerifyRepository {
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to write JSON to stdout: {}", source))]
+    WriteJsonStdout {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to write to stdout: {}", source))]
+    WriteStdout {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed writing target data to disk: {}", source))]
     WriteTarget {
         source: std::io::Error,
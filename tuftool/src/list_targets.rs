@@ -0,0 +1,74 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::load_metadata_repo;
+use crate::error::{self, Result};
+use crate::output::OutputFormat;
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use url::Url;
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ListTargetsArgs {
+    /// Path to root.json file for the repository
+    #[structopt(short = "r", long = "root")]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[structopt(short = "m", long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Print the targets as a JSON array; the same as `--format json`
+    #[structopt(long)]
+    json: bool,
+}
+
+/// A target as printed by `list-targets`.
+#[derive(Debug, Serialize)]
+struct TargetListing<'a> {
+    name: &'a str,
+    length: u64,
+    /// The role that signs for the target, or `None` if the TUF delegation search wouldn't reach
+    /// any role that lists it.
+    role: Option<&'a str>,
+}
+
+impl ListTargetsArgs {
+    pub(crate) fn run(&self, format: OutputFormat) -> Result<()> {
+        let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone())?;
+
+        let mut listings = Vec::new();
+        for name in repository.target_names() {
+            let length = repository
+                .target_length(name)
+                .context(error::Metadata)?
+                .context(error::TargetNotFound { target: name })?;
+            listings.push(TargetListing {
+                name,
+                length,
+                role: repository.role_for_target(name),
+            });
+        }
+
+        let format = if self.json {
+            OutputFormat::Json
+        } else {
+            format
+        };
+        format.print(&listings, |out, listings| {
+            for listing in listings {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}",
+                    listing.name,
+                    listing.length,
+                    listing.role.unwrap_or("-")
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -13,20 +13,27 @@
 
 mod add_key_role;
 mod add_role;
+mod clone;
 mod common;
 mod create;
 mod create_role;
 mod datetime;
 mod download;
+mod download_target;
 mod error;
+mod list_targets;
+mod output;
 mod remove_key_role;
 mod remove_role;
 mod root;
 mod source;
+mod transport;
 mod update;
 mod update_targets;
+mod verify;
 
 use crate::error::Result;
+use crate::output::OutputFormat;
 use rayon::prelude::*;
 use simplelog::{ConfigBuilder, LevelFilter, TermLogger, TerminalMode};
 use snafu::{ErrorCompat, OptionExt, ResultExt};
@@ -34,6 +41,7 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
+use structopt::clap::ErrorKind;
 use structopt::StructOpt;
 use tempfile::NamedTempFile;
 use tough::schema::Target;
@@ -44,55 +52,78 @@ static SPEC_VERSION: &str = "1.0.0";
 /// This wrapper enables global options and initializes the logger before running any subcommands.
 #[derive(StructOpt)]
 struct Program {
-    /// Set logging verbosity [trace|debug|info|warn|error]
-    #[structopt(
-        name = "log-level",
-        short = "l",
-        long = "log-level",
-        default_value = "info"
-    )]
-    log_level: LevelFilter,
+    /// Set logging verbosity [trace|debug|info|warn|error|off] [default: info, or off with
+    /// `--format json`]
+    #[structopt(name = "log-level", short = "l", long = "log-level")]
+    log_level: Option<LevelFilter>,
+    /// Output format of informational commands and errors [text|json]
+    #[structopt(long = "format", default_value = "text")]
+    format: OutputFormat,
     #[structopt(subcommand)]
     cmd: Command,
 }
 
 impl Program {
     fn run(self) -> Result<()> {
+        // Keep stdout for the JSON document alone, and unless logs are requested, keep stderr for
+        // the JSON error alone.
+        let (log_level, terminal_mode) = match self.format {
+            OutputFormat::Json => (
+                self.log_level.unwrap_or(LevelFilter::Off),
+                TerminalMode::Stderr,
+            ),
+            OutputFormat::Text => (
+                self.log_level.unwrap_or(LevelFilter::Info),
+                TerminalMode::Mixed,
+            ),
+        };
         TermLogger::init(
-            self.log_level,
+            log_level,
             ConfigBuilder::new()
                 .add_filter_allow_str("tuftool")
                 .add_filter_allow_str("tough")
                 .build(),
-            TerminalMode::Mixed,
+            terminal_mode,
         )
         .context(error::Logger)?;
-        self.cmd.run()
+        self.cmd.run(self.format)
     }
 }
 
 #[derive(Debug, StructOpt)]
 enum Command {
+    /// Clone a TUF repository's metadata and targets to a local directory
+    Clone(clone::CloneArgs),
     /// Create a TUF repository
     Create(create::CreateArgs),
     /// Download a TUF repository's resources
     Download(download::DownloadArgs),
+    /// Download and verify a single target from a TUF repository
+    DownloadTarget(download_target::DownloadTargetArgs),
+    /// List the targets of a TUF repository, including delegated targets
+    ListTargets(list_targets::ListTargetsArgs),
     /// Update a TUF repository's metadata and optionally add targets
     Update(Box<update::UpdateArgs>),
     /// Manipulate a root.json metadata file
     Root(root::Command),
     /// Delegation Commands
     Delegation(Delegation),
+    /// Verify a TUF repository's metadata and show when each role expires
+    Verify(verify::VerifyArgs),
 }
 
 impl Command {
-    fn run(self) -> Result<()> {
+    fn run(self, format: OutputFormat) -> Result<()> {
         match self {
+            Command::Clone(args) => args.run(),
             Command::Create(args) => args.run(),
-            Command::Root(root_subcommand) => root_subcommand.run(),
+            Command::Root(root_subcommand) => root_subcommand.run(format),
             Command::Download(args) => args.run(),
+            Command::DownloadTarget(args) => args.run(),
+            Command::ListTargets(args) => args.run(format),
             Command::Update(args) => args.run(),
             Command::Delegation(cmd) => cmd.run(),
+            Command::Verify(args) => args.run(format),
         }
     }
 }
@@ -157,11 +188,41 @@ fn process_target(path: &Path) -> Result<(String, Target)> {
     Ok((target_name, target))
 }
 
+/// Prints an error in parsing the arguments and exits. The error is printed as a JSON object if
+/// `--format json` was passed; requests for help or the version are printed as usual.
+fn exit_with_argument_error(err: structopt::clap::Error) -> ! {
+    let args: Vec<String> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let json = args.iter().any(|arg| arg == "--format=json")
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "--format" && pair[1] == "json");
+    match err.kind {
+        ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => err.exit(),
+        _ if json => {
+            eprintln!("{}", serde_json::json!({ "error": err.message.trim() }));
+            std::process::exit(1);
+        }
+        _ => err.exit(),
+    }
+}
+
 fn main() -> ! {
-    std::process::exit(match Program::from_args().run() {
+    let program = match Program::from_iter_safe(std::env::args_os()) {
+        Ok(program) => program,
+        Err(err) => exit_with_argument_error(err),
+    };
+    let format = program.format;
+    std::process::exit(match program.run() {
         Ok(()) => 0,
         Err(err) => {
-            eprintln!("{}", err);
+            match format {
+                OutputFormat::Json => {
+                    eprintln!("{}", serde_json::json!({ "error": err.to_string() }));
+                }
+                OutputFormat::Text => eprintln!("{}", err),
+            }
             if let Some(var) = std::env::var_os("RUST_BACKTRACE") {
                 if var != "0" {
                     if let Some(backtrace) = err.backtrace() {
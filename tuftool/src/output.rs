@@ -0,0 +1,51 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Printing the results of informational commands as text or JSON.
+
+use crate::error::{self, Result};
+use serde::Serialize;
+use snafu::ResultExt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// How informational commands print their results, set with the global `--format` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    /// Human-readable text.
+    Text,
+    /// A single JSON value, for scripts.
+    Json,
+}
+
+impl OutputFormat {
+    /// Prints `value` to stdout as pretty JSON if the format is `Json`, or otherwise calls `text`
+    /// to print it for humans.
+    pub(crate) fn print<T, F>(self, value: &T, text: F) -> Result<()>
+    where
+        T: Serialize,
+        F: FnOnce(&mut dyn Write, &T) -> std::io::Result<()>,
+    {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        match self {
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut stdout, value).context(error::WriteJsonStdout)?;
+                writeln!(stdout).context(error::WriteStdout)
+            }
+            OutputFormat::Text => text(&mut stdout, value).context(error::WriteStdout),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format '{}'", s)),
+        }
+    }
+}
@@ -3,6 +3,7 @@
 
 use crate::datetime::parse_datetime;
 use crate::error::{self, Result};
+use crate::output::OutputFormat;
 use crate::source::parse_key_source;
 use crate::{load_file, write_file};
 use chrono::{DateTime, Timelike, Utc};
@@ -125,7 +126,7 @@ macro_rules! role_keys {
 }
 
 impl Command {
-    pub(crate) fn run(self) -> Result<()> {
+    pub(crate) fn run(self, format: OutputFormat) -> Result<()> {
         match self {
             Command::Init { path } => Command::init(&path),
             Command::BumpVersion { path } => Command::bump_version(&path),
@@ -140,7 +141,7 @@ impl Command {
                 path,
                 roles,
                 key_source,
-            } => Command::add_key(&path, &roles, &key_source),
+            } => Command::add_key(&path, &roles, &key_source, format),
             Command::RemoveKey { path, key_id, role } => Command::remove_key(&path, &key_id, role),
             Command::GenRsaKey {
                 path,
@@ -148,7 +149,7 @@ impl Command {
                 key_source,
                 bits,
                 exponent,
-            } => Command::gen_rsa_key(&path, &roles, &key_source, bits, exponent),
+            } => Command::gen_rsa_key(&path, &roles, &key_source, bits, exponent, format),
             Command::Sign {
                 path,
                 key_sources,
@@ -220,7 +221,12 @@ impl Command {
     }
 
     #[allow(clippy::borrowed_box)]
-    fn add_key(path: &PathBuf, roles: &[RoleType], key_source: &Box<dyn KeySource>) -> Result<()> {
+    fn add_key(
+        path: &PathBuf,
+        roles: &[RoleType],
+        key_source: &Box<dyn KeySource>,
+        format: OutputFormat,
+    ) -> Result<()> {
         let mut root: Signed<Root> = load_file(path)?;
         let key_pair = key_source
             .as_sign()
@@ -228,8 +234,8 @@ impl Command {
             .tuf_key();
         let key_id = hex::encode(add_key(&mut root.signed, roles, key_pair)?);
         clear_sigs(&mut root);
-        println!("{}", key_id);
-        write_file(path, &root)
+        write_file(path, &root)?;
+        print_key_id(format, &key_id)
     }
 
     fn remove_key(path: &PathBuf, key_id: &Decoded<Hex>, role: Option<RoleType>) -> Result<()> {
@@ -263,6 +269,7 @@ impl Command {
         key_source: &Box<dyn KeySource>,
         bits: u16,
         exponent: u32,
+        format: OutputFormat,
     ) -> Result<()> {
         let mut root: Signed<Root> = load_file(path)?;
 
@@ -294,8 +301,8 @@ impl Command {
             .write(&stdout, &key_id)
             .context(error::WriteKeySource)?;
         clear_sigs(&mut root);
-        println!("{}", key_id);
-        write_file(path, &root)
+        write_file(path, &root)?;
+        print_key_id(format, &key_id)
     }
 
     fn sign(
@@ -375,6 +382,13 @@ fn round_time(time: DateTime<Utc>) -> DateTime<Utc> {
     time.with_nanosecond(0).unwrap()
 }
 
+/// Prints the ID of a key that was added to root.json.
+fn print_key_id(format: OutputFormat, key_id: &str) -> Result<()> {
+    format.print(&serde_json::json!({ "keyid": key_id }), |out, _| {
+        writeln!(out, "{}", key_id)
+    })
+}
+
 /// Removes signatures from a role. Useful if the content is updated.
 fn clear_sigs<T>(role: &mut Signed<T>) {
     role.signatures.clear();
@@ -32,15 +32,35 @@
 //!
 //! You may also skip the profile bit and just use your local environment's default profile:
 //! "aws-ssm:///a/key" (notice the 3 slashes after the colon)
+//!
+//! When the "aws-kms" feature is enabled (it is by default), keys stored in AWS KMS use the key ID
+//! or alias of the KMS key:
+//! "aws-kms://<aws profile>/<key id or alias>?signing-algorithm=ECDSA_SHA_256"
+//!
+//! "signing-algorithm" is optional and defaults to "RSASSA_PSS_SHA_256". Use "ECDSA_SHA_256"
+//! for an "ECC_NIST_P256" KMS key.
+//!
+//! When the "pkcs11" feature is enabled, keys on a PKCS#11 token (e.g. an HSM) use a PKCS#11 URI
+//! (RFC 7512) naming the token, and the key by label ("object"), ID ("id"), or both:
+//! "pkcs11:token=tuf;object=root?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-value=1234"
+//!
+//! "module-path" is required. "pin-value" is optional; without it, the token is used without
+//! logging in.
 
 use crate::error::{self, Result};
-use snafu::ResultExt;
-use std::path::PathBuf;
+use snafu::{OptionExt, ResultExt};
 use tough::key_source::{KeySource, LocalKeySource};
-use tough_kms::{KmsKeySource, KmsSigningAlgorithm};
 use tough_ssm::SsmKeySource;
 use url::Url;
 
+#[cfg(feature = "aws-kms")]
+use tough_kms::{KmsKeySource, KmsSigningAlgorithm};
+
+#[cfg(feature = "pkcs11")]
+use percent_encoding::percent_decode_str;
+#[cfg(feature = "pkcs11")]
+use tough_pkcs11::Pkcs11KeySource;
+
 /// Parses a user-specified source of signing keys.
 /// Sources are passed to `tuftool` as arguments in string format:
 /// "file:///..." or "./a/path/here" or "aws-ssm://...". See above
@@ -57,8 +77,12 @@ pub(crate) fn parse_key_source(input: &str) -> Result<Box<dyn KeySource>> {
         .parse(input)
         .context(error::UrlParse { url: input })?;
     match url.scheme() {
+        // `to_file_path` decodes the path, and handles Windows drive letters (`/C:/...`).
         "file" => Ok(Box::new(LocalKeySource {
-            path: PathBuf::from(url.path()),
+            path: url
+                .to_file_path()
+                .ok()
+                .context(error::FileUrl { url: input })?,
         })),
         #[cfg(any(feature = "rusoto-native-tls", feature = "rusoto-rustls"))]
         "aws-ssm" => Ok(Box::new(SsmKeySource {
@@ -80,26 +104,142 @@ pub(crate) fn parse_key_source(input: &str) -> Result<Box<dyn KeySource>> {
                 }
             }),
         })),
-        "aws-kms" => Ok(Box::new(KmsKeySource {
-            profile: url.host_str().and_then(|s| {
-                if s.is_empty() {
-                    None
-                } else {
-                    Some(s.to_owned())
-                }
-            }),
-            // remove first '/' from the path to get the key_id
-            key_id: if url.path().is_empty() {
-                String::from("")
-            } else {
-                url.path()[1..].to_string()
-            },
-            client: None,
-            signing_algorithm: KmsSigningAlgorithm::RsassaPssSha256,
-        })),
+        #[cfg(feature = "aws-kms")]
+        "aws-kms" => parse_kms_url(&url),
+        #[cfg(feature = "pkcs11")]
+        "pkcs11" => Ok(Box::new(parse_pkcs11_url(input, &url)?)),
         _ => error::UnrecognizedScheme {
             scheme: url.scheme(),
         }
         .fail(),
     }
 }
+
+/// Parses an `aws-kms` URL into a `KmsKeySource`.
+#[cfg(feature = "aws-kms")]
+fn parse_kms_url(url: &Url) -> Result<Box<dyn KeySource>> {
+    Ok(Box::new(KmsKeySource {
+        profile: url.host_str().and_then(|s| {
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        }),
+        // remove first '/' from the path to get the key_id
+        key_id: if url.path().is_empty() {
+            String::from("")
+        } else {
+            url.path()[1..].to_string()
+        },
+        client: None,
+        signing_algorithm: match url.query_pairs().find(|(k, _)| k == "signing-algorithm") {
+            None => KmsSigningAlgorithm::RsassaPssSha256,
+            Some((_, v)) if v == "RSASSA_PSS_SHA_256" => KmsSigningAlgorithm::RsassaPssSha256,
+            Some((_, v)) if v == "ECDSA_SHA_256" => KmsSigningAlgorithm::EcdsaSha256,
+            Some((_, v)) => {
+                return error::UnrecognizedSigningAlgorithm { algorithm: v }.fail();
+            }
+        },
+    }))
+}
+
+/// Parses a PKCS#11 URI into a `Pkcs11KeySource`. Path attributes are separated by ';' and may be
+/// percent-encoded; query attributes are separated by '&'.
+#[cfg(feature = "pkcs11")]
+fn parse_pkcs11_url(input: &str, url: &Url) -> Result<Pkcs11KeySource> {
+    let path_attribute = |name: &str| -> Option<Vec<u8>> {
+        url.path().split(';').find_map(|attribute| {
+            let mut parts = attribute.splitn(2, '=');
+            if parts.next() == Some(name) {
+                parts
+                    .next()
+                    .map(|value| percent_decode_str(value).collect())
+            } else {
+                None
+            }
+        })
+    };
+    let query_attribute = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    let to_string = |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).into_owned();
+
+    Ok(Pkcs11KeySource {
+        module_path: query_attribute("module-path")
+            .context(error::Pkcs11UrlAttribute {
+                url: input,
+                attribute: "module-path",
+            })?
+            .into(),
+        token_label: path_attribute("token")
+            .map(to_string)
+            .context(error::Pkcs11UrlAttribute {
+                url: input,
+                attribute: "token",
+            })?,
+        key_label: path_attribute("object").map(to_string),
+        key_id: path_attribute("id"),
+        pin: query_attribute("pin-value"),
+    })
+}
+
+#[cfg(all(test, feature = "pkcs11"))]
+mod tests {
+    use super::parse_pkcs11_url;
+    use crate::error::Error;
+    use std::path::Path;
+    use url::Url;
+
+    fn parse(input: &str) -> crate::error::Result<tough_pkcs11::Pkcs11KeySource> {
+        parse_pkcs11_url(input, &Url::parse(input).unwrap())
+    }
+
+    #[test]
+    fn pkcs11_url() {
+        let key_source = parse(
+            "pkcs11:token=tuf;object=root%20key;id=%01%ff\
+             ?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-value=1234",
+        )
+        .unwrap();
+        assert_eq!(
+            key_source.module_path,
+            Path::new("/usr/lib/softhsm/libsofthsm2.so")
+        );
+        assert_eq!(key_source.token_label, "tuf");
+        assert_eq!(key_source.key_label.as_deref(), Some("root key"));
+        assert_eq!(key_source.key_id, Some(vec![0x01, 0xff]));
+        assert_eq!(key_source.pin.as_deref(), Some("1234"));
+    }
+
+    // The key may be selected by label or ID alone, and the PIN is optional.
+    #[test]
+    fn pkcs11_url_optional_attributes() {
+        let key_source = parse("pkcs11:token=tuf;id=%02?module-path=/lib/p11.so").unwrap();
+        assert_eq!(key_source.key_label, None);
+        assert_eq!(key_source.key_id, Some(vec![0x02]));
+        assert_eq!(key_source.pin, None);
+
+        let key_source = parse("pkcs11:object=root;token=tuf?module-path=/lib/p11.so").unwrap();
+        assert_eq!(key_source.token_label, "tuf");
+        assert_eq!(key_source.key_label.as_deref(), Some("root"));
+        assert_eq!(key_source.key_id, None);
+    }
+
+    #[test]
+    fn pkcs11_url_missing_attribute() {
+        for (input, missing) in &[
+            ("pkcs11:token=tuf;object=root", "module-path"),
+            ("pkcs11:object=root?module-path=/lib/p11.so", "token"),
+        ] {
+            match parse(input) {
+                Err(Error::Pkcs11UrlAttribute { attribute, .. }) => {
+                    assert_eq!(attribute, *missing);
+                }
+                other => panic!("expected a missing attribute error, got {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The transport that `tuftool` loads repositories with, chosen by URL scheme.
+
+#[cfg(feature = "s3")]
+use std::collections::HashMap;
+use std::io::Read;
+use tough::{DefaultTransport, Transport, TransportError};
+#[cfg(feature = "s3")]
+use tough::{S3Transport, TransportErrorKind};
+use url::Url;
+
+/// A [`Transport`] that fetches `s3://<bucket>/<key>` URLs with an [`S3Transport`] for the bucket,
+/// and all other URLs with [`DefaultTransport`] (`file://`, `http://`, and `https://`).
+///
+/// S3 support requires the `s3` feature. Requests to S3 use the region and credentials from the
+/// environment, e.g. `AWS_REGION` and `AWS_PROFILE`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SchemeTransport {
+    default: DefaultTransport,
+    #[cfg(feature = "s3")]
+    s3: HashMap<String, S3Transport>,
+}
+
+#[cfg(feature = "s3")]
+impl SchemeTransport {
+    /// Creates a transport for the given repository URLs, with an `S3Transport` for each bucket
+    /// that they refer to.
+    pub(crate) fn for_urls(urls: &[&Url]) -> Self {
+        let mut transport = Self::default();
+        for url in urls.iter().filter(|url| url.scheme() == "s3") {
+            if let Some(bucket) = url.host_str() {
+                transport
+                    .s3
+                    .entry(bucket.to_owned())
+                    .or_insert_with(|| S3Transport::new(bucket, rusoto_core::Region::default()));
+            }
+        }
+        transport
+    }
+
+    /// Returns the transport for `url`.
+    fn transport(&self, url: &Url) -> Result<&dyn Transport, TransportError> {
+        if url.scheme() != "s3" {
+            return Ok(&self.default);
+        }
+        url.host_str()
+            .and_then(|bucket| self.s3.get(bucket))
+            .map(|transport| transport as &dyn Transport)
+            .ok_or_else(|| {
+                TransportError::new_with_cause(
+                    TransportErrorKind::Other,
+                    url,
+                    "S3 bucket is not one of the repository's buckets",
+                )
+            })
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+impl SchemeTransport {
+    /// Creates a transport for the given repository URLs. Without the `s3` feature, `s3://` URLs
+    /// are unsupported.
+    pub(crate) fn for_urls(_urls: &[&Url]) -> Self {
+        Self::default()
+    }
+
+    /// Returns the transport for `url`.
+    #[allow(clippy::unnecessary_wraps)]
+    fn transport(&self, _url: &Url) -> Result<&dyn Transport, TransportError> {
+        Ok(&self.default)
+    }
+}
+
+impl Transport for SchemeTransport {
+    fn fetch(&self, url: Url) -> Result<Box<dyn Read + Send>, TransportError> {
+        self.transport(&url)?.fetch(url)
+    }
+
+    fn exists(&self, url: Url) -> Result<bool, TransportError> {
+        self.transport(&url)?.exists(url)
+    }
+
+    fn exists_is_cheap(&self) -> bool {
+        true
+    }
+}
@@ -2,20 +2,20 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::build_targets;
+use crate::common::repository_loader;
 use crate::common::UNUSED_URL;
 use crate::datetime::parse_datetime;
 use crate::error::{self, Result};
 use crate::source::parse_key_source;
 use chrono::{DateTime, Utc};
 use snafu::{OptionExt, ResultExt};
-use std::fs::File;
 use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use tough::editor::signed::PathExists;
 use tough::editor::RepositoryEditor;
 use tough::key_source::KeySource;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use tough::ExpirationEnforcement;
 use url::Url;
 
 #[derive(Debug, StructOpt)]
@@ -113,11 +113,11 @@ impl UpdateArgs {
         } else {
             ExpirationEnforcement::Safe
         };
-        let repository = RepositoryLoader::new(
-            File::open(&self.root).context(error::OpenRoot { path: &self.root })?,
+        let repository = repository_loader(
+            &self.root,
             self.metadata_base_url.clone(),
             Url::parse(UNUSED_URL).context(error::UrlParse { url: UNUSED_URL })?,
-        )
+        )?
         .expiration_enforcement(expiration_enforcement)
         .load()
         .context(error::RepoLoad)?;
@@ -0,0 +1,148 @@
+// Copyright 2021 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::repository_loader;
+use crate::common::UNUSED_URL;
+use crate::error::{self, Result};
+use crate::output::OutputFormat;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use snafu::ResultExt;
+use std::io::Write;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use tough::schema::decoded::{Decoded, Hex};
+use tough::schema::RoleType;
+use tough::ExpirationEnforcement;
+use url::Url;
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct VerifyArgs {
+    /// Path to root.json file for the repository
+    #[structopt(short = "r", long = "root")]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[structopt(short = "m", long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Allow verifying a repository with expired metadata
+    #[structopt(long)]
+    allow_expired: bool,
+}
+
+impl VerifyArgs {
+    pub(crate) fn run(&self, format: OutputFormat) -> Result<()> {
+        let expiration_enforcement = if self.allow_expired {
+            ExpirationEnforcement::Unsafe
+        } else {
+            ExpirationEnforcement::Safe
+        };
+        // Targets are never fetched, so a dummy URL is passed for them.
+        let repository = repository_loader(
+            &self.root,
+            self.metadata_base_url.clone(),
+            Url::parse(UNUSED_URL).context(error::UrlParse { url: UNUSED_URL })?,
+        )?
+        .expiration_enforcement(expiration_enforcement)
+        .load()
+        .context(error::RepoLoad)?;
+        repository
+            .verify_metadata()
+            .context(error::VerifyRepository)?;
+
+        let now = Utc::now();
+        let root = &repository.root().signed;
+        let top_level_keyids = |role: RoleType| {
+            root.roles
+                .get(&role)
+                .map_or_else(Vec::new, |role_keys| role_keys.keyids.clone())
+        };
+        let mut roles = vec![
+            RoleStatus::new(
+                "root",
+                root.version,
+                root.expires,
+                top_level_keyids(RoleType::Root),
+                now,
+            ),
+            RoleStatus::new(
+                "timestamp",
+                repository.timestamp().signed.version,
+                repository.timestamp().signed.expires,
+                top_level_keyids(RoleType::Timestamp),
+                now,
+            ),
+            RoleStatus::new(
+                "snapshot",
+                repository.snapshot().signed.version,
+                repository.snapshot().signed.expires,
+                top_level_keyids(RoleType::Snapshot),
+                now,
+            ),
+            RoleStatus::new(
+                "targets",
+                repository.targets().signed.version,
+                repository.targets().signed.expires,
+                top_level_keyids(RoleType::Targets),
+                now,
+            ),
+        ];
+        for role in repository.delegated_roles() {
+            if let Some(targets) = &role.targets {
+                roles.push(RoleStatus::new(
+                    &role.name,
+                    targets.signed.version,
+                    targets.signed.expires,
+                    role.keyids.clone(),
+                    now,
+                ));
+            }
+        }
+
+        format.print(&roles, |out, roles| {
+            for role in roles {
+                let note = if role.expired { " (expired)" } else { "" };
+                writeln!(
+                    out,
+                    "{}: version {}, expires {}{}",
+                    role.name,
+                    role.version,
+                    role.expires.to_rfc3339(),
+                    note
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The version and expiration of a verified role, as printed by `verify`.
+#[derive(Debug, Serialize)]
+struct RoleStatus<'a> {
+    name: &'a str,
+    version: NonZeroU64,
+    expires: DateTime<Utc>,
+    expired: bool,
+    /// The IDs of the keys that may sign for the role.
+    keyids: Vec<Decoded<Hex>>,
+}
+
+impl<'a> RoleStatus<'a> {
+    fn new(
+        name: &'a str,
+        version: NonZeroU64,
+        expires: DateTime<Utc>,
+        keyids: Vec<Decoded<Hex>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            expires,
+            expired: expires <= now,
+            keyids,
+        }
+    }
+}
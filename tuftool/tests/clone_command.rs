@@ -0,0 +1,74 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tough::{Repository, RepositoryLoader};
+
+fn reference_impl() -> PathBuf {
+    test_utils::test_data().join("tuf-reference-impl")
+}
+
+fn root_json() -> PathBuf {
+    reference_impl().join("metadata").join("root.json")
+}
+
+fn clone_command(outdir: &Path, targets: &[&str]) {
+    let repo_dir = reference_impl();
+    let mut cmd = Command::cargo_bin("tuftool").unwrap();
+    cmd.args(&[
+        "clone",
+        "-r",
+        root_json().to_str().unwrap(),
+        "--metadata-url",
+        test_utils::dir_url(repo_dir.join("metadata")).as_str(),
+        "--targets-url",
+        test_utils::dir_url(repo_dir.join("targets")).as_str(),
+        outdir.to_str().unwrap(),
+    ]);
+    for target in targets {
+        cmd.args(&["--target", target]);
+    }
+    cmd.assert().success();
+}
+
+fn load_clone(outdir: &Path) -> Repository {
+    RepositoryLoader::new(
+        File::open(root_json()).unwrap(),
+        test_utils::dir_url(outdir.join("metadata")),
+        test_utils::dir_url(outdir.join("targets")),
+    )
+    .load()
+    .unwrap()
+}
+
+#[test]
+// Ensure that a cloned repository can be loaded and serves all of its targets
+fn clone_command_all_targets() {
+    let outdir = TempDir::new().unwrap();
+    clone_command(outdir.path(), &[]);
+
+    let repo = load_clone(outdir.path());
+    for target in &["file1.txt", "file2.txt"] {
+        assert_eq!(
+            test_utils::read_to_end(repo.read_target(target).unwrap().unwrap()),
+            std::fs::read(reference_impl().join("targets").join(target)).unwrap()
+        );
+    }
+}
+
+#[test]
+// Ensure that only the requested targets are cloned
+fn clone_command_target_subset() {
+    let outdir = TempDir::new().unwrap();
+    clone_command(outdir.path(), &["file1.txt"]);
+
+    assert!(outdir.path().join("targets").join("file1.txt").exists());
+    assert!(!outdir.path().join("targets").join("file2.txt").exists());
+    let repo = load_clone(outdir.path());
+    assert!(repo.read_target("file1.txt").unwrap().is_some());
+}
@@ -228,6 +228,7 @@ fn create_repository_ssm_key() {
 }
 
 #[test]
+#[cfg(feature = "aws-kms")]
 #[cfg_attr(not(feature = "integ"), ignore)]
 // Ensure we can use kms key to create and sign a repo created by the `tuftool` binary using the `tough` library
 fn create_repository_kms_key() {
@@ -0,0 +1,97 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn stderr(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+fn reference_impl() -> std::path::PathBuf {
+    test_utils::test_data().join("tuf-reference-impl")
+}
+
+fn download_target(targets_dir: &Path, target_name: &str, output: &Path) -> Assert {
+    let repo_dir = reference_impl();
+    let root_json = repo_dir.join("metadata").join("root.json");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&[
+            "download-target",
+            "-r",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(repo_dir.join("metadata")).as_str(),
+            "--targets-url",
+            test_utils::dir_url(targets_dir).as_str(),
+            target_name,
+            output.to_str().unwrap(),
+        ])
+        .assert()
+}
+
+#[test]
+// Ensure that a single target is downloaded to the output path
+fn download_target_command() {
+    let outdir = TempDir::new().unwrap();
+    let output = outdir.path().join("downloaded.txt");
+    let targets_dir = reference_impl().join("targets");
+    download_target(&targets_dir, "file1.txt", &output).success();
+    assert_eq!(
+        read_to_string(&output).unwrap(),
+        read_to_string(targets_dir.join("file1.txt")).unwrap()
+    );
+
+    // The output gets the permissions of any other new file, not those of the temporary file it
+    // was staged in.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path| std::fs::metadata(path).unwrap().permissions().mode();
+        let created = outdir.path().join("created");
+        std::fs::File::create(&created).unwrap();
+        assert_eq!(mode(&output), mode(&created));
+    }
+}
+
+#[test]
+// Ensure that a target that isn't listed in the metadata is reported as missing
+fn download_target_command_missing() {
+    let outdir = TempDir::new().unwrap();
+    let output = outdir.path().join("downloaded.txt");
+    let assert = download_target(
+        &reference_impl().join("targets"),
+        "no-such-file.txt",
+        &output,
+    )
+    .failure();
+    assert!(stderr(&assert).contains("not found in repository metadata"));
+    assert!(!output.exists());
+}
+
+#[test]
+// Ensure that a target that doesn't match its metadata fails verification and isn't written
+fn download_target_command_tampered() {
+    let targets_dir = TempDir::new().unwrap();
+    let target_path = targets_dir.path().join("file1.txt");
+    std::fs::copy(
+        reference_impl().join("targets").join("file1.txt"),
+        &target_path,
+    )
+    .unwrap();
+    let mut f = OpenOptions::new().append(true).open(&target_path).unwrap();
+    writeln!(f, "bloop").unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    let output = outdir.path().join("downloaded.txt");
+    let assert = download_target(targets_dir.path(), "file1.txt", &output).failure();
+    assert!(stderr(&assert).contains("Failed to save target 'file1.txt'"));
+    assert!(!output.exists());
+}
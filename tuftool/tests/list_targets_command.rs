@@ -0,0 +1,80 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use serde_json::json;
+
+fn list_targets(args: &[&str]) -> String {
+    let metadata_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("metadata");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&[
+            "list-targets",
+            "-r",
+            metadata_dir.join("root.json").to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(&metadata_dir).as_str(),
+        ])
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+// Ensure that top-level and delegated targets are listed with their lengths and roles
+fn list_targets_command() {
+    assert_eq!(
+        list_targets(&[]),
+        "file1.txt\t31\ttargets\nfile2.txt\t39\ttargets\nfile3.txt\t28\trole1\n"
+    );
+}
+
+#[test]
+// Ensure that --json lists the same targets as a JSON array
+fn list_targets_command_json() {
+    let listings: serde_json::Value = serde_json::from_str(&list_targets(&["--json"])).unwrap();
+    assert_eq!(
+        listings,
+        json!([
+            { "name": "file1.txt", "length": 31, "role": "targets" },
+            { "name": "file2.txt", "length": 39, "role": "targets" },
+            { "name": "file3.txt", "length": 28, "role": "role1" },
+        ])
+    );
+}
+
+#[test]
+// Ensure that the global --format json option is the same as --json
+fn list_targets_command_format_json() {
+    let metadata_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("metadata");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&[
+            "--format",
+            "json",
+            "list-targets",
+            "-r",
+            metadata_dir.join("root.json").to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(&metadata_dir).as_str(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        list_targets(&["--json"])
+    );
+}
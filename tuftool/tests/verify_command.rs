@@ -0,0 +1,166 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn stdout(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).into_owned()
+}
+
+fn verify(root_json: &Path, metadata_dir: &Path, allow_expired: bool) -> Assert {
+    let mut cmd = Command::cargo_bin("tuftool").unwrap();
+    cmd.args(&[
+        "verify",
+        "-r",
+        root_json.to_str().unwrap(),
+        "--metadata-url",
+        test_utils::dir_url(metadata_dir).as_str(),
+    ]);
+    if allow_expired {
+        cmd.arg("--allow-expired");
+    }
+    cmd.assert()
+}
+
+#[test]
+// Ensure that a valid repository is verified, and each role's version and expiration is shown
+fn verify_command() {
+    let repo_dir = test_utils::test_data().join("tuf-reference-impl");
+    let metadata_dir = repo_dir.join("metadata");
+    let assert = verify(&metadata_dir.join("root.json"), &metadata_dir, false).success();
+    let output = stdout(&assert);
+    for role in &["root", "timestamp", "snapshot", "targets", "role1", "role2"] {
+        assert!(
+            output.contains(&format!(
+                "{}: version 1, expires 2030-01-01T00:00:00+00:00",
+                role
+            )),
+            "missing {} in output: {}",
+            role,
+            output
+        );
+    }
+}
+
+#[test]
+// Ensure that verification fails when a delegated role's metadata has been altered
+fn verify_command_tampered() {
+    let repo_dir = test_utils::test_data().join("tuf-reference-impl");
+    let metadata_dir = TempDir::new().unwrap();
+    for entry in std::fs::read_dir(repo_dir.join("metadata")).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, metadata_dir.path().join(path.file_name().unwrap())).unwrap();
+    }
+    let role2 = metadata_dir.path().join("role2.json");
+    let tampered = std::fs::read_to_string(&role2)
+        .unwrap()
+        .replace("2030-01-01T00:00:00Z", "2031-01-01T00:00:00Z");
+    std::fs::write(&role2, tampered).unwrap();
+
+    verify(
+        &repo_dir.join("metadata").join("root.json"),
+        metadata_dir.path(),
+        false,
+    )
+    .failure();
+}
+
+#[test]
+// Ensure that an expired repository fails verification unless --allow-expired is passed
+fn verify_command_expired() {
+    let repo_dir = TempDir::new().unwrap();
+    test_utils::create_expired_repo(repo_dir.path());
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let metadata_dir = repo_dir.path().join("metadata");
+
+    verify(&root_json, &metadata_dir, false).failure();
+    let assert = verify(&root_json, &metadata_dir, true).success();
+    assert!(stdout(&assert).contains("timestamp: version 31, expires"));
+    assert!(stdout(&assert).contains("(expired)"));
+}
+
+#[test]
+// Ensure that --format json prints each role's version, expiration, and key IDs as JSON
+fn verify_command_json() {
+    let metadata_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("metadata");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&[
+            "--format",
+            "json",
+            "verify",
+            "-r",
+            metadata_dir.join("root.json").to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(&metadata_dir).as_str(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let roles: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let roles = roles.as_array().unwrap();
+    let names: Vec<&str> = roles.iter().map(|r| r["name"].as_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        ["root", "timestamp", "snapshot", "targets", "role1", "role2"]
+    );
+    for role in roles {
+        assert_eq!(role["version"], 1);
+        assert_eq!(role["expires"], "2030-01-01T00:00:00Z");
+        assert_eq!(role["expired"], false);
+        assert!(!role["keyids"].as_array().unwrap().is_empty());
+    }
+}
+
+#[test]
+// Ensure that errors are printed as a JSON object with --format json
+fn verify_command_json_error() {
+    let repo_dir = TempDir::new().unwrap();
+    test_utils::create_expired_repo(repo_dir.path());
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&[
+            "--format",
+            "json",
+            "verify",
+            "-r",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(repo_dir.path().join("metadata")).as_str(),
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("expired"));
+}
+
+#[test]
+// Ensure that errors in the arguments are printed as a JSON object with --format json
+fn verify_command_json_argument_error() {
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(&["--format", "json", "verify", "--no-such-option"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .contains("--no-such-option"));
+}